@@ -0,0 +1,269 @@
+//! Command line client of the Snap control socket
+
+// Shared with the app, the server side of it is unused here
+#[allow(dead_code)]
+#[path = "../data/rpc.rs"]
+mod rpc;
+
+use std::{
+    env,
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    process::ExitCode,
+};
+
+use rpc::{methods, socket_path, RpcRequest, RpcResponse};
+use serde_json::{json, Value};
+
+const USAGE: &str = "usage: snapctl [--json] <command> [arguments]
+
+commands:
+  windows [--app NAME] [--bundle-id ID] [--all]
+                              list windows, --all includes minimized ones
+  screens                     list screens
+  frame [PID WINDOW_ID]       print the frame of a window, the focused one by default
+  move X Y WIDTH HEIGHT [PID WINDOW_ID]
+                              move a window, the focused one by default
+  mapping X Y WIDTH HEIGHT    run a mapping, the frame is relative to the screen (0 to 1)
+  arrange [split|grid]        arrange the windows of the current screen
+  mode snapping|fancy_zones   switch the window manager mode
+  caffeinate [on [MINUTES]|off]
+                              keep the Mac awake, toggles without an argument";
+
+struct Invocation {
+    json: bool,
+    command: String,
+    args: Vec<String>,
+}
+
+fn parse_invocation(mut args: Vec<String>) -> Option<Invocation> {
+    let json = match args.iter().position(|arg| arg == "--json") {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    };
+    if args.is_empty() {
+        return None;
+    }
+    let command = args.remove(0);
+
+    Some(Invocation {
+        json,
+        command,
+        args,
+    })
+}
+
+fn numbers(args: &[String]) -> Result<Vec<f64>, String> {
+    args.iter()
+        .map(|arg| {
+            arg.parse::<f64>()
+                .map_err(|_| format!("{} is not a number", arg))
+        })
+        .collect()
+}
+
+fn frame_params(args: &[String]) -> Result<Value, String> {
+    match numbers(args)?.as_slice() {
+        [x, y, width, height] => Ok(json!({
+            "position": { "x": x, "y": y },
+            "size": { "width": width, "height": height },
+        })),
+        _ => Err(String::from("expected X Y WIDTH HEIGHT")),
+    }
+}
+
+fn window_params(args: &[String]) -> Result<Value, String> {
+    match args {
+        [] => Ok(json!({})),
+        [pid, window_id] => {
+            let pid: i64 = pid.parse().map_err(|_| format!("invalid pid {}", pid))?;
+            let window_id: u32 = window_id
+                .parse()
+                .map_err(|_| format!("invalid window id {}", window_id))?;
+            Ok(json!({ "pid": pid, "window_id": window_id }))
+        }
+        _ => Err(String::from("expected PID WINDOW_ID")),
+    }
+}
+
+fn filter_params(args: &[String]) -> Result<Value, String> {
+    let mut filter = json!({});
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--all" => filter["include_minimized"] = json!(true),
+            "--app" | "--bundle-id" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", arg))?;
+                let key = if arg == "--app" { "app" } else { "bundle_id" };
+                filter[key] = json!(value);
+            }
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+
+    Ok(filter)
+}
+
+/// Method and params for a command
+fn request_for(command: &str, args: &[String]) -> Result<(&'static str, Value), String> {
+    match command {
+        "windows" => Ok((methods::LIST_WINDOWS, filter_params(args)?)),
+        "screens" => Ok((methods::LIST_SCREENS, Value::Null)),
+        "frame" => Ok((methods::GET_FRAME, window_params(args)?)),
+        "move" => {
+            if args.len() < 4 {
+                return Err(String::from("expected X Y WIDTH HEIGHT"));
+            }
+            let mut params = window_params(&args[4..])?;
+            params["frame"] = frame_params(&args[..4])?;
+            Ok((methods::SET_FRAME, params))
+        }
+        "mapping" => Ok((
+            methods::RUN_MAPPING,
+            json!({ "frame": frame_params(args)? }),
+        )),
+        "arrange" => match args {
+            [] => Ok((methods::ARRANGE, Value::Null)),
+            [strategy] if strategy == "split" || strategy == "grid" => {
+                Ok((methods::ARRANGE, json!({ "strategy": strategy })))
+            }
+            _ => Err(String::from("expected split or grid")),
+        },
+        "mode" => match args {
+            [mode] if mode == "snapping" || mode == "fancy_zones" => {
+                Ok((methods::SET_MODE, json!({ "mode": mode })))
+            }
+            _ => Err(String::from("expected snapping or fancy_zones")),
+        },
+        "caffeinate" => match args {
+            [] => Ok((methods::SET_CAFFEINATE, json!({}))),
+            [state] if state == "on" || state == "off" => {
+                Ok((methods::SET_CAFFEINATE, json!({ "enabled": state == "on" })))
+            }
+            [state, minutes] if state == "on" => {
+                let minutes: u32 = minutes
+                    .parse()
+                    .map_err(|_| format!("{} is not a number of minutes", minutes))?;
+                Ok((
+                    methods::SET_CAFFEINATE,
+                    json!({ "enabled": true, "minutes": minutes }),
+                ))
+            }
+            _ => Err(String::from("expected on [MINUTES] or off")),
+        },
+        _ => Err(format!("unknown command {}", command)),
+    }
+}
+
+fn send(request: &RpcRequest) -> Result<RpcResponse, String> {
+    let path = socket_path();
+    let stream = UnixStream::connect(&path)
+        .map_err(|error| format!("can't reach Snap at {}: {}", path.display(), error))?;
+
+    let mut writer = stream.try_clone().map_err(|error| error.to_string())?;
+    let mut line = serde_json::to_string(request).map_err(|error| error.to_string())?;
+    line.push('\n');
+    writer
+        .write_all(line.as_bytes())
+        .map_err(|error| error.to_string())?;
+
+    let mut response = String::new();
+    BufReader::new(stream)
+        .read_line(&mut response)
+        .map_err(|error| error.to_string())?;
+
+    serde_json::from_str(&response).map_err(|error| format!("invalid response: {}", error))
+}
+
+fn format_frame(frame: &Value) -> String {
+    format!(
+        "{}x{} at {},{}",
+        frame["size"]["width"],
+        frame["size"]["height"],
+        frame["position"]["x"],
+        frame["position"]["y"]
+    )
+}
+
+fn text(value: &Value) -> String {
+    value.as_str().unwrap_or("-").to_string()
+}
+
+fn print_human(command: &str, result: &Value) {
+    match command {
+        "windows" => {
+            for window in result.as_array().into_iter().flatten() {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    window["pid"],
+                    window["id"],
+                    text(&window["name"]),
+                    text(&window["title"]),
+                    format_frame(&window["frame"])
+                );
+            }
+        }
+        "screens" => {
+            for (index, screen) in result.as_array().into_iter().flatten().enumerate() {
+                println!(
+                    "{}\t{}\tvisible {}",
+                    index,
+                    format_frame(&screen["frame"]),
+                    format_frame(&screen["visible_frame"])
+                );
+            }
+        }
+        "frame" | "move" => println!("{}", format_frame(result)),
+        _ => println!("ok"),
+    }
+}
+
+fn main() -> ExitCode {
+    let Some(invocation) = parse_invocation(env::args().skip(1).collect()) else {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+
+    let (method, params) = match request_for(&invocation.command, &invocation.args) {
+        Ok(request) => request,
+        Err(message) => {
+            eprintln!("snapctl: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let response = match send(&RpcRequest::new(1, method, params)) {
+        Ok(response) => response,
+        Err(message) => {
+            eprintln!("snapctl: {}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Some(error) = response.error {
+        if invocation.json {
+            println!("{}", json!({ "error": error }));
+        } else {
+            eprintln!("snapctl: {}", error.message);
+        }
+        return ExitCode::FAILURE;
+    }
+
+    let result = response.result.unwrap_or(Value::Null);
+    if invocation.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&result).unwrap_or_default()
+        );
+    } else {
+        print_human(&invocation.command, &result);
+    }
+
+    ExitCode::SUCCESS
+}
@@ -1,21 +1,36 @@
+use std::sync::mpsc::RecvError;
+
 use accessibility_sys::{
-    kAXTrustedCheckOptionPrompt, AXIsProcessTrusted, AXIsProcessTrustedWithOptions,
+    kAXTrustedCheckOptionPrompt, AXIsProcessTrusted, AXIsProcessTrustedWithOptions, AXUIElementRef,
 };
 use cocoa::appkit::CGPoint;
 use core_foundation::{
-    base::TCFType, boolean::CFBoolean, dictionary::CFDictionary, string::CFString,
+    base::{CFRelease, CFTypeRef, TCFType},
+    boolean::CFBoolean,
+    dictionary::CFDictionary,
+    string::CFString,
 };
 
 use core_graphics::geometry::CGSize;
 use objc2_foundation::NSProcessInfo;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tauri::command;
 
 use crate::{
-    data::window_info::WindowInfo,
-    extensions::accessibility_elements::{
-        get_active_window, get_window_from_id, set_frame_animated, set_position, set_size,
-        AnimationConfig,
+    data::{
+        alignment::Alignment,
+        error::{SnapError, SnapResult},
+        frame::Frame,
+        window_info::WindowInfo,
+    },
+    extensions::{
+        accessibility_elements::{
+            get_active_window, get_frame, get_window_from_id, set_frame_animated, OnSettled,
+        },
+        animation::{AnimationCompletion, AnimationConfig, AnimationOutcome, QueuePolicy},
+        easing::EasingFunction,
+        hooks,
+        placement::{place, resolve_anchor, FRAME_TOLERANCE},
     },
 };
 
@@ -23,6 +38,8 @@ use crate::{
 pub struct AnimationOptions {
     pub duration_ms: Option<u64>,
     pub fps: Option<u64>,
+    pub easing: Option<EasingFunction>,
+    pub queue_policy: Option<QueuePolicy>,
 }
 
 impl From<AnimationOptions> for AnimationConfig {
@@ -30,119 +47,199 @@ impl From<AnimationOptions> for AnimationConfig {
         AnimationConfig {
             duration_ms: options.duration_ms.unwrap_or(200),
             fps: options.fps.unwrap_or(60),
+            easing: options.easing.unwrap_or_default(),
+            queue: options.queue_policy.unwrap_or_default(),
         }
     }
 }
 
-#[command]
-pub fn accessibility_element_set_frame_animated(
-    window_info: WindowInfo,
-    animation_options: Option<AnimationOptions>,
-) {
+/// Resolves the AX element of a window that Snap is allowed to move
+fn resolve_window(window_info: &WindowInfo) -> SnapResult<AXUIElementRef> {
     let process_info = NSProcessInfo::processInfo();
     let pid: i64 = unsafe { process_info.processIdentifier() as i64 };
 
     if window_info.pid == pid {
-        return;
+        return Err(SnapError::OwnWindowRefused);
+    }
+    if !unsafe { AXIsProcessTrusted() } {
+        return Err(SnapError::AccessibilityNotTrusted);
     }
 
-    let window = get_window_from_id(window_info.pid, window_info.window_id);
-
-    match window {
-        Ok(window) => {
-            let target_point = CGPoint {
-                x: window_info.frame.position.x,
-                y: window_info.frame.position.y,
-            };
-            let target_size = CGSize {
-                width: window_info.frame.size.width,
-                height: window_info.frame.size.height,
-            };
-
-            let config = animation_options
-                .map(AnimationConfig::from)
-                .unwrap_or_default();
+    get_window_from_id(window_info.pid, window_info.window_id)
+}
 
-            set_frame_animated(window, target_point, target_size, config);
+/// A window closed between being resolved and being moved is reported as not found
+fn window_error(window_info: &WindowInfo) -> impl Fn(SnapError) -> SnapError + '_ {
+    move |error| match error {
+        SnapError::AxError { code, operation } => {
+            SnapError::from_window_ax(code, &operation, window_info.pid, window_info.window_id)
         }
-        Err(_) => {}
+        error => error,
     }
 }
 
+/// Apps can refuse or adjust a frame, reads back what the window really looks like
+fn settled_frame(window_info: &WindowInfo, expected: Frame) -> Frame {
+    get_window_from_id(window_info.pid, window_info.window_id)
+        .and_then(|window| {
+            let frame = get_frame(window);
+            unsafe { CFRelease(window as CFTypeRef) };
+            frame
+        })
+        .unwrap_or(expected)
+}
+
+/// `window_snapped` hooks see where the window ended up and only run once it got there
+fn snapped_hook(window_info: &WindowInfo) -> OnSettled {
+    let (pid, window_id) = (window_info.pid, window_info.window_id);
+
+    Box::new(move |frame| hooks::window_snapped(pid, window_id, frame))
+}
+
+fn start_frame_animation(
+    window_info: &WindowInfo,
+    config: AnimationConfig,
+    anchor: Option<Alignment>,
+    snapped: bool,
+) -> SnapResult<AnimationCompletion> {
+    let window = resolve_window(window_info)?;
+    let target_point = CGPoint {
+        x: window_info.frame.position.x,
+        y: window_info.frame.position.y,
+    };
+    let target_size = CGSize {
+        width: window_info.frame.size.width,
+        height: window_info.frame.size.height,
+    };
+
+    let on_settled = snapped.then(|| snapped_hook(window_info));
+
+    // The animation keeps its own reference to the element
+    let completion = set_frame_animated(
+        window,
+        target_point,
+        target_size,
+        config,
+        anchor,
+        on_settled,
+    );
+    unsafe { CFRelease(window as CFTypeRef) };
+
+    completion.map_err(window_error(window_info))
+}
+
+/// `anchor` pins the window to a side or corner of the frame when the app refuses its size,
+/// by default it follows where the frame sits on the screen. `snapped` is set by the snap
+/// and mapping paths, it runs the `window_snapped` hooks once the window got there
 #[command]
-pub fn accessibility_element_set_frame_instant(window_info: WindowInfo) {
-    let process_info = NSProcessInfo::processInfo();
-    let pid: i64 = unsafe { process_info.processIdentifier() as i64 };
+pub fn accessibility_element_set_frame_animated(
+    window_info: WindowInfo,
+    animation_options: Option<AnimationOptions>,
+    anchor: Option<Alignment>,
+    snapped: Option<bool>,
+) -> SnapResult<()> {
+    let config = animation_options
+        .map(AnimationConfig::from)
+        .unwrap_or_default();
 
-    if window_info.pid == pid {
-        return;
-    }
+    start_frame_animation(&window_info, config, anchor, snapped.unwrap_or(false)).map(|_| ())
+}
 
-    let window = get_window_from_id(window_info.pid, window_info.window_id);
-
-    match window {
-        Ok(window) => {
-            set_position(
-                window,
-                CGPoint {
-                    x: window_info.frame.position.x,
-                    y: window_info.frame.position.y,
-                },
-            );
-            set_size(
-                window,
-                CGSize {
-                    width: window_info.frame.size.width,
-                    height: window_info.frame.size.height,
-                },
-            );
-        }
-        Err(_) => {}
-    }
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationStatus {
+    Finished,
+    Cancelled,
+    Ignored,
 }
 
+#[derive(Serialize)]
+pub struct AnimationResult {
+    pub status: AnimationStatus,
+    /// Frame read back from the window once the animation is over
+    pub frame: Frame,
+    /// The app didn't accept the requested frame (minimum size, character grid)
+    pub clamped: bool,
+}
+
+/// Same as `accessibility_element_set_frame_animated`, but resolves only when the
+/// animation is over and returns the frame the window actually ended up with
 #[command]
-pub fn accessibility_element_under_cursor() -> Result<WindowInfo, ()> {
-    let win = get_active_window();
-    let result = match win {
-        Ok(win) => Ok(WindowInfo {
-            pid: win.process_id,
-            window_id: win.window_id as u32,
-            frame: win.frame,
-        }),
-        Err(_) => Err(()),
+pub async fn accessibility_element_set_frame_animated_await(
+    window_info: WindowInfo,
+    animation_options: Option<AnimationOptions>,
+    anchor: Option<Alignment>,
+) -> SnapResult<AnimationResult> {
+    let config = animation_options
+        .map(AnimationConfig::from)
+        .unwrap_or_default();
+    let completion = start_frame_animation(&window_info, config, anchor, false)?;
+
+    // The window is read back once the animation is over, on the blocking pool like the wait
+    let settling = window_info.clone();
+    let (outcome, frame) = tauri::async_runtime::spawn_blocking(move || {
+        let outcome = completion.recv()?;
+        let expected = match &outcome {
+            AnimationOutcome::Finished(frame) | AnimationOutcome::Cancelled(frame) => frame.clone(),
+            AnimationOutcome::Ignored => settling.frame.clone(),
+        };
+        let frame = settled_frame(&settling, expected);
+
+        Ok::<_, RecvError>((outcome, frame))
+    })
+    .await
+    .map_err(|error| SnapError::platform(error.to_string()))?
+    .map_err(|_| SnapError::platform("animation scheduler stopped"))?;
+
+    let status = match outcome {
+        AnimationOutcome::Finished(_) => AnimationStatus::Finished,
+        AnimationOutcome::Cancelled(_) => AnimationStatus::Cancelled,
+        AnimationOutcome::Ignored => AnimationStatus::Ignored,
     };
+    let clamped = !frame.approx_eq(&window_info.frame, FRAME_TOLERANCE);
 
-    return result;
+    Ok(AnimationResult {
+        status,
+        frame,
+        clamped,
+    })
 }
 
+/// Applies the frame immediately and returns the frame the window ended up with,
+/// `snapped` runs the `window_snapped` hooks like for `accessibility_element_set_frame_animated`
 #[command]
-pub fn accessibility_element_set_frame(window_info: WindowInfo) {
-    let process_info = NSProcessInfo::processInfo();
-    let pid: i64 = unsafe { process_info.processIdentifier() as i64 };
+pub fn accessibility_element_set_frame_instant(
+    window_info: WindowInfo,
+    anchor: Option<Alignment>,
+    snapped: Option<bool>,
+) -> SnapResult<Frame> {
+    let window = resolve_window(&window_info)?;
+    let anchor = resolve_anchor(&window_info.frame, anchor);
 
-    if window_info.pid == pid {
-        return;
+    let placed = place(window, &window_info.frame, anchor);
+    unsafe { CFRelease(window as CFTypeRef) };
+    let frame = placed.map_err(window_error(&window_info))?.frame;
+    if snapped.unwrap_or(false) {
+        hooks::window_snapped(window_info.pid, window_info.window_id, &frame);
     }
 
-    let window = get_window_from_id(window_info.pid, window_info.window_id);
-
-    match window {
-        Ok(window) => {
-            let target_point = CGPoint {
-                x: window_info.frame.position.x,
-                y: window_info.frame.position.y,
-            };
-            let target_size = CGSize {
-                width: window_info.frame.size.width,
-                height: window_info.frame.size.height,
-            };
-
-            let config = AnimationConfig::default();
-            set_frame_animated(window, target_point, target_size, config);
-        }
-        Err(_) => {}
-    }
+    Ok(frame)
+}
+
+#[command]
+pub fn accessibility_element_under_cursor() -> SnapResult<WindowInfo> {
+    let win = get_active_window()?;
+
+    Ok(WindowInfo {
+        pid: win.process_id,
+        window_id: win.window_id as u32,
+        frame: win.frame,
+    })
+}
+
+#[command]
+pub fn accessibility_element_set_frame(window_info: WindowInfo) -> SnapResult<()> {
+    start_frame_animation(&window_info, AnimationConfig::default(), None, false).map(|_| ())
 }
 
 #[command]
@@ -0,0 +1,73 @@
+use serde::Serialize;
+use tauri::{command, Emitter, Manager, Webview};
+
+use crate::{
+    data::frame::Point,
+    extensions::{
+        accessibility_elements::{
+            add_animation_listener, get_active_animation_count, remove_animation_listener,
+        },
+        easing::EasingFunction,
+    },
+};
+
+#[derive(Serialize)]
+pub struct EasingPreview {
+    /// Springs report their settling time, other curves echo the requested duration
+    pub duration_ms: u64,
+    /// `x` is the animation progress, `y` the eased value
+    pub points: Vec<Point>,
+}
+
+/// Samples an easing curve so the settings can draw a preview of it
+#[command]
+pub fn animation_sample_easing(
+    easing: EasingFunction,
+    samples: Option<usize>,
+    duration_ms: Option<u64>,
+) -> EasingPreview {
+    let samples = samples.unwrap_or(60).clamp(2, 1000);
+    let duration_ms = easing
+        .settling_duration()
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(duration_ms.unwrap_or(200));
+
+    let points = (0..samples)
+        .map(|i| {
+            let t = i as f64 / (samples - 1) as f64;
+            Point {
+                x: t,
+                y: easing.apply(t),
+            }
+        })
+        .collect();
+
+    EasingPreview {
+        duration_ms,
+        points,
+    }
+}
+
+/// Emits `animation_started`, `animation_finished` and `animation_cancelled` of every
+/// window animation to the calling webview until unsubscribed with the returned id or
+/// the webview closes
+#[command]
+pub fn animation_listen_events(webview: Webview) -> u64 {
+    let label = webview.label().to_string();
+    let app = webview.app_handle().clone();
+
+    add_animation_listener(Some(&label), move |phase, event| {
+        let _ = app.emit_to(label.as_str(), phase.event_name(), event);
+    })
+}
+
+/// `false` when the subscription was already gone
+#[command]
+pub fn animation_unlisten_events(id: u64) -> bool {
+    remove_animation_listener(id)
+}
+
+#[command]
+pub fn animation_active_count() -> usize {
+    get_active_animation_count()
+}
@@ -0,0 +1,33 @@
+use tauri::command;
+
+use crate::{
+    data::error::SnapResult,
+    extensions::{
+        caffeinate::{self, CaffeinateRequest, CaffeinateStatus},
+        caffeinate_triggers::TriggersConfig,
+    },
+};
+
+/// Keeps the Mac awake, replacing the running session.
+/// The status is also emitted as `caffeinate_state_changed`
+#[command]
+pub fn caffeinate_start(request: Option<CaffeinateRequest>) -> SnapResult<CaffeinateStatus> {
+    caffeinate::start(&request.unwrap_or_default())
+}
+
+#[command]
+pub fn caffeinate_stop() -> SnapResult<CaffeinateStatus> {
+    caffeinate::stop()
+}
+
+#[command]
+pub fn caffeinate_status() -> CaffeinateStatus {
+    caffeinate::status()
+}
+
+/// Replaces the triggers that keep the Mac awake on their own, an empty list turns
+/// them off
+#[command]
+pub fn caffeinate_configure_triggers(config: TriggersConfig) -> SnapResult<()> {
+    caffeinate::configure_triggers(config)
+}
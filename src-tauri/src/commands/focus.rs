@@ -0,0 +1,24 @@
+use tauri::command;
+
+use crate::{
+    data::error::SnapResult,
+    extensions::{focus_history, windows::WindowInfo},
+};
+
+/// Focuses the window that had focus before the current one and returns it
+#[command]
+pub fn focus_previous_window() -> SnapResult<WindowInfo> {
+    focus_history::focus_previous()
+}
+
+/// Focuses the `n`-th most recently used window, `0` being the focused one
+#[command]
+pub fn focus_mru(n: usize) -> SnapResult<WindowInfo> {
+    focus_history::focus_mru(n)
+}
+
+/// Windows ordered from the most recently focused one
+#[command]
+pub fn focus_mru_list() -> Vec<WindowInfo> {
+    focus_history::get_history()
+}
@@ -2,10 +2,13 @@ use cocoa::base::id;
 use objc::{msg_send, runtime::Class, sel, sel_impl};
 use tauri::command;
 
+use crate::data::error::{SnapError, SnapResult};
+
 #[command]
-pub fn trigger_haptic_feedback() {
+pub fn trigger_haptic_feedback() -> SnapResult<()> {
     // Get the NSHapticFeedbackManager class
-    let manager_class = Class::get("NSHapticFeedbackManager").unwrap();
+    let manager_class = Class::get("NSHapticFeedbackManager")
+        .ok_or_else(|| SnapError::platform("NSHapticFeedbackManager is unavailable"))?;
 
     // Get the shared haptic feedback manager instance
     let manager: id = unsafe { msg_send![manager_class, defaultPerformer] };
@@ -14,4 +17,6 @@ pub fn trigger_haptic_feedback() {
     let performance_time: u64 = 0; // Use appropriate performance time
     let _: id =
         unsafe { msg_send![manager, performFeedbackPattern:1 performanceTime:performance_time] };
+
+    Ok(())
 }
@@ -0,0 +1,18 @@
+use tauri::command;
+
+use crate::{
+    data::error::SnapResult,
+    extensions::hooks::{self, HookEvent, HooksConfig},
+};
+
+/// Replaces the commands run on events, the previous ones are kept when the config is invalid
+#[command]
+pub fn hooks_configure(config: HooksConfig) -> SnapResult<()> {
+    hooks::configure(config)
+}
+
+/// Runs the hooks of events that happen in the frontend stores (mode, caffeinate, arrange)
+#[command]
+pub fn hooks_run(event: HookEvent) {
+    hooks::run(event);
+}
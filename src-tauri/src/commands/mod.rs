@@ -1,6 +1,19 @@
 pub mod accessibility_element;
+pub mod animation;
+pub mod caffeinate;
+pub mod focus;
 pub mod haptic_feedback;
+pub mod hooks;
+pub mod modifier_drag;
+pub mod notification;
 pub mod nscolor;
 pub mod nsevent;
 pub mod nsscreen;
 pub mod nswindow;
+pub mod plugins;
+pub mod scripting;
+pub mod snapping;
+pub mod window_finder;
+pub mod window_switcher;
+pub mod window_transaction;
+pub mod window_watcher;
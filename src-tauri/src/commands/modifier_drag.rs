@@ -0,0 +1,15 @@
+use tauri::{command, AppHandle, Emitter};
+
+use crate::{
+    data::error::SnapResult,
+    extensions::modifier_drag::{self, ModifierDragConfig},
+};
+
+/// Moves windows on modifier + left drag and resizes them on modifier + right drag,
+/// emitting `modifier_drag_started`, `modifier_drag_moved` and `modifier_drag_ended`
+#[command]
+pub fn modifier_drag_configure(app: AppHandle, config: ModifierDragConfig) -> SnapResult<()> {
+    modifier_drag::configure(config, move |phase, event| {
+        let _ = app.emit(phase.event_name(), event);
+    })
+}
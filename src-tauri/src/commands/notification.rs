@@ -0,0 +1,10 @@
+use tauri::command;
+
+use crate::extensions::notification;
+
+/// Stops a subscription made by one of the `*_listen_*` commands, `false` when it
+/// was already gone
+#[command]
+pub fn notification_unsubscribe(id: u64) -> bool {
+    notification::unsubscribe(id)
+}
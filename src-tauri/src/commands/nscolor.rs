@@ -1,37 +1,100 @@
-use std::sync::Mutex;
-
-use crate::extensions::{notification::NSNotificationCenter, nscolor::NSAColor};
+use crate::{
+    data::{
+        color::{Appearance, Color, Palette},
+        error::{SnapError, SnapResult},
+    },
+    extensions::{
+        hooks::{self, HookEvent},
+        notification::{self, NotificationCenter},
+        nscolor::{self, NSAColor},
+    },
+};
 
 use cocoa::base::nil;
-use tauri::{command, AppHandle, Emitter};
+use tauri::{command, Emitter, Manager, Webview};
+
+/// Posted for accent and highlight color changes
+const ACCENT_CHANGED: &str = "AppleColorPreferencesChangedNotification";
 
 #[command]
-pub fn nscolor_get_accent() -> String {
+pub fn nscolor_get_accent() -> SnapResult<Color> {
     unsafe {
         let color = NSAColor::accent_color(nil);
 
-        return NSAColor::to_rgb(color);
+        NSAColor::to_color(color)
+            .ok_or_else(|| SnapError::platform("the accent color has no sRGB representation"))
     }
 }
 
-static mut GLOBAL_OBSERVER: Option<NSNotificationCenter> = None;
+/// Semantic colors for the current appearance
+#[command]
+pub fn nscolor_get_palette() -> SnapResult<Palette> {
+    nscolor::palette(nscolor::appearance_mode(), nscolor::increase_contrast())
+        .ok_or_else(|| SnapError::platform("the system colors are unavailable"))
+}
 
 #[command]
-pub fn nscolor_listen_accent(app: AppHandle) {
-    if let Some(notification) = unsafe { &GLOBAL_OBSERVER } {
-        notification.stop();
-    }
-    let notification = NSNotificationCenter::new(String::from("NSDistributedNotificationCenter"));
-    let app = Mutex::new(app);
-    let notification = notification.listen(
-        String::from("AppleColorPreferencesChangedNotification"),
-        move |_| {
-            let app = app.lock().unwrap();
-            let _ = app.emit("nscolor_change", nscolor_get_accent());
+pub fn nscolor_get_appearance() -> SnapResult<Appearance> {
+    nscolor::appearance().ok_or_else(|| SnapError::platform("the system colors are unavailable"))
+}
+
+/// Runs the `accent_color_changed` hooks, once for the whole app
+pub fn run_hooks_on_accent_change() {
+    notification::subscribe(
+        None,
+        &[(NotificationCenter::Distributed, ACCENT_CHANGED)],
+        |_| {
+            if let Ok(color) = nscolor_get_accent() {
+                hooks::run(HookEvent::AccentColorChanged {
+                    color: color.to_css(),
+                });
+            }
         },
     );
+}
 
-    unsafe {
-        GLOBAL_OBSERVER = Some(notification);
-    }
+/// Emits `nscolor_change` to the calling webview until unsubscribed with the
+/// returned id or the webview closes
+#[command]
+pub fn nscolor_listen_accent(webview: Webview) -> u64 {
+    let label = webview.label().to_string();
+    let app = webview.app_handle().clone();
+
+    notification::subscribe(
+        Some(&label),
+        &[(NotificationCenter::Distributed, ACCENT_CHANGED)],
+        move |_| {
+            if let Ok(color) = nscolor_get_accent() {
+                let _ = app.emit_to(label.as_str(), "nscolor_change", &color);
+            }
+        },
+    )
+}
+
+/// Emits `appearance_change` to the calling webview when dark mode, increase
+/// contrast, the accent or the highlight color change
+#[command]
+pub fn nscolor_listen_appearance(webview: Webview) -> u64 {
+    let label = webview.label().to_string();
+    let app = webview.app_handle().clone();
+
+    notification::subscribe(
+        Some(&label),
+        &[
+            (
+                NotificationCenter::Distributed,
+                "AppleInterfaceThemeChangedNotification",
+            ),
+            (NotificationCenter::Distributed, ACCENT_CHANGED),
+            (
+                NotificationCenter::Workspace,
+                "NSWorkspaceAccessibilityDisplayOptionsDidChangeNotification",
+            ),
+        ],
+        move |_| {
+            if let Some(appearance) = nscolor::appearance() {
+                let _ = app.emit_to(label.as_str(), "appearance_change", &appearance);
+            }
+        },
+    )
 }
@@ -1,81 +1,152 @@
+use std::{
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
 use nanoid::nanoid;
 
-use crate::data::{frame::Point, mouse::MouseEvent};
-use crate::extensions::event_monitor::EventMonitor;
+use crate::data::{
+    error::{SnapError, SnapResult},
+    frame::Point,
+    mouse::{MonitorOptions, MouseEvent},
+};
+use crate::extensions::{
+    drag_velocity::{DragSample, DRAG_VELOCITY},
+    event_monitor::{self, EventMonitor},
+    event_throttle::EventThrottle,
+};
 
 use cocoa::{
-    appkit::{NSEvent, NSEventMask},
+    appkit::{CGPoint, NSEvent, NSEventMask, NSEventType},
     base::nil,
 };
 use tauri::{command, Emitter, Window};
 
-struct MonitorListeners {
-    id: String,
-    monitor: Option<EventMonitor>,
+fn track_drag(event_type: NSEventType, position: CGPoint, timestamp: f64) {
+    let mut estimator = DRAG_VELOCITY.lock().unwrap();
+    match event_type {
+        NSEventType::NSLeftMouseDown => estimator.reset(),
+        NSEventType::NSLeftMouseDragged => estimator.push(DragSample {
+            point: (position.x, position.y),
+            timestamp,
+        }),
+        NSEventType::NSLeftMouseUp => estimator.release(timestamp),
+        _ => (),
+    }
 }
 
-static mut GLOBAL_MONITORS: Vec<MonitorListeners> = vec![];
-
+/// Cursor position in Cocoa coordinates (bottom-left origin)
 #[command]
-pub fn nsevent_mouse_location() -> Option<Point> {
-    unsafe {
-        let mouse_location = NSEvent::mouseLocation(nil);
-
-        Some(Point {
-            x: mouse_location.x,
-            y: mouse_location.y,
-        })
+pub fn nsevent_mouse_location() -> SnapResult<Point> {
+    let mouse_location = unsafe { NSEvent::mouseLocation(nil) };
+    if !mouse_location.x.is_finite() || !mouse_location.y.is_finite() {
+        return Err(SnapError::platform("the cursor position is unavailable"));
     }
+
+    Ok(Point {
+        x: mouse_location.x,
+        y: mouse_location.y,
+    })
 }
 
 #[command]
-pub fn nsevent_remove_monitor(id: String) {
-    unsafe {
-        let monitor_manager = GLOBAL_MONITORS
-            .iter()
-            .enumerate()
-            .find(|el| el.1.id == id.clone());
-
-        match monitor_manager {
-            Some((index, listener)) => match &listener.monitor {
-                Some(monitor) => {
-                    monitor.stop();
-                    GLOBAL_MONITORS.remove(index);
-                }
-                None => (),
-            },
-            _ => (),
-        }
+pub fn nsevent_remove_monitor(id: String) -> SnapResult<()> {
+    if event_monitor::remove(&id) {
+        Ok(())
+    } else {
+        Err(SnapError::invalid_argument(
+            "id",
+            format!("no monitor with id {}", id),
+        ))
     }
 }
 
+/// Only the window that added the monitor gets its events
+fn emit_to_owner(window: &Window, event: &MouseEvent) {
+    let _ = window.emit_to(window.label(), "nsevent_on_event", event);
+}
+
+/// Sends the held back events of a throttled monitor once their delay is over.
+/// One thread per monitor, it ends when the monitor is removed and drops the sender
+fn spawn_flusher(
+    throttle: Arc<Mutex<EventThrottle<MouseEvent>>>,
+    window: Window,
+    started: Instant,
+) -> Sender<Instant> {
+    let (sender, receiver) = mpsc::channel::<Instant>();
+
+    thread::spawn(move || {
+        for flush_at in receiver {
+            thread::sleep(flush_at.saturating_duration_since(Instant::now()));
+            let now_ms = started.elapsed().as_millis() as u64;
+            if let Some(payload) = throttle.lock().unwrap().flush(now_ms) {
+                emit_to_owner(&window, &payload);
+            }
+        }
+    });
+
+    sender
+}
+
+/// Monitors the given event types system wide until removed or until the window
+/// closes. Drags are tracked for throw detection before any throttling
 #[command]
-pub fn nsevent_add_global_monitor_for_events(mask: u64, window: Window) -> String {
+pub fn nsevent_add_monitor(options: MonitorOptions, window: Window) -> SnapResult<String> {
+    if options.events.is_empty() {
+        return Err(SnapError::invalid_argument(
+            "events",
+            "no event type selected",
+        ));
+    }
+    if options.throttle_ms == Some(0) {
+        return Err(SnapError::invalid_argument(
+            "throttle_ms",
+            "must be greater than zero",
+        ));
+    }
+    let mask = NSEventMask::from_bits(options.mask())
+        .ok_or_else(|| SnapError::platform("unsupported event types"))?;
+
     let instance_id = nanoid!();
-    nsevent_remove_monitor(instance_id.clone());
-    let mask = NSEventMask::from_bits(mask);
     let cloned_id = instance_id.clone();
-    let monitor = EventMonitor::global_monitor(mask.unwrap(), move |event| {
+    let owner = window.label().to_string();
+    let started = Instant::now();
+    let throttle = options.throttle_ms.map(|ms| {
+        let throttle = Arc::new(Mutex::new(EventThrottle::new(ms, options.coalesce)));
+        let flusher = spawn_flusher(throttle.clone(), window.clone(), started);
+        (throttle, flusher)
+    });
+
+    let monitor = EventMonitor::global_monitor(mask, move |event| {
         let position = EventMonitor::location(event);
         let event_type = EventMonitor::event_type(event);
-        let payload = MouseEvent {
-            point: Point {
-                x: position.x,
-                y: position.y,
-            },
-            monitor_id: cloned_id.clone(),
-            event_type: event_type as u64,
+        track_drag(event_type, position, EventMonitor::timestamp(event));
+
+        let payload = EventMonitor::to_mouse_event(event, &cloned_id)?;
+
+        let Some((throttle, flusher)) = &throttle else {
+            emit_to_owner(&window, &payload);
+            return None;
         };
+        let continuous = payload.event_type.is_continuous();
+        let now_ms = started.elapsed().as_millis() as u64;
+        let throttled = throttle.lock().unwrap().push(payload, continuous, now_ms);
 
-        let _ = window.emit("nsevent_on_event", payload);
+        throttled
+            .send
+            .iter()
+            .for_each(|payload| emit_to_owner(&window, payload));
+        if let Some(delay) = throttled.flush_in_ms {
+            let _ = flusher.send(Instant::now() + Duration::from_millis(delay));
+        }
         None
-    });
+    })
+    .ok_or_else(|| SnapError::platform("failed to install the global event monitor"))?;
 
-    unsafe {
-        GLOBAL_MONITORS.push(MonitorListeners {
-            id: instance_id.clone(),
-            monitor: Some(monitor),
-        });
-    }
-    return instance_id;
+    event_monitor::register(instance_id.clone(), &owner, monitor);
+    Ok(instance_id)
 }
@@ -1,13 +1,15 @@
-use std::sync::Mutex;
-
 use crate::{
     data::{
+        error::{SnapError, SnapResult},
         frame::{Frame, Point, Size},
         screen::Screen,
     },
-    extensions::notification::NSNotificationCenter,
+    extensions::{
+        hooks::{self, HookEvent},
+        notification::{self, NotificationCenter},
+    },
 };
-use tauri::{command, AppHandle, Emitter};
+use tauri::{command, Emitter, Manager, Webview};
 
 use cocoa::{
     self,
@@ -16,6 +18,8 @@ use cocoa::{
     foundation::NSArray,
 };
 
+const SCREENS_CHANGED: &str = "NSApplicationDidChangeScreenParametersNotification";
+
 fn nsscreen_to_screen(nsscreen: id) -> Screen {
     unsafe {
         let frame = NSScreen::frame(nsscreen);
@@ -46,45 +50,59 @@ fn nsscreen_to_screen(nsscreen: id) -> Screen {
 }
 
 #[command]
-pub fn nsscreen_get_screens() -> Option<Vec<Screen>> {
+pub fn nsscreen_get_screens() -> SnapResult<Vec<Screen>> {
     unsafe {
         let screens = NSScreen::screens(nil);
+        if screens == nil {
+            return Err(SnapError::platform("the screen list is unavailable"));
+        }
         let mut frames: Vec<Screen> = vec![];
         (0..screens.count()).for_each(|i| {
             frames.push(nsscreen_to_screen(screens.objectAtIndex(i)));
         });
 
-        Some(frames)
+        Ok(frames)
     }
 }
 
+/// The screen with the key window, `mainScreen` is nil while no screen is connected
 #[command]
-pub fn nsscreen_main() -> Option<Screen> {
+pub fn nsscreen_main() -> SnapResult<Screen> {
     unsafe {
         let screen = NSScreen::mainScreen(nil);
+        if screen == nil {
+            return Err(SnapError::platform("there is no main screen"));
+        }
 
-        Some(nsscreen_to_screen(screen))
+        Ok(nsscreen_to_screen(screen))
     }
 }
 
-static mut GLOBAL_OBSERVER: Option<NSNotificationCenter> = None;
+/// Runs the `screens_changed` hooks, once for the whole app
+pub fn run_hooks_on_screen_change() {
+    notification::subscribe(
+        None,
+        &[(NotificationCenter::Default, SCREENS_CHANGED)],
+        |_| {
+            hooks::run(HookEvent::ScreensChanged {
+                screens: nsscreen_get_screens().unwrap_or_default(),
+            });
+        },
+    );
+}
 
+/// Emits `nsscreen_change` to the calling webview until unsubscribed with the
+/// returned id or the webview closes
 #[command]
-pub fn nsscreen_listen_change(app: AppHandle) {
-    if let Some(notification) = unsafe { &GLOBAL_OBSERVER } {
-        notification.stop();
-    }
-    let notification = NSNotificationCenter::new(String::from("NSNotificationCenter"));
-    let app = Mutex::new(app);
-    let notification = notification.listen(
-        String::from("NSApplicationDidChangeScreenParametersNotification"),
+pub fn nsscreen_listen_change(webview: Webview) -> u64 {
+    let label = webview.label().to_string();
+    let app = webview.app_handle().clone();
+
+    notification::subscribe(
+        Some(&label),
+        &[(NotificationCenter::Default, SCREENS_CHANGED)],
         move |_| {
-            let app = app.lock().unwrap();
-            let _ = app.emit("nsscreen_change", "");
+            let _ = app.emit_to(label.as_str(), "nsscreen_change", "");
         },
-    );
-
-    unsafe {
-        GLOBAL_OBSERVER = Some(notification);
-    }
+    )
 }
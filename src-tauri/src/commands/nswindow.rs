@@ -5,32 +5,44 @@ use cocoa::{
 };
 use tauri::{command, Window};
 
-use crate::{data::frame::Frame, extensions::windows::get_windows_on_screen};
+use crate::{
+    data::{
+        error::{SnapError, SnapResult},
+        frame::Frame,
+    },
+    extensions::windows::{get_windows_on_screen, WindowFilter, WindowInfo},
+};
 
 #[command]
-pub fn nswindow_set_frame(window: Window, frame: Frame) {
+pub fn nswindow_set_frame(window: Window, frame: Frame) -> SnapResult<()> {
+    let ns_window = window
+        .ns_window()
+        .map_err(|error| SnapError::platform(error.to_string()))?;
+
     unsafe {
-        let ns_window = window.ns_window();
-        match ns_window {
-            Ok(ns_window) => (ns_window as id).setFrame_display_(
-                NSRect {
-                    origin: NSPoint {
-                        x: frame.position.x,
-                        y: frame.position.y,
-                    },
-                    size: NSSize {
-                        width: frame.size.width,
-                        height: frame.size.height,
-                    },
+        (ns_window as id).setFrame_display_(
+            NSRect {
+                origin: NSPoint {
+                    x: frame.position.x,
+                    y: frame.position.y,
+                },
+                size: NSSize {
+                    width: frame.size.width,
+                    height: frame.size.height,
                 },
-                YES,
-            ),
-            Err(_) => {}
-        }
+            },
+            YES,
+        );
     }
+
+    Ok(())
 }
 
+/// Lists application windows, `filter` narrows them down by screen, app, size or role
 #[command]
-pub fn cgwindow_get_on_screen() -> Vec<crate::extensions::windows::WindowInfo> {
-    return get_windows_on_screen();
+pub fn cgwindow_get_on_screen(filter: Option<WindowFilter>) -> SnapResult<Vec<WindowInfo>> {
+    let filter = filter.unwrap_or_default();
+    filter.validate()?;
+
+    Ok(get_windows_on_screen(&filter))
 }
@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+
+use tauri::{command, AppHandle, Manager};
+
+use crate::{
+    data::error::{SnapError, SnapResult},
+    extensions::{
+        plugins::{self, LayoutInput, PluginInfo, PluginWindow, ABI_VERSION},
+        rpc_backend::AppBackend,
+        scripting::{self, WindowFrame},
+    },
+};
+
+/// `plugins` in the app config directory
+pub fn plugins_dir(app: &AppHandle) -> SnapResult<PathBuf> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join("plugins"))
+        .map_err(|error| SnapError::platform(error.to_string()))
+}
+
+/// Plugins found by the last discovery, with the reason the broken ones can't be used
+#[command]
+pub fn plugins_list() -> Vec<PluginInfo> {
+    plugins::list()
+}
+
+/// Loads the plugins directory again, for plugins added or rebuilt while Snap runs
+#[command]
+pub async fn plugins_reload(app: AppHandle) -> SnapResult<Vec<PluginInfo>> {
+    let dir = plugins_dir(&app)?;
+
+    tauri::async_runtime::spawn_blocking(move || plugins::discover(&dir))
+        .await
+        .map_err(|error| SnapError::platform(error.to_string()))?
+}
+
+/// Frames in screen coordinates by window id from `snap_layout` of the plugin, windows
+/// that are gone or left out by the plugin are missing. The screen is the one of the first window
+#[command]
+pub async fn plugin_layout(
+    app: AppHandle,
+    name: String,
+    window_ids: Vec<i64>,
+    gap: f64,
+) -> SnapResult<Vec<WindowFrame>> {
+    let backend = AppBackend::new(app);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let (windows, screen) = scripting::windows_and_screen(&backend, &window_ids)?;
+        let input = LayoutInput {
+            abi_version: ABI_VERSION,
+            visible_frame: screen.visible_frame,
+            gap,
+            windows: windows.iter().filter_map(PluginWindow::from_info).collect(),
+        };
+
+        plugins::layout(&name, &input)
+    })
+    .await
+    .map_err(|error| SnapError::platform(error.to_string()))?
+}
@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use tauri::{command, AppHandle};
+
+use crate::{
+    data::error::{SnapError, SnapResult},
+    extensions::{
+        rpc_backend::AppBackend,
+        scripting::{self, ScriptHost, ScriptSource, WindowFrame},
+    },
+};
+
+/// Replaces the scripts that shortcuts, links and arrange strategies can run,
+/// returns the errors of the ones that don't compile
+#[command]
+pub fn script_configure(scripts: Vec<ScriptSource>) -> Vec<SnapError> {
+    scripting::configure(scripts)
+}
+
+/// Compiles a script without keeping it, for the editor
+#[command]
+pub fn script_check(name: String, source: String) -> SnapResult<()> {
+    scripting::compile(&name, &source).map(|_| ())
+}
+
+#[command]
+pub async fn script_run(app: AppHandle, name: String) -> SnapResult<()> {
+    let host: Arc<dyn ScriptHost> = Arc::new(AppBackend::new(app));
+
+    tauri::async_runtime::spawn_blocking(move || scripting::run(&name, host))
+        .await
+        .map_err(|error| SnapError::platform(error.to_string()))?
+}
+
+/// Frames in CG coordinates by window id from `fn layout(windows, screen)` of the script,
+/// windows that are gone are missing. The screen is the one of the first window
+#[command]
+pub async fn script_layout(
+    app: AppHandle,
+    name: String,
+    window_ids: Vec<i64>,
+) -> SnapResult<Vec<WindowFrame>> {
+    let host: Arc<dyn ScriptHost> = Arc::new(AppBackend::new(app));
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let (windows, screen) = scripting::windows_and_screen(host.as_ref(), &window_ids)?;
+        scripting::layout(&name, host, windows, screen)
+    })
+    .await
+    .map_err(|error| SnapError::platform(error.to_string()))?
+}
@@ -0,0 +1,72 @@
+use serde::Deserialize;
+use tauri::command;
+
+use crate::{
+    data::{
+        error::{SnapError, SnapResult},
+        frame::Frame,
+    },
+    extensions::drag_velocity::{detect_throw, Throw, ThrowConfig, DRAG_VELOCITY},
+};
+
+#[derive(Deserialize)]
+pub struct ThrowOptions {
+    pub min_speed: Option<f64>,
+    pub sample_window_ms: Option<f64>,
+    pub projection_ms: Option<f64>,
+    pub edge_reach: Option<f64>,
+    pub corner_ratio: Option<f64>,
+}
+
+impl From<ThrowOptions> for ThrowConfig {
+    fn from(options: ThrowOptions) -> Self {
+        let default = ThrowConfig::default();
+        ThrowConfig {
+            min_speed: options.min_speed.unwrap_or(default.min_speed),
+            sample_window_ms: options.sample_window_ms.unwrap_or(default.sample_window_ms),
+            projection_ms: options.projection_ms.unwrap_or(default.projection_ms),
+            edge_reach: options.edge_reach.unwrap_or(default.edge_reach),
+            corner_ratio: options.corner_ratio.unwrap_or(default.corner_ratio),
+        }
+    }
+}
+
+/// Checks whether the last finished drag was a flick toward an edge or corner of `screen`
+#[command]
+pub fn snapping_detect_throw(
+    screen: Frame,
+    throw_options: Option<ThrowOptions>,
+) -> SnapResult<Option<Throw>> {
+    let config = throw_options.map(ThrowConfig::from).unwrap_or_default();
+    validate(&config)?;
+    let estimator = DRAG_VELOCITY.lock().unwrap();
+
+    Ok(detect_throw(&estimator, &screen, &config))
+}
+
+/// Thresholds come from the settings, a zero or negative one would throw every drag
+fn validate(config: &ThrowConfig) -> SnapResult<()> {
+    let positive = [
+        ("min_speed", config.min_speed),
+        ("sample_window_ms", config.sample_window_ms),
+        ("projection_ms", config.projection_ms),
+        ("edge_reach", config.edge_reach),
+    ];
+    if let Some((name, _)) = positive
+        .iter()
+        .find(|(_, value)| !value.is_finite() || *value <= 0.0)
+    {
+        return Err(SnapError::invalid_argument(
+            name,
+            "must be greater than zero",
+        ));
+    }
+    if !(0.0..=1.0).contains(&config.corner_ratio) {
+        return Err(SnapError::invalid_argument(
+            "corner_ratio",
+            "must be between 0 and 1",
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use std::collections::HashMap;
+
+use tauri::command;
+
+use crate::{
+    data::error::{SnapError, SnapResult},
+    extensions::{fuzzy::RankedMatch, window_finder::find_windows, windows::WindowInfo},
+};
+
+/// Windows ranked by fuzzy match of the query over their title and app name,
+/// with the matched ranges to highlight. An empty query lists recent windows first
+#[command]
+pub async fn window_finder_search(
+    query: String,
+    limit: Option<usize>,
+    app_priorities: Option<HashMap<String, f64>>,
+) -> SnapResult<Vec<RankedMatch<WindowInfo>>> {
+    let app_priorities = app_priorities.unwrap_or_default();
+
+    let mut found =
+        tauri::async_runtime::spawn_blocking(move || find_windows(&query, &app_priorities))
+            .await
+            .map_err(|error| SnapError::platform(error.to_string()))?;
+
+    if let Some(limit) = limit {
+        found.truncate(limit);
+    }
+
+    Ok(found)
+}
@@ -0,0 +1,32 @@
+use tauri::command;
+
+use crate::{
+    data::error::{SnapError, SnapResult},
+    extensions::{
+        focus_history::focus_window,
+        window_switcher::{switchable_windows, SwitcherItem},
+    },
+};
+
+/// Windows to show in the switcher, most recently used first.
+/// `icon_size` is the icon width and height in pixels
+#[command]
+pub async fn window_switcher_list(icon_size: Option<u32>) -> SnapResult<Vec<SwitcherItem>> {
+    let icon_size = icon_size.unwrap_or(64);
+    if !(16..=1024).contains(&icon_size) {
+        return Err(SnapError::invalid_argument(
+            "icon_size",
+            "must be between 16 and 1024",
+        ));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || switchable_windows(icon_size))
+        .await
+        .map_err(|error| SnapError::platform(error.to_string()))
+}
+
+/// Focuses the chosen window, restoring it if it is minimized
+#[command]
+pub fn window_switcher_focus(pid: i64, window_id: u32) -> SnapResult<()> {
+    focus_window(pid, window_id)
+}
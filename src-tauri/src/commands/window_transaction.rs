@@ -0,0 +1,37 @@
+use tauri::command;
+
+use crate::{
+    data::error::{SnapError, SnapResult},
+    extensions::{
+        animation::AnimationConfig,
+        window_transaction::{self, MoveResult, WindowMove},
+    },
+};
+
+use super::accessibility_element::AnimationOptions;
+
+/// Moves several windows at once. Without `animation_options` the frames are applied instantly.
+/// Resolves after every window has settled, with one result per move in the same order.
+#[command]
+pub async fn window_transaction_apply(
+    moves: Vec<WindowMove>,
+    animation_options: Option<AnimationOptions>,
+) -> SnapResult<Vec<MoveResult>> {
+    let config = animation_options.map(AnimationConfig::from);
+
+    tauri::async_runtime::spawn_blocking(move || window_transaction::apply(moves, config))
+        .await
+        .map_err(|error| SnapError::platform(error.to_string()))
+}
+
+/// Moves the windows of the last transaction back, `null` when there is nothing to undo
+#[command]
+pub async fn window_transaction_undo(
+    animation_options: Option<AnimationOptions>,
+) -> SnapResult<Option<Vec<MoveResult>>> {
+    let config = animation_options.map(AnimationConfig::from);
+
+    tauri::async_runtime::spawn_blocking(move || window_transaction::undo(config))
+        .await
+        .map_err(|error| SnapError::platform(error.to_string()))
+}
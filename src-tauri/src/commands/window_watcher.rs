@@ -0,0 +1,38 @@
+use tauri::{command, AppHandle, Emitter};
+
+use crate::{
+    data::error::{SnapError, SnapResult},
+    extensions::window_watcher::{self, WatcherOptions},
+};
+
+/// Starts emitting `window_created`, `window_destroyed`, `window_moved`, `window_resized`,
+/// `window_focused` and `app_activated` events, restarting the watcher with the new options
+#[command]
+pub fn window_watcher_start(app: AppHandle, options: Option<WatcherOptions>) -> SnapResult<()> {
+    let options = options.unwrap_or_default();
+    if options.interval_ms == 0 {
+        return Err(SnapError::invalid_argument(
+            "interval_ms",
+            "must be greater than zero",
+        ));
+    }
+
+    window_watcher::subscribe("frontend", move |event| {
+        let _ = app.emit(event.event_name(), event);
+    });
+    window_watcher::start(options);
+
+    Ok(())
+}
+
+/// Stops the events sent to the frontend, returns `false` when they weren't sent.
+/// The watcher only samples while someone listens
+#[command]
+pub fn window_watcher_stop() -> bool {
+    let was_listening = window_watcher::unsubscribe("frontend");
+    if !window_watcher::has_subscribers() {
+        window_watcher::stop();
+    }
+
+    was_listening
+}
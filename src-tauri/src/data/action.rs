@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+use super::frame::Frame;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowManagerMode {
+    Snapping,
+    FancyZones,
+}
+
+/// How `Arrange` lays the windows out
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArrangeStrategy {
+    /// Hand-picked layouts up to five windows, halving the largest space beyond that
+    #[default]
+    Split,
+    /// Rows and columns of equal cells
+    Grid,
+    /// `fn layout(windows, screen)` of the user script with this name
+    Script(String),
+    /// `snap_layout` of the wasm plugin with this name
+    Plugin(String),
+}
+
+/// Actions handled by the frontend stores, sent to the overlay as a `snap_action` event
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum SnapAction {
+    /// Runs a mapping on the focused window, `frame` is relative to the screen (0 to 1)
+    Mapping {
+        frame: Frame,
+    },
+    /// Arranges the windows of the current screen
+    Arrange {
+        #[serde(default)]
+        strategy: ArrangeStrategy,
+    },
+    SetMode {
+        mode: WindowManagerMode,
+    },
+    /// With `minutes` the Mac is kept awake for that long only
+    SetCaffeinate {
+        enabled: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        minutes: Option<u32>,
+    },
+    ToggleCaffeinate,
+    /// Runs the user script with this name
+    RunScript {
+        name: String,
+    },
+    /// Remembers the frames of the windows on screen under this name
+    SaveLayout {
+        name: String,
+    },
+    /// Moves the windows back to the layout saved under this name
+    RestoreLayout {
+        name: String,
+    },
+}
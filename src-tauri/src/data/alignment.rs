@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// Mirrors `Alignment` on the frontend: each axis is -1 (start), 0 (center) or 1 (end)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Alignment {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Alignment {
+    pub fn new(x: f64, y: f64) -> Self {
+        Alignment { x, y }
+    }
+}
@@ -0,0 +1,258 @@
+use serde::Serialize;
+
+/// sRGB color with 8-bit channels, serialized with its hex, HSL and CSS forms
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(into = "SerializedColor")]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    /// From 0 to 1
+    pub alpha: f64,
+}
+
+/// Hue in degrees, saturation and lightness in percent
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct Hsl {
+    pub hue: f64,
+    pub saturation: f64,
+    pub lightness: f64,
+}
+
+#[derive(Serialize)]
+struct SerializedColor {
+    red: u8,
+    green: u8,
+    blue: u8,
+    alpha: f64,
+    hex: String,
+    hsl: Hsl,
+    css: String,
+}
+
+impl From<Color> for SerializedColor {
+    fn from(color: Color) -> Self {
+        SerializedColor {
+            red: color.red,
+            green: color.green,
+            blue: color.blue,
+            alpha: color.alpha,
+            hex: color.to_hex(),
+            hsl: color.to_hsl(),
+            css: color.to_css(),
+        }
+    }
+}
+
+fn round_to(value: f64, digits: i32) -> f64 {
+    let scale = 10f64.powi(digits);
+    (value * scale).round() / scale
+}
+
+impl Color {
+    /// From sRGB components between 0 and 1, values outside are clamped
+    pub fn from_srgb(red: f64, green: f64, blue: f64, alpha: f64) -> Self {
+        let channel = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        Color {
+            red: channel(red),
+            green: channel(green),
+            blue: channel(blue),
+            alpha: round_to(alpha.clamp(0.0, 1.0), 3),
+        }
+    }
+
+    /// `#rrggbb`, or `#rrggbbaa` when not opaque
+    pub fn to_hex(self) -> String {
+        let hex = format!("#{:02x}{:02x}{:02x}", self.red, self.green, self.blue);
+        if self.alpha < 1.0 {
+            format!("{}{:02x}", hex, (self.alpha * 255.0).round() as u8)
+        } else {
+            hex
+        }
+    }
+
+    pub fn to_hsl(self) -> Hsl {
+        let [red, green, blue] = [self.red, self.green, self.blue].map(|c| c as f64 / 255.0);
+        let max = red.max(green).max(blue);
+        let min = red.min(green).min(blue);
+        let delta = max - min;
+        let lightness = (max + min) / 2.0;
+
+        let (hue, saturation) = if delta == 0.0 {
+            (0.0, 0.0)
+        } else {
+            let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+            let hue = if max == red {
+                ((green - blue) / delta).rem_euclid(6.0)
+            } else if max == green {
+                (blue - red) / delta + 2.0
+            } else {
+                (red - green) / delta + 4.0
+            };
+            (hue * 60.0, saturation)
+        };
+
+        Hsl {
+            hue: round_to(hue, 1),
+            saturation: round_to(saturation * 100.0, 1),
+            lightness: round_to(lightness * 100.0, 1),
+        }
+    }
+
+    /// `rgba(r, g, b, a)` with whole channels
+    pub fn to_css(self) -> String {
+        format!(
+            "rgba({}, {}, {}, {})",
+            self.red, self.green, self.blue, self.alpha
+        )
+    }
+}
+
+/// Semantic system colors, resolved for the current appearance
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Palette {
+    pub accent: Color,
+    /// Text selection color picked in System Settings
+    pub highlight: Color,
+    pub label: Color,
+    pub separator: Color,
+    pub window_background: Color,
+    pub selected_content: Color,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AppearanceMode {
+    Light,
+    Dark,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Appearance {
+    pub mode: AppearanceMode,
+    pub increase_contrast: bool,
+    pub palette: Palette,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(red: u8, green: u8, blue: u8) -> Color {
+        Color {
+            red,
+            green,
+            blue,
+            alpha: 1.0,
+        }
+    }
+
+    fn hsl(hue: f64, saturation: f64, lightness: f64) -> Hsl {
+        Hsl {
+            hue,
+            saturation,
+            lightness,
+        }
+    }
+
+    #[test]
+    fn from_srgb_rounds_and_clamps_the_channels() {
+        assert_eq!(Color::from_srgb(0.0, 0.5, 1.0, 1.0), rgb(0, 128, 255));
+        assert_eq!(Color::from_srgb(-0.2, 1.3, 0.2, 2.0), rgb(0, 255, 51));
+
+        let translucent = Color::from_srgb(1.0, 1.0, 1.0, 0.33333);
+        assert_eq!(translucent.alpha, 0.333);
+        assert_eq!(Color::from_srgb(0.0, 0.0, 0.0, -1.0).alpha, 0.0);
+    }
+
+    #[test]
+    fn hex_has_alpha_only_when_translucent() {
+        assert_eq!(rgb(0, 122, 255).to_hex(), "#007aff");
+        assert_eq!(rgb(255, 255, 255).to_hex(), "#ffffff");
+        assert_eq!(
+            Color {
+                alpha: 0.5,
+                ..rgb(0, 122, 255)
+            }
+            .to_hex(),
+            "#007aff80"
+        );
+        assert_eq!(
+            Color {
+                alpha: 0.0,
+                ..rgb(0, 0, 0)
+            }
+            .to_hex(),
+            "#00000000"
+        );
+    }
+
+    #[test]
+    fn greys_have_no_hue_or_saturation() {
+        assert_eq!(rgb(0, 0, 0).to_hsl(), hsl(0.0, 0.0, 0.0));
+        assert_eq!(rgb(128, 128, 128).to_hsl(), hsl(0.0, 0.0, 50.2));
+        assert_eq!(rgb(255, 255, 255).to_hsl(), hsl(0.0, 0.0, 100.0));
+    }
+
+    #[test]
+    fn hue_follows_the_largest_channel() {
+        let sectors = [
+            (rgb(255, 0, 0), hsl(0.0, 100.0, 50.0)),
+            (rgb(255, 128, 0), hsl(30.1, 100.0, 50.0)),
+            (rgb(255, 255, 0), hsl(60.0, 100.0, 50.0)),
+            (rgb(0, 255, 0), hsl(120.0, 100.0, 50.0)),
+            (rgb(0, 255, 255), hsl(180.0, 100.0, 50.0)),
+            (rgb(0, 0, 255), hsl(240.0, 100.0, 50.0)),
+            (rgb(255, 0, 255), hsl(300.0, 100.0, 50.0)),
+            // Red is the largest and blue above green, wraps around below 360
+            (rgb(255, 0, 128), hsl(329.9, 100.0, 50.0)),
+        ];
+
+        for (color, expected) in sectors {
+            assert_eq!(color.to_hsl(), expected, "{:?}", color);
+        }
+    }
+
+    #[test]
+    fn saturation_depends_on_lightness() {
+        assert_eq!(rgb(0, 122, 255).to_hsl(), hsl(211.3, 100.0, 50.0));
+        assert_eq!(rgb(191, 64, 64).to_hsl(), hsl(0.0, 49.8, 50.0));
+        assert_eq!(rgb(230, 200, 200).to_hsl(), hsl(0.0, 37.5, 84.3));
+    }
+
+    #[test]
+    fn css_keeps_whole_channels_and_the_alpha() {
+        assert_eq!(rgb(0, 122, 255).to_css(), "rgba(0, 122, 255, 1)");
+        assert_eq!(
+            Color {
+                alpha: 0.333,
+                ..rgb(10, 20, 30)
+            }
+            .to_css(),
+            "rgba(10, 20, 30, 0.333)"
+        );
+    }
+
+    #[test]
+    fn serializes_every_form() {
+        let value = serde_json::to_value(Color {
+            alpha: 0.5,
+            ..rgb(255, 0, 0)
+        })
+        .unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "red": 255,
+                "green": 0,
+                "blue": 0,
+                "alpha": 0.5,
+                "hex": "#ff000080",
+                "hsl": { "hue": 0.0, "saturation": 100.0, "lightness": 50.0 },
+                "css": "rgba(255, 0, 0, 0.5)",
+            })
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+
+use accessibility_sys::{kAXErrorAPIDisabled, kAXErrorInvalidUIElement, AXError};
+use core_graphics::window::CGWindowID;
+use serde::Serialize;
+
+/// Error returned by commands, serialized as `{ "kind": "...", ...details }`
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SnapError {
+    /// Snap isn't allowed to control the computer in the privacy settings
+    AccessibilityNotTrusted,
+    /// `window_id` is `None` when looking for the window of an application
+    WindowNotFound {
+        pid: i64,
+        window_id: Option<CGWindowID>,
+    },
+    /// An accessibility call failed with the given `AXError` code
+    AxError {
+        code: AXError,
+        operation: String,
+    },
+    InvalidArgument {
+        argument: String,
+        reason: String,
+    },
+    /// Snap doesn't move its own windows through accessibility
+    OwnWindowRefused,
+    /// A system API was unavailable or failed without an error code
+    Platform {
+        message: String,
+    },
+    /// A user script failed to compile or run, `line` and `column` start at 1
+    Script {
+        script: String,
+        message: String,
+        line: Option<usize>,
+        column: Option<usize>,
+    },
+    /// A wasm plugin failed to load or returned something unusable
+    Plugin {
+        plugin: String,
+        message: String,
+    },
+}
+
+pub type SnapResult<T> = Result<T, SnapError>;
+
+impl SnapError {
+    /// Maps an `AXError` to the most specific error
+    #[allow(non_upper_case_globals)]
+    pub fn from_ax(code: AXError, operation: &str) -> Self {
+        match code {
+            kAXErrorAPIDisabled => SnapError::AccessibilityNotTrusted,
+            _ => SnapError::AxError {
+                code,
+                operation: String::from(operation),
+            },
+        }
+    }
+
+    /// Like `from_ax`, but a dead element means the window is gone
+    #[allow(non_upper_case_globals)]
+    pub fn from_window_ax(code: AXError, operation: &str, pid: i64, window_id: CGWindowID) -> Self {
+        match code {
+            kAXErrorInvalidUIElement => SnapError::WindowNotFound {
+                pid,
+                window_id: Some(window_id),
+            },
+            _ => SnapError::from_ax(code, operation),
+        }
+    }
+
+    pub fn invalid_argument(argument: &str, reason: impl Into<String>) -> Self {
+        SnapError::InvalidArgument {
+            argument: String::from(argument),
+            reason: reason.into(),
+        }
+    }
+
+    pub fn platform(message: impl Into<String>) -> Self {
+        SnapError::Platform {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SnapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapError::AccessibilityNotTrusted => write!(f, "accessibility access is not granted"),
+            SnapError::WindowNotFound {
+                pid,
+                window_id: Some(window_id),
+            } => write!(f, "window {} of process {} not found", window_id, pid),
+            SnapError::WindowNotFound {
+                pid,
+                window_id: None,
+            } => write!(f, "process {} has no matching window", pid),
+            SnapError::AxError { code, operation } => {
+                write!(f, "{} failed with AXError {}", operation, code)
+            }
+            SnapError::InvalidArgument { argument, reason } => {
+                write!(f, "invalid {}: {}", argument, reason)
+            }
+            SnapError::OwnWindowRefused => write!(f, "refusing to move a window of Snap itself"),
+            SnapError::Platform { message } => write!(f, "{}", message),
+            SnapError::Script {
+                script,
+                message,
+                line: Some(line),
+                column,
+            } => write!(
+                f,
+                "{} line {}:{}: {}",
+                script,
+                line,
+                column.unwrap_or(1),
+                message
+            ),
+            SnapError::Script {
+                script, message, ..
+            } => write!(f, "{}: {}", script, message),
+            SnapError::Plugin { plugin, message } => {
+                write!(f, "plugin {}: {}", plugin, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapError {}
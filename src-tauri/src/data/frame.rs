@@ -1,23 +1,61 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Frame {
     pub size: Size,
     pub position: Point,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Point {
     pub x: f64,
     pub y: f64,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Size {
     pub width: f64,
     pub height: f64,
 }
 
+impl Frame {
+    /// Whether every edge of both frames is within `tolerance` points
+    pub fn approx_eq(&self, other: &Frame, tolerance: f64) -> bool {
+        (self.position.x - other.position.x).abs() <= tolerance
+            && (self.position.y - other.position.y).abs() <= tolerance
+            && (self.size.width - other.size.width).abs() <= tolerance
+            && (self.size.height - other.size.height).abs() <= tolerance
+    }
+
+    /// Snaps the frame to whole points
+    pub fn rounded(&self) -> Frame {
+        Frame {
+            size: Size {
+                width: self.size.width.round(),
+                height: self.size.height.round(),
+            },
+            position: Point {
+                x: self.position.x.round(),
+                y: self.position.y.round(),
+            },
+        }
+    }
+
+    /// Every value is finite and the frame has an area, what a window can be given
+    pub fn is_placeable(&self) -> bool {
+        [
+            self.position.x,
+            self.position.y,
+            self.size.width,
+            self.size.height,
+        ]
+        .iter()
+        .all(|value| value.is_finite())
+            && self.size.width > 0.0
+            && self.size.height > 0.0
+    }
+}
+
 impl Default for Frame {
     fn default() -> Self {
         Frame {
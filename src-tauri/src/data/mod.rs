@@ -1,4 +1,9 @@
+pub mod action;
+pub mod alignment;
+pub mod color;
+pub mod error;
 pub mod frame;
 pub mod mouse;
+pub mod rpc;
 pub mod screen;
 pub mod window_info;
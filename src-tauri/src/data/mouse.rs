@@ -2,9 +2,432 @@ use serde::{Deserialize, Serialize};
 
 use super::frame::Point;
 
+/// What a global monitor sends for every event
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct MouseEvent {
     pub monitor_id: String,
     pub point: Point,
-    pub event_type: u64,
+    pub event_type: EventKind,
+    pub modifiers: Modifiers,
+    /// 0 is the left button, 1 the right one, set for clicks and drags
+    pub button: Option<i64>,
+    /// Set for mouse down and up events
+    pub click_count: Option<i64>,
+    /// Set for scroll wheel events
+    pub scroll: Option<ScrollDelta>,
+    /// Set for key and modifier events
+    pub key: Option<KeyInfo>,
+    /// Seconds since the system started
+    pub timestamp: f64,
+}
+
+/// Modifier keys held while the event happened
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub option: bool,
+    pub command: bool,
+    pub caps_lock: bool,
+    pub function: bool,
+}
+
+impl Modifiers {
+    /// From `NSEventModifierFlags`
+    pub fn from_flags(flags: u64) -> Self {
+        let has = |bit: u32| flags & (1 << bit) != 0;
+
+        Modifiers {
+            caps_lock: has(16),
+            shift: has(17),
+            control: has(18),
+            option: has(19),
+            command: has(20),
+            function: has(23),
+        }
+    }
+
+    /// Whether no shortcut key is held, caps lock and fn don't count
+    pub fn is_empty(&self) -> bool {
+        !(self.shift || self.control || self.option || self.command)
+    }
+
+    /// Same shortcut keys held, caps lock and fn are ignored
+    pub fn matches(&self, other: &Modifiers) -> bool {
+        self.shift == other.shift
+            && self.control == other.control
+            && self.option == other.option
+            && self.command == other.command
+    }
+}
+
+/// Where a trackpad or Magic Mouse gesture is, plain wheels are always `none`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrollPhase {
+    None,
+    MayBegin,
+    Began,
+    Stationary,
+    Changed,
+    Ended,
+    Cancelled,
+}
+
+impl ScrollPhase {
+    /// From `NSEventPhase`
+    pub fn from_raw(phase: u64) -> Self {
+        match phase {
+            1 => ScrollPhase::Began,
+            2 => ScrollPhase::Stationary,
+            4 => ScrollPhase::Changed,
+            8 => ScrollPhase::Ended,
+            16 => ScrollPhase::Cancelled,
+            32 => ScrollPhase::MayBegin,
+            _ => ScrollPhase::None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScrollDelta {
+    pub delta_x: f64,
+    pub delta_y: f64,
+    /// Points from a trackpad, lines from a plain wheel otherwise
+    pub precise: bool,
+    pub phase: ScrollPhase,
+    /// Phase of the inertia scrolling after the fingers are lifted
+    pub momentum_phase: ScrollPhase,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct KeyInfo {
+    /// Virtual key code, independent of the keyboard layout
+    pub key_code: u16,
+    /// `None` for modifier changes
+    pub characters: Option<String>,
+    pub characters_ignoring_modifiers: Option<String>,
+    pub is_repeat: bool,
+}
+
+/// Event types a monitor can subscribe to
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    LeftMouseDown,
+    LeftMouseUp,
+    LeftMouseDragged,
+    RightMouseDown,
+    RightMouseUp,
+    RightMouseDragged,
+    OtherMouseDown,
+    OtherMouseUp,
+    OtherMouseDragged,
+    MouseMoved,
+    ScrollWheel,
+    KeyDown,
+    KeyUp,
+    FlagsChanged,
+}
+
+impl EventKind {
+    const ALL: [EventKind; 14] = [
+        EventKind::LeftMouseDown,
+        EventKind::LeftMouseUp,
+        EventKind::LeftMouseDragged,
+        EventKind::RightMouseDown,
+        EventKind::RightMouseUp,
+        EventKind::RightMouseDragged,
+        EventKind::OtherMouseDown,
+        EventKind::OtherMouseUp,
+        EventKind::OtherMouseDragged,
+        EventKind::MouseMoved,
+        EventKind::ScrollWheel,
+        EventKind::KeyDown,
+        EventKind::KeyUp,
+        EventKind::FlagsChanged,
+    ];
+
+    /// `NSEventType` value, its bit in an event mask is `1 << value`
+    pub fn event_type(&self) -> u64 {
+        match self {
+            EventKind::LeftMouseDown => 1,
+            EventKind::LeftMouseUp => 2,
+            EventKind::RightMouseDown => 3,
+            EventKind::RightMouseUp => 4,
+            EventKind::MouseMoved => 5,
+            EventKind::LeftMouseDragged => 6,
+            EventKind::RightMouseDragged => 7,
+            EventKind::KeyDown => 10,
+            EventKind::KeyUp => 11,
+            EventKind::FlagsChanged => 12,
+            EventKind::ScrollWheel => 22,
+            EventKind::OtherMouseDown => 25,
+            EventKind::OtherMouseUp => 26,
+            EventKind::OtherMouseDragged => 27,
+        }
+    }
+
+    pub fn from_event_type(event_type: u64) -> Option<Self> {
+        EventKind::ALL
+            .into_iter()
+            .find(|kind| kind.event_type() == event_type)
+    }
+
+    /// Moves and drags, only the latest of these matters
+    pub fn is_continuous(&self) -> bool {
+        matches!(
+            self,
+            EventKind::MouseMoved
+                | EventKind::LeftMouseDragged
+                | EventKind::RightMouseDragged
+                | EventKind::OtherMouseDragged
+        )
+    }
+
+    /// Clicks and drags, the only ones that have a button number
+    pub fn has_button(&self) -> bool {
+        self.is_click()
+            || matches!(
+                self,
+                EventKind::LeftMouseDragged
+                    | EventKind::RightMouseDragged
+                    | EventKind::OtherMouseDragged
+            )
+    }
+
+    pub fn is_key(&self) -> bool {
+        matches!(
+            self,
+            EventKind::KeyDown | EventKind::KeyUp | EventKind::FlagsChanged
+        )
+    }
+
+    /// Only these have a click count
+    pub fn is_click(&self) -> bool {
+        matches!(
+            self,
+            EventKind::LeftMouseDown
+                | EventKind::LeftMouseUp
+                | EventKind::RightMouseDown
+                | EventKind::RightMouseUp
+                | EventKind::OtherMouseDown
+                | EventKind::OtherMouseUp
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MonitorOptions {
+    pub events: Vec<EventKind>,
+    /// At most one move or drag per interval, clicks, keys and scrolls always go out
+    #[serde(default)]
+    pub throttle_ms: Option<u64>,
+    /// Send the latest held back move or drag once the interval is over instead of
+    /// dropping it
+    #[serde(default = "default_coalesce")]
+    pub coalesce: bool,
+}
+
+fn default_coalesce() -> bool {
+    true
+}
+
+impl MonitorOptions {
+    pub fn mask(&self) -> u64 {
+        self.events
+            .iter()
+            .fold(0, |mask, kind| mask | 1 << kind.event_type())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `NSEventModifierFlags` masks from AppKit
+    const CAPS_LOCK: u64 = 1 << 16;
+    const SHIFT: u64 = 1 << 17;
+    const CONTROL: u64 = 1 << 18;
+    const OPTION: u64 = 1 << 19;
+    const COMMAND: u64 = 1 << 20;
+    const FUNCTION: u64 = 1 << 23;
+
+    #[test]
+    fn modifiers_read_the_appkit_flags() {
+        let single = [
+            (
+                CAPS_LOCK,
+                Modifiers {
+                    caps_lock: true,
+                    ..Modifiers::default()
+                },
+            ),
+            (
+                SHIFT,
+                Modifiers {
+                    shift: true,
+                    ..Modifiers::default()
+                },
+            ),
+            (
+                CONTROL,
+                Modifiers {
+                    control: true,
+                    ..Modifiers::default()
+                },
+            ),
+            (
+                OPTION,
+                Modifiers {
+                    option: true,
+                    ..Modifiers::default()
+                },
+            ),
+            (
+                COMMAND,
+                Modifiers {
+                    command: true,
+                    ..Modifiers::default()
+                },
+            ),
+            (
+                FUNCTION,
+                Modifiers {
+                    function: true,
+                    ..Modifiers::default()
+                },
+            ),
+        ];
+        for (flags, modifiers) in single {
+            assert_eq!(Modifiers::from_flags(flags), modifiers, "{:#x}", flags);
+        }
+
+        // Device dependent bits below 16 are ignored
+        assert_eq!(Modifiers::from_flags(0xffff), Modifiers::default());
+        assert_eq!(
+            Modifiers::from_flags(CONTROL | COMMAND | 0x108),
+            Modifiers {
+                control: true,
+                command: true,
+                ..Modifiers::default()
+            }
+        );
+    }
+
+    #[test]
+    fn caps_lock_and_fn_are_not_shortcut_keys() {
+        let locked = Modifiers::from_flags(CAPS_LOCK | FUNCTION);
+        assert!(locked.is_empty());
+
+        let shortcut = Modifiers::from_flags(CONTROL | OPTION);
+        assert!(!shortcut.is_empty());
+        assert!(shortcut.matches(&Modifiers::from_flags(CONTROL | OPTION | CAPS_LOCK)));
+        assert!(!shortcut.matches(&Modifiers::from_flags(CONTROL)));
+        assert!(!shortcut.matches(&Modifiers::from_flags(CONTROL | OPTION | SHIFT)));
+    }
+
+    #[test]
+    fn scroll_phases_read_the_appkit_values() {
+        let phases = [
+            (0, ScrollPhase::None),
+            (1, ScrollPhase::Began),
+            (2, ScrollPhase::Stationary),
+            (4, ScrollPhase::Changed),
+            (8, ScrollPhase::Ended),
+            (16, ScrollPhase::Cancelled),
+            (32, ScrollPhase::MayBegin),
+            (3, ScrollPhase::None),
+            (64, ScrollPhase::None),
+        ];
+
+        for (raw, phase) in phases {
+            assert_eq!(ScrollPhase::from_raw(raw), phase, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn event_kinds_keep_their_appkit_values() {
+        let types = [
+            (EventKind::LeftMouseDown, 1),
+            (EventKind::LeftMouseUp, 2),
+            (EventKind::RightMouseDown, 3),
+            (EventKind::RightMouseUp, 4),
+            (EventKind::MouseMoved, 5),
+            (EventKind::LeftMouseDragged, 6),
+            (EventKind::RightMouseDragged, 7),
+            (EventKind::KeyDown, 10),
+            (EventKind::KeyUp, 11),
+            (EventKind::FlagsChanged, 12),
+            (EventKind::ScrollWheel, 22),
+            (EventKind::OtherMouseDown, 25),
+            (EventKind::OtherMouseUp, 26),
+            (EventKind::OtherMouseDragged, 27),
+        ];
+        assert_eq!(types.len(), EventKind::ALL.len());
+
+        for (kind, event_type) in types {
+            assert_eq!(kind.event_type(), event_type, "{:?}", kind);
+            assert_eq!(EventKind::from_event_type(event_type), Some(kind));
+        }
+        assert_eq!(EventKind::from_event_type(0), None);
+        assert_eq!(EventKind::from_event_type(9), None);
+    }
+
+    #[test]
+    fn serde_names_are_snake_case() {
+        let names = serde_json::to_value([
+            EventKind::LeftMouseDown,
+            EventKind::OtherMouseDragged,
+            EventKind::ScrollWheel,
+            EventKind::FlagsChanged,
+        ])
+        .unwrap();
+        assert_eq!(
+            names,
+            serde_json::json!([
+                "left_mouse_down",
+                "other_mouse_dragged",
+                "scroll_wheel",
+                "flags_changed"
+            ])
+        );
+
+        let phases = serde_json::to_value([ScrollPhase::MayBegin, ScrollPhase::None]).unwrap();
+        assert_eq!(phases, serde_json::json!(["may_begin", "none"]));
+
+        let kind: EventKind = serde_json::from_str("\"right_mouse_up\"").unwrap();
+        assert_eq!(kind, EventKind::RightMouseUp);
+    }
+
+    #[test]
+    fn mask_sets_the_bit_of_every_event() {
+        let options = MonitorOptions {
+            events: vec![
+                EventKind::LeftMouseDown,
+                EventKind::LeftMouseDragged,
+                EventKind::ScrollWheel,
+                EventKind::LeftMouseDown,
+            ],
+            throttle_ms: None,
+            coalesce: true,
+        };
+        assert_eq!(options.mask(), (1 << 1) | (1 << 6) | (1 << 22));
+
+        let none = MonitorOptions {
+            events: vec![],
+            ..options
+        };
+        assert_eq!(none.mask(), 0);
+    }
+
+    #[test]
+    fn monitor_options_coalesce_by_default() {
+        let options: MonitorOptions =
+            serde_json::from_str(r#"{"events": ["mouse_moved"]}"#).unwrap();
+
+        assert_eq!(options.events, vec![EventKind::MouseMoved]);
+        assert_eq!(options.throttle_ms, None);
+        assert!(options.coalesce);
+    }
 }
@@ -0,0 +1,114 @@
+//! JSON-RPC 2.0 messages of the control socket, one JSON document per line.
+//! Also compiled into `snapctl`, so nothing outside of serde is used here
+
+use std::{env, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const JSONRPC_VERSION: &str = "2.0";
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// The command failed, `data` holds the `SnapError`
+pub const SNAP_ERROR: i64 = -32000;
+
+pub mod methods {
+    /// Params: window filter, all fields optional
+    pub const LIST_WINDOWS: &str = "windows.list";
+    pub const LIST_SCREENS: &str = "screens.list";
+    /// Params: `{ pid, window_id }`, the focused window when omitted
+    pub const GET_FRAME: &str = "window.get_frame";
+    /// Params: `{ pid, window_id, frame, anchor }`, the focused window without `pid`
+    pub const SET_FRAME: &str = "window.set_frame";
+    /// Params: `{ frame }` relative to the screen
+    pub const RUN_MAPPING: &str = "mapping.run";
+    pub const ARRANGE: &str = "windows.arrange";
+    /// Params: `{ mode }`, `snapping` or `fancy_zones`
+    pub const SET_MODE: &str = "mode.set";
+    /// Params: `{ enabled }`, toggles when omitted
+    pub const SET_CAFFEINATE: &str = "caffeinate.set";
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    /// Notifications have no id and get no response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    pub id: Value,
+    /// `null` results deserialize as `None`, check `error` to tell success apart
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl RpcRequest {
+    pub fn new(id: u64, method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: String::from(JSONRPC_VERSION),
+            id: Some(Value::from(id)),
+            method: String::from(method),
+            params,
+        }
+    }
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl RpcResponse {
+    pub fn success(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: String::from(JSONRPC_VERSION),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn failure(id: Value, error: RpcError) -> Self {
+        Self {
+            jsonrpc: String::from(JSONRPC_VERSION),
+            id,
+            result: None,
+            error: Some(error),
+        }
+    }
+}
+
+extern "C" {
+    fn getuid() -> u32;
+}
+
+/// `$TMPDIR/snap-<uid>.sock`, `TMPDIR` is already private to the user on macOS
+pub fn socket_path() -> PathBuf {
+    let uid = unsafe { getuid() };
+    env::temp_dir().join(format!("snap-{}.sock", uid))
+}
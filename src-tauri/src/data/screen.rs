@@ -1,7 +1,7 @@
 use super::frame::Frame;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Screen {
     pub visible_frame: Frame,
     pub frame: Frame,
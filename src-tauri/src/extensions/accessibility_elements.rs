@@ -3,84 +3,173 @@ use std::{
     ffi::c_void,
     path::PathBuf,
     ptr,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
-    },
-    thread,
-    time::Duration,
+    sync::{Arc, Mutex},
 };
 
 use once_cell::sync::Lazy;
 
-use super::helpers::FromCgRect;
+use super::{
+    animation::{
+        completed, AnimationCompletion, AnimationConfig, AnimationEvent, AnimationPhase,
+        AnimationRequest, AnimationScheduler, FrameApplier, SystemClock,
+    },
+    helpers::FromCgRect,
+    placement::{place, realign_window, resolve_anchor},
+};
+
+/// Called on the scheduler thread with the frame a window settled on once its animation
+/// finished, not called when it is cancelled or replaced
+pub type OnSettled = Box<dyn FnOnce(&Frame) + Send>;
 
-/// Thread-safe wrapper for AXUIElementRef
-#[derive(Clone, Copy)]
-pub struct SafeAXUIElement {
-    process_id: i32,
-    window_id: CGWindowID,
+/// Applies animation frames to a window resolved once when the animation starts
+struct AXFrameApplier {
+    window: AXUIElementRef,
+    last_frame: Frame,
+    /// Part of the target the window is pinned to if the app adjusts its size
+    anchor: Alignment,
+    on_settled: Option<OnSettled>,
 }
 
-impl SafeAXUIElement {
-    pub fn new(process_id: i32, window_id: CGWindowID) -> Self {
+impl AXFrameApplier {
+    fn new(
+        window: AXUIElementRef,
+        current_frame: Frame,
+        anchor: Alignment,
+        on_settled: Option<OnSettled>,
+    ) -> Self {
+        unsafe { CFRetain(window as CFTypeRef) };
+
         Self {
-            process_id,
-            window_id,
+            window,
+            last_frame: current_frame,
+            anchor,
+            on_settled,
         }
     }
+}
 
-    pub fn get_element(&self) -> Result<AXUIElementRef, ()> {
-        get_window_from_id(self.process_id as i64, self.window_id)
+// The element is retained for the lifetime of the applier and only used by the scheduler thread
+unsafe impl Send for AXFrameApplier {}
+
+impl Drop for AXFrameApplier {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.window as CFTypeRef) }
     }
 }
 
-unsafe impl Send for SafeAXUIElement {}
-unsafe impl Sync for SafeAXUIElement {}
+impl FrameApplier for AXFrameApplier {
+    fn apply(&mut self, frame: &Frame) {
+        let frame = frame.rounded();
+
+        // Skip the attribute that didn't change, every AX call is a round trip to the app.
+        // Failures are ignored: a window closed mid-animation just stops moving
+        if frame.position != self.last_frame.position {
+            let _ = set_position(
+                self.window,
+                CGPoint {
+                    x: frame.position.x,
+                    y: frame.position.y,
+                },
+            );
+        }
+        if frame.size != self.last_frame.size {
+            let _ = set_size(
+                self.window,
+                CGSize {
+                    width: frame.size.width,
+                    height: frame.size.height,
+                },
+            );
+        }
 
-/// Handle for managing animation lifecycle and cancellation
-pub struct AnimationHandle {
-    cancel_flag: Arc<AtomicBool>,
-}
+        self.last_frame = frame;
+    }
 
-impl AnimationHandle {
-    pub fn cancel(&self) {
-        self.cancel_flag.store(true, Ordering::SeqCst);
+    fn finish(&mut self, frame: &Frame) -> Frame {
+        let frame = match realign_window(self.window, frame, self.anchor) {
+            Ok(placement) => {
+                self.last_frame = placement.frame.clone();
+                placement.frame
+            }
+            Err(_) => frame.clone(),
+        };
+        if let Some(on_settled) = self.on_settled.take() {
+            on_settled(&frame);
+        }
+
+        frame
     }
 }
 
-impl Drop for AnimationHandle {
-    fn drop(&mut self) {
-        // Signal cancellation
-        self.cancel_flag.store(true, Ordering::SeqCst);
-        // Don't join threads in drop to avoid deadlocks - threads will clean up themselves
-        // when they check the cancel flag and exit naturally
-    }
+/// Scheduler shared by all window animations
+static ANIMATION_SCHEDULER: Lazy<Arc<AnimationScheduler>> = Lazy::new(|| {
+    let scheduler = Arc::new(AnimationScheduler::new(Arc::new(SystemClock::default())));
+    scheduler.set_listener(dispatch_animation_event);
+    scheduler.spawn();
+    scheduler
+});
+
+type AnimationListener = Arc<dyn Fn(AnimationPhase, &AnimationEvent) + Send + Sync>;
+
+struct AnimationSubscription {
+    /// Label of the webview that subscribed, `None` for the app itself
+    owner: Option<String>,
+    listener: AnimationListener,
+}
+
+#[derive(Default)]
+struct AnimationListeners {
+    next_id: u64,
+    subscriptions: HashMap<u64, AnimationSubscription>,
 }
 
-/// Global state for tracking active animations per window
-static ANIMATION_STATE: Lazy<Mutex<HashMap<CGWindowID, AnimationHandle>>> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
-use crate::{data::frame::Frame, extensions::windows::is_main_window};
+static ANIMATION_LISTENERS: Lazy<Mutex<AnimationListeners>> =
+    Lazy::new(|| Mutex::new(AnimationListeners::default()));
+
+/// Hands the scheduler events to every subscription, outside the registry lock
+fn dispatch_animation_event(phase: AnimationPhase, event: &AnimationEvent) {
+    let listeners: Vec<AnimationListener> = ANIMATION_LISTENERS
+        .lock()
+        .unwrap()
+        .subscriptions
+        .values()
+        .map(|subscription| subscription.listener.clone())
+        .collect();
+
+    for listener in listeners {
+        listener(phase, event);
+    }
+}
+use crate::{
+    data::{
+        alignment::Alignment,
+        error::{SnapError, SnapResult},
+        frame::{Frame, Point, Size},
+    },
+    extensions::windows::is_main_window,
+};
 use accessibility_sys::{
-    kAXErrorSuccess, kAXPositionAttribute, kAXSizeAttribute, kAXValueTypeCGPoint,
+    kAXErrorInvalidUIElement, kAXErrorSuccess, kAXFocusedWindowAttribute, kAXMainAttribute,
+    kAXPositionAttribute, kAXRaiseAction, kAXSizeAttribute, kAXValueTypeCGPoint,
     kAXValueTypeCGSize, kAXWindowsAttribute, AXError, AXUIElementCopyAttributeValue,
-    AXUIElementCreateApplication, AXUIElementRef, AXUIElementSetAttributeValue, AXValueCreate,
+    AXUIElementCreateApplication, AXUIElementPerformAction, AXUIElementRef,
+    AXUIElementSetAttributeValue, AXValueCreate,
 };
 use cocoa::{appkit::CGPoint, base::id, foundation::NSArray};
 use core_foundation::{
-    base::{CFGetTypeID, CFTypeID, CFTypeRef, TCFType, ToVoid},
+    base::{CFGetTypeID, CFRetain, CFTypeID, CFTypeRef, TCFType, ToVoid},
+    boolean::CFBoolean,
     dictionary::CFDictionaryGetTypeID,
     number::{
         CFBooleanGetTypeID, CFBooleanGetValue, CFNumberGetType, CFNumberGetTypeID,
         CFNumberGetValue, CFNumberRef, CFNumberType,
     },
-    string::{CFString, CFStringGetTypeID},
+    string::{CFString, CFStringGetTypeID, CFStringRef},
 };
 use core_graphics::base::boolean_t;
 use core_graphics::{display::*, geometry::CGSize};
 use objc2::{msg_send, runtime::AnyObject};
-use objc2_app_kit::NSWorkspace;
+use objc2_app_kit::{NSApplicationActivationOptions, NSRunningApplication, NSWorkspace};
 
 #[link(name = "CoreGraphics", kind = "framework")]
 extern "C" {
@@ -93,100 +182,232 @@ extern "C" {
 #[derive(Debug)]
 pub enum DictEntryValue {
     _Number(i64),
+    _Float(f64),
     _Bool(bool),
     _String(String),
     _Rect(Frame),
     _Unknown,
 }
 
-pub fn get_window_from_id(pid: i64, id: u32) -> Result<AXUIElementRef, ()> {
+/// AX elements of all windows of an application together with their window ids.
+/// The elements are retained for the caller, release them once done
+#[allow(non_upper_case_globals)]
+pub fn get_app_windows(pid: i64) -> SnapResult<Vec<(CGWindowID, AXUIElementRef)>> {
     let window_owner = unsafe { AXUIElementCreateApplication(pid as i32) };
 
     let mut windows_ref: CFTypeRef = ptr::null();
 
-    unsafe {
-        AXUIElementCopyAttributeValue(
+    let result = unsafe {
+        let result = AXUIElementCopyAttributeValue(
             window_owner,
             CFString::new(kAXWindowsAttribute).as_concrete_TypeRef(),
             &mut windows_ref as *mut CFTypeRef,
         );
+        CFRelease(window_owner as CFTypeRef);
+        result
+    };
+
+    if result != kAXErrorSuccess {
+        return Err(match result {
+            kAXErrorInvalidUIElement => SnapError::WindowNotFound {
+                pid,
+                window_id: None,
+            },
+            _ => SnapError::from_ax(result, "reading application windows"),
+        });
     }
 
     if windows_ref.is_null() {
-        return Err(());
+        return Ok(vec![]);
     }
 
     let applications_windows_nsarray = windows_ref as id;
+    let count = unsafe { NSArray::count(applications_windows_nsarray) };
 
-    let target_window_ax = {
-        let count = unsafe { NSArray::count(applications_windows_nsarray) };
-        let mut window_ax_option: Option<id> = None;
-        for i in 0..count {
+    let windows = (0..count)
+        .filter_map(|i| {
             let window_ax = unsafe { NSArray::objectAtIndex(applications_windows_nsarray, i) };
 
-            let window_id = {
-                let mut window_id: CGWindowID = 0;
-                if unsafe { _AXUIElementGetWindow(window_ax as AXUIElementRef, &mut window_id) }
-                    != kAXErrorSuccess
-                {
-                    continue;
-                }
-                window_id
-            };
-
-            if window_id == id {
-                window_ax_option = Some(window_ax);
-                break;
+            let mut window_id: CGWindowID = 0;
+            if unsafe { _AXUIElementGetWindow(window_ax as AXUIElementRef, &mut window_id) }
+                != kAXErrorSuccess
+            {
+                return None;
             }
+
+            // The array owns its elements, they have to outlive it
+            unsafe { CFRetain(window_ax as CFTypeRef) };
+            Some((window_id, window_ax as AXUIElementRef))
+        })
+        .collect();
+
+    unsafe { CFRelease(windows_ref) };
+
+    Ok(windows)
+}
+
+/// The element is retained for the caller, release it once done
+pub fn get_window_from_id(pid: i64, id: u32) -> SnapResult<AXUIElementRef> {
+    let not_found = SnapError::WindowNotFound {
+        pid,
+        window_id: Some(id),
+    };
+
+    let windows = get_app_windows(pid).map_err(|error| match error {
+        SnapError::WindowNotFound { .. } => not_found.clone(),
+        error => error,
+    })?;
+
+    let mut found = None;
+    for (window_id, window) in windows {
+        if found.is_none() && window_id == id {
+            found = Some(window);
+        } else {
+            unsafe { CFRelease(window as CFTypeRef) };
         }
-        window_ax_option
     }
-    .ok_or(())? as AXUIElementRef;
 
-    return Ok(target_window_ax);
+    found.ok_or(not_found)
 }
 
-#[derive(Clone)]
-pub struct AnimationConfig {
-    pub duration_ms: u64,
-    pub fps: u64,
+/// Reads a string attribute such as `AXRole`, `None` when the element doesn't have it
+pub fn get_string_attribute(element: AXUIElementRef, attribute: &str) -> Option<String> {
+    let mut value: CFTypeRef = ptr::null();
+
+    unsafe {
+        let result = AXUIElementCopyAttributeValue(
+            element,
+            CFString::new(attribute).as_concrete_TypeRef(),
+            &mut value as *mut CFTypeRef,
+        );
+        if result != kAXErrorSuccess || value.is_null() {
+            return None;
+        }
+        if CFGetTypeID(value) != CFStringGetTypeID() {
+            CFRelease(value);
+            return None;
+        }
+
+        Some(CFString::wrap_under_create_rule(value as CFStringRef).to_string())
+    }
 }
 
-impl Default for AnimationConfig {
-    fn default() -> Self {
-        Self {
-            duration_ms: 200,
-            fps: 60,
+/// Reads a boolean attribute such as `AXMinimized`, `None` when the element doesn't have it
+pub fn get_bool_attribute(element: AXUIElementRef, attribute: &str) -> Option<bool> {
+    let mut value: CFTypeRef = ptr::null();
+
+    unsafe {
+        let result = AXUIElementCopyAttributeValue(
+            element,
+            CFString::new(attribute).as_concrete_TypeRef(),
+            &mut value as *mut CFTypeRef,
+        );
+        if result != kAXErrorSuccess || value.is_null() {
+            return None;
         }
+
+        let flag = if CFGetTypeID(value) == CFBooleanGetTypeID() {
+            Some(CFBooleanGetValue(value.cast()))
+        } else {
+            None
+        };
+        CFRelease(value);
+
+        flag
     }
 }
 
-/// Easing functions for smooth animations
-#[derive(Clone, Copy)]
-pub enum EasingFunction {
-    Linear,
-    EaseOutCubic,
-    EaseInOutQuad,
-}
-
-impl EasingFunction {
-    fn apply(&self, t: f64) -> f64 {
-        match self {
-            EasingFunction::Linear => t,
-            EasingFunction::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
-            EasingFunction::EaseInOutQuad => {
-                if t < 0.5 {
-                    2.0 * t * t
-                } else {
-                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
-                }
-            }
+/// Window id of an AX window element, `None` for elements that aren't windows
+pub fn get_window_id(window: AXUIElementRef) -> Option<CGWindowID> {
+    let mut window_id: CGWindowID = 0;
+    let result = unsafe { _AXUIElementGetWindow(window, &mut window_id) };
+
+    (result == kAXErrorSuccess).then_some(window_id)
+}
+
+/// Id of the focused window of an application, `None` when it has none
+pub fn get_focused_window_id(pid: i64) -> Option<CGWindowID> {
+    let mut value: CFTypeRef = ptr::null();
+
+    unsafe {
+        let application = AXUIElementCreateApplication(pid as i32);
+        let result = AXUIElementCopyAttributeValue(
+            application,
+            CFString::new(kAXFocusedWindowAttribute).as_concrete_TypeRef(),
+            &mut value as *mut CFTypeRef,
+        );
+        CFRelease(application as CFTypeRef);
+        if result != kAXErrorSuccess || value.is_null() {
+            return None;
         }
+
+        let window_id = get_window_id(value as AXUIElementRef);
+        CFRelease(value);
+        window_id
+    }
+}
+
+#[allow(non_upper_case_globals)]
+pub fn set_bool_attribute(element: AXUIElementRef, attribute: &str, value: bool) -> SnapResult<()> {
+    let value = if value {
+        CFBoolean::true_value()
+    } else {
+        CFBoolean::false_value()
+    };
+    let result = unsafe {
+        AXUIElementSetAttributeValue(
+            element,
+            CFString::new(attribute).as_concrete_TypeRef(),
+            value.as_CFTypeRef(),
+        )
+    };
+
+    match result {
+        kAXErrorSuccess => Ok(()),
+        code => Err(SnapError::from_ax(code, &format!("setting {}", attribute))),
+    }
+}
+
+/// Brings the window in front of the other windows of its application and makes it main,
+/// the application itself has to be activated separately
+pub fn raise_window(window: AXUIElementRef) -> SnapResult<()> {
+    let result = unsafe {
+        AXUIElementPerformAction(window, CFString::new(kAXRaiseAction).as_concrete_TypeRef())
+    };
+    if result != kAXErrorSuccess {
+        return Err(SnapError::from_ax(result, "raising window"));
+    }
+
+    // Some apps only update their key window through the main attribute
+    let _ = set_bool_attribute(window, kAXMainAttribute, true);
+
+    Ok(())
+}
+
+/// Makes the application frontmost
+pub fn activate_application(pid: i64) -> SnapResult<()> {
+    let app = unsafe { NSRunningApplication::runningApplicationWithProcessIdentifier(pid as i32) }
+        .ok_or(SnapError::WindowNotFound {
+            pid,
+            window_id: None,
+        })?;
+
+    // Ignored since macOS 14, needed before it to take focus from the frontmost app
+    #[allow(deprecated)]
+    let options = NSApplicationActivationOptions::NSApplicationActivateIgnoringOtherApps;
+
+    if unsafe { app.activateWithOptions(options) } {
+        Ok(())
+    } else {
+        Err(SnapError::platform(format!(
+            "process {} refused activation",
+            pid
+        )))
     }
 }
 
 /// Get current window position
-fn get_position(window: AXUIElementRef) -> Result<CGPoint, ()> {
+fn get_position(window: AXUIElementRef) -> SnapResult<CGPoint> {
     let mut position_ref: CFTypeRef = ptr::null();
 
     unsafe {
@@ -196,8 +417,11 @@ fn get_position(window: AXUIElementRef) -> Result<CGPoint, ()> {
             &mut position_ref as *mut CFTypeRef,
         );
 
-        if result != kAXErrorSuccess || position_ref.is_null() {
-            return Err(());
+        if result != kAXErrorSuccess {
+            return Err(SnapError::from_ax(result, "reading window position"));
+        }
+        if position_ref.is_null() {
+            return Err(SnapError::platform("window has no position"));
         }
 
         let mut point = CGPoint { x: 0.0, y: 0.0 };
@@ -212,13 +436,13 @@ fn get_position(window: AXUIElementRef) -> Result<CGPoint, ()> {
             Ok(point)
         } else {
             CFRelease(position_ref);
-            Err(())
+            Err(SnapError::platform("window position is not a point"))
         }
     }
 }
 
 /// Get current window size
-fn get_size(window: AXUIElementRef) -> Result<CGSize, ()> {
+fn get_size(window: AXUIElementRef) -> SnapResult<CGSize> {
     let mut size_ref: CFTypeRef = ptr::null();
 
     unsafe {
@@ -228,8 +452,11 @@ fn get_size(window: AXUIElementRef) -> Result<CGSize, ()> {
             &mut size_ref as *mut CFTypeRef,
         );
 
-        if result != kAXErrorSuccess || size_ref.is_null() {
-            return Err(());
+        if result != kAXErrorSuccess {
+            return Err(SnapError::from_ax(result, "reading window size"));
+        }
+        if size_ref.is_null() {
+            return Err(SnapError::platform("window has no size"));
         }
 
         let mut size = CGSize {
@@ -247,294 +474,279 @@ fn get_size(window: AXUIElementRef) -> Result<CGSize, ()> {
             Ok(size)
         } else {
             CFRelease(size_ref);
-            Err(())
+            Err(SnapError::platform("window size is not a size"))
         }
     }
 }
 
 /// Immediately set window position without animation
-pub fn set_position(window: AXUIElementRef, mut point: CGPoint) {
+#[allow(non_upper_case_globals)]
+pub fn set_position(window: AXUIElementRef, mut point: CGPoint) -> SnapResult<()> {
     let ptr = &mut point as *mut _ as *mut c_void;
-    unsafe {
-        AXUIElementSetAttributeValue(
+    let result = unsafe {
+        let value = AXValueCreate(kAXValueTypeCGPoint, ptr);
+        let result = AXUIElementSetAttributeValue(
             window,
             CFString::new(kAXPositionAttribute).as_concrete_TypeRef(),
-            AXValueCreate(kAXValueTypeCGPoint, ptr).cast(),
+            value.cast(),
         );
+        CFRelease(value as CFTypeRef);
+        result
+    };
+
+    match result {
+        kAXErrorSuccess => Ok(()),
+        code => Err(SnapError::from_ax(code, "setting window position")),
     }
 }
 
 /// Immediately set window size without animation
-pub fn set_size(window: AXUIElementRef, mut size: CGSize) {
+#[allow(non_upper_case_globals)]
+pub fn set_size(window: AXUIElementRef, mut size: CGSize) -> SnapResult<()> {
     let ptr = &mut size as *mut _ as *mut c_void;
-    unsafe {
-        AXUIElementSetAttributeValue(
+    let result = unsafe {
+        let value = AXValueCreate(kAXValueTypeCGSize, ptr);
+        let result = AXUIElementSetAttributeValue(
             window,
             CFString::new(kAXSizeAttribute).as_concrete_TypeRef(),
-            AXValueCreate(kAXValueTypeCGSize, ptr).cast(),
+            value.cast(),
         );
+        CFRelease(value as CFTypeRef);
+        result
+    };
+
+    match result {
+        kAXErrorSuccess => Ok(()),
+        code => Err(SnapError::from_ax(code, "setting window size")),
     }
 }
 
+/// Immediately set window frame without animation.
+/// The size is applied before and after the position: apps keep windows inside the
+/// screen, so a large window can't be moved towards an edge before it shrinks
+pub fn set_frame(window: AXUIElementRef, frame: &Frame) -> SnapResult<()> {
+    let size = CGSize {
+        width: frame.size.width,
+        height: frame.size.height,
+    };
+
+    set_size(window, size)?;
+    set_position(
+        window,
+        CGPoint {
+            x: frame.position.x,
+            y: frame.position.y,
+        },
+    )?;
+    set_size(window, size)
+}
+
+/// Get current window frame
+pub fn get_frame(window: AXUIElementRef) -> SnapResult<Frame> {
+    let origin = get_position(window)?;
+    let size = get_size(window)?;
+
+    Ok(Frame::from_cg_rect(&CGRect { origin, size }))
+}
+
 /// Animate window position change
 pub fn set_position_animated(
     window: AXUIElementRef,
     target_point: CGPoint,
     config: AnimationConfig,
-) {
-    // Get window ID for tracking
-    let mut window_id: CGWindowID = 0;
-    if unsafe { _AXUIElementGetWindow(window, &mut window_id) } != kAXErrorSuccess {
-        // Fallback to immediate positioning if we can't get window ID
-        set_position(window, target_point);
-        return;
-    }
-
-    // Get process ID
-    let process_id = match get_process_id_from_window(window) {
-        Some(pid) => pid,
-        None => {
-            set_position(window, target_point);
-            return;
-        }
+) -> SnapResult<AnimationCompletion> {
+    let current = get_frame(window)?;
+    let mut target = current.clone();
+    target.position = Point {
+        x: target_point.x,
+        y: target_point.y,
     };
 
-    let safe_element = SafeAXUIElement::new(process_id, window_id);
-
-    // Capture start position once before animation
-    let start_point = get_position(window).unwrap_or(target_point);
-
-    animate_window_property(
-        window_id,
-        safe_element,
-        move |safe_window, progress, _| {
-            if let Ok(window) = safe_window.get_element() {
-                let eased_progress = EasingFunction::EaseInOutQuad.apply(progress);
-
-                let current_x = start_point.x + (target_point.x - start_point.x) * eased_progress;
-                let current_y = start_point.y + (target_point.y - start_point.y) * eased_progress;
-
-                let current_point = CGPoint {
-                    x: current_x,
-                    y: current_y,
-                };
-
-                set_position(window, current_point);
-            }
-        },
-        config,
-    );
+    Ok(animate_frame(window, current, target, config, None, None))
 }
 
 /// Animate window size change
-pub fn set_size_animated(window: AXUIElementRef, target_size: CGSize, config: AnimationConfig) {
-    // Get window ID for tracking
-    let mut window_id: CGWindowID = 0;
-    if unsafe { _AXUIElementGetWindow(window, &mut window_id) } != kAXErrorSuccess {
-        // Fallback to immediate sizing if we can't get window ID
-        set_size(window, target_size);
-        return;
-    }
-
-    // Get process ID
-    let process_id = match get_process_id_from_window(window) {
-        Some(pid) => pid,
-        None => {
-            set_size(window, target_size);
-            return;
-        }
+pub fn set_size_animated(
+    window: AXUIElementRef,
+    target_size: CGSize,
+    config: AnimationConfig,
+) -> SnapResult<AnimationCompletion> {
+    let current = get_frame(window)?;
+    let mut target = current.clone();
+    target.size = Size {
+        width: target_size.width,
+        height: target_size.height,
     };
 
-    let safe_element = SafeAXUIElement::new(process_id, window_id);
-
-    // Capture start size once before animation
-    let start_size = get_size(window).unwrap_or(target_size);
-
-    animate_window_property(
-        window_id,
-        safe_element,
-        move |safe_window, progress, _| {
-            if let Ok(window) = safe_window.get_element() {
-                let eased_progress = EasingFunction::EaseInOutQuad.apply(progress);
-
-                let current_width =
-                    start_size.width + (target_size.width - start_size.width) * eased_progress;
-                let current_height =
-                    start_size.height + (target_size.height - start_size.height) * eased_progress;
-
-                let current_size = CGSize {
-                    width: current_width,
-                    height: current_height,
-                };
-
-                set_size(window, current_size);
-            }
-        },
-        config,
-    );
+    Ok(animate_frame(window, current, target, config, None, None))
 }
 
-/// Animate both window position and size simultaneously
+/// Animate both window position and size simultaneously.
+/// `anchor` is the part of the target the window stays pinned to if the app refuses the size,
+/// `None` infers it from where the target sits on its screen.
 pub fn set_frame_animated(
     window: AXUIElementRef,
     target_point: CGPoint,
     target_size: CGSize,
     config: AnimationConfig,
-) {
-    // Get window ID and process ID for tracking
-    // Get window ID and process ID for tracking
-    let mut window_id: CGWindowID = 0;
-    if unsafe { _AXUIElementGetWindow(window, &mut window_id) } != kAXErrorSuccess {
-        // Fallback to immediate positioning and sizing
-        set_position(window, target_point);
-        set_size(window, target_size);
-        return;
-    }
+    anchor: Option<Alignment>,
+    on_settled: Option<OnSettled>,
+) -> SnapResult<AnimationCompletion> {
+    let current = get_frame(window)?;
+    let target = Frame::from_cg_rect(&CGRect {
+        origin: target_point,
+        size: target_size,
+    });
 
-    // Extract process ID from the current window
-    let process_id = match get_process_id_from_window(window) {
-        Some(pid) => pid,
-        None => {
-            set_position(window, target_point);
-            set_size(window, target_size);
-            return;
-        }
-    };
+    Ok(animate_frame(
+        window, current, target, config, anchor, on_settled,
+    ))
+}
 
-    let safe_element = SafeAXUIElement::new(process_id, window_id);
+/// Hands the animation over to the shared scheduler
+fn animate_frame(
+    window: AXUIElementRef,
+    from: Frame,
+    to: Frame,
+    config: AnimationConfig,
+    anchor: Option<Alignment>,
+    on_settled: Option<OnSettled>,
+) -> AnimationCompletion {
+    let anchor = resolve_anchor(&to, anchor);
 
-    // Capture start values once before animation
-    let start_point = get_position(window).unwrap_or(target_point);
-    let start_size = get_size(window).unwrap_or(target_size);
+    let mut window_id: CGWindowID = 0;
+    if unsafe { _AXUIElementGetWindow(window, &mut window_id) } != kAXErrorSuccess {
+        // Fallback to immediate positioning if the window can't be tracked
+        let frame = place(window, &to, anchor)
+            .map(|placement| placement.frame)
+            .unwrap_or(to);
+        if let Some(on_settled) = on_settled {
+            on_settled(&frame);
+        }
+        return completed(frame);
+    }
 
-    animate_window_property(
+    ANIMATION_SCHEDULER.start(
         window_id,
-        safe_element,
-        move |safe_window, progress, _| {
-            if let Ok(window) = safe_window.get_element() {
-                let eased_progress = EasingFunction::EaseInOutQuad.apply(progress);
-
-                // Animate position
-                let current_x = start_point.x + (target_point.x - start_point.x) * eased_progress;
-                let current_y = start_point.y + (target_point.y - start_point.y) * eased_progress;
-                let current_point = CGPoint {
-                    x: current_x,
-                    y: current_y,
-                };
-
-                // Animate size
-                let current_width =
-                    start_size.width + (target_size.width - start_size.width) * eased_progress;
-                let current_height =
-                    start_size.height + (target_size.height - start_size.height) * eased_progress;
-                let current_size = CGSize {
-                    width: current_width,
-                    height: current_height,
-                };
-
-                set_position(window, current_point);
-                set_size(window, current_size);
-            }
-        },
+        from.clone(),
+        to,
         config,
-    );
-}
-
-/// Helper function to get process ID from AXUIElementRef
-fn get_process_id_from_window(window: AXUIElementRef) -> Option<i32> {
-    let mut pid: i32 = 0;
-    let result = unsafe { accessibility_sys::AXUIElementGetPid(window, &mut pid) };
-    if result == kAXErrorSuccess {
-        Some(pid)
-    } else {
-        None
-    }
+        Box::new(AXFrameApplier::new(window, from, anchor, on_settled)),
+    )
 }
 
-/// Generic animation function that handles thread safety and cancellation
-fn animate_window_property<F>(
-    window_id: CGWindowID,
-    safe_window: SafeAXUIElement,
-    mut animation_fn: F,
+/// Animates several windows from their current frame, all animations start in the same tick.
+/// Results are returned in the order of `targets`.
+pub fn set_frames_animated(
+    targets: Vec<(AXUIElementRef, Frame, Option<Alignment>)>,
     config: AnimationConfig,
-) where
-    F: FnMut(SafeAXUIElement, f64, bool) + Send + Copy + 'static,
-{
-    // Cancel any existing animation for this window
-    let cancel_flag = Arc::new(AtomicBool::new(false));
+) -> Vec<SnapResult<AnimationCompletion>> {
+    let mut completions: Vec<SnapResult<AnimationCompletion>> = vec![];
+    let mut requests = vec![];
+    let mut scheduled = vec![];
+
+    for (index, (window, to, anchor)) in targets.into_iter().enumerate() {
+        let from = match get_frame(window) {
+            Ok(from) => from,
+            Err(error) => {
+                completions.push(Err(error));
+                continue;
+            }
+        };
 
-    {
-        let mut state = ANIMATION_STATE.lock().unwrap();
-        // Cancel existing animation if it exists
-        if let Some(existing) = state.get(&window_id) {
-            existing.cancel();
+        let mut window_id: CGWindowID = 0;
+        if unsafe { _AXUIElementGetWindow(window, &mut window_id) } != kAXErrorSuccess {
+            completions.push(Ok(animate_frame(
+                window,
+                from,
+                to,
+                config.clone(),
+                anchor,
+                None,
+            )));
+            continue;
         }
 
-        // Store the new animation handle immediately
-        let animation_handle = AnimationHandle {
-            cancel_flag: cancel_flag.clone(),
-        };
-        state.insert(window_id, animation_handle);
+        let anchor = resolve_anchor(&to, anchor);
+        requests.push(AnimationRequest {
+            window_id,
+            from: from.clone(),
+            to: to.clone(),
+            config: config.clone(),
+            applier: Box::new(AXFrameApplier::new(window, from, anchor, None)),
+        });
+        scheduled.push(index);
+        // Replaced by the scheduler's completion below
+        completions.push(Ok(completed(to)));
     }
 
-    let cancel_flag_clone = cancel_flag.clone();
-
-    thread::spawn(move || {
-        let total_frames = (config.duration_ms * config.fps / 1000).max(1);
-        let frame_duration = Duration::from_millis(1000 / config.fps);
-
-        for frame in 0..=total_frames {
-            // Check if animation should be cancelled
-            if cancel_flag_clone.load(Ordering::SeqCst) {
-                break;
-            }
-
-            let progress = frame as f64 / total_frames as f64;
-            let is_first_frame = frame == 0;
-
-            thread::spawn(move || {
-                animation_fn(safe_window, progress, is_first_frame);
-            });
-
-            if frame < total_frames {
-                thread::sleep(frame_duration);
-            }
-        }
+    for (index, completion) in scheduled
+        .into_iter()
+        .zip(ANIMATION_SCHEDULER.start_batch(requests))
+    {
+        completions[index] = Ok(completion);
+    }
 
-        // Remove this animation from the state when it completes or is cancelled
-        let mut state = ANIMATION_STATE.lock().unwrap();
-        // Only remove if it's still the same animation (check by cancel_flag address)
-        if let Some(current) = state.get(&window_id) {
-            if Arc::ptr_eq(&current.cancel_flag, &cancel_flag_clone) {
-                state.remove(&window_id);
-            }
-        }
-    });
+    completions
 }
 
 /// Cancel all active animations
 pub fn cancel_all_animations() {
-    let mut state = ANIMATION_STATE.lock().unwrap();
-    // Cancel all animations before clearing
-    for (_, handle) in state.iter() {
-        handle.cancel();
-    }
-    state.clear();
+    ANIMATION_SCHEDULER.cancel_all();
 }
 
 /// Cancel animation for a specific window
 pub fn cancel_window_animation(window_id: CGWindowID) {
-    let mut state = ANIMATION_STATE.lock().unwrap();
-    if let Some(handle) = state.get(&window_id) {
-        handle.cancel();
-    }
-    state.remove(&window_id);
+    ANIMATION_SCHEDULER.cancel(window_id);
+}
+
+/// Receive started / finished / cancelled events of every window animation until
+/// `remove_animation_listener` is called with the returned id
+pub fn add_animation_listener<F>(owner: Option<&str>, listener: F) -> u64
+where
+    F: Fn(AnimationPhase, &AnimationEvent) + Send + Sync + 'static,
+{
+    // The scheduler forwards its events to the registry once it exists
+    Lazy::force(&ANIMATION_SCHEDULER);
+
+    let mut listeners = ANIMATION_LISTENERS.lock().unwrap();
+    listeners.next_id += 1;
+    let id = listeners.next_id;
+    listeners.subscriptions.insert(
+        id,
+        AnimationSubscription {
+            owner: owner.map(String::from),
+            listener: Arc::new(listener),
+        },
+    );
+
+    id
+}
+
+/// `false` when there was no such listener
+pub fn remove_animation_listener(id: u64) -> bool {
+    ANIMATION_LISTENERS
+        .lock()
+        .unwrap()
+        .subscriptions
+        .remove(&id)
+        .is_some()
+}
+
+/// Drops the listeners a closed webview left behind, returns how many there were
+pub fn remove_animation_listeners(owner: &str) -> usize {
+    let mut listeners = ANIMATION_LISTENERS.lock().unwrap();
+    let before = listeners.subscriptions.len();
+    listeners
+        .subscriptions
+        .retain(|_, subscription| subscription.owner.as_deref() != Some(owner));
+
+    before - listeners.subscriptions.len()
 }
 
 /// Get the number of active animations (useful for testing)
 pub fn get_active_animation_count() -> usize {
-    let state = ANIMATION_STATE.lock().unwrap();
-    state.len()
+    ANIMATION_SCHEDULER.active_count()
 }
 
 extern "C" {
@@ -557,45 +769,70 @@ extern "C" {
 /// use core_graphics::geometry::CGSize;
 ///
 /// // Get window reference
-/// let window = get_window_from_id(pid, window_id).unwrap();
+/// let window = get_window_from_id(pid, window_id)?;
 ///
 /// // Animate to new position with custom config
 /// let config = AnimationConfig {
 ///     duration_ms: 300,
 ///     fps: 60,
 ///     easing: EasingFunction::EaseOutCubic,
+///     queue: QueuePolicy::Replace,
 /// };
-/// set_position_animated(window, CGPoint { x: 100.0, y: 100.0 }, config.clone());
+/// set_position_animated(window, CGPoint { x: 100.0, y: 100.0 }, config.clone())?;
 ///
 /// // Animate to new size
-/// set_size_animated(window, CGSize { width: 800.0, height: 600.0 }, config.clone());
+/// set_size_animated(window, CGSize { width: 800.0, height: 600.0 }, config.clone())?;
 ///
 /// // Animate both position and size simultaneously
 /// set_frame_animated(
 ///     window,
 ///     CGPoint { x: 200.0, y: 150.0 },
 ///     CGSize { width: 1000.0, height: 700.0 },
-///     config
-/// );
+///     config.clone(),
+///     None,
+///     None,
+/// )?;
+///
+/// // Wait until the window arrives (or the animation is interrupted)
+/// let completion = set_position_animated(window, CGPoint { x: 0.0, y: 0.0 }, config.clone())?;
+/// if let Ok(AnimationOutcome::Finished(frame)) = completion.recv() {
+///     println!("arrived at {:?}", frame);
+/// }
 ///
 /// // Cancel specific window animation
 /// cancel_window_animation(window_id);
 ///
 /// // Cancel all animations
 /// cancel_all_animations();
+///
+/// // Animations keep their own reference, the resolved element is released
+/// unsafe { CFRelease(window as CFTypeRef) };
 /// ```
 ///
 /// Features:
-/// - Thread-safe: Multiple windows can be animated simultaneously
-/// - Cancellable: Starting a new animation automatically cancels the previous one for that window
-/// - Smooth easing: Multiple easing functions available
-/// - No blocking: Animations run in background threads
+/// - Single scheduler thread: frames of all windows are applied in order, one tick at a time
+/// - Frame coalescing: a late tick jumps straight to the frame for the current time
+/// - Retargeting: a new target for a moving window continues from its current frame and velocity,
+///   or is queued / ignored depending on `QueuePolicy`
+/// - Lifecycle: every animation reports started / finished / cancelled to the listener and
+///   resolves its `AnimationCompletion`
+/// - Realignment: apps that refuse the final size are re-aligned within the target
+///   according to its anchor
+/// - Cheap frames: the AX element is resolved once per animation, unchanged attributes are skipped
 /// - Automatic cleanup: Animation state is cleaned up when animations complete or are cancelled
 
 #[allow(non_upper_case_globals)]
 pub const kCFNumberSInt32Type: CFNumberType = 3;
 #[allow(non_upper_case_globals)]
 pub const kCFNumberSInt64Type: CFNumberType = 4;
+#[allow(non_upper_case_globals)]
+pub const kCFNumberFloat32Type: CFNumberType = 5;
+#[allow(non_upper_case_globals)]
+pub const kCFNumberFloat64Type: CFNumberType = 6;
+#[allow(non_upper_case_globals)]
+pub const kCFNumberFloatType: CFNumberType = 12;
+#[allow(non_upper_case_globals)]
+pub const kCFNumberDoubleType: CFNumberType = 13;
 
 // Taken from https://github.com/sassman/t-rec-rs/blob/v0.7.0/src/macos/window_id.rs#L73
 // Modified to support dictionary type id for kCGWindowBounds
@@ -627,6 +864,16 @@ pub fn get_from_dict(dict: CFDictionaryRef, key: &str) -> DictEntryValue {
                         return DictEntryValue::_Number(value_i32 as i64);
                     }
                 }
+                kCFNumberFloat32Type | kCFNumberFloat64Type | kCFNumberFloatType
+                | kCFNumberDoubleType => {
+                    let mut value_f64 = 0_f64;
+                    let out_value: *mut f64 = &mut value_f64;
+                    let converted =
+                        unsafe { CFNumberGetValue(value, kCFNumberFloat64Type, out_value.cast()) };
+                    if converted {
+                        return DictEntryValue::_Float(value_f64);
+                    }
+                }
                 n => {
                     eprintln!("Unsupported Number of typeId: {}", n);
                 }
@@ -674,7 +921,7 @@ pub struct ActiveWindowInfo {
     pub process_path: PathBuf,
 }
 
-pub fn get_active_window() -> Result<ActiveWindowInfo, ()> {
+pub fn get_active_window() -> SnapResult<ActiveWindowInfo> {
     const OPTIONS: CGWindowListOption =
         kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements;
     let window_list_info = unsafe { CGWindowListCopyWindowInfo(OPTIONS, kCGNullWindowID) };
@@ -683,7 +930,11 @@ pub fn get_active_window() -> Result<ActiveWindowInfo, ()> {
 
     let active_app = unsafe {
         let workspace = NSWorkspace::sharedWorkspace();
-        workspace.frontmostApplication().unwrap()
+        workspace.frontmostApplication()
+    };
+    let Some(active_app) = active_app else {
+        unsafe { CFRelease(window_list_info as CFTypeRef) }
+        return Err(SnapError::platform("there is no frontmost application"));
     };
 
     let active_window_pid = unsafe { active_app.processIdentifier() as i64 };
@@ -726,12 +977,12 @@ pub fn get_active_window() -> Result<ActiveWindowInfo, ()> {
             }
 
             let process_path: PathBuf = unsafe {
-                let bundle_url = &active_app
+                // Command line tools have no bundle
+                active_app
                     .bundleURL()
-                    .map(|url| url.path())
-                    .unwrap()
-                    .unwrap();
-                PathBuf::from(&bundle_url.to_string())
+                    .and_then(|url| url.path())
+                    .map(|path| PathBuf::from(path.to_string()))
+                    .unwrap_or_default()
             };
 
             if let DictEntryValue::_Number(window_id) = get_from_dict(dic_ref, "kCGWindowNumber") {
@@ -758,5 +1009,8 @@ pub fn get_active_window() -> Result<ActiveWindowInfo, ()> {
 
     unsafe { CFRelease(window_list_info as CFTypeRef) }
 
-    Err(())
+    Err(SnapError::WindowNotFound {
+        pid: active_window_pid,
+        window_id: None,
+    })
 }
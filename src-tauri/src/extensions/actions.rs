@@ -0,0 +1,12 @@
+use tauri::{AppHandle, Emitter};
+
+use crate::data::{
+    action::SnapAction,
+    error::{SnapError, SnapResult},
+};
+
+/// Hands an action to the stores of the overlay, where global shortcuts end up too
+pub fn dispatch(app: &AppHandle, action: SnapAction) -> SnapResult<()> {
+    app.emit("snap_action", action)
+        .map_err(|error| SnapError::platform(error.to_string()))
+}
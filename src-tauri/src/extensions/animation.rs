@@ -0,0 +1,860 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use core_graphics::window::CGWindowID;
+use serde::{Deserialize, Serialize};
+
+use crate::data::frame::{Frame, Point, Size};
+
+use super::easing::EasingFunction;
+
+/// What happens when a window gets a new target while it is still animating
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuePolicy {
+    /// Retarget the running animation, keeping the current velocity
+    #[default]
+    Replace,
+    /// Run after the animations already scheduled for the window
+    Append,
+    /// Drop the new target while the window is moving
+    IgnoreWhileRunning,
+}
+
+#[derive(Clone)]
+pub struct AnimationConfig {
+    pub duration_ms: u64,
+    pub fps: u64,
+    pub easing: EasingFunction,
+    pub queue: QueuePolicy,
+}
+
+impl Default for AnimationConfig {
+    fn default() -> Self {
+        Self {
+            duration_ms: 200,
+            fps: 60,
+            easing: EasingFunction::default(),
+            queue: QueuePolicy::default(),
+        }
+    }
+}
+
+impl AnimationConfig {
+    fn frame_interval(&self) -> Duration {
+        Duration::from_micros(1_000_000 / self.fps.max(1))
+    }
+
+    /// Springs run until they settle, every other curve uses `duration_ms`
+    pub fn duration(&self) -> Duration {
+        self.easing
+            .settling_duration()
+            .unwrap_or(Duration::from_millis(self.duration_ms))
+    }
+}
+
+/// Source of time for the scheduler, replaceable to drive animations deterministically
+pub trait Clock: Send + Sync {
+    /// Time elapsed since an arbitrary fixed origin
+    fn now(&self) -> Duration;
+}
+
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// Receives the frames computed by the scheduler, e.g. pushes them to an AX window
+pub trait FrameApplier: Send {
+    fn apply(&mut self, frame: &Frame);
+
+    /// Called after the last frame, returns the frame the target really ended up with
+    fn finish(&mut self, frame: &Frame) -> Frame {
+        frame.clone()
+    }
+}
+
+/// Lifecycle stage reported to the animation listener
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimationPhase {
+    Started,
+    Finished,
+    Cancelled,
+}
+
+impl AnimationPhase {
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            AnimationPhase::Started => "animation_started",
+            AnimationPhase::Finished => "animation_finished",
+            AnimationPhase::Cancelled => "animation_cancelled",
+        }
+    }
+}
+
+/// Payload of the lifecycle events. `frame` is the target for `Started`,
+/// the frame the window settled on for `Finished` and the frame at interruption for `Cancelled`.
+#[derive(Serialize, Clone, Debug)]
+pub struct AnimationEvent {
+    pub window_id: CGWindowID,
+    pub frame: Frame,
+}
+
+type AnimationListener = Arc<dyn Fn(AnimationPhase, &AnimationEvent) + Send + Sync>;
+
+/// How an animation ended, delivered through its `AnimationCompletion`
+#[derive(Clone, Debug)]
+pub enum AnimationOutcome {
+    Finished(Frame),
+    /// Cancelled or replaced by a newer target, carries the frame at that moment
+    Cancelled(Frame),
+    /// Dropped because of `QueuePolicy::IgnoreWhileRunning`
+    Ignored,
+}
+
+pub type AnimationCompletion = Receiver<AnimationOutcome>;
+
+/// Completion for changes that were applied without going through the scheduler
+pub fn completed(frame: Frame) -> AnimationCompletion {
+    let (sender, receiver) = mpsc::channel();
+    let _ = sender.send(AnimationOutcome::Finished(frame));
+    receiver
+}
+
+/// x, y, width and height of a frame, animated independently
+type Channels = [f64; 4];
+
+fn to_channels(frame: &Frame) -> Channels {
+    [
+        frame.position.x,
+        frame.position.y,
+        frame.size.width,
+        frame.size.height,
+    ]
+}
+
+fn from_channels([x, y, width, height]: Channels) -> Frame {
+    Frame {
+        size: Size { width, height },
+        position: Point { x, y },
+    }
+}
+
+/// A single window animation for `AnimationScheduler::start_batch`
+pub struct AnimationRequest {
+    pub window_id: CGWindowID,
+    pub from: Frame,
+    pub to: Frame,
+    pub config: AnimationConfig,
+    pub applier: Box<dyn FrameApplier>,
+}
+
+/// Target waiting for the running animation of its window to finish
+struct QueuedAnimation {
+    to: Frame,
+    config: AnimationConfig,
+    applier: Box<dyn FrameApplier>,
+    completion: Sender<AnimationOutcome>,
+}
+
+struct ActiveAnimation {
+    generation: u64,
+    from: Frame,
+    to: Frame,
+    /// Velocity inherited from the animation this one replaced, in points per second
+    initial_velocity: Channels,
+    started_at: Duration,
+    duration: Duration,
+    frame_interval: Duration,
+    easing: EasingFunction,
+    applier: Arc<Mutex<Box<dyn FrameApplier>>>,
+    cancelled: Arc<AtomicBool>,
+    queue: VecDeque<QueuedAnimation>,
+    completion: Sender<AnimationOutcome>,
+}
+
+impl ActiveAnimation {
+    fn progress(&self, now: Duration) -> f64 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        let elapsed = now.saturating_sub(self.started_at).as_secs_f64();
+
+        (elapsed / self.duration.as_secs_f64()).clamp(0.0, 1.0)
+    }
+
+    fn frame_at(&self, now: Duration) -> Frame {
+        let progress = self.progress(now);
+        if progress >= 1.0 {
+            return self.to.clone();
+        }
+
+        let elapsed = now.saturating_sub(self.started_at).as_secs_f64();
+        let duration = self.duration.as_secs_f64();
+        let from = to_channels(&self.from);
+        let to = to_channels(&self.to);
+        let channels = [0, 1, 2, 3].map(|i| {
+            let velocity = self.initial_velocity[i];
+            match self.easing {
+                EasingFunction::Spring { .. } => {
+                    to[i]
+                        + self
+                            .easing
+                            .spring_offset(from[i] - to[i], velocity, elapsed)
+                }
+                // Curves have no notion of velocity, blend it in with a term that starts
+                // with the inherited slope and fades out before the end
+                _ => {
+                    from[i]
+                        + (to[i] - from[i]) * self.easing.apply(progress)
+                        + velocity * duration * progress * (1.0 - progress).powi(2)
+                }
+            }
+        });
+
+        from_channels(channels)
+    }
+
+    fn velocity_at(&self, now: Duration) -> Channels {
+        if self.progress(now) >= 1.0 {
+            return [0.0; 4];
+        }
+
+        let step = Duration::from_millis(1);
+        let before = now.saturating_sub(step).max(self.started_at);
+        let after = now + step;
+        let span = (after - before).as_secs_f64();
+
+        let start = to_channels(&self.frame_at(before));
+        let end = to_channels(&self.frame_at(after));
+
+        [0, 1, 2, 3].map(|i| (end[i] - start[i]) / span)
+    }
+
+    /// Marks the animation and everything queued after it as cancelled at `frame`
+    fn interrupt(self, window_id: CGWindowID, frame: Frame) -> (AnimationPhase, AnimationEvent) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        let _ = self
+            .completion
+            .send(AnimationOutcome::Cancelled(frame.clone()));
+        for queued in self.queue {
+            let _ = queued
+                .completion
+                .send(AnimationOutcome::Cancelled(frame.clone()));
+        }
+
+        (
+            AnimationPhase::Cancelled,
+            AnimationEvent { window_id, frame },
+        )
+    }
+}
+
+/// Drives every window animation from a single thread.
+///
+/// Each tick computes the frame of every active animation for the current clock time,
+/// so frames that were missed while the thread was busy are coalesced into one,
+/// and applies them in order. A new target for a window that is already animating
+/// is handled according to its `QueuePolicy`.
+pub struct AnimationScheduler {
+    clock: Arc<dyn Clock>,
+    animations: Mutex<HashMap<CGWindowID, ActiveAnimation>>,
+    wake: Condvar,
+    generation: AtomicU64,
+    running: AtomicBool,
+    listener: Mutex<Option<AnimationListener>>,
+}
+
+impl AnimationScheduler {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            animations: Mutex::new(HashMap::new()),
+            wake: Condvar::new(),
+            generation: AtomicU64::new(0),
+            running: AtomicBool::new(false),
+            listener: Mutex::new(None),
+        }
+    }
+
+    /// Registers the callback that receives started / finished / cancelled events
+    pub fn set_listener<F>(&self, listener: F)
+    where
+        F: Fn(AnimationPhase, &AnimationEvent) + Send + Sync + 'static,
+    {
+        *self.listener.lock().unwrap() = Some(Arc::new(listener));
+    }
+
+    /// Called without holding the animations lock so listeners may query the scheduler
+    fn notify(&self, events: Vec<(AnimationPhase, AnimationEvent)>) {
+        let listener = self.listener.lock().unwrap().clone();
+        if let Some(listener) = listener {
+            for (phase, event) in events {
+                listener(phase, &event);
+            }
+        }
+    }
+
+    /// Starts the scheduler thread, does nothing if it is already running
+    pub fn spawn(self: &Arc<Self>) {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let scheduler = self.clone();
+        thread::Builder::new()
+            .name(String::from("snap-animation-scheduler"))
+            .spawn(move || scheduler.run())
+            .expect("failed to spawn animation scheduler thread");
+    }
+
+    fn run(&self) {
+        loop {
+            {
+                let mut animations = self.animations.lock().unwrap();
+                while animations.is_empty() {
+                    animations = self.wake.wait(animations).unwrap();
+                }
+            }
+
+            let tick_started = Instant::now();
+            if let Some(interval) = self.tick() {
+                thread::sleep(interval.saturating_sub(tick_started.elapsed()));
+            }
+        }
+    }
+
+    fn create(
+        &self,
+        from: Frame,
+        initial_velocity: Channels,
+        target: QueuedAnimation,
+        started_at: Duration,
+    ) -> ActiveAnimation {
+        ActiveAnimation {
+            generation: self.generation.fetch_add(1, Ordering::SeqCst),
+            from,
+            to: target.to,
+            initial_velocity,
+            started_at,
+            duration: target.config.duration(),
+            frame_interval: target.config.frame_interval(),
+            easing: target.config.easing,
+            applier: Arc::new(Mutex::new(target.applier)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            queue: VecDeque::new(),
+            completion: target.completion,
+        }
+    }
+
+    /// Animates a window from `from` to `to`. If the window is already animating,
+    /// `config.queue` decides whether the running animation is retargeted from its
+    /// current frame and velocity, the new one is queued, or it is dropped.
+    /// The returned completion resolves once the window reaches its target or the
+    /// animation is interrupted.
+    pub fn start(
+        &self,
+        window_id: CGWindowID,
+        from: Frame,
+        to: Frame,
+        config: AnimationConfig,
+        applier: Box<dyn FrameApplier>,
+    ) -> AnimationCompletion {
+        let request = AnimationRequest {
+            window_id,
+            from,
+            to,
+            config,
+            applier,
+        };
+
+        self.start_batch(vec![request]).remove(0)
+    }
+
+    /// Starts several animations at the same instant so their frames are applied in the same ticks.
+    /// Completions are returned in the order of the requests.
+    pub fn start_batch(&self, requests: Vec<AnimationRequest>) -> Vec<AnimationCompletion> {
+        let now = self.clock.now();
+        let mut events = vec![];
+        let mut completions = Vec::with_capacity(requests.len());
+        {
+            let mut animations = self.animations.lock().unwrap();
+
+            for request in requests {
+                let (sender, receiver) = mpsc::channel();
+                completions.push(receiver);
+                let AnimationRequest {
+                    window_id,
+                    from,
+                    to,
+                    config,
+                    applier,
+                } = request;
+
+                let (from, velocity) = match animations.get_mut(&window_id) {
+                    Some(running) => match config.queue {
+                        QueuePolicy::IgnoreWhileRunning => {
+                            let _ = sender.send(AnimationOutcome::Ignored);
+                            continue;
+                        }
+                        QueuePolicy::Append => {
+                            running.queue.push_back(QueuedAnimation {
+                                to,
+                                config,
+                                applier,
+                                completion: sender,
+                            });
+                            continue;
+                        }
+                        QueuePolicy::Replace => (running.frame_at(now), running.velocity_at(now)),
+                    },
+                    None => (from, [0.0; 4]),
+                };
+
+                let target = QueuedAnimation {
+                    to: to.clone(),
+                    config,
+                    applier,
+                    completion: sender,
+                };
+                let animation = self.create(from.clone(), velocity, target, now);
+                if let Some(existing) = animations.insert(window_id, animation) {
+                    events.push(existing.interrupt(window_id, from));
+                }
+                events.push((
+                    AnimationPhase::Started,
+                    AnimationEvent {
+                        window_id,
+                        frame: to,
+                    },
+                ));
+            }
+            self.wake.notify_one();
+        }
+
+        self.notify(events);
+        completions
+    }
+
+    /// Stops the animation of a window and drops its queue, the window keeps its current frame
+    pub fn cancel(&self, window_id: CGWindowID) -> bool {
+        let now = self.clock.now();
+        let removed = self.animations.lock().unwrap().remove(&window_id);
+
+        match removed {
+            Some(animation) => {
+                let frame = animation.frame_at(now);
+                self.notify(vec![animation.interrupt(window_id, frame)]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cancel_all(&self) {
+        let now = self.clock.now();
+        let removed: Vec<_> = self.animations.lock().unwrap().drain().collect();
+
+        let events = removed
+            .into_iter()
+            .map(|(window_id, animation)| {
+                let frame = animation.frame_at(now);
+                animation.interrupt(window_id, frame)
+            })
+            .collect();
+        self.notify(events);
+    }
+
+    pub fn active_count(&self) -> usize {
+        self.animations.lock().unwrap().len()
+    }
+
+    /// Applies the current frame of every active animation.
+    /// Returns the delay until the next frame is due, or `None` when nothing is animating.
+    pub fn tick(&self) -> Option<Duration> {
+        let now = self.clock.now();
+
+        // Frames are applied outside of the lock: an unresponsive app must not block
+        // commands that start or cancel animations
+        let due: Vec<_> = {
+            let animations = self.animations.lock().unwrap();
+            animations
+                .iter()
+                .map(|(window_id, animation)| {
+                    (
+                        *window_id,
+                        animation.generation,
+                        animation.progress(now) >= 1.0,
+                        animation.frame_at(now),
+                        animation.applier.clone(),
+                        animation.cancelled.clone(),
+                    )
+                })
+                .collect()
+        };
+
+        let mut finished = vec![];
+        for (window_id, generation, is_finished, frame, applier, cancelled) in due {
+            if cancelled.load(Ordering::SeqCst) {
+                continue;
+            }
+            let mut applier = applier.lock().unwrap();
+            applier.apply(&frame);
+
+            if is_finished {
+                finished.push((window_id, generation, applier.finish(&frame)));
+            }
+        }
+
+        let mut events = vec![];
+        let mut animations = self.animations.lock().unwrap();
+        for (window_id, generation, final_frame) in finished {
+            // The window could have been retargeted while its last frame was applied
+            let is_current = animations
+                .get(&window_id)
+                .is_some_and(|animation| animation.generation == generation);
+            if !is_current {
+                continue;
+            }
+            let Some(mut animation) = animations.remove(&window_id) else {
+                continue;
+            };
+
+            let _ = animation
+                .completion
+                .send(AnimationOutcome::Finished(final_frame.clone()));
+            events.push((
+                AnimationPhase::Finished,
+                AnimationEvent {
+                    window_id,
+                    frame: final_frame.clone(),
+                },
+            ));
+
+            if let Some(next) = animation.queue.pop_front() {
+                events.push((
+                    AnimationPhase::Started,
+                    AnimationEvent {
+                        window_id,
+                        frame: next.to.clone(),
+                    },
+                ));
+                let mut queued = self.create(final_frame, [0.0; 4], next, self.clock.now());
+                queued.queue = animation.queue;
+                animations.insert(window_id, queued);
+            }
+        }
+
+        let interval = animations
+            .values()
+            .map(|animation| animation.frame_interval)
+            .min();
+        drop(animations);
+
+        self.notify(events);
+        interval
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Clock that only moves when it is advanced explicitly
+    #[derive(Default)]
+    struct ManualClock {
+        now: Mutex<Duration>,
+    }
+
+    impl ManualClock {
+        fn advance(&self, ms: u64) {
+            *self.now.lock().unwrap() += Duration::from_millis(ms);
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Duration {
+            *self.now.lock().unwrap()
+        }
+    }
+
+    /// Keeps every frame it was given
+    #[derive(Clone, Default)]
+    struct Recorder {
+        frames: Arc<Mutex<Vec<Frame>>>,
+    }
+
+    impl Recorder {
+        fn xs(&self) -> Vec<f64> {
+            self.frames
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|frame| frame.position.x)
+                .collect()
+        }
+    }
+
+    impl FrameApplier for Recorder {
+        fn apply(&mut self, frame: &Frame) {
+            self.frames.lock().unwrap().push(frame.clone());
+        }
+    }
+
+    fn at(x: f64) -> Frame {
+        Frame {
+            size: Size {
+                width: 100.0,
+                height: 100.0,
+            },
+            position: Point { x, y: 0.0 },
+        }
+    }
+
+    fn linear(queue: QueuePolicy) -> AnimationConfig {
+        AnimationConfig {
+            duration_ms: 100,
+            fps: 50,
+            easing: EasingFunction::Linear,
+            queue,
+        }
+    }
+
+    fn setup() -> (Arc<ManualClock>, AnimationScheduler) {
+        let clock = Arc::new(ManualClock::default());
+        let scheduler = AnimationScheduler::new(clock.clone());
+        (clock, scheduler)
+    }
+
+    fn start(
+        scheduler: &AnimationScheduler,
+        window_id: CGWindowID,
+        from: f64,
+        to: f64,
+        queue: QueuePolicy,
+    ) -> (Recorder, AnimationCompletion) {
+        let recorder = Recorder::default();
+        let completion = scheduler.start(
+            window_id,
+            at(from),
+            at(to),
+            linear(queue),
+            Box::new(recorder.clone()),
+        );
+        (recorder, completion)
+    }
+
+    fn assert_xs(actual: Vec<f64>, expected: &[f64]) {
+        assert_eq!(
+            actual.len(),
+            expected.len(),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 1e-6,
+                "{} != {}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    fn outcome_x(completion: &AnimationCompletion) -> (&'static str, Option<f64>) {
+        match completion.try_recv() {
+            Ok(AnimationOutcome::Finished(frame)) => ("finished", Some(frame.position.x)),
+            Ok(AnimationOutcome::Cancelled(frame)) => ("cancelled", Some(frame.position.x)),
+            Ok(AnimationOutcome::Ignored) => ("ignored", None),
+            Err(_) => ("pending", None),
+        }
+    }
+
+    fn assert_outcome(completion: &AnimationCompletion, kind: &str, x: Option<f64>) {
+        let (actual_kind, actual_x) = outcome_x(completion);
+        assert_eq!(actual_kind, kind);
+        match (actual_x, x) {
+            (Some(actual), Some(expected)) => assert!((actual - expected).abs() < 1e-6),
+            (actual, expected) => assert_eq!(actual, expected),
+        }
+    }
+
+    #[test]
+    fn missed_frames_are_coalesced_into_one() {
+        let (clock, scheduler) = setup();
+        let (recorder, completion) = start(&scheduler, 1, 0.0, 100.0, QueuePolicy::Replace);
+
+        clock.advance(20);
+        assert_eq!(scheduler.tick(), Some(Duration::from_millis(20)));
+        // The thread was busy for several frame intervals, only the current frame is applied
+        clock.advance(50);
+        scheduler.tick();
+        assert_xs(recorder.xs(), &[20.0, 70.0]);
+        assert_outcome(&completion, "pending", None);
+
+        clock.advance(500);
+        assert_eq!(scheduler.tick(), None);
+        assert_xs(recorder.xs(), &[20.0, 70.0, 100.0]);
+        assert_outcome(&completion, "finished", Some(100.0));
+        assert_eq!(scheduler.active_count(), 0);
+    }
+
+    #[test]
+    fn batch_frames_are_applied_in_the_same_tick() {
+        let (clock, scheduler) = setup();
+        let (first, second) = (Recorder::default(), Recorder::default());
+        let request = |window_id, to, recorder: &Recorder| AnimationRequest {
+            window_id,
+            from: at(0.0),
+            to: at(to),
+            config: linear(QueuePolicy::Replace),
+            applier: Box::new(recorder.clone()),
+        };
+        scheduler.start_batch(vec![request(1, 100.0, &first), request(2, 200.0, &second)]);
+
+        clock.advance(50);
+        scheduler.tick();
+        assert_xs(first.xs(), &[50.0]);
+        assert_xs(second.xs(), &[100.0]);
+    }
+
+    #[test]
+    fn cancel_only_stops_that_window() {
+        let (clock, scheduler) = setup();
+        let (first, first_completion) = start(&scheduler, 1, 0.0, 100.0, QueuePolicy::Replace);
+        let (second, second_completion) = start(&scheduler, 2, 0.0, 100.0, QueuePolicy::Replace);
+
+        clock.advance(40);
+        assert!(scheduler.cancel(1));
+        assert!(!scheduler.cancel(1));
+        assert_outcome(&first_completion, "cancelled", Some(40.0));
+
+        clock.advance(10);
+        scheduler.tick();
+        assert_xs(first.xs(), &[]);
+        assert_xs(second.xs(), &[50.0]);
+        assert_outcome(&second_completion, "pending", None);
+        assert_eq!(scheduler.active_count(), 1);
+    }
+
+    #[test]
+    fn cancel_drops_the_queued_targets_too() {
+        let (clock, scheduler) = setup();
+        let (_, running) = start(&scheduler, 1, 0.0, 100.0, QueuePolicy::Replace);
+        let (queued_recorder, queued) = start(&scheduler, 1, 0.0, 300.0, QueuePolicy::Append);
+
+        clock.advance(30);
+        scheduler.cancel_all();
+        assert_outcome(&running, "cancelled", Some(30.0));
+        assert_outcome(&queued, "cancelled", Some(30.0));
+
+        clock.advance(500);
+        assert_eq!(scheduler.tick(), None);
+        assert_xs(queued_recorder.xs(), &[]);
+    }
+
+    #[test]
+    fn replace_retargets_from_the_current_frame() {
+        let (clock, scheduler) = setup();
+        let phases = Arc::new(Mutex::new(vec![]));
+        let recorded = phases.clone();
+        scheduler.set_listener(move |phase, event| {
+            recorded
+                .lock()
+                .unwrap()
+                .push((phase, event.window_id, event.frame.position.x));
+        });
+
+        let (_, first) = start(&scheduler, 1, 0.0, 100.0, QueuePolicy::Replace);
+        clock.advance(50);
+        scheduler.tick();
+        let (recorder, second) = start(&scheduler, 1, 0.0, 200.0, QueuePolicy::Replace);
+        assert_outcome(&first, "cancelled", Some(50.0));
+
+        // The new animation starts where the window is, not at the requested `from`,
+        // and keeps moving in the same direction
+        clock.advance(10);
+        scheduler.tick();
+        let xs = recorder.xs();
+        assert!(xs[0] > 50.0 && xs[0] < 200.0, "{:?}", xs);
+
+        clock.advance(90);
+        scheduler.tick();
+        assert_outcome(&second, "finished", Some(200.0));
+        assert_eq!(
+            *phases.lock().unwrap(),
+            vec![
+                (AnimationPhase::Started, 1, 100.0),
+                (AnimationPhase::Cancelled, 1, 50.0),
+                (AnimationPhase::Started, 1, 200.0),
+                (AnimationPhase::Finished, 1, 200.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn append_runs_after_the_running_animation() {
+        let (clock, scheduler) = setup();
+        let (first, first_completion) = start(&scheduler, 1, 0.0, 100.0, QueuePolicy::Replace);
+        clock.advance(50);
+        let (second, second_completion) = start(&scheduler, 1, 0.0, 300.0, QueuePolicy::Append);
+        assert_outcome(&second_completion, "pending", None);
+
+        clock.advance(50);
+        scheduler.tick();
+        assert_outcome(&first_completion, "finished", Some(100.0));
+        assert_xs(first.xs(), &[100.0]);
+        assert_xs(second.xs(), &[]);
+
+        // Starts from where the first one ended, its clock starts at that moment
+        clock.advance(50);
+        scheduler.tick();
+        clock.advance(50);
+        scheduler.tick();
+        assert_xs(second.xs(), &[200.0, 300.0]);
+        assert_outcome(&second_completion, "finished", Some(300.0));
+    }
+
+    #[test]
+    fn ignore_while_running_drops_the_new_target() {
+        let (clock, scheduler) = setup();
+        let (first, first_completion) = start(&scheduler, 1, 0.0, 100.0, QueuePolicy::Replace);
+        clock.advance(50);
+        let (second, second_completion) =
+            start(&scheduler, 1, 0.0, 300.0, QueuePolicy::IgnoreWhileRunning);
+        assert_outcome(&second_completion, "ignored", None);
+
+        clock.advance(50);
+        scheduler.tick();
+        assert_xs(first.xs(), &[100.0]);
+        assert_xs(second.xs(), &[]);
+        assert_outcome(&first_completion, "finished", Some(100.0));
+
+        // Nothing is running anymore, the same policy starts right away
+        let (_, third) = start(&scheduler, 1, 100.0, 0.0, QueuePolicy::IgnoreWhileRunning);
+        assert_outcome(&third, "pending", None);
+        assert_eq!(scheduler.active_count(), 1);
+    }
+}
@@ -0,0 +1,85 @@
+use std::{collections::HashMap, ptr, sync::Mutex};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use objc2::ClassType;
+use objc2_app_kit::{
+    NSBitmapImageFileType, NSBitmapImageRep, NSCompositingOperation, NSDeviceRGBColorSpace,
+    NSGraphicsContext, NSImage, NSRunningApplication,
+};
+use objc2_foundation::{NSDictionary, NSPoint, NSRect, NSSize};
+use once_cell::sync::Lazy;
+
+/// Icons as `data:` URLs, per bundle and pixel size
+static ICON_CACHE: Lazy<Mutex<HashMap<(String, u32), Option<String>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Path of the application bundle, `None` for command line tools
+pub fn bundle_path(pid: i64) -> Option<String> {
+    unsafe {
+        NSRunningApplication::runningApplicationWithProcessIdentifier(pid as i32)?
+            .bundleURL()?
+            .path()
+            .map(|path| path.to_string())
+    }
+}
+
+/// Icon of the application as a PNG `data:` URL, `size` is in pixels.
+/// Apps without a bundle aren't cached, pids are reused and nothing evicts them
+pub fn app_icon(pid: i64, bundle_path: Option<&str>, size: u32) -> Option<String> {
+    let key = bundle_path.map(|path| (path.to_string(), size));
+    if let Some(icon) = key
+        .as_ref()
+        .and_then(|key| ICON_CACHE.lock().unwrap().get(key).cloned())
+    {
+        return icon;
+    }
+
+    let icon = unsafe {
+        NSRunningApplication::runningApplicationWithProcessIdentifier(pid as i32)
+            .and_then(|app| app.icon())
+            .and_then(|image| render_png(&image, size))
+    }
+    .map(|png| format!("data:image/png;base64,{}", STANDARD.encode(png)));
+
+    if let Some(key) = key {
+        ICON_CACHE.lock().unwrap().insert(key, icon.clone());
+    }
+    icon
+}
+
+/// Draws the image into a square bitmap, picking the best representation for the size
+unsafe fn render_png(image: &NSImage, size: u32) -> Option<Vec<u8>> {
+    let pixels = size as isize;
+    let bitmap = NSBitmapImageRep::initWithBitmapDataPlanes_pixelsWide_pixelsHigh_bitsPerSample_samplesPerPixel_hasAlpha_isPlanar_colorSpaceName_bytesPerRow_bitsPerPixel(
+        NSBitmapImageRep::alloc(),
+        ptr::null_mut(),
+        pixels,
+        pixels,
+        8,
+        4,
+        true,
+        false,
+        NSDeviceRGBColorSpace,
+        0,
+        0,
+    )?;
+    // One point per pixel, otherwise the context scales for the backing store
+    let rect = NSRect::new(NSPoint::ZERO, NSSize::new(size as f64, size as f64));
+    bitmap.setSize(rect.size);
+
+    let context = NSGraphicsContext::graphicsContextWithBitmapImageRep(&bitmap)?;
+    NSGraphicsContext::saveGraphicsState_class();
+    NSGraphicsContext::setCurrentContext(Some(&context));
+    image.drawInRect_fromRect_operation_fraction(
+        rect,
+        NSRect::ZERO,
+        NSCompositingOperation::Copy,
+        1.0,
+    );
+    context.flushGraphics();
+    NSGraphicsContext::restoreGraphicsState_class();
+
+    bitmap
+        .representationUsingType_properties(NSBitmapImageFileType::PNG, &NSDictionary::new())
+        .map(|data| data.bytes().to_vec())
+}
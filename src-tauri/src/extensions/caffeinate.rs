@@ -0,0 +1,711 @@
+use std::{
+    io,
+    sync::{Mutex, Once},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use core_foundation::{
+    base::TCFType,
+    string::{CFString, CFStringRef},
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::data::error::{SnapError, SnapResult};
+
+use super::caffeinate_triggers::{current_environment, evaluate, CaffeinateReason, TriggersConfig};
+
+/// How often timed sessions, watched processes and triggers are checked
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+/// Shown by `pmset -g assertions` as the reason the Mac stays awake
+const ASSERTION_NAME: &str = "Snap caffeinate";
+
+const K_IOPM_ASSERTION_LEVEL_ON: u32 = 255;
+const K_IORETURN_SUCCESS: i32 = 0;
+const ESRCH: i32 = 3;
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn IOPMAssertionCreateWithName(
+        assertion_type: CFStringRef,
+        level: u32,
+        name: CFStringRef,
+        id: *mut u32,
+    ) -> i32;
+    fn IOPMAssertionRelease(id: u32) -> i32;
+}
+
+extern "C" {
+    fn kill(pid: i32, signal: i32) -> i32;
+}
+
+/// What is kept awake, the display keeps the system awake too
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SleepKind {
+    #[default]
+    Display,
+    System,
+}
+
+impl SleepKind {
+    fn assertion_type(&self) -> &'static str {
+        match self {
+            SleepKind::Display => "PreventUserIdleDisplaySleep",
+            SleepKind::System => "PreventUserIdleSystemSleep",
+        }
+    }
+}
+
+/// Keeps the Mac from sleeping while an assertion is held, replaceable to run sessions
+/// without touching the power settings
+pub trait PowerAssertion: Send + Sync {
+    fn acquire(&self, kind: SleepKind, name: &str) -> SnapResult<u32>;
+    fn release(&self, id: u32) -> SnapResult<()>;
+}
+
+/// IOPMAssertion, macOS drops the assertions of a process that dies
+pub struct IoPmAssertion;
+
+impl PowerAssertion for IoPmAssertion {
+    fn acquire(&self, kind: SleepKind, name: &str) -> SnapResult<u32> {
+        let assertion_type = CFString::new(kind.assertion_type());
+        let name = CFString::new(name);
+        let mut id = 0;
+
+        let result = unsafe {
+            IOPMAssertionCreateWithName(
+                assertion_type.as_concrete_TypeRef(),
+                K_IOPM_ASSERTION_LEVEL_ON,
+                name.as_concrete_TypeRef(),
+                &mut id,
+            )
+        };
+        if result != K_IORETURN_SUCCESS {
+            return Err(SnapError::platform(format!(
+                "IOPMAssertionCreateWithName failed with {:#x}",
+                result
+            )));
+        }
+
+        Ok(id)
+    }
+
+    fn release(&self, id: u32) -> SnapResult<()> {
+        match unsafe { IOPMAssertionRelease(id) } {
+            K_IORETURN_SUCCESS => Ok(()),
+            result => Err(SnapError::platform(format!(
+                "IOPMAssertionRelease failed with {:#x}",
+                result
+            ))),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct CaffeinateRequest {
+    pub kind: SleepKind,
+    /// Ends the session after this long
+    pub minutes: Option<u32>,
+    /// Ends the session once this process exits
+    pub until_pid: Option<i32>,
+}
+
+impl CaffeinateRequest {
+    pub fn validate(&self) -> SnapResult<()> {
+        if self.minutes == Some(0) {
+            return Err(SnapError::invalid_argument(
+                "minutes",
+                "must be greater than zero",
+            ));
+        }
+        if let Some(pid) = self.until_pid {
+            if pid <= 0 {
+                return Err(SnapError::invalid_argument(
+                    "until_pid",
+                    "must be greater than zero",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct CaffeinateStatus {
+    pub active: bool,
+    pub kind: Option<SleepKind>,
+    pub reason: Option<CaffeinateReason>,
+    /// Unix time in milliseconds the session ends at
+    pub ends_at_ms: Option<u64>,
+    /// Seconds left when the status was taken
+    pub remaining_seconds: Option<u64>,
+    pub until_pid: Option<i32>,
+}
+
+struct Session {
+    assertion: u32,
+    kind: SleepKind,
+    reason: CaffeinateReason,
+    ends_at_ms: Option<u64>,
+    until_pid: Option<i32>,
+}
+
+/// At most one session, a new one replaces the running one. Sessions started by hand
+/// win over triggers, and switching off keeps the trigger that matched at the time from
+/// switching on again until it stops matching
+pub struct Caffeinate {
+    power: Box<dyn PowerAssertion>,
+    session: Option<Session>,
+    triggers: TriggersConfig,
+    /// Trigger that matched on the last tick
+    matched: Option<CaffeinateReason>,
+    suppressed: Option<CaffeinateReason>,
+}
+
+impl Caffeinate {
+    pub fn new(power: Box<dyn PowerAssertion>) -> Self {
+        Self {
+            power,
+            session: None,
+            triggers: TriggersConfig::default(),
+            matched: None,
+            suppressed: None,
+        }
+    }
+
+    pub fn status(&self, now_ms: u64) -> CaffeinateStatus {
+        match &self.session {
+            Some(session) => CaffeinateStatus {
+                active: true,
+                kind: Some(session.kind),
+                reason: Some(session.reason.clone()),
+                ends_at_ms: session.ends_at_ms,
+                remaining_seconds: session
+                    .ends_at_ms
+                    .map(|ends_at| ends_at.saturating_sub(now_ms).div_ceil(1000)),
+                until_pid: session.until_pid,
+            },
+            None => CaffeinateStatus::default(),
+        }
+    }
+
+    pub fn triggers(&self) -> &TriggersConfig {
+        &self.triggers
+    }
+
+    /// Takes effect on the next tick
+    pub fn set_triggers(&mut self, triggers: TriggersConfig) {
+        self.triggers = triggers;
+    }
+
+    /// The new assertion is taken before the old one is released, so the Mac never
+    /// gets a chance to sleep in between
+    fn replace_session(
+        &mut self,
+        session: impl FnOnce(u32) -> Session,
+        kind: SleepKind,
+    ) -> SnapResult<()> {
+        let assertion = self.power.acquire(kind, ASSERTION_NAME)?;
+        if let Some(previous) = self.session.replace(session(assertion)) {
+            self.power.release(previous.assertion)?;
+        }
+
+        Ok(())
+    }
+
+    /// `alive` tells whether a process is running
+    pub fn start(
+        &mut self,
+        request: &CaffeinateRequest,
+        now_ms: u64,
+        alive: impl Fn(i32) -> bool,
+    ) -> SnapResult<CaffeinateStatus> {
+        request.validate()?;
+        if let Some(pid) = request.until_pid {
+            if !alive(pid) {
+                return Err(SnapError::invalid_argument(
+                    "until_pid",
+                    format!("process {} is not running", pid),
+                ));
+            }
+        }
+
+        let reason = match (request.until_pid, request.minutes) {
+            (Some(pid), _) => CaffeinateReason::UntilProcess { pid },
+            (None, Some(_)) => CaffeinateReason::Timer,
+            (None, None) => CaffeinateReason::Manual,
+        };
+        self.replace_session(
+            |assertion| Session {
+                assertion,
+                kind: request.kind,
+                reason,
+                ends_at_ms: request
+                    .minutes
+                    .map(|minutes| now_ms + u64::from(minutes) * 60_000),
+                until_pid: request.until_pid,
+            },
+            request.kind,
+        )?;
+
+        Ok(self.status(now_ms))
+    }
+
+    pub fn stop(&mut self, now_ms: u64) -> SnapResult<CaffeinateStatus> {
+        self.suppressed = self.matched.clone();
+        if let Some(session) = self.session.take() {
+            self.power.release(session.assertion)?;
+        }
+
+        Ok(self.status(now_ms))
+    }
+
+    /// Ends a session whose time is up or whose process exited, and follows the trigger
+    /// that matches now (with the seconds it has left). Returns the new status when it changed
+    pub fn tick(
+        &mut self,
+        now_ms: u64,
+        alive: impl Fn(i32) -> bool,
+        matched: Option<(CaffeinateReason, Option<u32>)>,
+    ) -> SnapResult<Option<CaffeinateStatus>> {
+        let before = self.status(now_ms);
+
+        let ended = self.session.as_ref().is_some_and(|session| {
+            !session.reason.is_automatic()
+                && (session.ends_at_ms.is_some_and(|ends_at| now_ms >= ends_at)
+                    || session.until_pid.is_some_and(|pid| !alive(pid)))
+        });
+        if ended {
+            if let Some(session) = self.session.take() {
+                self.power.release(session.assertion)?;
+            }
+        }
+
+        let (reason, remaining) = matched.unzip();
+        if self.suppressed != reason {
+            self.suppressed = None;
+        }
+        self.matched = reason.clone();
+        let ends_at_ms = remaining
+            .flatten()
+            .map(|remaining| now_ms + u64::from(remaining) * 1000);
+        let kind = self.triggers.kind;
+
+        match (&mut self.session, reason) {
+            (Some(session), _) if !session.reason.is_automatic() => {}
+            (Some(_), None) => {
+                if let Some(session) = self.session.take() {
+                    self.power.release(session.assertion)?;
+                }
+            }
+            (Some(session), Some(reason)) if session.kind == kind => {
+                session.reason = reason;
+                session.ends_at_ms = ends_at_ms;
+            }
+            (_, Some(reason)) if self.suppressed.is_none() => {
+                self.replace_session(
+                    |assertion| Session {
+                        assertion,
+                        kind,
+                        reason,
+                        ends_at_ms,
+                        until_pid: None,
+                    },
+                    kind,
+                )?;
+            }
+            _ => {}
+        }
+
+        let after = self.status(now_ms);
+        // The countdown of a schedule alone is no change
+        let changed = before.active != after.active
+            || before.kind != after.kind
+            || before.reason != after.reason;
+        Ok(changed.then_some(after))
+    }
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Whether the process exists, one owned by another user counts as running
+pub fn process_alive(pid: i32) -> bool {
+    if unsafe { kill(pid, 0) } == 0 {
+        return true;
+    }
+    io::Error::last_os_error().raw_os_error() != Some(ESRCH)
+}
+
+static CAFFEINATE: Lazy<Mutex<Caffeinate>> =
+    Lazy::new(|| Mutex::new(Caffeinate::new(Box::new(IoPmAssertion))));
+
+type Listener = Box<dyn Fn(&CaffeinateStatus) + Send + Sync>;
+
+static LISTENER: Lazy<Mutex<Option<Listener>>> = Lazy::new(|| Mutex::new(None));
+static TICKER: Once = Once::new();
+
+fn notify(status: &CaffeinateStatus) {
+    if let Some(listener) = LISTENER.lock().unwrap().as_ref() {
+        listener(status);
+    }
+}
+
+/// Called with the new status whenever it changes, from any thread
+pub fn set_listener(listener: impl Fn(&CaffeinateStatus) + Send + Sync + 'static) {
+    *LISTENER.lock().unwrap() = Some(Box::new(listener));
+}
+
+/// Checks timed sessions, watched processes and triggers in the background, started
+/// with the first session or triggers that need it
+fn start_ticker() {
+    TICKER.call_once(|| {
+        thread::spawn(|| loop {
+            thread::sleep(TICK_INTERVAL);
+
+            // Probed without the lock, listing the running apps takes a moment
+            let triggers = CAFFEINATE.lock().unwrap().triggers().triggers.clone();
+            let matched = (!triggers.is_empty())
+                .then(|| evaluate(&triggers, &current_environment(&triggers)))
+                .flatten();
+
+            let changed = CAFFEINATE
+                .lock()
+                .unwrap()
+                .tick(now_ms(), process_alive, matched);
+            match changed {
+                Ok(Some(status)) => notify(&status),
+                Ok(None) => {}
+                Err(error) => eprintln!("caffeinate failed: {}", error),
+            }
+        });
+    });
+}
+
+pub fn start(request: &CaffeinateRequest) -> SnapResult<CaffeinateStatus> {
+    let status = CAFFEINATE
+        .lock()
+        .unwrap()
+        .start(request, now_ms(), process_alive)?;
+    if status.ends_at_ms.is_some() || status.until_pid.is_some() {
+        start_ticker();
+    }
+
+    notify(&status);
+    Ok(status)
+}
+
+pub fn stop() -> SnapResult<CaffeinateStatus> {
+    let status = CAFFEINATE.lock().unwrap().stop(now_ms())?;
+    notify(&status);
+    Ok(status)
+}
+
+pub fn status() -> CaffeinateStatus {
+    CAFFEINATE.lock().unwrap().status(now_ms())
+}
+
+pub fn configure_triggers(triggers: TriggersConfig) -> SnapResult<()> {
+    triggers.validate()?;
+    let needs_ticker = !triggers.triggers.is_empty();
+    CAFFEINATE.lock().unwrap().set_triggers(triggers);
+    if needs_ticker {
+        start_ticker();
+    }
+
+    Ok(())
+}
+
+/// Lets the Mac sleep again, called when Snap exits
+pub fn release_all() {
+    if let Err(error) = CAFFEINATE.lock().unwrap().stop(now_ms()) {
+        eprintln!("caffeinate release failed: {}", error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+
+    const MINUTE_MS: u64 = 60_000;
+    const PID: i32 = 42;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Call {
+        Acquire(u32, SleepKind),
+        Release(u32),
+    }
+
+    /// Assertions kept in memory with the calls in order, cloned into the `Caffeinate`
+    /// so the test can look at them
+    #[derive(Clone, Default)]
+    struct FakePowerAssertion {
+        held: Arc<Mutex<HashMap<u32, SleepKind>>>,
+        calls: Arc<Mutex<Vec<Call>>>,
+    }
+
+    impl FakePowerAssertion {
+        fn held(&self) -> Vec<SleepKind> {
+            self.held.lock().unwrap().values().copied().collect()
+        }
+
+        fn calls(&self) -> Vec<Call> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl PowerAssertion for FakePowerAssertion {
+        fn acquire(&self, kind: SleepKind, _name: &str) -> SnapResult<u32> {
+            let mut calls = self.calls.lock().unwrap();
+            let id = calls.len() as u32 + 1;
+            calls.push(Call::Acquire(id, kind));
+            self.held.lock().unwrap().insert(id, kind);
+            Ok(id)
+        }
+
+        fn release(&self, id: u32) -> SnapResult<()> {
+            self.calls.lock().unwrap().push(Call::Release(id));
+            self.held
+                .lock()
+                .unwrap()
+                .remove(&id)
+                .map(|_| ())
+                .ok_or_else(|| SnapError::platform(format!("assertion {} is not held", id)))
+        }
+    }
+
+    fn caffeinate() -> (Caffeinate, FakePowerAssertion) {
+        let power = FakePowerAssertion::default();
+        (Caffeinate::new(Box::new(power.clone())), power)
+    }
+
+    fn alive(_pid: i32) -> bool {
+        true
+    }
+
+    fn exited(_pid: i32) -> bool {
+        false
+    }
+
+    fn request(minutes: Option<u32>, until_pid: Option<i32>) -> CaffeinateRequest {
+        CaffeinateRequest {
+            kind: SleepKind::Display,
+            minutes,
+            until_pid,
+        }
+    }
+
+    #[test]
+    fn timed_session_ends_when_the_time_is_up() {
+        let (mut caffeinate, power) = caffeinate();
+
+        let status = caffeinate.start(&request(Some(5), None), 0, alive).unwrap();
+        assert_eq!(status.reason, Some(CaffeinateReason::Timer));
+        assert_eq!(status.ends_at_ms, Some(5 * MINUTE_MS));
+        assert_eq!(status.remaining_seconds, Some(300));
+
+        assert_eq!(
+            caffeinate.tick(5 * MINUTE_MS - 1, alive, None).unwrap(),
+            None
+        );
+        assert_eq!(power.held(), vec![SleepKind::Display]);
+
+        let status = caffeinate.tick(5 * MINUTE_MS, alive, None).unwrap();
+        assert_eq!(status, Some(CaffeinateStatus::default()));
+        assert!(power.held().is_empty());
+    }
+
+    #[test]
+    fn process_session_ends_when_the_process_exits() {
+        let (mut caffeinate, power) = caffeinate();
+
+        let status = caffeinate
+            .start(&request(None, Some(PID)), 0, alive)
+            .unwrap();
+        assert_eq!(
+            status.reason,
+            Some(CaffeinateReason::UntilProcess { pid: PID })
+        );
+
+        assert_eq!(caffeinate.tick(MINUTE_MS, alive, None).unwrap(), None);
+        let status = caffeinate.tick(2 * MINUTE_MS, exited, None).unwrap();
+        assert_eq!(status, Some(CaffeinateStatus::default()));
+        assert!(power.held().is_empty());
+    }
+
+    #[test]
+    fn process_that_is_not_running_is_refused() {
+        let (mut caffeinate, power) = caffeinate();
+
+        let error = caffeinate
+            .start(&request(None, Some(PID)), 0, exited)
+            .unwrap_err();
+
+        assert!(matches!(error, SnapError::InvalidArgument { .. }));
+        assert!(power.calls().is_empty());
+    }
+
+    #[test]
+    fn new_session_is_acquired_before_the_old_one_is_released() {
+        let (mut caffeinate, power) = caffeinate();
+
+        caffeinate.start(&request(None, None), 0, alive).unwrap();
+        let status = caffeinate
+            .start(
+                &CaffeinateRequest {
+                    kind: SleepKind::System,
+                    ..request(Some(1), None)
+                },
+                0,
+                alive,
+            )
+            .unwrap();
+
+        assert_eq!(status.kind, Some(SleepKind::System));
+        assert_eq!(
+            power.calls(),
+            vec![
+                Call::Acquire(1, SleepKind::Display),
+                Call::Acquire(2, SleepKind::System),
+                Call::Release(1),
+            ]
+        );
+        assert_eq!(power.held(), vec![SleepKind::System]);
+    }
+
+    #[test]
+    fn stop_releases_the_assertion() {
+        let (mut caffeinate, power) = caffeinate();
+
+        caffeinate.start(&request(None, None), 0, alive).unwrap();
+        let status = caffeinate.stop(0).unwrap();
+
+        assert_eq!(status, CaffeinateStatus::default());
+        assert_eq!(
+            power.calls(),
+            vec![Call::Acquire(1, SleepKind::Display), Call::Release(1)]
+        );
+
+        // Stopping again has nothing left to release
+        caffeinate.stop(0).unwrap();
+        assert_eq!(power.calls().len(), 2);
+    }
+
+    fn on_ac_power() -> Option<(CaffeinateReason, Option<u32>)> {
+        Some((CaffeinateReason::OnAcPower, None))
+    }
+
+    #[test]
+    fn triggers_start_and_end_a_session() {
+        let (mut caffeinate, power) = caffeinate();
+
+        let status = caffeinate.tick(0, alive, on_ac_power()).unwrap().unwrap();
+        assert_eq!(status.reason, Some(CaffeinateReason::OnAcPower));
+        assert_eq!(status.kind, Some(SleepKind::Display));
+
+        // A schedule only updates the countdown of the running session
+        let scheduled = Some((CaffeinateReason::Schedule, Some(60)));
+        let status = caffeinate.tick(0, alive, scheduled).unwrap().unwrap();
+        assert_eq!(status.reason, Some(CaffeinateReason::Schedule));
+        assert_eq!(status.ends_at_ms, Some(MINUTE_MS));
+        assert_eq!(power.held(), vec![SleepKind::Display]);
+
+        let status = caffeinate.tick(MINUTE_MS, alive, None).unwrap();
+        assert_eq!(status, Some(CaffeinateStatus::default()));
+        assert!(power.held().is_empty());
+    }
+
+    #[test]
+    fn stop_suppresses_the_trigger_until_it_stops_matching() {
+        let (mut caffeinate, power) = caffeinate();
+
+        caffeinate.tick(0, alive, on_ac_power()).unwrap();
+        caffeinate.stop(0).unwrap();
+
+        assert_eq!(caffeinate.tick(1000, alive, on_ac_power()).unwrap(), None);
+        assert!(power.held().is_empty());
+
+        // Unplugged and plugged in again
+        assert_eq!(caffeinate.tick(2000, alive, None).unwrap(), None);
+        let status = caffeinate.tick(3000, alive, on_ac_power()).unwrap();
+        assert_eq!(
+            status.and_then(|status| status.reason),
+            Some(CaffeinateReason::OnAcPower)
+        );
+    }
+
+    #[test]
+    fn another_trigger_is_not_suppressed() {
+        let (mut caffeinate, _) = caffeinate();
+
+        caffeinate.tick(0, alive, on_ac_power()).unwrap();
+        caffeinate.stop(0).unwrap();
+
+        let scheduled = Some((CaffeinateReason::Schedule, Some(60)));
+        let status = caffeinate.tick(1000, alive, scheduled).unwrap();
+        assert_eq!(
+            status.and_then(|status| status.reason),
+            Some(CaffeinateReason::Schedule)
+        );
+    }
+
+    #[test]
+    fn triggers_give_way_to_a_manual_session() {
+        let (mut caffeinate, power) = caffeinate();
+
+        caffeinate.tick(0, alive, on_ac_power()).unwrap();
+        let status = caffeinate.start(&request(Some(5), None), 0, alive).unwrap();
+        assert_eq!(status.reason, Some(CaffeinateReason::Timer));
+
+        // The trigger matching or not leaves the timer alone
+        assert_eq!(caffeinate.tick(1000, alive, on_ac_power()).unwrap(), None);
+        assert_eq!(caffeinate.tick(2000, alive, None).unwrap(), None);
+        assert_eq!(power.held(), vec![SleepKind::Display]);
+
+        // Once the timer is up the trigger takes over again
+        let status = caffeinate
+            .tick(5 * MINUTE_MS, alive, on_ac_power())
+            .unwrap()
+            .unwrap();
+        assert_eq!(status.reason, Some(CaffeinateReason::OnAcPower));
+        assert_eq!(power.held(), vec![SleepKind::Display]);
+    }
+
+    #[test]
+    fn a_kind_change_replaces_the_trigger_session() {
+        let (mut caffeinate, power) = caffeinate();
+
+        caffeinate.tick(0, alive, on_ac_power()).unwrap();
+        caffeinate.set_triggers(TriggersConfig {
+            triggers: vec![],
+            kind: SleepKind::System,
+        });
+
+        let status = caffeinate
+            .tick(1000, alive, on_ac_power())
+            .unwrap()
+            .unwrap();
+        assert_eq!(status.kind, Some(SleepKind::System));
+        assert_eq!(
+            power.calls(),
+            vec![
+                Call::Acquire(1, SleepKind::Display),
+                Call::Acquire(2, SleepKind::System),
+                Call::Release(1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,551 @@
+use std::{ffi::c_void, ptr, time::SystemTime};
+
+use core_foundation::{
+    base::{CFRelease, CFTypeRef, TCFType},
+    string::{CFString, CFStringRef},
+};
+use objc2_app_kit::NSWorkspace;
+use serde::{Deserialize, Serialize};
+
+use crate::data::error::{SnapError, SnapResult};
+
+use super::caffeinate::SleepKind;
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+/// `kIOPMACPowerKey`, the providing power source when plugged in
+const AC_POWER: &str = "AC Power";
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn IOPSCopyPowerSourcesInfo() -> CFTypeRef;
+    fn IOPSGetProvidingPowerSourceType(snapshot: CFTypeRef) -> CFStringRef;
+}
+
+/// `struct tm` of the macOS libc
+#[repr(C)]
+struct Tm {
+    tm_sec: i32,
+    tm_min: i32,
+    tm_hour: i32,
+    tm_mday: i32,
+    tm_mon: i32,
+    tm_year: i32,
+    tm_wday: i32,
+    tm_yday: i32,
+    tm_isdst: i32,
+    tm_gmtoff: i64,
+    tm_zone: *const c_void,
+}
+
+extern "C" {
+    fn localtime_r(time: *const i64, result: *mut Tm) -> *mut Tm;
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    /// From `tm_wday`, where Sunday is 0
+    fn from_sunday_based(day: i32) -> Self {
+        Weekday::ALL[(day.rem_euclid(7) as usize + 6) % 7]
+    }
+
+    fn previous(&self) -> Self {
+        let index = Weekday::ALL.iter().position(|day| day == self).unwrap_or(0);
+        Weekday::ALL[(index + 6) % 7]
+    }
+}
+
+/// Parses `HH:MM` into seconds since midnight
+fn parse_time(value: &str) -> Option<u32> {
+    let (hours, minutes) = value.split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 3600 + minutes * 60)
+}
+
+/// When caffeinate turns itself on, the first matching trigger wins
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "trigger", rename_all = "snake_case")]
+pub enum CaffeinateTrigger {
+    /// `app` is a bundle id or an application name, compared case-insensitively
+    AppRunning {
+        app: String,
+    },
+    AppFrontmost {
+        app: String,
+    },
+    OnAcPower,
+    /// From `start` to `end` (`HH:MM`, local time) on the given days, an `end` before
+    /// `start` runs past midnight into the next day
+    Schedule {
+        weekdays: Vec<Weekday>,
+        start: String,
+        end: String,
+    },
+}
+
+impl CaffeinateTrigger {
+    fn validate(&self) -> SnapResult<()> {
+        match self {
+            CaffeinateTrigger::AppRunning { app } | CaffeinateTrigger::AppFrontmost { app }
+                if app.trim().is_empty() =>
+            {
+                Err(SnapError::invalid_argument("app", "must not be empty"))
+            }
+            CaffeinateTrigger::Schedule {
+                weekdays,
+                start,
+                end,
+            } => {
+                if weekdays.is_empty() {
+                    return Err(SnapError::invalid_argument(
+                        "weekdays",
+                        "at least one day is needed",
+                    ));
+                }
+                let (Some(start_at), Some(end_at)) = (parse_time(start), parse_time(end)) else {
+                    return Err(SnapError::invalid_argument(
+                        "schedule",
+                        format!("{} to {} is not HH:MM to HH:MM", start, end),
+                    ));
+                };
+                if start_at == end_at {
+                    return Err(SnapError::invalid_argument(
+                        "schedule",
+                        "start and end must differ",
+                    ));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct TriggersConfig {
+    pub triggers: Vec<CaffeinateTrigger>,
+    /// What the triggers keep awake
+    pub kind: SleepKind,
+}
+
+impl TriggersConfig {
+    pub fn validate(&self) -> SnapResult<()> {
+        self.triggers
+            .iter()
+            .try_for_each(CaffeinateTrigger::validate)
+    }
+}
+
+/// Why the Mac is kept awake
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum CaffeinateReason {
+    Manual,
+    Timer,
+    UntilProcess { pid: i32 },
+    AppRunning { app: String },
+    AppFrontmost { app: String },
+    OnAcPower,
+    Schedule,
+}
+
+impl CaffeinateReason {
+    /// Set by a trigger rather than by hand
+    pub fn is_automatic(&self) -> bool {
+        !matches!(
+            self,
+            CaffeinateReason::Manual
+                | CaffeinateReason::Timer
+                | CaffeinateReason::UntilProcess { .. }
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunningApp {
+    pub bundle_id: Option<String>,
+    pub name: Option<String>,
+}
+
+impl RunningApp {
+    fn matches(&self, app: &str) -> bool {
+        let app = app.trim();
+        [&self.bundle_id, &self.name]
+            .into_iter()
+            .flatten()
+            .any(|value| value.eq_ignore_ascii_case(app))
+    }
+}
+
+/// What the triggers are checked against
+#[derive(Clone, Debug, PartialEq)]
+pub struct TriggerEnvironment {
+    pub running: Vec<RunningApp>,
+    pub frontmost: Option<RunningApp>,
+    pub on_ac_power: bool,
+    pub weekday: Weekday,
+    /// Local time
+    pub seconds_of_day: u32,
+}
+
+/// Seconds until the schedule ends when it is running now
+fn schedule_remaining(
+    weekdays: &[Weekday],
+    start: u32,
+    end: u32,
+    env: &TriggerEnvironment,
+) -> Option<u32> {
+    let now = env.seconds_of_day;
+    let today = weekdays.contains(&env.weekday);
+    let yesterday = weekdays.contains(&env.weekday.previous());
+
+    if start < end {
+        (today && (start..end).contains(&now)).then(|| end - now)
+    } else if today && now >= start {
+        Some(SECONDS_PER_DAY - now + end)
+    } else if yesterday && now < end {
+        Some(end - now)
+    } else {
+        None
+    }
+}
+
+/// The first matching trigger as a reason, with the seconds left for a schedule
+pub fn evaluate(
+    triggers: &[CaffeinateTrigger],
+    env: &TriggerEnvironment,
+) -> Option<(CaffeinateReason, Option<u32>)> {
+    triggers.iter().find_map(|trigger| match trigger {
+        CaffeinateTrigger::AppRunning { app } => env
+            .running
+            .iter()
+            .any(|running| running.matches(app))
+            .then(|| (CaffeinateReason::AppRunning { app: app.clone() }, None)),
+        CaffeinateTrigger::AppFrontmost { app } => env
+            .frontmost
+            .as_ref()
+            .is_some_and(|frontmost| frontmost.matches(app))
+            .then(|| (CaffeinateReason::AppFrontmost { app: app.clone() }, None)),
+        CaffeinateTrigger::OnAcPower => env
+            .on_ac_power
+            .then_some((CaffeinateReason::OnAcPower, None)),
+        CaffeinateTrigger::Schedule {
+            weekdays,
+            start,
+            end,
+        } => {
+            let remaining =
+                schedule_remaining(weekdays, parse_time(start)?, parse_time(end)?, env)?;
+            Some((CaffeinateReason::Schedule, Some(remaining)))
+        }
+    })
+}
+
+fn running_app(app: &objc2_app_kit::NSRunningApplication) -> RunningApp {
+    unsafe {
+        RunningApp {
+            bundle_id: app
+                .bundleIdentifier()
+                .map(|bundle_id| bundle_id.to_string()),
+            name: app.localizedName().map(|name| name.to_string()),
+        }
+    }
+}
+
+fn on_ac_power() -> bool {
+    unsafe {
+        let snapshot = IOPSCopyPowerSourcesInfo();
+        if snapshot.is_null() {
+            return false;
+        }
+        let source = IOPSGetProvidingPowerSourceType(snapshot);
+        // Follows the get rule, owned by the snapshot
+        let on_ac =
+            !source.is_null() && CFString::wrap_under_get_rule(source).to_string() == AC_POWER;
+        CFRelease(snapshot);
+        on_ac
+    }
+}
+
+fn local_time() -> (Weekday, u32) {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default();
+    let mut tm = Tm {
+        tm_sec: 0,
+        tm_min: 0,
+        tm_hour: 0,
+        tm_mday: 0,
+        tm_mon: 0,
+        tm_year: 0,
+        tm_wday: 0,
+        tm_yday: 0,
+        tm_isdst: 0,
+        tm_gmtoff: 0,
+        tm_zone: ptr::null(),
+    };
+    unsafe { localtime_r(&now, &mut tm) };
+
+    (
+        Weekday::from_sunday_based(tm.tm_wday),
+        (tm.tm_hour * 3600 + tm.tm_min * 60 + tm.tm_sec) as u32,
+    )
+}
+
+/// Reads the running apps, the power source and the local time, only the apps
+/// when a trigger needs them
+pub fn current_environment(triggers: &[CaffeinateTrigger]) -> TriggerEnvironment {
+    let needs_apps = triggers.iter().any(|trigger| {
+        matches!(
+            trigger,
+            CaffeinateTrigger::AppRunning { .. } | CaffeinateTrigger::AppFrontmost { .. }
+        )
+    });
+    let (running, frontmost) = if needs_apps {
+        let workspace = unsafe { NSWorkspace::sharedWorkspace() };
+        let running = unsafe { workspace.runningApplications() }
+            .iter()
+            .map(running_app)
+            .collect();
+        let frontmost = unsafe { workspace.frontmostApplication() }.map(|app| running_app(&app));
+        (running, frontmost)
+    } else {
+        (vec![], None)
+    };
+    let (weekday, seconds_of_day) = local_time();
+
+    TriggerEnvironment {
+        running,
+        frontmost,
+        on_ac_power: triggers.contains(&CaffeinateTrigger::OnAcPower) && on_ac_power(),
+        weekday,
+        seconds_of_day,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u32 = 3600;
+
+    fn app(bundle_id: &str, name: &str) -> RunningApp {
+        RunningApp {
+            bundle_id: Some(String::from(bundle_id)),
+            name: Some(String::from(name)),
+        }
+    }
+
+    /// Monday noon on battery, Xcode running and Safari in front
+    fn env() -> TriggerEnvironment {
+        TriggerEnvironment {
+            running: vec![
+                app("com.apple.dt.Xcode", "Xcode"),
+                app("com.apple.Safari", "Safari"),
+            ],
+            frontmost: Some(app("com.apple.Safari", "Safari")),
+            on_ac_power: false,
+            weekday: Weekday::Monday,
+            seconds_of_day: 12 * HOUR,
+        }
+    }
+
+    fn at(weekday: Weekday, seconds_of_day: u32) -> TriggerEnvironment {
+        TriggerEnvironment {
+            weekday,
+            seconds_of_day,
+            ..env()
+        }
+    }
+
+    fn schedule(weekdays: &[Weekday], start: &str, end: &str) -> CaffeinateTrigger {
+        CaffeinateTrigger::Schedule {
+            weekdays: weekdays.to_vec(),
+            start: String::from(start),
+            end: String::from(end),
+        }
+    }
+
+    #[test]
+    fn parse_time_reads_hours_and_minutes() {
+        assert_eq!(parse_time("00:00"), Some(0));
+        assert_eq!(parse_time("09:30"), Some(9 * HOUR + 30 * 60));
+        assert_eq!(parse_time("23:59"), Some(23 * HOUR + 59 * 60));
+
+        for value in ["24:00", "12:60", "12", "12:", ":30", "ab:cd", "-1:00", ""] {
+            assert_eq!(parse_time(value), None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn weekdays_convert_from_sunday_based_days() {
+        assert_eq!(Weekday::from_sunday_based(0), Weekday::Sunday);
+        assert_eq!(Weekday::from_sunday_based(1), Weekday::Monday);
+        assert_eq!(Weekday::from_sunday_based(6), Weekday::Saturday);
+        assert_eq!(Weekday::from_sunday_based(7), Weekday::Sunday);
+
+        assert_eq!(Weekday::Monday.previous(), Weekday::Sunday);
+        assert_eq!(Weekday::Sunday.previous(), Weekday::Saturday);
+    }
+
+    #[test]
+    fn apps_match_by_bundle_id_or_name_ignoring_case() {
+        let running = |app: &str| CaffeinateTrigger::AppRunning {
+            app: String::from(app),
+        };
+
+        assert_eq!(
+            evaluate(&[running("xcode")], &env()),
+            Some((
+                CaffeinateReason::AppRunning {
+                    app: String::from("xcode")
+                },
+                None
+            ))
+        );
+        assert!(evaluate(&[running(" COM.APPLE.DT.XCODE ")], &env()).is_some());
+        assert!(evaluate(&[running("Keynote")], &env()).is_none());
+
+        let frontmost = |app: &str| CaffeinateTrigger::AppFrontmost {
+            app: String::from(app),
+        };
+        assert!(evaluate(&[frontmost("Safari")], &env()).is_some());
+        assert!(evaluate(&[frontmost("Xcode")], &env()).is_none());
+
+        let nothing_in_front = TriggerEnvironment {
+            frontmost: None,
+            ..env()
+        };
+        assert!(evaluate(&[frontmost("Safari")], &nothing_in_front).is_none());
+    }
+
+    #[test]
+    fn the_first_matching_trigger_wins() {
+        let triggers = [
+            CaffeinateTrigger::OnAcPower,
+            CaffeinateTrigger::AppRunning {
+                app: String::from("Keynote"),
+            },
+            schedule(&[Weekday::Monday], "11:00", "13:00"),
+            CaffeinateTrigger::AppRunning {
+                app: String::from("Xcode"),
+            },
+        ];
+
+        assert_eq!(
+            evaluate(&triggers, &env()),
+            Some((CaffeinateReason::Schedule, Some(HOUR)))
+        );
+
+        let plugged_in = TriggerEnvironment {
+            on_ac_power: true,
+            ..env()
+        };
+        assert_eq!(
+            evaluate(&triggers, &plugged_in),
+            Some((CaffeinateReason::OnAcPower, None))
+        );
+
+        assert_eq!(evaluate(&[], &env()), None);
+    }
+
+    #[test]
+    fn schedules_run_on_their_days_only() {
+        let office = [schedule(
+            &[Weekday::Monday, Weekday::Tuesday],
+            "09:00",
+            "17:00",
+        )];
+
+        assert_eq!(
+            evaluate(&office, &at(Weekday::Monday, 9 * HOUR)),
+            Some((CaffeinateReason::Schedule, Some(8 * HOUR)))
+        );
+        // The end is excluded
+        assert_eq!(evaluate(&office, &at(Weekday::Monday, 17 * HOUR)), None);
+        assert_eq!(evaluate(&office, &at(Weekday::Monday, 8 * HOUR)), None);
+        assert_eq!(evaluate(&office, &at(Weekday::Sunday, 12 * HOUR)), None);
+    }
+
+    #[test]
+    fn schedules_past_midnight_continue_into_the_next_day() {
+        let night = [Weekday::Friday];
+        let (start, end) = (22 * HOUR, 2 * HOUR);
+
+        // Friday night counts up to midnight and on into Saturday
+        assert_eq!(
+            schedule_remaining(&night, start, end, &at(Weekday::Friday, 23 * HOUR)),
+            Some(3 * HOUR)
+        );
+        assert_eq!(
+            schedule_remaining(&night, start, end, &at(Weekday::Saturday, HOUR)),
+            Some(HOUR)
+        );
+        assert_eq!(
+            schedule_remaining(&night, start, end, &at(Weekday::Saturday, end)),
+            None
+        );
+
+        // Friday early morning belongs to Thursday night, which is not scheduled
+        assert_eq!(
+            schedule_remaining(&night, start, end, &at(Weekday::Friday, HOUR)),
+            None
+        );
+        assert_eq!(
+            schedule_remaining(&night, start, end, &at(Weekday::Saturday, 23 * HOUR)),
+            None
+        );
+
+        // Sunday night runs into Monday, the week wraps around
+        assert_eq!(
+            schedule_remaining(&[Weekday::Sunday], start, end, &at(Weekday::Monday, 0)),
+            Some(2 * HOUR)
+        );
+    }
+
+    #[test]
+    fn validate_rejects_unusable_triggers() {
+        let config = |trigger: CaffeinateTrigger| TriggersConfig {
+            triggers: vec![trigger],
+            kind: SleepKind::Display,
+        };
+
+        assert!(config(schedule(&[Weekday::Monday], "22:00", "02:00"))
+            .validate()
+            .is_ok());
+        assert!(config(schedule(&[], "09:00", "17:00")).validate().is_err());
+        assert!(config(schedule(&[Weekday::Monday], "9am", "17:00"))
+            .validate()
+            .is_err());
+        assert!(config(schedule(&[Weekday::Monday], "09:00", "09:00"))
+            .validate()
+            .is_err());
+        assert!(config(CaffeinateTrigger::AppRunning {
+            app: String::from("  ")
+        })
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use tauri::AppHandle;
+use tauri_plugin_deep_link::DeepLinkExt;
+use url::Url;
+
+use crate::data::{
+    action::{ArrangeStrategy, SnapAction, WindowManagerMode},
+    error::{SnapError, SnapResult},
+    frame::{Frame, Point, Size},
+};
+
+use super::actions::dispatch;
+
+pub const SCHEME: &str = "snap";
+
+/// Mappings of `snap://action/<name>` as x, y, width and height relative to the screen
+const PRESETS: &[(&str, [f64; 4])] = &[
+    ("maximize", [0.0, 0.0, 1.0, 1.0]),
+    ("left-half", [0.0, 0.0, 0.5, 1.0]),
+    ("right-half", [0.5, 0.0, 0.5, 1.0]),
+    ("top-half", [0.0, 0.0, 1.0, 0.5]),
+    ("bottom-half", [0.0, 0.5, 1.0, 0.5]),
+    ("top-left", [0.0, 0.0, 0.5, 0.5]),
+    ("top-right", [0.5, 0.0, 0.5, 0.5]),
+    ("bottom-left", [0.0, 0.5, 0.5, 0.5]),
+    ("bottom-right", [0.5, 0.5, 0.5, 0.5]),
+    ("left-third", [0.0, 0.0, 1.0 / 3.0, 1.0]),
+    ("center-third", [1.0 / 3.0, 0.0, 1.0 / 3.0, 1.0]),
+    ("right-third", [2.0 / 3.0, 0.0, 1.0 / 3.0, 1.0]),
+    ("left-two-thirds", [0.0, 0.0, 2.0 / 3.0, 1.0]),
+    ("center-two-thirds", [1.0 / 6.0, 0.0, 2.0 / 3.0, 1.0]),
+    ("right-two-thirds", [1.0 / 3.0, 0.0, 2.0 / 3.0, 1.0]),
+];
+
+fn invalid(reason: impl Into<String>) -> SnapError {
+    SnapError::invalid_argument("url", reason)
+}
+
+fn preset(name: &str) -> SnapResult<Frame> {
+    let Some((_, [x, y, width, height])) = PRESETS.iter().find(|(preset, _)| *preset == name)
+    else {
+        let names: Vec<&str> = PRESETS.iter().map(|(preset, _)| *preset).collect();
+        return Err(SnapError::invalid_argument(
+            "action",
+            format!(
+                "unknown action {}, expected one of {}",
+                name,
+                names.join(", ")
+            ),
+        ));
+    };
+
+    Ok(Frame {
+        size: Size {
+            width: *width,
+            height: *height,
+        },
+        position: Point { x: *x, y: *y },
+    })
+}
+
+/// Query parameters of the link, anything not in `allowed` or given twice is an error
+fn query(url: &Url, allowed: &[&str]) -> SnapResult<HashMap<String, String>> {
+    let mut params = HashMap::new();
+
+    for (key, value) in url.query_pairs() {
+        if !allowed.contains(&key.as_ref()) {
+            return Err(invalid(format!("unexpected parameter {}", key)));
+        }
+        if params.insert(key.to_string(), value.to_string()).is_some() {
+            return Err(invalid(format!("{} is given more than once", key)));
+        }
+    }
+
+    Ok(params)
+}
+
+fn parse_enabled(value: &str) -> SnapResult<bool> {
+    match value {
+        "true" | "on" | "1" => Ok(true),
+        "false" | "off" | "0" => Ok(false),
+        _ => Err(SnapError::invalid_argument(
+            "enabled",
+            format!("{} is not true or false", value),
+        )),
+    }
+}
+
+fn parse_minutes(value: &str) -> SnapResult<u32> {
+    match value.parse::<u32>() {
+        Ok(minutes) if minutes > 0 => Ok(minutes),
+        _ => Err(SnapError::invalid_argument(
+            "minutes",
+            format!("{} is not a positive number of minutes", value),
+        )),
+    }
+}
+
+/// Turns a link such as `snap://action/left-half`, `snap://arrange?strategy=grid`,
+/// `snap://mode/fancy_zones`, `snap://caffeinate?minutes=30`, `snap://script?name=work`
+/// or `snap://layout/restore?name=work` into its action
+pub fn parse(url: &Url) -> SnapResult<SnapAction> {
+    if url.scheme() != SCHEME {
+        return Err(invalid(format!("expected a {}:// link", SCHEME)));
+    }
+
+    let path: Vec<&str> = url
+        .path_segments()
+        .into_iter()
+        .flatten()
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let action = url.host_str().unwrap_or_default();
+
+    match (action, path.as_slice()) {
+        ("action", [name]) => {
+            query(url, &[])?;
+            Ok(SnapAction::Mapping {
+                frame: preset(name)?,
+            })
+        }
+        ("action", _) => Err(invalid("expected snap://action/<name>")),
+        ("arrange", []) => {
+            let params = query(url, &["strategy", "script", "plugin"])?;
+            let strategy = match (
+                params.get("strategy").map(String::as_str),
+                params.get("script"),
+                params.get("plugin"),
+            ) {
+                (None | Some("script"), Some(script), None) => {
+                    ArrangeStrategy::Script(script.clone())
+                }
+                (None | Some("plugin"), None, Some(plugin)) => {
+                    ArrangeStrategy::Plugin(plugin.clone())
+                }
+                (_, Some(_), _) | (_, _, Some(_)) => {
+                    return Err(invalid(
+                        "script only goes with strategy=script and plugin with strategy=plugin",
+                    ))
+                }
+                (None | Some("split"), None, None) => ArrangeStrategy::Split,
+                (Some("grid"), None, None) => ArrangeStrategy::Grid,
+                (Some(kind @ ("script" | "plugin")), None, None) => {
+                    return Err(SnapError::invalid_argument(
+                        kind,
+                        format!("a {} name is needed", kind),
+                    ))
+                }
+                (Some(strategy), None, None) => {
+                    return Err(SnapError::invalid_argument(
+                        "strategy",
+                        format!("{} is not split, grid, script or plugin", strategy),
+                    ))
+                }
+            };
+            Ok(SnapAction::Arrange { strategy })
+        }
+        ("mode", [mode]) => {
+            query(url, &[])?;
+            let mode = match *mode {
+                "snapping" => WindowManagerMode::Snapping,
+                "fancy_zones" | "fancy-zones" => WindowManagerMode::FancyZones,
+                _ => {
+                    return Err(SnapError::invalid_argument(
+                        "mode",
+                        format!("{} is not snapping or fancy_zones", mode),
+                    ))
+                }
+            };
+            Ok(SnapAction::SetMode { mode })
+        }
+        ("mode", _) => Err(invalid("expected snap://mode/<snapping|fancy_zones>")),
+        ("caffeinate", []) => {
+            let params = query(url, &["enabled", "minutes"])?;
+            let enabled = params.get("enabled").map(|value| parse_enabled(value));
+            let minutes = params.get("minutes").map(|value| parse_minutes(value));
+
+            match (enabled.transpose()?, minutes.transpose()?) {
+                (None, None) => Ok(SnapAction::ToggleCaffeinate),
+                (Some(false), Some(_)) => Err(SnapError::invalid_argument(
+                    "minutes",
+                    "only applies when enabling",
+                )),
+                (enabled, minutes) => Ok(SnapAction::SetCaffeinate {
+                    enabled: enabled.unwrap_or(true),
+                    minutes,
+                }),
+            }
+        }
+        ("script", []) => {
+            let mut params = query(url, &["name"])?;
+            let name = params
+                .remove("name")
+                .ok_or_else(|| SnapError::invalid_argument("name", "a script name is needed"))?;
+            Ok(SnapAction::RunScript { name })
+        }
+        ("layout", [command @ ("save" | "restore")]) => {
+            let mut params = query(url, &["name"])?;
+            let name = params
+                .remove("name")
+                .filter(|name| !name.trim().is_empty())
+                .ok_or_else(|| SnapError::invalid_argument("name", "a layout name is needed"))?;
+            Ok(match *command {
+                "save" => SnapAction::SaveLayout { name },
+                _ => SnapAction::RestoreLayout { name },
+            })
+        }
+        ("layout", _) => Err(invalid("expected snap://layout/<save|restore>?name=<name>")),
+        ("arrange" | "caffeinate" | "script", _) => Err(invalid(format!(
+            "snap://{} takes no path, only parameters",
+            action
+        ))),
+        ("", _) => Err(invalid("missing action")),
+        _ => Err(invalid(format!("unknown action {}", action))),
+    }
+}
+
+fn open(app: &AppHandle, url: &Url) {
+    if let Err(error) = parse(url).and_then(|action| dispatch(app, action)) {
+        eprintln!("can't open {}: {}", url, error);
+    }
+}
+
+/// Runs the links opened while Snap is running, or the one that launched it
+pub fn listen(app: &AppHandle) {
+    let handle = app.clone();
+
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            open(&handle, &url);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(url: &str) -> SnapResult<SnapAction> {
+        parse(&Url::parse(url).unwrap())
+    }
+
+    fn invalid_argument(result: SnapResult<SnapAction>) -> String {
+        match result {
+            Err(SnapError::InvalidArgument { argument, .. }) => argument,
+            result => panic!("expected an invalid argument, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn layout_links() {
+        assert_eq!(
+            parse_str("snap://layout/restore?name=work"),
+            Ok(SnapAction::RestoreLayout {
+                name: String::from("work")
+            })
+        );
+        assert_eq!(
+            parse_str("snap://layout/save?name=deep%20work"),
+            Ok(SnapAction::SaveLayout {
+                name: String::from("deep work")
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_layout_links() {
+        assert_eq!(invalid_argument(parse_str("snap://layout/restore")), "name");
+        assert_eq!(
+            invalid_argument(parse_str("snap://layout/restore?name=")),
+            "name"
+        );
+        assert_eq!(
+            invalid_argument(parse_str("snap://layout/delete?name=work")),
+            "url"
+        );
+        assert_eq!(
+            invalid_argument(parse_str("snap://layout?name=work")),
+            "url"
+        );
+        assert_eq!(
+            invalid_argument(parse_str("snap://layout/restore?name=work&screen=1")),
+            "url"
+        );
+    }
+
+    #[test]
+    fn action_links() {
+        assert_eq!(
+            parse_str("snap://action/left-half"),
+            Ok(SnapAction::Mapping {
+                frame: preset("left-half").unwrap()
+            })
+        );
+        assert_eq!(
+            parse_str("snap://arrange?strategy=grid"),
+            Ok(SnapAction::Arrange {
+                strategy: ArrangeStrategy::Grid
+            })
+        );
+        assert_eq!(
+            parse_str("snap://caffeinate?minutes=30"),
+            Ok(SnapAction::SetCaffeinate {
+                enabled: true,
+                minutes: Some(30)
+            })
+        );
+        assert_eq!(
+            invalid_argument(parse_str("snap://action/sideways")),
+            "action"
+        );
+        assert_eq!(
+            invalid_argument(parse_str("https://action/left-half")),
+            "url"
+        );
+    }
+}
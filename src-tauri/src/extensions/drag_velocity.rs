@@ -0,0 +1,355 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use crate::data::{
+    alignment::Alignment,
+    frame::{Frame, Point},
+};
+
+/// A single drag position with the `NSEvent` timestamp (seconds since boot)
+#[derive(Clone, Copy, Debug)]
+pub struct DragSample {
+    pub point: (f64, f64),
+    pub timestamp: f64,
+}
+
+/// Thresholds for turning a drag release into a throw
+#[derive(Clone, Debug)]
+pub struct ThrowConfig {
+    /// Minimum release speed in points per second
+    pub min_speed: f64,
+    /// Only samples this close to the release are used for the estimate
+    pub sample_window_ms: f64,
+    /// How far ahead the release position is projected along the velocity
+    pub projection_ms: f64,
+    /// Distance from a screen edge the projected point has to reach
+    pub edge_reach: f64,
+    /// Minimal share of the speed on the secondary axis to treat the throw as diagonal
+    pub corner_ratio: f64,
+}
+
+impl Default for ThrowConfig {
+    fn default() -> Self {
+        Self {
+            min_speed: 1500.0,
+            sample_window_ms: 80.0,
+            projection_ms: 250.0,
+            edge_reach: 100.0,
+            corner_ratio: 0.38,
+        }
+    }
+}
+
+/// Keeps the most recent drag samples and estimates the release velocity
+pub struct VelocityEstimator {
+    samples: VecDeque<DragSample>,
+    released_at: Option<f64>,
+    capacity: usize,
+}
+
+impl Default for VelocityEstimator {
+    fn default() -> Self {
+        Self::new(32)
+    }
+}
+
+impl VelocityEstimator {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            released_at: None,
+            capacity: capacity.max(2),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.samples.clear();
+        self.released_at = None;
+    }
+
+    pub fn push(&mut self, sample: DragSample) {
+        // Several monitors can deliver the same event, keep the buffer strictly ordered
+        if let Some(last) = self.samples.back() {
+            if sample.timestamp <= last.timestamp {
+                return;
+            }
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn release(&mut self, timestamp: f64) {
+        self.released_at = Some(timestamp);
+    }
+
+    pub fn last_point(&self) -> Option<(f64, f64)> {
+        self.samples.back().map(|sample| sample.point)
+    }
+
+    /// Least-squares slope of the samples inside the window, in points per second.
+    /// Returns zero when the cursor was held still before the release.
+    pub fn velocity(&self, sample_window_ms: f64) -> (f64, f64) {
+        let Some(last) = self.samples.back() else {
+            return (0.0, 0.0);
+        };
+        let window = sample_window_ms / 1000.0;
+
+        if let Some(released_at) = self.released_at {
+            if released_at - last.timestamp > window {
+                return (0.0, 0.0);
+            }
+        }
+
+        let recent: Vec<&DragSample> = self
+            .samples
+            .iter()
+            .filter(|sample| last.timestamp - sample.timestamp <= window)
+            .collect();
+
+        if recent.len() < 2 {
+            return (0.0, 0.0);
+        }
+
+        let count = recent.len() as f64;
+        let mean_t = recent.iter().map(|s| s.timestamp).sum::<f64>() / count;
+        let mean_x = recent.iter().map(|s| s.point.0).sum::<f64>() / count;
+        let mean_y = recent.iter().map(|s| s.point.1).sum::<f64>() / count;
+
+        let mut variance = 0.0;
+        let mut covariance_x = 0.0;
+        let mut covariance_y = 0.0;
+        for sample in recent {
+            let dt = sample.timestamp - mean_t;
+            variance += dt * dt;
+            covariance_x += dt * (sample.point.0 - mean_x);
+            covariance_y += dt * (sample.point.1 - mean_y);
+        }
+
+        if variance <= f64::EPSILON {
+            return (0.0, 0.0);
+        }
+
+        (covariance_x / variance, covariance_y / variance)
+    }
+}
+
+/// Snap region picked by a throw together with the release velocity
+#[derive(Serialize, Clone, Debug)]
+pub struct Throw {
+    pub alignment: Alignment,
+    pub velocity: Point,
+}
+
+/// Resolves a throw from the recorded samples.
+/// `screen` is the CG (top-left origin) frame of the screen the drag ended on.
+pub fn detect_throw(
+    estimator: &VelocityEstimator,
+    screen: &Frame,
+    config: &ThrowConfig,
+) -> Option<Throw> {
+    let (x, y) = estimator.last_point()?;
+    let (vx, vy) = estimator.velocity(config.sample_window_ms);
+    let speed = (vx * vx + vy * vy).sqrt();
+
+    if speed < config.min_speed {
+        return None;
+    }
+
+    let projection = config.projection_ms / 1000.0;
+    let projected_x = x + vx * projection;
+    let projected_y = y + vy * projection;
+
+    let left = screen.position.x;
+    let top = screen.position.y;
+    let right = left + screen.size.width;
+    let bottom = top + screen.size.height;
+
+    let reaches_left = vx < 0.0 && projected_x <= left + config.edge_reach;
+    let reaches_right = vx > 0.0 && projected_x >= right - config.edge_reach;
+    let reaches_top = vy < 0.0 && projected_y <= top + config.edge_reach;
+    let reaches_bottom = vy > 0.0 && projected_y >= bottom - config.edge_reach;
+
+    let horizontal = vx.abs() / speed >= config.corner_ratio;
+    let vertical = vy.abs() / speed >= config.corner_ratio;
+
+    let column = match (horizontal, reaches_left, reaches_right) {
+        (true, true, _) => -1.0,
+        (true, _, true) => 1.0,
+        _ => 0.0,
+    };
+    let row = match (vertical, reaches_top, reaches_bottom) {
+        (true, true, _) => -1.0,
+        (true, _, true) => 1.0,
+        _ => 0.0,
+    };
+
+    // There is no bottom-center snap side, a plain downward flick is ignored
+    if column == 0.0 && row >= 0.0 {
+        return None;
+    }
+
+    Some(Throw {
+        alignment: Alignment::new(column, row),
+        velocity: Point { x: vx, y: vy },
+    })
+}
+
+/// Samples of the drag currently tracked by the global event monitors
+pub static DRAG_VELOCITY: Lazy<Mutex<VelocityEstimator>> =
+    Lazy::new(|| Mutex::new(VelocityEstimator::default()));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Frame = Frame {
+        size: crate::data::frame::Size {
+            width: 1440.0,
+            height: 900.0,
+        },
+        position: Point { x: 0.0, y: 0.0 },
+    };
+
+    /// Samples every 8 ms moving with a constant velocity, the last one at `to`
+    fn stream(to: (f64, f64), velocity: (f64, f64), count: usize) -> VelocityEstimator {
+        let mut estimator = VelocityEstimator::default();
+        let step = 0.008;
+        for i in 0..count {
+            let back = (count - 1 - i) as f64 * step;
+            estimator.push(DragSample {
+                point: (to.0 - velocity.0 * back, to.1 - velocity.1 * back),
+                timestamp: 10.0 - back,
+            });
+        }
+        estimator.release(10.0);
+        estimator
+    }
+
+    fn assert_close(actual: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-6 && (actual.1 - expected.1).abs() < 1e-6,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn estimates_a_constant_velocity() {
+        let estimator = stream((700.0, 400.0), (2000.0, -500.0), 10);
+        assert_close(estimator.velocity(80.0), (2000.0, -500.0));
+    }
+
+    #[test]
+    fn only_recent_samples_count() {
+        let mut estimator = VelocityEstimator::default();
+        // Slow at first, then fast for the last 80 ms
+        for i in 0..10 {
+            estimator.push(DragSample {
+                point: (i as f64, 0.0),
+                timestamp: 9.0 + i as f64 * 0.01,
+            });
+        }
+        for i in 1..=10 {
+            estimator.push(DragSample {
+                point: (9.0 + i as f64 * 24.0, 0.0),
+                timestamp: 9.09 + i as f64 * 0.008,
+            });
+        }
+        assert_close(estimator.velocity(75.0), (3000.0, 0.0));
+    }
+
+    #[test]
+    fn holding_still_before_release_stops_the_throw() {
+        let mut estimator = stream((700.0, 400.0), (3000.0, 0.0), 10);
+        estimator.release(10.5);
+        assert_close(estimator.velocity(80.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn out_of_order_and_duplicate_samples_are_dropped() {
+        let mut estimator = stream((700.0, 400.0), (1000.0, 0.0), 5);
+        estimator.push(DragSample {
+            point: (0.0, 0.0),
+            timestamp: 9.99,
+        });
+        estimator.push(DragSample {
+            point: (0.0, 0.0),
+            timestamp: 10.0,
+        });
+        assert_eq!(estimator.last_point(), Some((700.0, 400.0)));
+        assert_close(estimator.velocity(80.0), (1000.0, 0.0));
+    }
+
+    #[test]
+    fn capacity_keeps_the_latest_samples() {
+        let mut estimator = VelocityEstimator::new(3);
+        for i in 0..5 {
+            estimator.push(DragSample {
+                point: (i as f64, 0.0),
+                timestamp: i as f64,
+            });
+        }
+        assert_eq!(estimator.samples.len(), 3);
+        assert_eq!(estimator.samples.front().map(|s| s.point), Some((2.0, 0.0)));
+    }
+
+    #[test]
+    fn a_single_sample_has_no_velocity() {
+        let estimator = stream((700.0, 400.0), (3000.0, 0.0), 1);
+        assert_close(estimator.velocity(80.0), (0.0, 0.0));
+        assert!(detect_throw(&estimator, &SCREEN, &ThrowConfig::default()).is_none());
+    }
+
+    #[test]
+    fn flick_toward_an_edge_picks_that_side() {
+        let config = ThrowConfig::default();
+        let left = stream((300.0, 450.0), (-2500.0, 0.0), 10);
+        let throw = detect_throw(&left, &SCREEN, &config).unwrap();
+        assert_eq!(throw.alignment, Alignment::new(-1.0, 0.0));
+
+        let top = stream((720.0, 200.0), (0.0, -2500.0), 10);
+        let throw = detect_throw(&top, &SCREEN, &config).unwrap();
+        assert_eq!(throw.alignment, Alignment::new(0.0, -1.0));
+    }
+
+    #[test]
+    fn diagonal_flick_picks_a_corner() {
+        let estimator = stream((1200.0, 750.0), (2000.0, 1500.0), 10);
+        let throw = detect_throw(&estimator, &SCREEN, &ThrowConfig::default()).unwrap();
+        assert_eq!(throw.alignment, Alignment::new(1.0, 1.0));
+    }
+
+    #[test]
+    fn slow_or_short_drags_are_not_throws() {
+        let config = ThrowConfig::default();
+        let slow = stream((300.0, 450.0), (-800.0, 0.0), 10);
+        assert!(detect_throw(&slow, &SCREEN, &config).is_none());
+
+        // Fast, but the projection stays far from the edge
+        let short = stream((900.0, 450.0), (-1600.0, 0.0), 10);
+        assert!(detect_throw(&short, &SCREEN, &config).is_none());
+    }
+
+    #[test]
+    fn plain_downward_flick_is_ignored() {
+        let estimator = stream((720.0, 750.0), (0.0, 3000.0), 10);
+        assert!(detect_throw(&estimator, &SCREEN, &ThrowConfig::default()).is_none());
+    }
+
+    #[test]
+    fn thresholds_come_from_the_config() {
+        let estimator = stream((300.0, 450.0), (-800.0, 0.0), 10);
+        let config = ThrowConfig {
+            min_speed: 500.0,
+            edge_reach: 200.0,
+            ..ThrowConfig::default()
+        };
+        let throw = detect_throw(&estimator, &SCREEN, &config).unwrap();
+        assert_eq!(throw.alignment, Alignment::new(-1.0, 0.0));
+    }
+}
@@ -0,0 +1,427 @@
+use std::{str::FromStr, time::Duration};
+
+use serde::Deserialize;
+
+/// Remaining spring displacement that counts as settled, as a share of the distance
+const SPRING_REST_THRESHOLD: f64 = 0.001;
+/// Springs that barely settle are cut off after this many seconds
+const SPRING_MAX_DURATION: f64 = 10.0;
+
+/// Easing functions for smooth animations
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(try_from = "EasingRepr")]
+pub enum EasingFunction {
+    Linear,
+    EaseOutCubic,
+    #[default]
+    EaseInOutQuad,
+    /// CSS-style `cubic-bezier(x1, y1, x2, y2)` curve
+    CubicBezier {
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+    },
+    /// Damped harmonic oscillator, the duration is derived from how long it takes to settle
+    Spring {
+        stiffness: f64,
+        damping: f64,
+        mass: f64,
+    },
+}
+
+impl EasingFunction {
+    pub fn apply(&self, t: f64) -> f64 {
+        if t <= 0.0 {
+            return 0.0;
+        }
+        if t >= 1.0 {
+            return 1.0;
+        }
+
+        match *self {
+            EasingFunction::Linear => t,
+            EasingFunction::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            EasingFunction::EaseInOutQuad => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            EasingFunction::CubicBezier { x1, y1, x2, y2 } => cubic_bezier(x1, y1, x2, y2, t),
+            EasingFunction::Spring { .. } => {
+                let settling = self.settling_duration().unwrap_or_default();
+                1.0 - self.spring_displacement(t * settling.as_secs_f64())
+            }
+        }
+    }
+
+    /// Time a spring needs to come to rest, `None` for curves with a fixed duration
+    pub fn settling_duration(&self) -> Option<Duration> {
+        let (omega, zeta) = self.spring_parameters()?;
+
+        let seconds = if zeta < 1.0 {
+            // The oscillation never leaves its exponential envelope
+            let omega_d = omega * (1.0 - zeta * zeta).sqrt();
+            let amplitude = (1.0 + (zeta * omega / omega_d).powi(2)).sqrt();
+            (amplitude / SPRING_REST_THRESHOLD).ln() / (zeta * omega)
+        } else {
+            // Without oscillation the displacement only shrinks, bisect for the threshold
+            let (mut low, mut high) = (0.0, SPRING_MAX_DURATION);
+            for _ in 0..50 {
+                let middle = (low + high) / 2.0;
+                if self.spring_displacement(middle) > SPRING_REST_THRESHOLD {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            high
+        };
+
+        Some(Duration::from_secs_f64(seconds.min(SPRING_MAX_DURATION)))
+    }
+
+    /// Natural frequency and damping ratio of a spring
+    fn spring_parameters(&self) -> Option<(f64, f64)> {
+        let EasingFunction::Spring {
+            stiffness,
+            damping,
+            mass,
+        } = *self
+        else {
+            return None;
+        };
+
+        let omega = (stiffness / mass).sqrt();
+        let zeta = damping / (2.0 * (stiffness * mass).sqrt());
+
+        Some((omega, zeta))
+    }
+
+    /// Remaining distance to the target (1 at rest position, 0 at target) after `time` seconds
+    fn spring_displacement(&self, time: f64) -> f64 {
+        self.spring_offset(1.0, 0.0, time)
+    }
+
+    /// Offset from the target after `time` seconds for a spring released at `offset`
+    /// with `velocity` (units per second). Used to retarget moving windows without a jolt.
+    pub fn spring_offset(&self, offset: f64, velocity: f64, time: f64) -> f64 {
+        let Some((omega, zeta)) = self.spring_parameters() else {
+            return 0.0;
+        };
+
+        if zeta < 1.0 {
+            let omega_d = omega * (1.0 - zeta * zeta).sqrt();
+            (-zeta * omega * time).exp()
+                * (offset * (omega_d * time).cos()
+                    + (velocity + zeta * omega * offset) / omega_d * (omega_d * time).sin())
+        } else if zeta == 1.0 {
+            (-omega * time).exp() * (offset + (velocity + omega * offset) * time)
+        } else {
+            let root = (zeta * zeta - 1.0).sqrt();
+            let r1 = -omega * (zeta - root);
+            let r2 = -omega * (zeta + root);
+            let c2 = (velocity - r1 * offset) / (r2 - r1);
+            let c1 = offset - c2;
+            c1 * (r1 * time).exp() + c2 * (r2 * time).exp()
+        }
+    }
+}
+
+/// Solves `x(s) = t` for the curve parameter and returns `y(s)`
+fn cubic_bezier(x1: f64, y1: f64, x2: f64, y2: f64, t: f64) -> f64 {
+    let sample = |a: f64, b: f64, s: f64| {
+        let u = 1.0 - s;
+        3.0 * u * u * s * a + 3.0 * u * s * s * b + s * s * s
+    };
+    let slope = |a: f64, b: f64, s: f64| {
+        let u = 1.0 - s;
+        3.0 * u * u * a + 6.0 * u * s * (b - a) + 3.0 * s * s * (1.0 - b)
+    };
+
+    // Newton-Raphson converges in a few steps for most curves
+    let mut s = t;
+    for _ in 0..8 {
+        let error = sample(x1, x2, s) - t;
+        if error.abs() < 1e-7 {
+            return sample(y1, y2, s);
+        }
+        let derivative = slope(x1, x2, s);
+        if derivative.abs() < 1e-6 {
+            break;
+        }
+        s -= error / derivative;
+    }
+
+    // Fall back to bisection for flat segments
+    let (mut low, mut high) = (0.0, 1.0);
+    s = t;
+    for _ in 0..50 {
+        let x = sample(x1, x2, s);
+        if (x - t).abs() < 1e-7 {
+            break;
+        }
+        if x < t {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.0;
+    }
+
+    sample(y1, y2, s)
+}
+
+impl FromStr for EasingFunction {
+    type Err = String;
+
+    /// Accepts the named curves (`linear`, `ease`, `ease-in`, `ease-out`, `ease-in-out`,
+    /// `ease-out-cubic`, `ease-in-out-quad`) and `cubic-bezier(x1, y1, x2, y2)`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let bezier = |x1, y1, x2, y2| EasingFunction::CubicBezier { x1, y1, x2, y2 };
+
+        match value {
+            "linear" => return Ok(EasingFunction::Linear),
+            "ease-out-cubic" => return Ok(EasingFunction::EaseOutCubic),
+            "ease-in-out-quad" => return Ok(EasingFunction::EaseInOutQuad),
+            "ease" => return Ok(bezier(0.25, 0.1, 0.25, 1.0)),
+            "ease-in" => return Ok(bezier(0.42, 0.0, 1.0, 1.0)),
+            "ease-out" => return Ok(bezier(0.0, 0.0, 0.58, 1.0)),
+            "ease-in-out" => return Ok(bezier(0.42, 0.0, 0.58, 1.0)),
+            _ => (),
+        }
+
+        let arguments = value
+            .strip_prefix("cubic-bezier(")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .ok_or_else(|| format!("unknown easing '{}'", value))?;
+
+        let points = arguments
+            .split(',')
+            .map(|point| point.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("invalid number in '{}'", value))?;
+
+        let [x1, y1, x2, y2] = points[..] else {
+            return Err(format!("'{}' expects 4 numbers", value));
+        };
+        // `parse` takes "nan" and "inf" too
+        if !points.iter().all(|point| point.is_finite()) {
+            return Err(format!("'{}' expects finite numbers", value));
+        }
+
+        if !(0.0..=1.0).contains(&x1) || !(0.0..=1.0).contains(&x2) {
+            return Err(format!("x values of '{}' must be within [0, 1]", value));
+        }
+
+        Ok(bezier(x1, y1, x2, y2))
+    }
+}
+
+/// Wire format: a curve name / `cubic-bezier(...)` string, or a spring object
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EasingRepr {
+    Css(String),
+    Spring {
+        stiffness: f64,
+        damping: f64,
+        mass: Option<f64>,
+    },
+}
+
+impl TryFrom<EasingRepr> for EasingFunction {
+    type Error = String;
+
+    fn try_from(repr: EasingRepr) -> Result<Self, Self::Error> {
+        match repr {
+            EasingRepr::Css(value) => value.parse(),
+            EasingRepr::Spring {
+                stiffness,
+                damping,
+                mass,
+            } => {
+                let mass = mass.unwrap_or(1.0);
+                let positive = |value: f64| value.is_finite() && value > 0.0;
+                if !positive(stiffness) || !positive(damping) || !positive(mass) {
+                    return Err(String::from(
+                        "spring stiffness, damping and mass must be positive",
+                    ));
+                }
+
+                Ok(EasingFunction::Spring {
+                    stiffness,
+                    damping,
+                    mass,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [&str; 7] = [
+        "linear",
+        "ease",
+        "ease-in",
+        "ease-out",
+        "ease-in-out",
+        "cubic-bezier(0.68, -0.55, 0.27, 1.55)",
+        // Flat at the start, Newton gives up there and bisection takes over
+        "cubic-bezier(0, 0, 0, 1)",
+    ];
+
+    fn spring(stiffness: f64, damping: f64) -> EasingFunction {
+        EasingFunction::Spring {
+            stiffness,
+            damping,
+            mass: 1.0,
+        }
+    }
+
+    #[test]
+    fn curves_start_at_zero_and_end_at_one() {
+        for name in CURVES {
+            let easing: EasingFunction = name.parse().unwrap();
+
+            assert_eq!(easing.apply(0.0), 0.0, "{}", name);
+            assert_eq!(easing.apply(1.0), 1.0, "{}", name);
+            assert!(easing.apply(1e-6).abs() < 1e-3, "{}", name);
+            assert!((easing.apply(1.0 - 1e-6) - 1.0).abs() < 1e-3, "{}", name);
+        }
+    }
+
+    #[test]
+    fn bezier_curves_without_overshoot_are_monotonic() {
+        for name in &CURVES[..5] {
+            let easing: EasingFunction = name.parse().unwrap();
+            let values: Vec<f64> = (0..=200).map(|i| easing.apply(i as f64 / 200.0)).collect();
+
+            assert!(
+                values.windows(2).all(|pair| pair[1] >= pair[0] - 1e-9),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn bezier_solver_finds_the_curve_parameter() {
+        // With the control points on the diagonal the curve is the identity
+        let diagonal: EasingFunction = "cubic-bezier(0.25, 0.25, 0.75, 0.75)".parse().unwrap();
+        for i in 0..=20 {
+            let t = i as f64 / 20.0;
+            assert!((diagonal.apply(t) - t).abs() < 1e-6, "{}", t);
+        }
+
+        let flat: EasingFunction = "cubic-bezier(0, 0, 0, 1)".parse().unwrap();
+        assert!((flat.apply(0.5) - 0.9).abs() < 0.05);
+    }
+
+    #[test]
+    fn springs_settle_within_the_threshold() {
+        // Under, critically and over damped
+        for easing in [
+            spring(170.0, 26.0),
+            spring(100.0, 20.0),
+            spring(100.0, 60.0),
+        ] {
+            let settling = easing.settling_duration().unwrap();
+            assert!(settling > Duration::ZERO, "{:?}", easing);
+            assert!(
+                settling < Duration::from_secs_f64(SPRING_MAX_DURATION),
+                "{:?}",
+                easing
+            );
+
+            let seconds = settling.as_secs_f64();
+            assert!(easing.spring_displacement(seconds).abs() <= SPRING_REST_THRESHOLD * 1.01);
+            assert!(easing.spring_displacement(seconds * 1.5).abs() <= SPRING_REST_THRESHOLD);
+            assert_eq!(easing.apply(1.0), 1.0);
+        }
+
+        assert_eq!(EasingFunction::Linear.settling_duration(), None);
+    }
+
+    #[test]
+    fn underdamped_springs_overshoot() {
+        let easing = spring(300.0, 5.0);
+        let peak = (1..100)
+            .map(|i| easing.apply(i as f64 / 100.0))
+            .fold(f64::MIN, f64::max);
+
+        assert!(peak > 1.0);
+    }
+
+    #[test]
+    fn barely_damped_springs_are_cut_off() {
+        let easing = spring(100.0, 0.01);
+
+        assert_eq!(
+            easing.settling_duration(),
+            Some(Duration::from_secs_f64(SPRING_MAX_DURATION))
+        );
+    }
+
+    #[test]
+    fn spring_offset_keeps_the_release_velocity() {
+        let easing = spring(170.0, 26.0);
+        let step = 1e-5;
+        let velocity = (easing.spring_offset(100.0, 500.0, step) - 100.0) / step;
+
+        assert_eq!(easing.spring_offset(100.0, 500.0, 0.0), 100.0);
+        assert!((velocity - 500.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn parsing_rejects_unknown_and_invalid_curves() {
+        for value in [
+            "bounce",
+            "cubic-bezier(0.1, 0.2, 0.3)",
+            "cubic-bezier(0.1, 0.2, 0.3, 0.4, 0.5)",
+            "cubic-bezier(0.1, a, 0.3, 0.4)",
+            "cubic-bezier(-0.1, 0, 0.5, 1)",
+            "cubic-bezier(0, 0, 1.5, 1)",
+            "cubic-bezier(0, nan, 1, 1)",
+            "cubic-bezier(0, 0, 1, inf)",
+            "cubic-bezier(NaN, 0, 1, 1)",
+            "cubic-bezier 0, 0, 1, 1",
+        ] {
+            assert!(value.parse::<EasingFunction>().is_err(), "{}", value);
+        }
+
+        assert_eq!(
+            " ease-out-cubic ".parse::<EasingFunction>(),
+            Ok(EasingFunction::EaseOutCubic)
+        );
+    }
+
+    #[test]
+    fn deserializes_names_and_springs() {
+        let easing: EasingFunction = serde_json::from_str("\"ease-in-out-quad\"").unwrap();
+        assert_eq!(easing, EasingFunction::EaseInOutQuad);
+
+        let easing: EasingFunction =
+            serde_json::from_str(r#"{"stiffness": 170, "damping": 26}"#).unwrap();
+        assert_eq!(easing, spring(170.0, 26.0));
+
+        for value in [
+            r#"{"stiffness": 0, "damping": 26}"#,
+            r#"{"stiffness": 170, "damping": -1}"#,
+            r#"{"stiffness": 170, "damping": 26, "mass": 0}"#,
+            r#""cubic-bezier(0, nan, 1, 1)""#,
+        ] {
+            assert!(
+                serde_json::from_str::<EasingFunction>(value).is_err(),
+                "{}",
+                value
+            );
+        }
+    }
+}
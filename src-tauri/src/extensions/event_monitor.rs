@@ -1,10 +1,35 @@
+use std::{
+    collections::HashMap,
+    ffi::{c_void, CStr},
+    ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+};
+
 use block::ConcreteBlock;
 use cocoa::{
     appkit::{CGPoint, NSEvent, NSEventMask, NSEventType},
-    base::{id, BOOL},
-    foundation::{NSPoint, NSRect},
+    base::{id, nil, BOOL, NO},
+    foundation::{NSPoint, NSRect, NSString},
+};
+use core_foundation::{
+    base::{CFRelease, CFRetain, CFTypeRef},
+    mach_port::{CFMachPortCreateRunLoopSource, CFMachPortInvalidate, CFMachPortRef},
+    runloop::{
+        kCFRunLoopCommonModes, kCFRunLoopDefaultMode, CFRunLoopAddSource, CFRunLoopGetCurrent,
+        CFRunLoopRef, CFRunLoopRunInMode, CFRunLoopStop,
+    },
 };
 use objc::{class, msg_send, sel, sel_impl};
+use once_cell::sync::Lazy;
+
+use crate::data::{
+    frame::Point,
+    mouse::{EventKind, KeyInfo, Modifiers, MouseEvent, ScrollDelta, ScrollPhase},
+};
 
 #[repr(C)]
 pub struct __CGEvent;
@@ -16,6 +41,18 @@ pub struct EventMonitor {
     monitor_id: id,
 }
 
+// The monitor is an opaque token handed back to `NSEvent`, which accepts it on any thread
+unsafe impl Send for EventMonitor {}
+
+struct RegisteredMonitor {
+    /// Label of the window that added the monitor
+    owner: String,
+    monitor: EventMonitor,
+}
+
+static MONITORS: Lazy<Mutex<HashMap<String, RegisteredMonitor>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
 impl EventMonitor {
     pub fn location(event: id) -> CGPoint {
         unsafe {
@@ -29,13 +66,91 @@ impl EventMonitor {
         unsafe { event.eventType() }
     }
 
+    pub fn timestamp(event: id) -> f64 {
+        unsafe { event.timestamp() }
+    }
+
+    pub fn modifiers(event: id) -> Modifiers {
+        let flags: u64 = unsafe { msg_send![event, modifierFlags] };
+        Modifiers::from_flags(flags)
+    }
+
+    pub fn scroll(event: id) -> ScrollDelta {
+        unsafe {
+            let precise: BOOL = msg_send![event, hasPreciseScrollingDeltas];
+            let phase: u64 = msg_send![event, phase];
+            let momentum_phase: u64 = msg_send![event, momentumPhase];
+
+            ScrollDelta {
+                delta_x: msg_send![event, scrollingDeltaX],
+                delta_y: msg_send![event, scrollingDeltaY],
+                precise: precise != NO,
+                phase: ScrollPhase::from_raw(phase),
+                momentum_phase: ScrollPhase::from_raw(momentum_phase),
+            }
+        }
+    }
+
+    /// Modifier changes only have a key code, asking them for characters throws
+    pub fn key(event: id, kind: EventKind) -> KeyInfo {
+        unsafe {
+            let key_code: u16 = msg_send![event, keyCode];
+            if kind == EventKind::FlagsChanged {
+                return KeyInfo {
+                    key_code,
+                    characters: None,
+                    characters_ignoring_modifiers: None,
+                    is_repeat: false,
+                };
+            }
+            let is_repeat: BOOL = msg_send![event, isARepeat];
+
+            KeyInfo {
+                key_code,
+                characters: ns_string(msg_send![event, characters]),
+                characters_ignoring_modifiers: ns_string(msg_send![
+                    event,
+                    charactersIgnoringModifiers
+                ]),
+                is_repeat: is_repeat != NO,
+            }
+        }
+    }
+
+    /// The payload sent to the frontend, `None` for event types monitors can't
+    /// subscribe to. Only what the event type has is read, AppKit throws otherwise
+    pub fn to_mouse_event(event: id, monitor_id: &str) -> Option<MouseEvent> {
+        let kind = EventKind::from_event_type(Self::event_type(event) as u64)?;
+        let position = Self::location(event);
+
+        Some(MouseEvent {
+            monitor_id: String::from(monitor_id),
+            point: Point {
+                x: position.x,
+                y: position.y,
+            },
+            event_type: kind,
+            modifiers: Self::modifiers(event),
+            button: kind
+                .has_button()
+                .then(|| unsafe { msg_send![event, buttonNumber] }),
+            click_count: kind
+                .is_click()
+                .then(|| unsafe { msg_send![event, clickCount] }),
+            scroll: (kind == EventKind::ScrollWheel).then(|| Self::scroll(event)),
+            key: kind.is_key().then(|| Self::key(event, kind)),
+            timestamp: Self::timestamp(event),
+        })
+    }
+
     pub fn stop(&self) {
         unsafe {
             let () = msg_send![class!(NSEvent), removeMonitor: self.monitor_id];
         }
     }
 
-    pub fn global_monitor<F>(mask: NSEventMask, handler: F) -> Self
+    /// Returns `None` when AppKit refuses to install the monitor
+    pub fn global_monitor<F>(mask: NSEventMask, handler: F) -> Option<Self>
     where
         F: Fn(id) -> Option<id> + Send + Sync + 'static,
     {
@@ -46,14 +161,224 @@ impl EventMonitor {
         unsafe {
             let monitor: id = msg_send![class!(NSEvent), addGlobalMonitorForEventsMatchingMask:mask handler:&*block];
 
-            EventMonitor {
-                monitor_id: monitor,
+            if monitor.is_null() {
+                return None;
             }
+
+            Some(EventMonitor {
+                monitor_id: monitor,
+            })
+        }
+    }
+}
+
+fn ns_string(string: id) -> Option<String> {
+    if string == nil {
+        return None;
+    }
+    let utf8 = unsafe { string.UTF8String() };
+    (!utf8.is_null()).then(|| {
+        unsafe { CStr::from_ptr(utf8) }
+            .to_string_lossy()
+            .to_string()
+    })
+}
+
+pub fn register(id: String, owner: &str, monitor: EventMonitor) {
+    MONITORS.lock().unwrap().insert(
+        id,
+        RegisteredMonitor {
+            owner: String::from(owner),
+            monitor,
+        },
+    );
+}
+
+/// Stops and forgets the monitor, `false` when there is no such monitor
+pub fn remove(id: &str) -> bool {
+    let removed = MONITORS.lock().unwrap().remove(id);
+    match removed {
+        Some(registered) => {
+            registered.monitor.stop();
+            true
         }
+        None => false,
     }
 }
 
+/// Stops every monitor a closed window left behind, returns how many there were
+pub fn remove_owner(owner: &str) -> usize {
+    let removed: Vec<RegisteredMonitor> = {
+        let mut monitors = MONITORS.lock().unwrap();
+        let ids: Vec<String> = monitors
+            .iter()
+            .filter(|(_, registered)| registered.owner == owner)
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.iter().filter_map(|id| monitors.remove(id)).collect()
+    };
+    removed
+        .iter()
+        .for_each(|registered| registered.monitor.stop());
+
+    removed.len()
+}
+
+/// `kCGSessionEventTap`, events of the login session before they reach any app
+const SESSION_EVENT_TAP: u32 = 1;
+/// `kCGHeadInsertEventTap`, ahead of the taps already in place
+const HEAD_INSERT_EVENT_TAP: u32 = 0;
+/// `kCGEventTapOptionDefault`, an active tap that can change and drop events
+const EVENT_TAP_OPTION_DEFAULT: u32 = 0;
+/// `kCGEventTapDisabledByTimeout` and `kCGEventTapDisabledByUserInput`
+const EVENT_TAP_DISABLED_BY_TIMEOUT: u32 = 0xFFFF_FFFE;
+const EVENT_TAP_DISABLED_BY_USER_INPUT: u32 = 0xFFFF_FFFF;
+/// How long the tap thread sleeps in its run loop before checking whether it was stopped
+const TAP_RUN_INTERVAL_SECONDS: f64 = 1.0;
+
+type TapHandler = Box<dyn Fn(CGEventRef) -> bool + Send + Sync>;
+
+struct TapState {
+    handler: TapHandler,
+    /// Set once the tap exists, macOS disables slow taps and it's enabled again
+    port: CFMachPortRef,
+}
+
+/// An active `CGEventTap` on a thread of its own. Unlike a global monitor it sees the
+/// events before the apps do, and the events its handler returns `true` for are swallowed
+pub struct EventTap {
+    run_loop: CFRunLoopRef,
+    stopped: Arc<AtomicBool>,
+}
+
+// The run loop is retained until the tap is dropped and `CFRunLoopStop` takes it on any thread
+unsafe impl Send for EventTap {}
+
+unsafe extern "C" fn tap_callback(
+    _proxy: *mut c_void,
+    event_type: u32,
+    event: CGEventRef,
+    user_info: *mut c_void,
+) -> CGEventRef {
+    let state = &*(user_info as *const TapState);
+
+    if matches!(
+        event_type,
+        EVENT_TAP_DISABLED_BY_TIMEOUT | EVENT_TAP_DISABLED_BY_USER_INPUT
+    ) {
+        CGEventTapEnable(state.port, true);
+        return event;
+    }
+
+    if (state.handler)(event) {
+        ptr::null()
+    } else {
+        event
+    }
+}
+
+impl EventTap {
+    pub fn location(event: CGEventRef) -> CGPoint {
+        unsafe { CGEventGetLocation(event) }
+    }
+
+    /// Same values as `NSEventType` for the mouse and key events
+    pub fn event_type(event: CGEventRef) -> u64 {
+        u64::from(unsafe { CGEventGetType(event) })
+    }
+
+    pub fn modifiers(event: CGEventRef) -> Modifiers {
+        Modifiers::from_flags(unsafe { CGEventGetFlags(event) })
+    }
+
+    /// Returns `None` when macOS refuses the tap, which needs the accessibility permission.
+    /// `mask` has one bit per event type, like `NSEventMask`
+    pub fn start<F>(mask: u64, handler: F) -> Option<Self>
+    where
+        F: Fn(CGEventRef) -> bool + Send + Sync + 'static,
+    {
+        let (ready_sender, ready) = mpsc::channel::<Option<usize>>();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+
+        thread::spawn(move || {
+            let mut state = Box::new(TapState {
+                handler: Box::new(handler),
+                port: ptr::null_mut(),
+            });
+
+            unsafe {
+                let port = CGEventTapCreate(
+                    SESSION_EVENT_TAP,
+                    HEAD_INSERT_EVENT_TAP,
+                    EVENT_TAP_OPTION_DEFAULT,
+                    mask,
+                    tap_callback,
+                    &mut *state as *mut TapState as *mut c_void,
+                );
+                if port.is_null() {
+                    let _ = ready_sender.send(None);
+                    return;
+                }
+                state.port = port;
+
+                let source = CFMachPortCreateRunLoopSource(ptr::null(), port, 0);
+                let run_loop = CFRunLoopGetCurrent();
+                CFRunLoopAddSource(run_loop, source, kCFRunLoopCommonModes);
+                CGEventTapEnable(port, true);
+                CFRetain(run_loop as CFTypeRef);
+                let _ = ready_sender.send(Some(run_loop as usize));
+
+                // Stopped between two runs is caught by the flag
+                while !thread_stopped.load(Ordering::SeqCst) {
+                    CFRunLoopRunInMode(kCFRunLoopDefaultMode, TAP_RUN_INTERVAL_SECONDS, 0);
+                }
+
+                CFMachPortInvalidate(port);
+                CFRelease(source as CFTypeRef);
+                CFRelease(port as CFTypeRef);
+            }
+        });
+
+        let run_loop = ready.recv().ok().flatten()?;
+        Some(EventTap {
+            run_loop: run_loop as CFRunLoopRef,
+            stopped,
+        })
+    }
+
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        unsafe { CFRunLoopStop(self.run_loop) };
+    }
+}
+
+impl Drop for EventTap {
+    fn drop(&mut self) {
+        self.stop();
+        unsafe { CFRelease(self.run_loop as CFTypeRef) };
+    }
+}
+
+type CGEventTapCallBack = unsafe extern "C" fn(
+    proxy: *mut c_void,
+    event_type: u32,
+    event: CGEventRef,
+    user_info: *mut c_void,
+) -> CGEventRef;
+
 #[link(name = "ApplicationServices", kind = "framework")]
 extern "C" {
     fn CGEventGetLocation(event: CGEventRef) -> CGPoint;
+    fn CGEventGetType(event: CGEventRef) -> u32;
+    fn CGEventGetFlags(event: CGEventRef) -> u64;
+    fn CGEventTapCreate(
+        tap: u32,
+        place: u32,
+        options: u32,
+        events_of_interest: u64,
+        callback: CGEventTapCallBack,
+        user_info: *mut c_void,
+    ) -> CFMachPortRef;
+    fn CGEventTapEnable(tap: CFMachPortRef, enable: bool);
 }
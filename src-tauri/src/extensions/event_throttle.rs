@@ -0,0 +1,149 @@
+/// Limits how often continuous events (moves and drags) go out. Discrete ones such as
+/// clicks are never held back, and anything pending goes out first so the order holds
+pub struct EventThrottle<T> {
+    interval_ms: u64,
+    /// Keep the latest held back event and send it once the interval is over, instead
+    /// of dropping it
+    coalesce: bool,
+    last_sent_ms: Option<u64>,
+    pending: Option<T>,
+    flush_scheduled: bool,
+}
+
+/// What to send now, and when to call `flush` for a held back event
+pub struct Throttled<T> {
+    pub send: Vec<T>,
+    pub flush_in_ms: Option<u64>,
+}
+
+impl<T> EventThrottle<T> {
+    pub fn new(interval_ms: u64, coalesce: bool) -> Self {
+        EventThrottle {
+            interval_ms,
+            coalesce,
+            last_sent_ms: None,
+            pending: None,
+            flush_scheduled: false,
+        }
+    }
+
+    pub fn push(&mut self, event: T, continuous: bool, now_ms: u64) -> Throttled<T> {
+        if !continuous {
+            let mut send: Vec<T> = self.pending.take().into_iter().collect();
+            send.push(event);
+            self.last_sent_ms = Some(now_ms);
+            return Throttled {
+                send,
+                flush_in_ms: None,
+            };
+        }
+
+        let elapsed = self
+            .last_sent_ms
+            .map_or(u64::MAX, |last| now_ms.saturating_sub(last));
+        if elapsed >= self.interval_ms {
+            self.pending = None;
+            self.last_sent_ms = Some(now_ms);
+            return Throttled {
+                send: vec![event],
+                flush_in_ms: None,
+            };
+        }
+
+        if !self.coalesce {
+            return Throttled {
+                send: vec![],
+                flush_in_ms: None,
+            };
+        }
+        self.pending = Some(event);
+        let flush_in_ms = (!self.flush_scheduled).then(|| self.interval_ms - elapsed);
+        self.flush_scheduled = true;
+
+        Throttled {
+            send: vec![],
+            flush_in_ms,
+        }
+    }
+
+    /// The held back event, if nothing newer went out in the meantime
+    pub fn flush(&mut self, now_ms: u64) -> Option<T> {
+        self.flush_scheduled = false;
+        let event = self.pending.take()?;
+        self.last_sent_ms = Some(now_ms);
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL_MS: u64 = 16;
+
+    #[test]
+    fn discrete_events_are_never_dropped() {
+        for coalesce in [false, true] {
+            let mut throttle = EventThrottle::new(INTERVAL_MS, coalesce);
+
+            assert_eq!(throttle.push("down", false, 0).send, vec!["down"]);
+            assert!(throttle.push("drag", true, 1).send.is_empty());
+            assert_eq!(throttle.push("up", false, 2).send.last(), Some(&"up"));
+            assert_eq!(throttle.push("down", false, 3).send, vec!["down"]);
+            assert_eq!(throttle.push("up", false, 4).send, vec!["up"]);
+        }
+    }
+
+    #[test]
+    fn held_back_event_goes_out_before_a_discrete_one() {
+        let mut throttle = EventThrottle::new(INTERVAL_MS, true);
+
+        throttle.push("drag 1", true, 0);
+        throttle.push("drag 2", true, 5);
+        let throttled = throttle.push("up", false, 10);
+
+        assert_eq!(throttled.send, vec!["drag 2", "up"]);
+        assert_eq!(throttle.flush(16), None);
+    }
+
+    #[test]
+    fn latest_held_back_event_wins() {
+        let mut throttle = EventThrottle::new(INTERVAL_MS, true);
+
+        assert_eq!(throttle.push("move 1", true, 0).send, vec!["move 1"]);
+        let throttled = throttle.push("move 2", true, 4);
+        assert!(throttled.send.is_empty());
+        assert_eq!(throttled.flush_in_ms, Some(12));
+        // The flush is already scheduled, the newer event replaces the held one
+        let throttled = throttle.push("move 3", true, 8);
+        assert_eq!(throttled.flush_in_ms, None);
+
+        assert_eq!(throttle.flush(16), Some("move 3"));
+        assert_eq!(throttle.push("move 4", true, 20).flush_in_ms, Some(12));
+    }
+
+    #[test]
+    fn without_coalescing_held_back_events_are_dropped() {
+        let mut throttle = EventThrottle::new(INTERVAL_MS, false);
+
+        throttle.push("move 1", true, 0);
+        let throttled = throttle.push("move 2", true, 4);
+
+        assert!(throttled.send.is_empty());
+        assert_eq!(throttled.flush_in_ms, None);
+        assert_eq!(throttle.flush(16), None);
+        assert_eq!(throttle.push("move 3", true, 16).send, vec!["move 3"]);
+    }
+
+    #[test]
+    fn event_after_the_interval_replaces_the_held_one() {
+        let mut throttle = EventThrottle::new(INTERVAL_MS, true);
+
+        throttle.push("move 1", true, 0);
+        throttle.push("move 2", true, 4);
+
+        // The flush came late, the newer event goes out and nothing is left to flush
+        assert_eq!(throttle.push("move 3", true, 20).send, vec!["move 3"]);
+        assert_eq!(throttle.flush(21), None);
+    }
+}
@@ -0,0 +1,403 @@
+use std::{collections::HashMap, ffi::c_void, ptr, sync::Mutex};
+
+use accessibility_sys::{
+    kAXErrorSuccess, kAXFocusedWindowChangedNotification, kAXMinimizedAttribute,
+    AXObserverAddNotification, AXObserverCreate, AXObserverGetRunLoopSource, AXObserverRef,
+    AXObserverRemoveNotification, AXUIElementCreateApplication, AXUIElementRef,
+};
+use cocoa::{
+    base::{id, nil},
+    foundation::NSString,
+};
+use core_foundation::{
+    base::{CFRelease, CFTypeRef, TCFType},
+    runloop::{kCFRunLoopDefaultMode, CFRunLoopAddSource, CFRunLoopGetMain, CFRunLoopRemoveSource},
+    string::{CFString, CFStringRef},
+};
+use objc::{msg_send, sel, sel_impl};
+use once_cell::sync::Lazy;
+
+use crate::data::error::{SnapError, SnapResult};
+
+use super::{
+    accessibility_elements::{
+        activate_application, get_bool_attribute, get_focused_window_id, get_window_from_id,
+        get_window_id, raise_window, set_bool_attribute,
+    },
+    notification::{self, NotificationCenter},
+    window_watcher::take_snapshot,
+    windows::{get_windows_on_screen, window_exists, WindowFilter, WindowInfo},
+};
+
+const APP_ACTIVATED: &str = "NSWorkspaceDidActivateApplicationNotification";
+const APP_TERMINATED: &str = "NSWorkspaceDidTerminateApplicationNotification";
+/// Value of `NSWorkspaceApplicationKey`, the app in the `userInfo` of both notifications
+const APPLICATION_KEY: &str = "NSWorkspaceApplicationKey";
+
+/// Number of windows remembered
+const HISTORY_LIMIT: usize = 50;
+
+/// Windows ordered from the most recently focused one
+#[derive(Default)]
+pub struct FocusHistory {
+    windows: Vec<WindowInfo>,
+}
+
+impl FocusHistory {
+    pub fn focus(&mut self, window: WindowInfo) {
+        self.remove(window.id);
+        self.windows.insert(0, window);
+        self.windows.truncate(HISTORY_LIMIT);
+    }
+
+    /// Moves a known window to the front
+    pub fn promote(&mut self, window_id: i64) {
+        if let Some(index) = self
+            .windows
+            .iter()
+            .position(|window| window.id == Some(window_id))
+        {
+            let window = self.windows.remove(index);
+            self.windows.insert(0, window);
+        }
+    }
+
+    pub fn remove(&mut self, window_id: Option<i64>) {
+        self.windows.retain(|window| window.id != window_id);
+    }
+
+    /// Forgets the windows of an application that quit
+    pub fn remove_app(&mut self, pid: i64) {
+        self.windows.retain(|window| window.pid != Some(pid));
+    }
+
+    pub fn get(&self, index: usize) -> Option<&WindowInfo> {
+        self.windows.get(index)
+    }
+
+    pub fn windows(&self) -> &[WindowInfo] {
+        &self.windows
+    }
+}
+
+static HISTORY: Lazy<Mutex<FocusHistory>> = Lazy::new(|| Mutex::new(FocusHistory::default()));
+
+/// Follows `kAXFocusedWindowChangedNotification` of one application on the main run loop
+struct AppObserver {
+    observer: AXObserverRef,
+    application: AXUIElementRef,
+}
+
+// Both refs are owned by the observer, AX and the run loop take them on any thread
+unsafe impl Send for AppObserver {}
+
+impl AppObserver {
+    fn new(pid: i64) -> Option<Self> {
+        unsafe {
+            let mut observer: AXObserverRef = ptr::null_mut();
+            if AXObserverCreate(pid as i32, focused_window_changed, &mut observer)
+                != kAXErrorSuccess
+            {
+                return None;
+            }
+            let application = AXUIElementCreateApplication(pid as i32);
+            let app_observer = AppObserver {
+                observer,
+                application,
+            };
+
+            // The pid comes back as the refcon, the element passed along is the window
+            let result = AXObserverAddNotification(
+                observer,
+                application,
+                CFString::new(kAXFocusedWindowChangedNotification).as_concrete_TypeRef(),
+                pid as *mut c_void,
+            );
+            if result != kAXErrorSuccess {
+                return None;
+            }
+            CFRunLoopAddSource(
+                CFRunLoopGetMain(),
+                AXObserverGetRunLoopSource(observer),
+                kCFRunLoopDefaultMode,
+            );
+
+            Some(app_observer)
+        }
+    }
+}
+
+impl Drop for AppObserver {
+    fn drop(&mut self) {
+        unsafe {
+            AXObserverRemoveNotification(
+                self.observer,
+                self.application,
+                CFString::new(kAXFocusedWindowChangedNotification).as_concrete_TypeRef(),
+            );
+            CFRunLoopRemoveSource(
+                CFRunLoopGetMain(),
+                AXObserverGetRunLoopSource(self.observer),
+                kCFRunLoopDefaultMode,
+            );
+            CFRelease(self.observer as CFTypeRef);
+            CFRelease(self.application as CFTypeRef);
+        }
+    }
+}
+
+/// Applications activated since tracking started, by pid
+static OBSERVERS: Lazy<Mutex<HashMap<i64, AppObserver>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+unsafe extern "C" fn focused_window_changed(
+    _observer: AXObserverRef,
+    element: AXUIElementRef,
+    _notification: CFStringRef,
+    refcon: *mut c_void,
+) {
+    if let Some(window_id) = get_window_id(element) {
+        record_focus(refcon as i64, window_id);
+    }
+}
+
+/// Puts the window first, with its current title and frame. Only its application is asked
+fn record_focus(pid: i64, window_id: u32) {
+    let window = get_windows_on_screen(&WindowFilter {
+        pid: Some(pid),
+        include_minimized: true,
+        ..WindowFilter::default()
+    })
+    .into_iter()
+    .find(|window| window.id == Some(window_id as i64));
+
+    if let Some(window) = window {
+        HISTORY.lock().unwrap().focus(window);
+    }
+}
+
+/// Pid of the application a workspace notification is about
+fn notification_pid(notification: id) -> Option<i64> {
+    unsafe {
+        let user_info: id = msg_send![notification, userInfo];
+        if user_info == nil {
+            return None;
+        }
+        let key = NSString::alloc(nil).init_str(APPLICATION_KEY);
+        let application: id = msg_send![user_info, objectForKey: key];
+        let () = msg_send![key, release];
+        if application == nil {
+            return None;
+        }
+
+        let pid: i32 = msg_send![application, processIdentifier];
+        Some(pid as i64)
+    }
+}
+
+/// Records the focused window of the application and follows its focus changes from now on
+fn observe_application(pid: i64) {
+    let mut observers = OBSERVERS.lock().unwrap();
+    if !observers.contains_key(&pid) {
+        if let Some(observer) = AppObserver::new(pid) {
+            observers.insert(pid, observer);
+        }
+    }
+    drop(observers);
+
+    if let Some(window_id) = get_focused_window_id(pid) {
+        record_focus(pid, window_id);
+    }
+}
+
+/// Follows app activations and the focused window of every activated app, nothing is
+/// sampled in between. The history is seeded with the current windows, front to back
+pub fn start_tracking() {
+    let snapshot = take_snapshot();
+    {
+        let mut history = HISTORY.lock().unwrap();
+        for window in snapshot.windows.into_iter().rev() {
+            history.focus(window);
+        }
+    }
+
+    notification::subscribe(
+        None,
+        &[(NotificationCenter::Workspace, APP_ACTIVATED)],
+        |notification| {
+            if let Some(pid) = notification_pid(notification) {
+                observe_application(pid);
+            }
+        },
+    );
+    notification::subscribe(
+        None,
+        &[(NotificationCenter::Workspace, APP_TERMINATED)],
+        |notification| {
+            if let Some(pid) = notification_pid(notification) {
+                OBSERVERS.lock().unwrap().remove(&pid);
+                HISTORY.lock().unwrap().remove_app(pid);
+            }
+        },
+    );
+
+    if let Some(frontmost) = snapshot.frontmost {
+        observe_application(frontmost.pid);
+    }
+}
+
+pub fn get_history() -> Vec<WindowInfo> {
+    HISTORY.lock().unwrap().windows().to_vec()
+}
+
+fn restore_and_raise(element: AXUIElementRef) -> SnapResult<()> {
+    if get_bool_attribute(element, kAXMinimizedAttribute) == Some(true) {
+        set_bool_attribute(element, kAXMinimizedAttribute, false)?;
+    }
+    raise_window(element)
+}
+
+/// Restores the window if it is minimized, raises it and activates its application
+pub fn focus_window(pid: i64, window_id: u32) -> SnapResult<()> {
+    let element = get_window_from_id(pid, window_id)?;
+    let raised = restore_and_raise(element);
+    unsafe { CFRelease(element as CFTypeRef) };
+    raised?;
+    activate_application(pid)?;
+
+    // Updated right away so repeated calls don't wait for the watcher
+    HISTORY.lock().unwrap().promote(window_id as i64);
+
+    Ok(())
+}
+
+/// Focuses the window at `index` of the history, `0` being the focused window.
+/// Closed windows found on the way are pruned and the next one is tried
+pub fn focus_mru(index: usize) -> SnapResult<WindowInfo> {
+    loop {
+        let window = {
+            let history = HISTORY.lock().unwrap();
+            history.get(index).cloned().ok_or_else(|| {
+                SnapError::invalid_argument(
+                    "index",
+                    format!(
+                        "only {} windows are in the history",
+                        history.windows().len()
+                    ),
+                )
+            })?
+        };
+
+        let (Some(pid), Some(window_id)) = (window.pid, window.id) else {
+            HISTORY.lock().unwrap().remove(window.id);
+            continue;
+        };
+
+        match focus_window(pid, window_id as u32) {
+            Ok(()) => return Ok(window),
+            Err(SnapError::WindowNotFound { .. }) if !window_exists(window_id as u32) => {
+                HISTORY.lock().unwrap().remove(window.id);
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Switches back to the window that had focus before the current one
+pub fn focus_previous() -> SnapResult<WindowInfo> {
+    focus_mru(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(id: i64, pid: i64) -> WindowInfo {
+        WindowInfo {
+            id: Some(id),
+            pid: Some(pid),
+            name: None,
+            frame: None,
+            is_main: false,
+            title: None,
+            layer: 0,
+            alpha: 1.0,
+            bundle_id: None,
+            is_on_screen: true,
+            role: None,
+            subrole: None,
+            is_minimized: false,
+            is_fullscreen: false,
+            screen_index: None,
+        }
+    }
+
+    fn ids(history: &FocusHistory) -> Vec<i64> {
+        history
+            .windows()
+            .iter()
+            .filter_map(|window| window.id)
+            .collect()
+    }
+
+    #[test]
+    fn focused_window_comes_first_once() {
+        let mut history = FocusHistory::default();
+        history.focus(window(1, 10));
+        history.focus(window(2, 10));
+        history.focus(window(1, 10));
+
+        assert_eq!(ids(&history), vec![1, 2]);
+        assert_eq!(history.get(1).and_then(|window| window.id), Some(2));
+    }
+
+    #[test]
+    fn focus_replaces_the_known_window() {
+        let mut history = FocusHistory::default();
+        history.focus(window(1, 10));
+        let mut renamed = window(1, 10);
+        renamed.title = Some(String::from("Renamed"));
+        history.focus(renamed);
+
+        assert_eq!(history.windows().len(), 1);
+        assert_eq!(history.windows()[0].title.as_deref(), Some("Renamed"));
+    }
+
+    #[test]
+    fn oldest_windows_are_dropped_past_the_limit() {
+        let mut history = FocusHistory::default();
+        for id in 0..HISTORY_LIMIT as i64 + 5 {
+            history.focus(window(id, 10));
+        }
+
+        assert_eq!(history.windows().len(), HISTORY_LIMIT);
+        assert_eq!(ids(&history)[0], HISTORY_LIMIT as i64 + 4);
+        assert_eq!(ids(&history).last(), Some(&5));
+    }
+
+    #[test]
+    fn promote_only_moves_known_windows() {
+        let mut history = FocusHistory::default();
+        history.focus(window(1, 10));
+        history.focus(window(2, 10));
+
+        history.promote(1);
+        assert_eq!(ids(&history), vec![1, 2]);
+
+        history.promote(3);
+        assert_eq!(ids(&history), vec![1, 2]);
+    }
+
+    #[test]
+    fn remove_forgets_windows_and_applications() {
+        let mut history = FocusHistory::default();
+        history.focus(window(1, 10));
+        history.focus(window(2, 20));
+        history.focus(window(3, 10));
+
+        history.remove(Some(2));
+        assert_eq!(ids(&history), vec![3, 1]);
+
+        history.remove_app(10);
+        assert!(history.windows().is_empty());
+    }
+}
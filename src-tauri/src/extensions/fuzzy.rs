@@ -0,0 +1,389 @@
+use serde::Serialize;
+
+/// Every matched character
+const MATCH_SCORE: f64 = 1.0;
+/// Matched character right after the previous one
+const CONSECUTIVE_BONUS: f64 = 2.0;
+/// Matched character starting a word, `git` scores higher in `Git Hub` than in `digit`
+const WORD_START_BONUS: f64 = 3.0;
+/// Per character skipped between two matched ones
+const GAP_PENALTY: f64 = 0.2;
+/// Per character skipped before the first match, capped by `MAX_LEADING_PENALTY`
+const LEADING_PENALTY: f64 = 0.05;
+const MAX_LEADING_PENALTY: f64 = 1.0;
+/// Score added to the most recently used window, older ones get a fraction of it
+const RECENCY_BOOST: f64 = 4.0;
+/// Matches in the app name count less than in the title
+const APP_NAME_WEIGHT: f64 = 0.8;
+
+/// Highlighted part of a text, in UTF-16 code units so it can be sliced in JavaScript
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct MatchRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FuzzyMatch {
+    pub score: f64,
+    pub ranges: Vec<MatchRange>,
+}
+
+pub struct Candidate<T> {
+    pub item: T,
+    pub title: String,
+    pub app_name: String,
+    /// Position in the focus history, `0` being the focused window
+    pub recency: Option<usize>,
+    /// Share of the match score added to it, `0.5` ranks the app 50% higher and a
+    /// negative boost lower. Taken of the magnitude so that it also holds for the
+    /// negative scores of scattered matches
+    pub app_boost: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RankedMatch<T> {
+    pub item: T,
+    pub score: f64,
+    pub title_ranges: Vec<MatchRange>,
+    pub app_name_ranges: Vec<MatchRange>,
+}
+
+fn is_word_start(chars: &[char], index: usize) -> bool {
+    let Some(previous) = index.checked_sub(1).map(|previous| chars[previous]) else {
+        return true;
+    };
+    let current = chars[index];
+
+    !previous.is_alphanumeric() || (previous.is_lowercase() && current.is_uppercase())
+}
+
+fn to_ranges(chars: &[char], indices: &[usize]) -> Vec<MatchRange> {
+    let mut offsets = Vec::with_capacity(chars.len() + 1);
+    let mut offset = 0;
+    for c in chars {
+        offsets.push(offset);
+        offset += c.len_utf16();
+    }
+    offsets.push(offset);
+
+    let mut ranges: Vec<MatchRange> = vec![];
+    for &index in indices {
+        let (start, end) = (offsets[index], offsets[index + 1]);
+        match ranges.last_mut() {
+            Some(range) if range.end == start => range.end = end,
+            _ => ranges.push(MatchRange { start, end }),
+        }
+    }
+
+    ranges
+}
+
+/// Matches the characters of `query` in order, case insensitive.
+/// Every occurrence of the first character is tried and the best scoring alignment kept
+pub fn match_text(query: &str, text: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0.0,
+            ranges: vec![],
+        });
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    // Folded like the query, a character can lower to several (`İ` is `i` and a dot),
+    // `origin` maps every lowered character back to the one in `chars`
+    let (lowered, origin): (Vec<char>, Vec<usize>) = chars
+        .iter()
+        .enumerate()
+        .flat_map(|(index, c)| c.to_lowercase().map(move |lower| (lower, index)))
+        .unzip();
+
+    let mut best: Option<(f64, Vec<usize>)> = None;
+
+    for start in (0..lowered.len()).filter(|&start| lowered[start] == query[0]) {
+        let mut indices = vec![start];
+        let mut position = start + 1;
+        for &expected in &query[1..] {
+            let Some(found) = lowered[position..].iter().position(|&c| c == expected) else {
+                break;
+            };
+            indices.push(position + found);
+            position += found + 1;
+        }
+        if indices.len() < query.len() {
+            // Later starts only leave fewer characters to match
+            break;
+        }
+
+        let mut score = -(start as f64 * LEADING_PENALTY).min(MAX_LEADING_PENALTY);
+        for (i, &index) in indices.iter().enumerate() {
+            score += MATCH_SCORE;
+            let starts_char = index == 0 || origin[index - 1] != origin[index];
+            if starts_char && is_word_start(&chars, origin[index]) {
+                score += WORD_START_BONUS;
+            }
+            if i > 0 {
+                let gap = index - indices[i - 1] - 1;
+                if gap == 0 {
+                    score += CONSECUTIVE_BONUS;
+                } else {
+                    score -= gap as f64 * GAP_PENALTY;
+                }
+            }
+        }
+
+        if best
+            .as_ref()
+            .is_none_or(|(best_score, _)| score > *best_score)
+        {
+            best = Some((score, indices));
+        }
+    }
+
+    best.map(|(score, indices)| {
+        let mut matched: Vec<usize> = indices.iter().map(|&index| origin[index]).collect();
+        matched.dedup();
+
+        FuzzyMatch {
+            score,
+            ranges: to_ranges(&chars, &matched),
+        }
+    })
+}
+
+fn merge_ranges(ranges: &mut Vec<MatchRange>) {
+    ranges.sort_by_key(|range| range.start);
+    ranges.dedup_by(|next, range| {
+        if next.start <= range.end {
+            range.end = range.end.max(next.end);
+            true
+        } else {
+            false
+        }
+    });
+}
+
+/// Ranks candidates against a query of whitespace separated words.
+/// Each word has to match the title or the app name, so `chrome issues` finds
+/// the issues tab of Chrome. An empty query keeps every candidate, most recent first
+pub fn rank<T>(query: &str, candidates: Vec<Candidate<T>>) -> Vec<RankedMatch<T>> {
+    let words: Vec<&str> = query.split_whitespace().collect();
+
+    let mut ranked: Vec<RankedMatch<T>> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let mut match_score = 0.0;
+            let mut title_ranges = vec![];
+            let mut app_name_ranges = vec![];
+
+            for word in &words {
+                let title = match_text(word, &candidate.title);
+                let app_name = match_text(word, &candidate.app_name).map(|found| FuzzyMatch {
+                    score: found.score * APP_NAME_WEIGHT,
+                    ..found
+                });
+
+                match (title, app_name) {
+                    (Some(title), Some(app_name)) if app_name.score > title.score => {
+                        match_score += app_name.score;
+                        app_name_ranges.extend(app_name.ranges);
+                    }
+                    (Some(title), _) => {
+                        match_score += title.score;
+                        title_ranges.extend(title.ranges);
+                    }
+                    (None, Some(app_name)) => {
+                        match_score += app_name.score;
+                        app_name_ranges.extend(app_name.ranges);
+                    }
+                    (None, None) => return None,
+                }
+            }
+
+            merge_ranges(&mut title_ranges);
+            merge_ranges(&mut app_name_ranges);
+
+            let recency = candidate
+                .recency
+                .map_or(0.0, |recency| RECENCY_BOOST / (1.0 + recency as f64));
+
+            Some(RankedMatch {
+                score: match_score + match_score.abs() * candidate.app_boost + recency,
+                item: candidate.item,
+                title_ranges,
+                app_name_ranges,
+            })
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: usize, end: usize) -> MatchRange {
+        MatchRange { start, end }
+    }
+
+    fn score(query: &str, text: &str) -> f64 {
+        match_text(query, text).unwrap().score
+    }
+
+    fn candidate(title: &str, app_name: &str) -> Candidate<&'static str> {
+        Candidate {
+            item: "",
+            title: title.to_string(),
+            app_name: app_name.to_string(),
+            recency: None,
+            app_boost: 0.0,
+        }
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        let found = match_text("", "Safari").unwrap();
+
+        assert_eq!(found.score, 0.0);
+        assert!(found.ranges.is_empty());
+    }
+
+    #[test]
+    fn characters_have_to_appear_in_order() {
+        assert!(match_text("sfr", "Safari").is_some());
+        assert!(match_text("rfs", "Safari").is_none());
+        assert!(match_text("safarix", "Safari").is_none());
+    }
+
+    #[test]
+    fn ranges_merge_consecutive_characters() {
+        let found = match_text("safi", "Safari").unwrap();
+
+        assert_eq!(found.ranges, vec![range(0, 3), range(5, 6)]);
+    }
+
+    #[test]
+    fn ranges_are_utf16_offsets() {
+        // The emoji takes two UTF-16 code units
+        let found = match_text("ab", "😀 ab").unwrap();
+
+        assert_eq!(found.ranges, vec![range(3, 5)]);
+    }
+
+    #[test]
+    fn case_folding_is_the_same_for_query_and_text() {
+        // `İ` lowers to `i` followed by a combining dot
+        let found = match_text("i̇stanbul", "İstanbul").unwrap();
+        assert_eq!(found.ranges, vec![range(0, 8)]);
+
+        let found = match_text("İs", "i̇stanbul").unwrap();
+        assert_eq!(found.ranges, vec![range(0, 3)]);
+
+        assert!(match_text("SAFARI", "safari").is_some());
+    }
+
+    #[test]
+    fn prefix_scores_higher_than_a_later_match() {
+        assert!(score("term", "Terminal") > score("term", "My Terminal"));
+        assert!(score("term", "My Terminal") > score("term", "Determine"));
+    }
+
+    #[test]
+    fn word_boundaries_score_higher_than_inner_characters() {
+        assert!(score("gh", "Git Hub") > score("gh", "Highlight"));
+        // camelCase humps start words too
+        assert!(score("gh", "gitHub") > score("gh", "laughing"));
+    }
+
+    #[test]
+    fn best_alignment_is_kept() {
+        // The first `c` leads to a scattered match, the word `code` is better
+        let found = match_text("code", "cat code").unwrap();
+
+        assert_eq!(found.ranges, vec![range(4, 8)]);
+    }
+
+    #[test]
+    fn every_word_has_to_match_title_or_app_name() {
+        let ranked = rank(
+            "chrome issues",
+            vec![
+                candidate("Issues · snap", "Google Chrome"),
+                candidate("Issues · snap", "Safari"),
+            ],
+        );
+
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].title_ranges, vec![range(0, 6)]);
+        assert_eq!(ranked[0].app_name_ranges, vec![range(7, 13)]);
+    }
+
+    #[test]
+    fn recency_boosts_recent_windows() {
+        let mut older = candidate("Notes", "Notes");
+        older.item = "older";
+        older.recency = Some(3);
+        let mut recent = candidate("Notes", "Notes");
+        recent.item = "recent";
+        recent.recency = Some(0);
+
+        let ranked = rank("notes", vec![older, recent]);
+
+        assert_eq!(ranked[0].item, "recent");
+        assert!(ranked[0].score - ranked[1].score > RECENCY_BOOST / 2.0);
+    }
+
+    #[test]
+    fn empty_query_orders_by_recency() {
+        let mut unknown = candidate("Untracked", "App");
+        unknown.item = "unknown";
+        let mut second = candidate("Second", "App");
+        second.item = "second";
+        second.recency = Some(1);
+        let mut first = candidate("First", "App");
+        first.item = "first";
+        first.recency = Some(0);
+
+        let ranked = rank(" ", vec![unknown, second, first]);
+        let order: Vec<&str> = ranked.iter().map(|ranked| ranked.item).collect();
+
+        assert_eq!(order, vec!["first", "second", "unknown"]);
+    }
+
+    #[test]
+    fn app_boost_adds_its_share_of_the_match_score() {
+        let mut plain = candidate("Inbox", "Mail");
+        plain.item = "plain";
+        let mut boosted = candidate("Inbox", "Mail");
+        boosted.item = "boosted";
+        boosted.app_boost = 0.5;
+
+        let ranked = rank("inbox", vec![plain, boosted]);
+
+        assert_eq!(ranked[0].item, "boosted");
+        assert!((ranked[0].score - ranked[1].score * 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn app_boost_raises_negative_scores_too() {
+        // Matched characters far apart score below zero
+        let title = format!("a{}x", "b".repeat(40));
+        let mut plain = candidate(&title, "Notes");
+        plain.item = "plain";
+        let mut boosted = candidate(&title, "Notes");
+        boosted.item = "boosted";
+        boosted.app_boost = 0.5;
+        let mut demoted = candidate(&title, "Notes");
+        demoted.item = "demoted";
+        demoted.app_boost = -0.5;
+
+        let ranked = rank("ax", vec![demoted, plain, boosted]);
+        let order: Vec<_> = ranked.iter().map(|ranked| ranked.item).collect();
+
+        assert!(ranked[1].score < 0.0);
+        assert_eq!(order, vec!["boosted", "plain", "demoted"]);
+    }
+}
@@ -0,0 +1,635 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    os::unix::process::CommandExt,
+    process::{Command, ExitStatus, Stdio},
+    sync::{
+        mpsc::{self, Receiver, SyncSender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use core_graphics::window::CGWindowID;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::data::{
+    action::{ArrangeStrategy, WindowManagerMode},
+    error::{SnapError, SnapResult},
+    frame::{Frame, Point, Size},
+    screen::Screen,
+};
+
+use super::screens;
+
+/// How often a running hook is checked for having exited
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// Hooks waiting for a free worker, further ones are dropped
+const QUEUE_CAPACITY: usize = 64;
+const SIGKILL: i32 = 9;
+
+extern "C" {
+    fn kill(pid: i32, signal: i32) -> i32;
+}
+
+/// Events user commands can be hooked to, passed to them as JSON on stdin
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HookEvent {
+    /// `zone` is the frame relative to the visible frame of `screen` (0 to 1)
+    WindowSnapped {
+        pid: i64,
+        window_id: CGWindowID,
+        frame: Frame,
+        screen: Option<usize>,
+        zone: Option<Frame>,
+    },
+    ArrangeFinished {
+        strategy: ArrangeStrategy,
+        windows: usize,
+    },
+    ScreensChanged {
+        screens: Vec<Screen>,
+    },
+    ModeChanged {
+        mode: WindowManagerMode,
+    },
+    CaffeinateChanged {
+        enabled: bool,
+    },
+    AccentColorChanged {
+        color: String,
+    },
+}
+
+pub const EVENT_NAMES: &[&str] = &[
+    "window_snapped",
+    "arrange_finished",
+    "screens_changed",
+    "mode_changed",
+    "caffeinate_changed",
+    "accent_color_changed",
+];
+
+impl HookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::WindowSnapped { .. } => "window_snapped",
+            HookEvent::ArrangeFinished { .. } => "arrange_finished",
+            HookEvent::ScreensChanged { .. } => "screens_changed",
+            HookEvent::ModeChanged { .. } => "mode_changed",
+            HookEvent::CaffeinateChanged { .. } => "caffeinate_changed",
+            HookEvent::AccentColorChanged { .. } => "accent_color_changed",
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Commands by event name, each run with `/bin/sh -c`
+    pub hooks: HashMap<String, Vec<String>>,
+    /// Hooks still running after this are killed
+    pub timeout_ms: u64,
+    /// Hooks over this wait for a running one to finish
+    pub max_concurrent: usize,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            hooks: HashMap::new(),
+            timeout_ms: 10_000,
+            max_concurrent: 4,
+        }
+    }
+}
+
+impl HooksConfig {
+    pub fn validate(&self) -> SnapResult<()> {
+        if self.timeout_ms == 0 {
+            return Err(SnapError::invalid_argument(
+                "timeout_ms",
+                "must be greater than zero",
+            ));
+        }
+        if self.max_concurrent == 0 {
+            return Err(SnapError::invalid_argument(
+                "max_concurrent",
+                "must be greater than zero",
+            ));
+        }
+        if let Some(name) = self
+            .hooks
+            .keys()
+            .find(|name| !EVENT_NAMES.contains(&name.as_str()))
+        {
+            return Err(SnapError::invalid_argument(
+                "hooks",
+                format!(
+                    "unknown event {}, expected one of {}",
+                    name,
+                    EVENT_NAMES.join(", ")
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn commands(&self, name: &str) -> Vec<String> {
+        self.hooks
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|command| command.trim())
+            .filter(|command| !command.is_empty())
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// Where `frame` sits on its screen, as the index of the screen and the frame relative
+/// to its visible frame, rounded so that halves and thirds read as such
+pub fn zone_of(frame: &Frame, visible_frames: &[Frame]) -> Option<(usize, Frame)> {
+    let index = screens::containing(frame, visible_frames)?;
+    let screen = &visible_frames[index];
+    if screen.size.width <= 0.0 || screen.size.height <= 0.0 {
+        return None;
+    }
+
+    let relative = |value: f64, origin: f64, length: f64| {
+        ((value - origin) / length * 1000.0).round() / 1000.0
+    };
+
+    Some((
+        index,
+        Frame {
+            size: Size {
+                width: relative(frame.size.width, 0.0, screen.size.width),
+                height: relative(frame.size.height, 0.0, screen.size.height),
+            },
+            position: Point {
+                x: relative(frame.position.x, screen.position.x, screen.size.width),
+                y: relative(frame.position.y, screen.position.y, screen.size.height),
+            },
+        },
+    ))
+}
+
+fn push_env(vars: &mut Vec<(String, String)>, key: String, value: &Value) {
+    match value {
+        Value::Null => {}
+        Value::Bool(value) => vars.push((key, value.to_string())),
+        Value::Number(value) => vars.push((key, value.to_string())),
+        Value::String(value) => vars.push((key, value.clone())),
+        Value::Array(_) => vars.push((key, value.to_string())),
+        Value::Object(fields) => {
+            for (field, value) in fields {
+                push_env(vars, format!("{}_{}", key, field.to_uppercase()), value);
+            }
+        }
+    }
+}
+
+/// `SNAP_EVENT` plus one variable per field of the payload, nested fields joined
+/// with `_` (`SNAP_FRAME_POSITION_X`) and lists as JSON
+pub fn env_vars(event: &HookEvent) -> Vec<(String, String)> {
+    let mut vars = vec![(String::from("SNAP_EVENT"), String::from(event.name()))];
+
+    if let Ok(Value::Object(fields)) = serde_json::to_value(event) {
+        for (field, value) in fields.iter().filter(|(field, _)| *field != "event") {
+            push_env(&mut vars, format!("SNAP_{}", field.to_uppercase()), value);
+        }
+    }
+
+    vars
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Fixed number of workers taking jobs from a bounded queue, one at a time each
+struct Pool {
+    jobs: SyncSender<Job>,
+    size: usize,
+}
+
+impl Pool {
+    fn new(size: usize, capacity: usize) -> Self {
+        let (jobs, queue) = mpsc::sync_channel::<Job>(capacity);
+        let queue = Arc::new(Mutex::new(queue));
+
+        for index in 0..size {
+            let queue = queue.clone();
+            thread::Builder::new()
+                .name(format!("snap-hook-{}", index))
+                .spawn(move || Self::work(&queue))
+                .expect("failed to spawn hook worker thread");
+        }
+
+        Self { jobs, size }
+    }
+
+    /// Runs jobs until the pool is dropped and its queue drained
+    fn work(queue: &Mutex<Receiver<Job>>) {
+        loop {
+            // The lock is only held while waiting, the job runs without it
+            let job = queue.lock().unwrap().recv();
+            match job {
+                Ok(job) => job(),
+                Err(_) => return,
+            }
+        }
+    }
+
+    /// `false` when the queue is full and the job was dropped
+    fn submit(&self, job: Job) -> bool {
+        self.jobs.try_send(job).is_ok()
+    }
+}
+
+static CONFIG: Lazy<Mutex<HooksConfig>> = Lazy::new(|| Mutex::new(HooksConfig::default()));
+
+/// Created by the first hook, replaced when `max_concurrent` changes
+static POOL: Lazy<Mutex<Option<Pool>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn configure(config: HooksConfig) -> SnapResult<()> {
+    config.validate()?;
+
+    // The workers of a replaced pool exit once they ran what was already queued
+    let mut pool = POOL.lock().unwrap();
+    if pool
+        .as_ref()
+        .is_some_and(|pool| pool.size != config.max_concurrent)
+    {
+        *pool = None;
+    }
+
+    *CONFIG.lock().unwrap() = config;
+    Ok(())
+}
+
+pub fn has_hooks(name: &str) -> bool {
+    !CONFIG.lock().unwrap().commands(name).is_empty()
+}
+
+enum Outcome {
+    Exited(ExitStatus),
+    TimedOut,
+}
+
+fn execute(
+    command: &str,
+    payload: &[u8],
+    env: &[(String, String)],
+    timeout: Duration,
+) -> io::Result<(Outcome, String)> {
+    let mut child = Command::new("/bin/sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        // Its own process group, so a timeout also kills what the shell started
+        .process_group(0)
+        .spawn()?;
+
+    // Written on the side too, a hook that never reads its stdin would block a large payload.
+    // One that exits without reading it closes the pipe early, which is fine
+    if let Some(mut stdin) = child.stdin.take() {
+        let payload = payload.to_vec();
+        thread::spawn(move || {
+            let _ = stdin.write_all(&payload);
+        });
+    }
+
+    // Read on the side so a chatty hook can't fill the pipe and block
+    let stderr = child.stderr.take().map(|mut stderr| {
+        thread::spawn(move || {
+            let mut output = String::new();
+            let _ = stderr.read_to_string(&mut output);
+            output
+        })
+    });
+
+    let started = Instant::now();
+    let outcome = loop {
+        if let Some(status) = child.try_wait()? {
+            break Outcome::Exited(status);
+        }
+        if started.elapsed() >= timeout {
+            unsafe { kill(-(child.id() as i32), SIGKILL) };
+            let _ = child.wait();
+            break Outcome::TimedOut;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let stderr = stderr
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default();
+
+    Ok((outcome, stderr))
+}
+
+fn run_one(
+    name: &'static str,
+    command: &str,
+    payload: &[u8],
+    env: &[(String, String)],
+    timeout: Duration,
+) {
+    match execute(command, payload, env, timeout) {
+        Ok((outcome, stderr)) => {
+            match outcome {
+                Outcome::Exited(status) if status.success() => {}
+                Outcome::Exited(status) => {
+                    eprintln!("{} hook `{}` failed with {}", name, command, status)
+                }
+                Outcome::TimedOut => eprintln!(
+                    "{} hook `{}` killed after {}ms",
+                    name,
+                    command,
+                    timeout.as_millis()
+                ),
+            }
+            for line in stderr.lines() {
+                eprintln!("{} hook `{}`: {}", name, command, line);
+            }
+        }
+        Err(error) => eprintln!("{} hook `{}` could not start: {}", name, command, error),
+    }
+}
+
+/// Runs the commands hooked to the event in the background
+pub fn run(event: HookEvent) {
+    let (commands, timeout, limit) = {
+        let config = CONFIG.lock().unwrap();
+        (
+            config.commands(event.name()),
+            Duration::from_millis(config.timeout_ms),
+            config.max_concurrent,
+        )
+    };
+    if commands.is_empty() {
+        return;
+    }
+
+    let payload = match serde_json::to_vec(&event) {
+        Ok(payload) => payload,
+        Err(error) => {
+            eprintln!("{} hook payload failed: {}", event.name(), error);
+            return;
+        }
+    };
+    let env = env_vars(&event);
+    let name = event.name();
+
+    let mut pool = POOL.lock().unwrap();
+    let pool = pool.get_or_insert_with(|| Pool::new(limit, QUEUE_CAPACITY));
+    for command in commands {
+        let payload = payload.clone();
+        let env = env.clone();
+
+        let job: Job = Box::new(move || run_one(name, &command, &payload, &env, timeout));
+        if !pool.submit(job) {
+            eprintln!("{} hook dropped, too many hooks are waiting", name);
+        }
+    }
+}
+
+/// Fired once a window snapped or mapped to a zone got its frame
+pub fn window_snapped(pid: i64, window_id: CGWindowID, frame: &Frame) {
+    if !has_hooks("window_snapped") {
+        return;
+    }
+
+    let zone = zone_of(frame, &screens::visible_frames());
+    run(HookEvent::WindowSnapped {
+        pid,
+        window_id,
+        frame: frame.clone(),
+        screen: zone.as_ref().map(|(index, _)| *index),
+        zone: zone.map(|(_, zone)| zone),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn frame(x: f64, y: f64, width: f64, height: f64) -> Frame {
+        Frame {
+            position: Point { x, y },
+            size: Size { width, height },
+        }
+    }
+
+    fn snapped(zone: Option<Frame>) -> HookEvent {
+        HookEvent::WindowSnapped {
+            pid: 42,
+            window_id: 7,
+            frame: frame(0.0, 25.0, 720.0, 875.0),
+            screen: zone.as_ref().map(|_| 0),
+            zone,
+        }
+    }
+
+    fn config(hooks: &[(&str, &str)]) -> HooksConfig {
+        HooksConfig {
+            hooks: hooks
+                .iter()
+                .map(|(name, command)| (name.to_string(), vec![command.to_string()]))
+                .collect(),
+            ..HooksConfig::default()
+        }
+    }
+
+    #[test]
+    fn zone_is_relative_to_the_visible_frame() {
+        let screens = [
+            frame(0.0, 25.0, 1440.0, 875.0),
+            frame(1440.0, 0.0, 1920.0, 1080.0),
+        ];
+
+        let (index, zone) = zone_of(&frame(0.0, 25.0, 720.0, 875.0), &screens).unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(zone, frame(0.0, 0.0, 0.5, 1.0));
+
+        // Thirds are rounded so that they read as such
+        let (index, zone) = zone_of(&frame(2080.0, 0.0, 640.0, 540.0), &screens).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(zone, frame(0.333, 0.0, 0.333, 0.5));
+    }
+
+    #[test]
+    fn zone_needs_a_screen_with_an_area() {
+        let window = frame(100.0, 100.0, 400.0, 300.0);
+
+        assert_eq!(zone_of(&window, &[]), None);
+        assert_eq!(zone_of(&window, &[frame(-5000.0, 0.0, 100.0, 100.0)]), None);
+        assert_eq!(zone_of(&window, &[frame(0.0, 0.0, 1000.0, 0.0)]), None);
+    }
+
+    #[test]
+    fn env_vars_flatten_the_payload() {
+        let vars = env_vars(&snapped(Some(frame(0.0, 0.0, 0.5, 1.0))));
+        let value = |key: &str| {
+            vars.iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.as_str())
+        };
+
+        assert_eq!(
+            vars[0],
+            (String::from("SNAP_EVENT"), String::from("window_snapped"))
+        );
+        assert_eq!(value("SNAP_PID"), Some("42"));
+        assert_eq!(value("SNAP_WINDOW_ID"), Some("7"));
+        assert_eq!(value("SNAP_FRAME_POSITION_Y"), Some("25.0"));
+        assert_eq!(value("SNAP_FRAME_SIZE_WIDTH"), Some("720.0"));
+        assert_eq!(value("SNAP_ZONE_SIZE_WIDTH"), Some("0.5"));
+        assert_eq!(value("SNAP_SCREEN"), Some("0"));
+        assert_eq!(value("SNAP_EVENT_EVENT"), None);
+    }
+
+    #[test]
+    fn env_vars_skip_nulls_and_keep_lists_as_json() {
+        let vars = env_vars(&snapped(None));
+        assert!(!vars.iter().any(|(name, _)| name.starts_with("SNAP_ZONE")));
+        assert!(!vars.iter().any(|(name, _)| name == "SNAP_SCREEN"));
+
+        let vars = env_vars(&HookEvent::ScreensChanged { screens: vec![] });
+        assert_eq!(
+            vars,
+            vec![
+                (String::from("SNAP_EVENT"), String::from("screens_changed")),
+                (String::from("SNAP_SCREENS"), String::from("[]")),
+            ]
+        );
+
+        let mut vars = vec![];
+        push_env(&mut vars, String::from("SNAP_ENABLED"), &Value::Bool(true));
+        push_env(
+            &mut vars,
+            String::from("SNAP_COLOR"),
+            &Value::from("#ff0000"),
+        );
+        assert_eq!(
+            vars,
+            vec![
+                (String::from("SNAP_ENABLED"), String::from("true")),
+                (String::from("SNAP_COLOR"), String::from("#ff0000")),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_rejects_zero_limits_and_unknown_events() {
+        assert!(config(&[("window_snapped", "true")]).validate().is_ok());
+
+        let zero_timeout = HooksConfig {
+            timeout_ms: 0,
+            ..HooksConfig::default()
+        };
+        assert!(zero_timeout.validate().is_err());
+
+        let zero_workers = HooksConfig {
+            max_concurrent: 0,
+            ..HooksConfig::default()
+        };
+        assert!(zero_workers.validate().is_err());
+
+        let error = config(&[("window_moved", "true")]).validate().unwrap_err();
+        assert!(error.to_string().contains("window_moved"));
+    }
+
+    #[test]
+    fn commands_skip_blank_entries() {
+        let mut config = config(&[("mode_changed", "  say hi  ")]);
+        config
+            .hooks
+            .get_mut("mode_changed")
+            .unwrap()
+            .push(String::from("   "));
+
+        assert_eq!(
+            config.commands("mode_changed"),
+            vec![String::from("say hi")]
+        );
+        assert!(config.commands("window_snapped").is_empty());
+    }
+
+    #[test]
+    fn execute_passes_the_payload_and_captures_stderr() {
+        let env = vec![(String::from("SNAP_EVENT"), String::from("mode_changed"))];
+        let (outcome, stderr) = execute(
+            "cat >&2; echo \" $SNAP_EVENT\" >&2; exit 3",
+            b"{}",
+            &env,
+            Duration::from_secs(5),
+        )
+        .unwrap();
+
+        assert!(matches!(outcome, Outcome::Exited(status) if status.code() == Some(3)));
+        assert_eq!(stderr, "{} mode_changed\n");
+    }
+
+    #[test]
+    fn execute_kills_hooks_that_time_out() {
+        let started = Instant::now();
+        let (outcome, _) =
+            execute("sleep 5; sleep 5", b"", &[], Duration::from_millis(100)).unwrap();
+
+        assert!(matches!(outcome, Outcome::TimedOut));
+        // The whole process group went down, not just the shell
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn pool_runs_at_most_its_size_at_once() {
+        let pool = Pool::new(2, QUEUE_CAPACITY);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (done, finished) = mpsc::channel();
+
+        for _ in 0..6 {
+            let (running, peak, done) = (running.clone(), peak.clone(), done.clone());
+            assert!(pool.submit(Box::new(move || {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(20));
+                running.fetch_sub(1, Ordering::SeqCst);
+                let _ = done.send(());
+            })));
+        }
+
+        for _ in 0..6 {
+            finished.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[test]
+    fn pool_drops_jobs_once_the_queue_is_full() {
+        let pool = Pool::new(1, 1);
+        let (started, busy) = mpsc::channel();
+        let (release, blocked) = mpsc::channel::<()>();
+
+        assert!(pool.submit(Box::new(move || {
+            let _ = started.send(());
+            let _ = blocked.recv();
+        })));
+        busy.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert!(pool.submit(Box::new(|| {})));
+        assert!(!pool.submit(Box::new(|| {})));
+        drop(release);
+    }
+}
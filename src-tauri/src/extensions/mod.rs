@@ -1,6 +1,29 @@
 pub mod accessibility_elements;
+pub mod actions;
+pub mod animation;
+pub mod app_icons;
+pub mod caffeinate;
+pub mod caffeinate_triggers;
+pub mod deep_link;
+pub mod drag_velocity;
+pub mod easing;
 pub mod event_monitor;
+pub mod event_throttle;
+pub mod focus_history;
+pub mod fuzzy;
 pub mod helpers;
+pub mod hooks;
+pub mod modifier_drag;
 pub mod notification;
 pub mod nscolor;
+pub mod placement;
+pub mod plugins;
+pub mod rpc_backend;
+pub mod rpc_server;
+pub mod screens;
+pub mod scripting;
+pub mod window_finder;
+pub mod window_switcher;
+pub mod window_transaction;
+pub mod window_watcher;
 pub mod windows;
@@ -0,0 +1,760 @@
+use std::{
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use accessibility_sys::AXUIElementRef;
+use cocoa::appkit::CGPoint;
+use core_foundation::base::{CFRelease, CFTypeRef};
+use core_graphics::window::CGWindowID;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{
+    alignment::Alignment,
+    error::{SnapError, SnapResult},
+    frame::{Frame, Point, Size},
+    mouse::{EventKind, Modifiers, MonitorOptions},
+};
+
+use super::{
+    accessibility_elements::{
+        activate_application, get_frame, get_window_from_id, raise_window, set_frame, set_position,
+    },
+    event_monitor::{CGEventRef, EventTap},
+    event_throttle::EventThrottle,
+    placement::place,
+    windows::window_at,
+};
+
+/// Windows are not resized below this
+const MIN_SIZE: Size = Size {
+    width: 120.0,
+    height: 80.0,
+};
+/// At most one frame update per interval while dragging, about one per display frame
+const UPDATE_INTERVAL_MS: u64 = 16;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ModifierDragConfig {
+    pub enabled: bool,
+    /// Keys held to grab a window, caps lock and fn are ignored
+    pub modifiers: Modifiers,
+    /// Right drags resize from the corner nearest to the cursor
+    pub resize: bool,
+}
+
+impl Default for ModifierDragConfig {
+    fn default() -> Self {
+        ModifierDragConfig {
+            enabled: false,
+            modifiers: Modifiers {
+                control: true,
+                command: true,
+                ..Modifiers::default()
+            },
+            resize: true,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DragMode {
+    Move,
+    /// `corner` follows the cursor, the opposite one stays in place
+    Resize {
+        corner: Alignment,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DragPhase {
+    Started,
+    Moved,
+    Ended,
+}
+
+impl DragPhase {
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            DragPhase::Started => "modifier_drag_started",
+            DragPhase::Moved => "modifier_drag_moved",
+            DragPhase::Ended => "modifier_drag_ended",
+        }
+    }
+}
+
+/// Sent when a grab starts, for every frame update and on release
+#[derive(Serialize, Clone, Debug)]
+pub struct ModifierDragEvent {
+    pub pid: i64,
+    pub window_id: CGWindowID,
+    pub mode: DragMode,
+    /// Frame the window was given, in CG coordinates
+    pub frame: Frame,
+    pub point: Point,
+}
+
+/// The window grabbed by the current drag
+struct DragSession {
+    window: AXUIElementRef,
+    pid: i64,
+    window_id: CGWindowID,
+    mode: DragMode,
+    start_frame: Frame,
+    start_point: Point,
+    frame: Frame,
+    started: Instant,
+    throttle: EventThrottle<Frame>,
+}
+
+// The element is owned by the session and only used on the drag worker thread
+unsafe impl Send for DragSession {}
+
+impl Drop for DragSession {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.window as CFTypeRef) }
+    }
+}
+
+impl DragSession {
+    fn event(&self, point: Point) -> ModifierDragEvent {
+        ModifierDragEvent {
+            pid: self.pid,
+            window_id: self.window_id,
+            mode: self.mode,
+            frame: self.frame.clone(),
+            point,
+        }
+    }
+}
+
+/// What the tap hands to the worker, in CG coordinates
+#[derive(Clone, Debug, PartialEq)]
+enum DragInput {
+    Down { point: Point, resize: bool },
+    Dragged(Point),
+    Up(Point),
+}
+
+/// Button of the grab in progress, its drags and release are swallowed until it's up
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Grab {
+    Left,
+    Right,
+}
+
+static TAP: Lazy<Mutex<Option<EventTap>>> = Lazy::new(|| Mutex::new(None));
+
+/// Corner of `frame` closest to `point`, each axis is -1 (start) or 1 (end)
+pub fn nearest_corner(frame: &Frame, point: &Point) -> Alignment {
+    let side = |value: f64, start: f64, length: f64| {
+        if value < start + length / 2.0 {
+            -1.0
+        } else {
+            1.0
+        }
+    };
+
+    Alignment::new(
+        side(point.x, frame.position.x, frame.size.width),
+        side(point.y, frame.position.y, frame.size.height),
+    )
+}
+
+/// Frame of a window grabbed at `start` once the cursor moved by `delta`
+pub fn dragged_frame(start: &Frame, mode: DragMode, delta: &Point) -> Frame {
+    let corner = match mode {
+        DragMode::Move => {
+            return Frame {
+                size: start.size.clone(),
+                position: Point {
+                    x: start.position.x + delta.x,
+                    y: start.position.y + delta.y,
+                },
+            }
+        }
+        DragMode::Resize { corner } => corner,
+    };
+
+    // The start edge moves with the cursor and the end edge stays, or the other way
+    let axis = |start: f64, length: f64, delta: f64, side: f64, min: f64| {
+        if side < 0.0 {
+            let resized = (length - delta).max(min);
+            (start + length - resized, resized)
+        } else {
+            (start, (length + delta).max(min))
+        }
+    };
+    let (x, width) = axis(
+        start.position.x,
+        start.size.width,
+        delta.x,
+        corner.x,
+        MIN_SIZE.width,
+    );
+    let (y, height) = axis(
+        start.position.y,
+        start.size.height,
+        delta.y,
+        corner.y,
+        MIN_SIZE.height,
+    );
+
+    Frame {
+        size: Size { width, height },
+        position: Point { x, y },
+    }
+}
+
+fn begin(point: Point, mode: impl Fn(&Frame) -> DragMode) -> SnapResult<DragSession> {
+    let (pid, window_id, _) = window_at(&point)
+        .ok_or_else(|| SnapError::platform("there is no window under the cursor"))?;
+    let window = get_window_from_id(pid, window_id)?;
+    let frame = match get_frame(window) {
+        Ok(frame) => frame,
+        Err(error) => {
+            unsafe { CFRelease(window as CFTypeRef) };
+            return Err(error);
+        }
+    };
+    // Like X11 window managers, a grabbed window comes to the front. The click is
+    // swallowed, so the app has to be activated here
+    let _ = raise_window(window);
+    let _ = activate_application(pid);
+
+    Ok(DragSession {
+        window,
+        pid,
+        window_id,
+        mode: mode(&frame),
+        start_frame: frame.clone(),
+        start_point: point,
+        frame,
+        started: Instant::now(),
+        throttle: EventThrottle::new(UPDATE_INTERVAL_MS, false),
+    })
+}
+
+fn apply(session: &DragSession, frame: &Frame) -> SnapResult<()> {
+    let frame = frame.rounded();
+    match session.mode {
+        DragMode::Move => set_position(
+            session.window,
+            CGPoint {
+                x: frame.position.x,
+                y: frame.position.y,
+            },
+        ),
+        DragMode::Resize { .. } => set_frame(session.window, &frame),
+    }
+}
+
+/// The corner opposite of the grabbed one stays pinned when the app refuses the size
+fn finish(session: &DragSession, frame: &Frame) -> SnapResult<Frame> {
+    match session.mode {
+        DragMode::Move => {
+            apply(session, frame)?;
+            Ok(frame.rounded())
+        }
+        DragMode::Resize { corner } => {
+            let anchor = Alignment::new(-corner.x, -corner.y);
+            Ok(place(session.window, &frame.rounded(), anchor)?.frame)
+        }
+    }
+}
+
+/// Reads the event on the tap thread and hands it to `route`
+fn route_event(
+    event: CGEventRef,
+    config: &ModifierDragConfig,
+    grab: &mut Option<Grab>,
+    worker: &Sender<DragInput>,
+) -> bool {
+    let Some(kind) = EventKind::from_event_type(EventTap::event_type(event)) else {
+        return false;
+    };
+    let location = EventTap::location(event);
+    let point = Point {
+        x: location.x,
+        y: location.y,
+    };
+
+    route(
+        kind,
+        point,
+        &EventTap::modifiers(event),
+        config,
+        grab,
+        worker,
+    )
+}
+
+/// Decides whether the event belongs to a grab, without touching any window.
+/// Returns `true` for the events that are swallowed
+fn route(
+    kind: EventKind,
+    point: Point,
+    modifiers: &Modifiers,
+    config: &ModifierDragConfig,
+    grab: &mut Option<Grab>,
+    worker: &Sender<DragInput>,
+) -> bool {
+    let button = match kind {
+        EventKind::LeftMouseDown | EventKind::LeftMouseDragged | EventKind::LeftMouseUp => {
+            Grab::Left
+        }
+        _ => Grab::Right,
+    };
+
+    match kind {
+        EventKind::LeftMouseDown | EventKind::RightMouseDown => {
+            if grab.is_some() || !config.modifiers.matches(modifiers) {
+                return false;
+            }
+            let resize = button == Grab::Right;
+            if resize && !config.resize {
+                return false;
+            }
+
+            *grab = Some(button);
+            let _ = worker.send(DragInput::Down { point, resize });
+            true
+        }
+        EventKind::LeftMouseDragged | EventKind::RightMouseDragged => {
+            if *grab != Some(button) {
+                return false;
+            }
+            let _ = worker.send(DragInput::Dragged(point));
+            true
+        }
+        EventKind::LeftMouseUp | EventKind::RightMouseUp => {
+            if *grab != Some(button) {
+                return false;
+            }
+            *grab = None;
+            let _ = worker.send(DragInput::Up(point));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Waits for the next input. Drags queued up meanwhile collapse into the latest one,
+/// the first input that isn't a drag is kept in `next` for the following call.
+/// `None` once the tap is gone and dropped the sender
+fn next_input(inputs: &Receiver<DragInput>, next: &mut Option<DragInput>) -> Option<DragInput> {
+    let mut input = match next.take() {
+        Some(input) => input,
+        None => inputs.recv().ok()?,
+    };
+    if let DragInput::Dragged(_) = input {
+        for queued in inputs.try_iter() {
+            match queued {
+                DragInput::Dragged(_) => input = queued,
+                _ => {
+                    *next = Some(queued);
+                    break;
+                }
+            }
+        }
+    }
+
+    Some(input)
+}
+
+/// Applies the grabs on a thread of its own, the AX setters can take a while and the tap
+/// has to answer quickly
+fn run_worker<F>(inputs: Receiver<DragInput>, on_event: F)
+where
+    F: Fn(DragPhase, &ModifierDragEvent),
+{
+    let mut session: Option<DragSession> = None;
+    let mut next = None;
+
+    while let Some(input) = next_input(&inputs, &mut next) {
+        match input {
+            DragInput::Down { point, resize } => {
+                let mode = |frame: &Frame| {
+                    if resize {
+                        DragMode::Resize {
+                            corner: nearest_corner(frame, &point),
+                        }
+                    } else {
+                        DragMode::Move
+                    }
+                };
+
+                session = match begin(point.clone(), mode) {
+                    Ok(started) => {
+                        on_event(DragPhase::Started, &started.event(point));
+                        Some(started)
+                    }
+                    Err(error) => {
+                        eprintln!("modifier drag: {}", error);
+                        None
+                    }
+                };
+            }
+            DragInput::Dragged(point) => {
+                let Some(current) = session.as_mut() else {
+                    continue;
+                };
+                let delta = Point {
+                    x: point.x - current.start_point.x,
+                    y: point.y - current.start_point.y,
+                };
+                let frame = dragged_frame(&current.start_frame, current.mode, &delta);
+                let now_ms = current.started.elapsed().as_millis() as u64;
+
+                let mut throttled = current.throttle.push(frame, true, now_ms);
+                let Some(frame) = throttled.send.pop() else {
+                    continue;
+                };
+                // A window closed mid-drag just stops following
+                if apply(current, &frame).is_ok() {
+                    current.frame = frame;
+                    on_event(DragPhase::Moved, &current.event(point));
+                }
+            }
+            DragInput::Up(point) => {
+                let Some(mut ended) = session.take() else {
+                    continue;
+                };
+                let delta = Point {
+                    x: point.x - ended.start_point.x,
+                    y: point.y - ended.start_point.y,
+                };
+                let frame = dragged_frame(&ended.start_frame, ended.mode, &delta);
+
+                if let Ok(frame) = finish(&ended, &frame) {
+                    ended.frame = frame;
+                }
+                on_event(DragPhase::Ended, &ended.event(point));
+            }
+        }
+    }
+}
+
+/// Replaces the event tap that grabs windows, a disabled config just removes it.
+/// The tap swallows the clicks it grabs with, the window under the cursor never gets them
+pub fn configure<F>(config: ModifierDragConfig, on_event: F) -> SnapResult<()>
+where
+    F: Fn(DragPhase, &ModifierDragEvent) + Send + Sync + 'static,
+{
+    if config.enabled && config.modifiers.is_empty() {
+        return Err(SnapError::invalid_argument(
+            "modifiers",
+            "at least one of shift, control, option or command is required",
+        ));
+    }
+
+    // Dropping the tap ends its worker along with the grab in progress
+    if let Some(previous) = TAP.lock().unwrap().take() {
+        previous.stop();
+    }
+    if !config.enabled {
+        return Ok(());
+    }
+
+    let options = MonitorOptions {
+        events: vec![
+            EventKind::LeftMouseDown,
+            EventKind::LeftMouseDragged,
+            EventKind::LeftMouseUp,
+            EventKind::RightMouseDown,
+            EventKind::RightMouseDragged,
+            EventKind::RightMouseUp,
+        ],
+        throttle_ms: None,
+        coalesce: false,
+    };
+
+    let (worker, inputs) = mpsc::channel();
+    thread::spawn(move || run_worker(inputs, on_event));
+
+    let grab = Mutex::new(None);
+    let tap = EventTap::start(options.mask(), move |event| {
+        route_event(event, &config, &mut grab.lock().unwrap(), &worker)
+    })
+    .ok_or_else(|| {
+        SnapError::platform("failed to install the event tap, accessibility access is required")
+    })?;
+    *TAP.lock().unwrap() = Some(tap);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(x: f64, y: f64, width: f64, height: f64) -> Frame {
+        Frame {
+            position: Point { x, y },
+            size: Size { width, height },
+        }
+    }
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    fn enabled() -> ModifierDragConfig {
+        ModifierDragConfig {
+            enabled: true,
+            ..ModifierDragConfig::default()
+        }
+    }
+
+    fn held() -> Modifiers {
+        Modifiers {
+            control: true,
+            command: true,
+            ..Modifiers::default()
+        }
+    }
+
+    /// Routes the events in order, returns whether each was swallowed and what the worker got
+    fn route_all(
+        config: &ModifierDragConfig,
+        events: &[(EventKind, Modifiers)],
+    ) -> (Vec<bool>, Vec<DragInput>, Option<Grab>) {
+        let (worker, inputs) = mpsc::channel();
+        let mut grab = None;
+        let swallowed = events
+            .iter()
+            .enumerate()
+            .map(|(index, (kind, modifiers))| {
+                let point = point(index as f64, 0.0);
+                route(*kind, point, modifiers, config, &mut grab, &worker)
+            })
+            .collect();
+
+        (swallowed, inputs.try_iter().collect(), grab)
+    }
+
+    #[test]
+    fn nearest_corner_picks_the_quadrant_of_the_point() {
+        let window = frame(100.0, 100.0, 400.0, 200.0);
+
+        let corners = [
+            (point(110.0, 110.0), Alignment::new(-1.0, -1.0)),
+            (point(490.0, 110.0), Alignment::new(1.0, -1.0)),
+            (point(110.0, 290.0), Alignment::new(-1.0, 1.0)),
+            (point(490.0, 290.0), Alignment::new(1.0, 1.0)),
+            // The center belongs to the end
+            (point(300.0, 200.0), Alignment::new(1.0, 1.0)),
+        ];
+        for (point, corner) in corners {
+            assert_eq!(nearest_corner(&window, &point), corner, "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn moving_keeps_the_size() {
+        let start = frame(100.0, 100.0, 400.0, 200.0);
+
+        assert_eq!(
+            dragged_frame(&start, DragMode::Move, &point(-50.0, 30.0)),
+            frame(50.0, 130.0, 400.0, 200.0)
+        );
+    }
+
+    #[test]
+    fn resizing_moves_the_grabbed_corner_only() {
+        let start = frame(100.0, 100.0, 400.0, 200.0);
+        let resize = |x: f64, y: f64, delta: Point| {
+            let corner = Alignment::new(x, y);
+            dragged_frame(&start, DragMode::Resize { corner }, &delta)
+        };
+
+        assert_eq!(
+            resize(1.0, 1.0, point(50.0, -20.0)),
+            frame(100.0, 100.0, 450.0, 180.0)
+        );
+        assert_eq!(
+            resize(-1.0, -1.0, point(50.0, -20.0)),
+            frame(150.0, 80.0, 350.0, 220.0)
+        );
+    }
+
+    #[test]
+    fn resizing_stops_at_the_minimum_size_with_the_opposite_edge_pinned() {
+        let start = frame(100.0, 100.0, 400.0, 200.0);
+        let resize = |x: f64, y: f64, delta: Point| {
+            let corner = Alignment::new(x, y);
+            dragged_frame(&start, DragMode::Resize { corner }, &delta)
+        };
+
+        // The end corner pulled past the start, the start edges stay
+        assert_eq!(
+            resize(1.0, 1.0, point(-1000.0, -1000.0)),
+            frame(100.0, 100.0, MIN_SIZE.width, MIN_SIZE.height)
+        );
+        // The start corner pushed past the end, the end edges stay at 500 x 300
+        assert_eq!(
+            resize(-1.0, -1.0, point(1000.0, 1000.0)),
+            frame(
+                500.0 - MIN_SIZE.width,
+                300.0 - MIN_SIZE.height,
+                MIN_SIZE.width,
+                MIN_SIZE.height
+            )
+        );
+    }
+
+    #[test]
+    fn a_grab_swallows_its_drags_and_release() {
+        let (swallowed, inputs, grab) = route_all(
+            &enabled(),
+            &[
+                (EventKind::LeftMouseDown, held()),
+                (EventKind::LeftMouseDragged, Modifiers::default()),
+                (EventKind::LeftMouseUp, Modifiers::default()),
+                (EventKind::LeftMouseDragged, held()),
+            ],
+        );
+
+        assert_eq!(swallowed, vec![true, true, true, false]);
+        assert_eq!(
+            inputs,
+            vec![
+                DragInput::Down {
+                    point: point(0.0, 0.0),
+                    resize: false
+                },
+                DragInput::Dragged(point(1.0, 0.0)),
+                DragInput::Up(point(2.0, 0.0)),
+            ]
+        );
+        assert_eq!(grab, None);
+    }
+
+    #[test]
+    fn other_modifiers_pass_through() {
+        let mut option = held();
+        option.option = true;
+        let caps_lock = Modifiers {
+            caps_lock: true,
+            ..held()
+        };
+
+        let (swallowed, inputs, grab) = route_all(
+            &enabled(),
+            &[
+                (EventKind::LeftMouseDown, Modifiers::default()),
+                (
+                    EventKind::LeftMouseDown,
+                    Modifiers {
+                        command: true,
+                        ..Modifiers::default()
+                    },
+                ),
+                (EventKind::LeftMouseDown, option),
+            ],
+        );
+        assert_eq!(swallowed, vec![false, false, false]);
+        assert!(inputs.is_empty());
+        assert_eq!(grab, None);
+
+        // Caps lock doesn't count
+        let (swallowed, _, grab) = route_all(&enabled(), &[(EventKind::LeftMouseDown, caps_lock)]);
+        assert_eq!(swallowed, vec![true]);
+        assert_eq!(grab, Some(Grab::Left));
+    }
+
+    #[test]
+    fn right_drags_resize_unless_disabled() {
+        let (swallowed, inputs, grab) =
+            route_all(&enabled(), &[(EventKind::RightMouseDown, held())]);
+        assert_eq!(swallowed, vec![true]);
+        assert_eq!(
+            inputs,
+            vec![DragInput::Down {
+                point: point(0.0, 0.0),
+                resize: true
+            }]
+        );
+        assert_eq!(grab, Some(Grab::Right));
+
+        let config = ModifierDragConfig {
+            resize: false,
+            ..enabled()
+        };
+        let (swallowed, inputs, grab) = route_all(
+            &config,
+            &[
+                (EventKind::RightMouseDown, held()),
+                (EventKind::RightMouseDragged, held()),
+                (EventKind::RightMouseUp, held()),
+            ],
+        );
+        assert_eq!(swallowed, vec![false, false, false]);
+        assert!(inputs.is_empty());
+        assert_eq!(grab, None);
+    }
+
+    #[test]
+    fn the_other_button_does_not_end_a_grab() {
+        let (swallowed, inputs, grab) = route_all(
+            &enabled(),
+            &[
+                (EventKind::LeftMouseDown, held()),
+                (EventKind::RightMouseDown, held()),
+                (EventKind::RightMouseDragged, held()),
+                (EventKind::RightMouseUp, held()),
+                (EventKind::LeftMouseDragged, held()),
+            ],
+        );
+
+        assert_eq!(swallowed, vec![true, false, false, false, true]);
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(grab, Some(Grab::Left));
+    }
+
+    #[test]
+    fn queued_drags_collapse_into_the_latest() {
+        let (worker, inputs) = mpsc::channel();
+        let mut next = None;
+
+        for input in [
+            DragInput::Down {
+                point: point(0.0, 0.0),
+                resize: false,
+            },
+            DragInput::Dragged(point(1.0, 0.0)),
+            DragInput::Dragged(point(2.0, 0.0)),
+            DragInput::Dragged(point(3.0, 0.0)),
+            DragInput::Up(point(4.0, 0.0)),
+            DragInput::Dragged(point(5.0, 0.0)),
+        ] {
+            worker.send(input).unwrap();
+        }
+        drop(worker);
+
+        let mut received = vec![];
+        while let Some(input) = next_input(&inputs, &mut next) {
+            received.push(input);
+        }
+
+        assert_eq!(
+            received,
+            vec![
+                DragInput::Down {
+                    point: point(0.0, 0.0),
+                    resize: false
+                },
+                DragInput::Dragged(point(3.0, 0.0)),
+                DragInput::Up(point(4.0, 0.0)),
+                DragInput::Dragged(point(5.0, 0.0)),
+            ]
+        );
+    }
+}
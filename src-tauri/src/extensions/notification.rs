@@ -1,30 +1,71 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
 use block::ConcreteBlock;
 use cocoa::{
     base::{id, nil},
     foundation::NSString,
 };
-use objc::{class, msg_send, runtime::Class, sel, sel_impl};
+use objc::{class, msg_send, sel, sel_impl};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+/// Where a notification is posted
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationCenter {
+    /// `NSNotificationCenter.defaultCenter`, notifications of this app
+    Default,
+    /// `NSDistributedNotificationCenter.defaultCenter`, system wide ones
+    Distributed,
+    /// `NSWorkspace.sharedWorkspace.notificationCenter`
+    Workspace,
+}
+
+impl NotificationCenter {
+    fn center(&self) -> id {
+        unsafe {
+            match self {
+                NotificationCenter::Default => {
+                    msg_send![class!(NSNotificationCenter), defaultCenter]
+                }
+                NotificationCenter::Distributed => {
+                    msg_send![class!(NSDistributedNotificationCenter), defaultCenter]
+                }
+                NotificationCenter::Workspace => {
+                    let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+                    msg_send![workspace, notificationCenter]
+                }
+            }
+        }
+    }
+}
 
+/// One observer, removed from the center it was added to when stopped or dropped
 pub struct NSNotificationCenter {
-    observer: Option<id>, // NotificationCenter.default.removeObserver(self, name: NSApplication.didChangeScreenParametersNotification, object: nil)
-    center_name: String,
+    observer: Option<id>,
+    center: NotificationCenter,
 }
 
+// The observer token is only handed back to its center, which is thread-safe
+unsafe impl Send for NSNotificationCenter {}
+
 impl NSNotificationCenter {
-    pub fn new(name: String) -> Self {
+    pub fn new(center: NotificationCenter) -> Self {
         NSNotificationCenter {
             observer: None,
-            center_name: name,
+            center,
         }
     }
 
-    pub fn listen<F>(mut self, name: String, callback: F) -> Self
+    pub fn listen<F>(mut self, name: &str, callback: F) -> Self
     where
         F: Fn(id) -> () + Send + Sync + 'static,
     {
-        let notification_name = unsafe { NSString::alloc(nil).init_str(name.as_str()) };
-        let klass = Class::get(self.center_name.as_str()).unwrap();
-        let ns_center: id = unsafe { msg_send![klass, defaultCenter] };
+        let notification_name = unsafe { NSString::alloc(nil).init_str(name) };
+        let ns_center = self.center.center();
         let block = move |event: id| callback(event);
         let block = ConcreteBlock::new(block);
         let block = block.copy();
@@ -41,10 +82,176 @@ impl NSNotificationCenter {
         self
     }
 
-    pub fn stop(&self) {
-        if let Some(observer) = self.observer {
-            let ns_center: id = unsafe { msg_send![class!(NSNotificationCenter), defaultCenter] };
+    pub fn stop(&mut self) {
+        if let Some(observer) = self.observer.take() {
+            let ns_center = self.center.center();
             let _: id = unsafe { msg_send![ns_center, removeObserver:observer] };
         }
     }
 }
+
+impl Drop for NSNotificationCenter {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+struct Subscription<T> {
+    /// Label of the webview that subscribed, `None` for the app itself
+    owner: Option<String>,
+    observers: Vec<T>,
+}
+
+/// Subscriptions by id, `T` is whatever removes the observer when dropped
+struct ObserverRegistry<T> {
+    next_id: u64,
+    subscriptions: HashMap<u64, Subscription<T>>,
+}
+
+impl<T> Default for ObserverRegistry<T> {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            subscriptions: HashMap::new(),
+        }
+    }
+}
+
+impl<T> ObserverRegistry<T> {
+    fn insert(&mut self, owner: Option<&str>, observers: Vec<T>) -> u64 {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.subscriptions.insert(
+            id,
+            Subscription {
+                owner: owner.map(String::from),
+                observers,
+            },
+        );
+
+        id
+    }
+
+    fn remove(&mut self, id: u64) -> Option<Vec<T>> {
+        self.subscriptions
+            .remove(&id)
+            .map(|subscription| subscription.observers)
+    }
+
+    fn remove_owner(&mut self, owner: &str) -> Vec<Vec<T>> {
+        let ids: Vec<u64> = self
+            .subscriptions
+            .iter()
+            .filter(|(_, subscription)| subscription.owner.as_deref() == Some(owner))
+            .map(|(id, _)| *id)
+            .collect();
+
+        ids.into_iter().filter_map(|id| self.remove(id)).collect()
+    }
+}
+
+static OBSERVERS: Lazy<Mutex<ObserverRegistry<NSNotificationCenter>>> =
+    Lazy::new(|| Mutex::new(ObserverRegistry::default()));
+
+/// Observes every `(center, name)` pair with the same callback, all of them are
+/// removed together by `unsubscribe`
+pub fn subscribe<F>(
+    owner: Option<&str>,
+    notifications: &[(NotificationCenter, &str)],
+    callback: F,
+) -> u64
+where
+    F: Fn(id) + Send + Sync + 'static,
+{
+    let callback = Arc::new(callback);
+    let observers = notifications
+        .iter()
+        .map(|(center, name)| {
+            let callback = callback.clone();
+            NSNotificationCenter::new(*center).listen(name, move |event| callback(event))
+        })
+        .collect();
+
+    OBSERVERS.lock().unwrap().insert(owner, observers)
+}
+
+/// `false` when there was no such subscription
+pub fn unsubscribe(id: u64) -> bool {
+    let observers = OBSERVERS.lock().unwrap().remove(id);
+
+    // Observers are removed on drop, outside the lock
+    observers.is_some()
+}
+
+/// Drops what a closed webview left behind, returns how many subscriptions it had
+pub fn unsubscribe_owner(owner: &str) -> usize {
+    let removed = OBSERVERS.lock().unwrap().remove_owner(owner);
+
+    removed.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts how many observers were removed
+    struct Observer(Arc<Mutex<usize>>);
+
+    impl Drop for Observer {
+        fn drop(&mut self) {
+            *self.0.lock().unwrap() += 1;
+        }
+    }
+
+    fn observers(count: usize, dropped: &Arc<Mutex<usize>>) -> Vec<Observer> {
+        (0..count).map(|_| Observer(dropped.clone())).collect()
+    }
+
+    #[test]
+    fn ids_are_never_reused() {
+        let mut registry = ObserverRegistry::<()>::default();
+
+        let first = registry.insert(None, vec![]);
+        let second = registry.insert(Some("main"), vec![]);
+        assert_ne!(first, 0);
+        assert_ne!(first, second);
+
+        registry.remove(second);
+        let third = registry.insert(None, vec![]);
+        assert!(third != first && third != second);
+    }
+
+    #[test]
+    fn unsubscribe_drops_every_observer_once() {
+        let dropped = Arc::new(Mutex::new(0));
+        let mut registry = ObserverRegistry::default();
+        let id = registry.insert(Some("main"), observers(3, &dropped));
+        let kept = registry.insert(Some("main"), observers(1, &dropped));
+
+        drop(registry.remove(id));
+        assert_eq!(*dropped.lock().unwrap(), 3);
+        assert!(registry.remove(id).is_none());
+        assert!(registry.subscriptions.contains_key(&kept));
+        assert_eq!(*dropped.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn unsubscribe_owner_leaves_other_subscriptions_alone() {
+        let dropped = Arc::new(Mutex::new(0));
+        let mut registry = ObserverRegistry::default();
+        registry.insert(Some("statusbar"), observers(2, &dropped));
+        registry.insert(Some("statusbar"), observers(1, &dropped));
+        let other = registry.insert(Some("main"), observers(1, &dropped));
+        let app = registry.insert(None, observers(1, &dropped));
+
+        let removed = registry.remove_owner("statusbar");
+        assert_eq!(removed.len(), 2);
+        drop(removed);
+        assert_eq!(*dropped.lock().unwrap(), 3);
+
+        assert_eq!(registry.subscriptions.len(), 2);
+        assert!(registry.subscriptions.contains_key(&other));
+        assert!(registry.subscriptions.contains_key(&app));
+        assert!(registry.remove_owner("statusbar").is_empty());
+    }
+}
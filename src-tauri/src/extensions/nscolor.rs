@@ -1,24 +1,40 @@
-use cocoa::{appkit::CGFloat, base::id};
+use cocoa::{
+    appkit::CGFloat,
+    base::{id, nil, BOOL, NO},
+    foundation::NSString,
+};
 use objc::{class, msg_send, sel, sel_impl};
 
+use crate::data::color::{Appearance, AppearanceMode, Color, Palette};
+
+const APPEARANCE_LIGHT: &str = "NSAppearanceNameAqua";
+const APPEARANCE_DARK: &str = "NSAppearanceNameDarkAqua";
+const APPEARANCE_LIGHT_CONTRAST: &str = "NSAppearanceNameAccessibilityHighContrastAqua";
+const APPEARANCE_DARK_CONTRAST: &str = "NSAppearanceNameAccessibilityHighContrastDarkAqua";
+
 pub trait NSAColor: Sized {
     unsafe fn accent_color(_: Self) -> Self;
-    unsafe fn to_rgb(color: Self) -> String;
+    /// `None` for pattern colors and others without sRGB components
+    unsafe fn to_color(color: Self) -> Option<Color>;
 }
 
 impl NSAColor for id {
-    unsafe fn to_rgb(color: Self) -> String {
-        let cg_color: id = msg_send![color, CGColor];
-        let color_id: cocoa::base::id =
-            unsafe { msg_send![class!(CIColor), colorWithCGColor: cg_color] };
-        let r: CGFloat = msg_send![color_id, red];
-        let g: CGFloat = msg_send![color_id, green];
-        let b: CGFloat = msg_send![color_id, blue];
-        let a: CGFloat = msg_send![color_id, alpha];
-
-        let formatted = format!("rgba({}, {}, {}, {})", r * 255.0, g * 255.0, b * 255.0, a);
-
-        return formatted;
+    unsafe fn to_color(color: Self) -> Option<Color> {
+        if color == nil {
+            return None;
+        }
+        let srgb: id = msg_send![class!(NSColorSpace), sRGBColorSpace];
+        let converted: id = msg_send![color, colorUsingColorSpace: srgb];
+        if converted == nil {
+            return None;
+        }
+
+        let r: CGFloat = msg_send![converted, redComponent];
+        let g: CGFloat = msg_send![converted, greenComponent];
+        let b: CGFloat = msg_send![converted, blueComponent];
+        let a: CGFloat = msg_send![converted, alphaComponent];
+
+        Some(Color::from_srgb(r, g, b, a))
     }
 
     unsafe fn accent_color(_: Self) -> Self {
@@ -28,3 +44,93 @@ impl NSAColor for id {
         }
     }
 }
+
+/// `AppleInterfaceStyle` is only set while dark, it changes before the app's own
+/// appearance does when the theme switches
+pub fn appearance_mode() -> AppearanceMode {
+    unsafe {
+        let defaults: id = msg_send![class!(NSUserDefaults), standardUserDefaults];
+        let key = NSString::alloc(nil).init_str("AppleInterfaceStyle");
+        let style: id = msg_send![defaults, stringForKey: key];
+        let _: () = msg_send![key, release];
+
+        let dark = style != nil
+            && std::ffi::CStr::from_ptr(style.UTF8String())
+                .to_string_lossy()
+                .eq_ignore_ascii_case("dark");
+        if dark {
+            AppearanceMode::Dark
+        } else {
+            AppearanceMode::Light
+        }
+    }
+}
+
+pub fn increase_contrast() -> bool {
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let increase: BOOL = msg_send![workspace, accessibilityDisplayShouldIncreaseContrast];
+        increase != NO
+    }
+}
+
+fn named_appearance(name: &str) -> id {
+    unsafe {
+        let name = NSString::alloc(nil).init_str(name);
+        let appearance: id = msg_send![class!(NSAppearance), appearanceNamed: name];
+        let _: () = msg_send![name, release];
+        appearance
+    }
+}
+
+/// Semantic colors are dynamic, they are resolved against the given appearance
+/// rather than whatever is current on the calling thread
+pub fn palette(mode: AppearanceMode, increase_contrast: bool) -> Option<Palette> {
+    let names = match mode {
+        AppearanceMode::Light => [APPEARANCE_LIGHT_CONTRAST, APPEARANCE_LIGHT],
+        AppearanceMode::Dark => [APPEARANCE_DARK_CONTRAST, APPEARANCE_DARK],
+    };
+    let names = if increase_contrast {
+        &names[..]
+    } else {
+        &names[1..]
+    };
+
+    unsafe {
+        let appearance = names
+            .iter()
+            .map(|name| named_appearance(name))
+            .find(|appearance| *appearance != nil)
+            .unwrap_or(nil);
+        let previous: id = msg_send![class!(NSAppearance), currentAppearance];
+        let _: () = msg_send![class!(NSAppearance), setCurrentAppearance: appearance];
+
+        let class = class!(NSColor);
+        let color = |color: id| NSAColor::to_color(color);
+        // Read in one go so the previous appearance is always put back
+        let palette = (|| {
+            Some(Palette {
+                accent: color(NSAColor::accent_color(nil))?,
+                highlight: color(msg_send![class, selectedTextBackgroundColor])?,
+                label: color(msg_send![class, labelColor])?,
+                separator: color(msg_send![class, separatorColor])?,
+                window_background: color(msg_send![class, windowBackgroundColor])?,
+                selected_content: color(msg_send![class, selectedContentBackgroundColor])?,
+            })
+        })();
+
+        let _: () = msg_send![class!(NSAppearance), setCurrentAppearance: previous];
+        palette
+    }
+}
+
+pub fn appearance() -> Option<Appearance> {
+    let mode = appearance_mode();
+    let increase_contrast = increase_contrast();
+
+    Some(Appearance {
+        mode,
+        increase_contrast,
+        palette: palette(mode, increase_contrast)?,
+    })
+}
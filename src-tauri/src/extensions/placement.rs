@@ -0,0 +1,263 @@
+use accessibility_sys::AXUIElementRef;
+
+use crate::data::{
+    alignment::Alignment,
+    error::SnapResult,
+    frame::{Frame, Point, Size},
+};
+
+use super::{
+    accessibility_elements::{get_frame, set_frame},
+    screens::{containing, visible_frames},
+};
+
+/// Frames within this distance of each other are considered equal
+pub const FRAME_TOLERANCE: f64 = 1.0;
+/// Free space up to this size on both sides of an axis still counts as spanning the screen
+const SPAN_MARGIN: f64 = 40.0;
+/// How often a window is re-aligned after the app adjusted its size
+const MAX_ATTEMPTS: usize = 3;
+
+/// Result of placing a window
+#[derive(Clone, Debug)]
+pub struct Placement {
+    /// Frame read back from the window
+    pub frame: Frame,
+    /// The app didn't accept the requested frame (minimum size, character grid)
+    pub clamped: bool,
+}
+
+/// Guesses which part of `target` a window should stay pinned to from where the frame
+/// sits on its screen: left half pins left, a corner pins that corner, full height pins top.
+pub fn infer_anchor(target: &Frame, screens: &[Frame]) -> Alignment {
+    let Some(screen) = containing(target, screens).map(|index| &screens[index]) else {
+        // Same corner the accessibility API keeps when it resizes
+        return Alignment::new(-1.0, -1.0);
+    };
+
+    let axis = |start: f64, length: f64, screen_start: f64, screen_length: f64, span: f64| {
+        let before = start - screen_start;
+        let after = screen_start + screen_length - (start + length);
+
+        if (before - after).abs() <= FRAME_TOLERANCE * 2.0 {
+            if before <= SPAN_MARGIN {
+                span
+            } else {
+                0.0
+            }
+        } else if before < after {
+            -1.0
+        } else {
+            1.0
+        }
+    };
+
+    Alignment::new(
+        axis(
+            target.position.x,
+            target.size.width,
+            screen.position.x,
+            screen.size.width,
+            0.0,
+        ),
+        // A full height window keeps its title bar in place
+        axis(
+            target.position.y,
+            target.size.height,
+            screen.position.y,
+            screen.size.height,
+            -1.0,
+        ),
+    )
+}
+
+/// `anchor` when the caller picked one, otherwise inferred from the screens
+pub fn resolve_anchor(target: &Frame, anchor: Option<Alignment>) -> Alignment {
+    anchor.unwrap_or_else(|| infer_anchor(target, &visible_frames()))
+}
+
+/// Positions a window of `size` inside (or around, when larger) `target` according to `anchor`
+pub fn realign(target: &Frame, size: &Size, anchor: Alignment) -> Frame {
+    let offset = |target_length: f64, length: f64, anchor: f64| {
+        (target_length - length) * (anchor.clamp(-1.0, 1.0) + 1.0) / 2.0
+    };
+
+    Frame {
+        size: size.clone(),
+        position: Point {
+            x: target.position.x + offset(target.size.width, size.width, anchor.x),
+            y: target.position.y + offset(target.size.height, size.height, anchor.y),
+        },
+    }
+}
+
+/// Reads the frame back and moves the window until it sits at `anchor` of `target`
+/// with the size the app settled on. Gives up after a few attempts for apps that keep
+/// adjusting the frame.
+pub fn realign_window(
+    window: AXUIElementRef,
+    target: &Frame,
+    anchor: Alignment,
+) -> SnapResult<Placement> {
+    let mut actual = get_frame(window)?;
+
+    for _ in 0..MAX_ATTEMPTS {
+        let desired = realign(target, &actual.size, anchor);
+        if actual.approx_eq(&desired, FRAME_TOLERANCE) {
+            break;
+        }
+
+        set_frame(window, &desired)?;
+        actual = get_frame(window)?;
+    }
+
+    Ok(Placement {
+        clamped: !actual.approx_eq(target, FRAME_TOLERANCE),
+        frame: actual,
+    })
+}
+
+/// Applies `target` immediately and re-aligns the window if the app adjusted its size
+pub fn place(window: AXUIElementRef, target: &Frame, anchor: Alignment) -> SnapResult<Placement> {
+    set_frame(window, target)?;
+
+    realign_window(window, target, anchor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(x: f64, y: f64, width: f64, height: f64) -> Frame {
+        Frame {
+            position: Point { x, y },
+            size: Size { width, height },
+        }
+    }
+
+    /// Visible frame of a 1440 x 900 screen below the menu bar
+    fn screens() -> Vec<Frame> {
+        vec![frame(0.0, 25.0, 1440.0, 875.0)]
+    }
+
+    #[test]
+    fn halves_pin_their_side_and_the_top() {
+        assert_eq!(
+            infer_anchor(&frame(0.0, 25.0, 720.0, 875.0), &screens()),
+            Alignment::new(-1.0, -1.0)
+        );
+        assert_eq!(
+            infer_anchor(&frame(720.0, 25.0, 720.0, 875.0), &screens()),
+            Alignment::new(1.0, -1.0)
+        );
+        assert_eq!(
+            infer_anchor(&frame(0.0, 462.5, 1440.0, 437.5), &screens()),
+            Alignment::new(0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn corners_pin_that_corner() {
+        let corners = [
+            (frame(0.0, 25.0, 720.0, 437.5), Alignment::new(-1.0, -1.0)),
+            (frame(720.0, 25.0, 720.0, 437.5), Alignment::new(1.0, -1.0)),
+            (frame(0.0, 462.5, 720.0, 437.5), Alignment::new(-1.0, 1.0)),
+            (frame(720.0, 462.5, 720.0, 437.5), Alignment::new(1.0, 1.0)),
+        ];
+
+        for (target, anchor) in corners {
+            assert_eq!(infer_anchor(&target, &screens()), anchor, "{:?}", target);
+        }
+    }
+
+    #[test]
+    fn centered_frames_pin_the_center_unless_they_span_the_screen() {
+        // Centered with room around it
+        assert_eq!(
+            infer_anchor(&frame(320.0, 225.0, 800.0, 475.0), &screens()),
+            Alignment::new(0.0, 0.0)
+        );
+        // Filling the screen with a gap keeps the title bar in place
+        assert_eq!(
+            infer_anchor(&frame(10.0, 35.0, 1420.0, 855.0), &screens()),
+            Alignment::new(0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn frames_off_every_screen_pin_the_top_left() {
+        assert_eq!(
+            infer_anchor(&frame(5000.0, 5000.0, 400.0, 300.0), &screens()),
+            Alignment::new(-1.0, -1.0)
+        );
+        assert_eq!(
+            infer_anchor(&frame(0.0, 25.0, 720.0, 875.0), &[]),
+            Alignment::new(-1.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn realign_keeps_the_anchor_of_a_smaller_window() {
+        let target = frame(100.0, 100.0, 400.0, 300.0);
+        let size = Size {
+            width: 300.0,
+            height: 200.0,
+        };
+
+        let anchors = [
+            (Alignment::new(-1.0, -1.0), Point { x: 100.0, y: 100.0 }),
+            (Alignment::new(1.0, -1.0), Point { x: 200.0, y: 100.0 }),
+            (Alignment::new(-1.0, 1.0), Point { x: 100.0, y: 200.0 }),
+            (Alignment::new(1.0, 1.0), Point { x: 200.0, y: 200.0 }),
+            (Alignment::new(0.0, 0.0), Point { x: 150.0, y: 150.0 }),
+            (Alignment::new(0.0, -1.0), Point { x: 150.0, y: 100.0 }),
+        ];
+        for (anchor, position) in anchors {
+            assert_eq!(
+                realign(&target, &size, anchor),
+                Frame {
+                    size: size.clone(),
+                    position
+                },
+                "{:?}",
+                anchor
+            );
+        }
+    }
+
+    #[test]
+    fn realign_lets_a_larger_window_grow_away_from_the_anchor() {
+        let target = frame(100.0, 100.0, 400.0, 300.0);
+        let size = Size {
+            width: 500.0,
+            height: 400.0,
+        };
+
+        assert_eq!(
+            realign(&target, &size, Alignment::new(-1.0, -1.0)).position,
+            Point { x: 100.0, y: 100.0 }
+        );
+        assert_eq!(
+            realign(&target, &size, Alignment::new(1.0, 1.0)).position,
+            Point { x: 0.0, y: 0.0 }
+        );
+        assert_eq!(
+            realign(&target, &size, Alignment::new(0.0, 0.0)).position,
+            Point { x: 50.0, y: 50.0 }
+        );
+    }
+
+    #[test]
+    fn realign_clamps_the_anchor() {
+        let target = frame(0.0, 0.0, 400.0, 300.0);
+        let size = Size {
+            width: 200.0,
+            height: 100.0,
+        };
+
+        assert_eq!(
+            realign(&target, &size, Alignment::new(5.0, -5.0)),
+            realign(&target, &size, Alignment::new(1.0, -1.0))
+        );
+    }
+}
@@ -0,0 +1,521 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+
+use crate::data::{
+    error::{SnapError, SnapResult},
+    frame::Frame,
+};
+
+use super::{scripting::WindowFrame, windows::WindowInfo};
+
+/// Version of the plugin interface, bumped on any incompatible change.
+///
+/// A plugin is a `.wasm` module without imports that exports:
+/// - `memory`
+/// - `snap_abi_version() -> i32`, returning this version
+/// - `snap_alloc(len: i32) -> i32`, returning a buffer of `len` bytes for the input
+/// - `snap_layout(ptr: i32, len: i32) -> i64`, reading the input JSON from the buffer and
+///   returning the output JSON as `ptr << 32 | len`
+///
+/// The input is a `LayoutInput`, the output a list of frames or `{ "error": "..." }`
+pub const ABI_VERSION: u32 = 1;
+
+/// Instructions a plugin may run per call, loops included
+const FUEL: u64 = 500_000_000;
+/// Linear memory a plugin may grow to
+const MAX_MEMORY: usize = 64 << 20;
+/// Output larger than this is refused instead of copied
+const MAX_OUTPUT: usize = 1 << 20;
+const EXTENSION: &str = "wasm";
+
+const EXPORT_MEMORY: &str = "memory";
+const EXPORT_ABI_VERSION: &str = "snap_abi_version";
+const EXPORT_ALLOC: &str = "snap_alloc";
+const EXPORT_LAYOUT: &str = "snap_layout";
+
+/// What a plugin gets to arrange, frames are in the same coordinates as the windows
+#[derive(Serialize, Clone, Debug)]
+pub struct LayoutInput {
+    pub abi_version: u32,
+    pub visible_frame: Frame,
+    pub gap: f64,
+    pub windows: Vec<PluginWindow>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PluginWindow {
+    pub id: i64,
+    pub pid: i64,
+    pub name: Option<String>,
+    pub title: Option<String>,
+    pub bundle_id: Option<String>,
+    pub frame: Frame,
+}
+
+impl PluginWindow {
+    /// `None` for windows without an id, a pid or a frame
+    pub fn from_info(window: &WindowInfo) -> Option<Self> {
+        Some(PluginWindow {
+            id: window.id?,
+            pid: window.pid?,
+            name: window.name.clone(),
+            title: window.title.clone(),
+            bundle_id: window.bundle_id.clone(),
+            frame: window.frame.clone()?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LayoutOutput {
+    Frames(Vec<Frame>),
+    Error { error: String },
+}
+
+/// A plugin found in the plugins directory, `error` tells why it can't be used
+#[derive(Serialize, Clone, Debug)]
+pub struct PluginInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub abi_version: Option<u32>,
+    pub error: Option<SnapError>,
+}
+
+struct LoadedPlugin {
+    info: PluginInfo,
+    /// Compiled once, every call gets a fresh instance
+    module: Option<Module>,
+}
+
+struct PluginState {
+    limits: StoreLimits,
+}
+
+static ENGINE: Lazy<Engine> = Lazy::new(|| {
+    let mut config = Config::new();
+    config.consume_fuel(true);
+    Engine::new(&config).expect("the wasm engine failed to start")
+});
+
+static PLUGINS: Lazy<Mutex<Vec<LoadedPlugin>>> = Lazy::new(|| Mutex::new(vec![]));
+
+fn plugin_error(plugin: &str, message: impl Into<String>) -> SnapError {
+    SnapError::Plugin {
+        plugin: String::from(plugin),
+        message: message.into(),
+    }
+}
+
+/// Running out of fuel reads as a trap, it's reported as the limit it is
+fn wasm_error(plugin: &str, error: wasmtime::Error) -> SnapError {
+    match error.downcast_ref::<Trap>() {
+        Some(Trap::OutOfFuel) => {
+            plugin_error(plugin, format!("stopped after {} instructions", FUEL))
+        }
+        _ => plugin_error(plugin, format!("{:#}", error)),
+    }
+}
+
+/// Fresh instance with its own fuel and memory limit, plugins get no imports
+fn instantiate(module: &Module) -> wasmtime::Result<(Store<PluginState>, Instance)> {
+    let limits = StoreLimitsBuilder::new()
+        .memory_size(MAX_MEMORY)
+        .instances(1)
+        .build();
+    let mut store = Store::new(&ENGINE, PluginState { limits });
+    store.limiter(|state| &mut state.limits);
+    store.set_fuel(FUEL)?;
+
+    let instance = Instance::new(&mut store, module, &[])?;
+    Ok((store, instance))
+}
+
+fn abi_version(module: &Module) -> wasmtime::Result<u32> {
+    let (mut store, instance) = instantiate(module)?;
+    let version = instance
+        .get_typed_func::<(), i32>(&mut store, EXPORT_ABI_VERSION)?
+        .call(&mut store, ())?;
+
+    Ok(version as u32)
+}
+
+fn call_layout(module: &Module, input: &[u8]) -> wasmtime::Result<Vec<u8>> {
+    let (mut store, instance) = instantiate(module)?;
+    let memory = instance
+        .get_memory(&mut store, EXPORT_MEMORY)
+        .ok_or_else(|| wasmtime::Error::msg("the plugin exports no memory"))?;
+    let alloc = instance.get_typed_func::<i32, i32>(&mut store, EXPORT_ALLOC)?;
+    let layout = instance.get_typed_func::<(i32, i32), i64>(&mut store, EXPORT_LAYOUT)?;
+
+    let len = i32::try_from(input.len())?;
+    let ptr = alloc.call(&mut store, len)?;
+    memory.write(&mut store, ptr as u32 as usize, input)?;
+
+    let packed = layout.call(&mut store, (ptr, len))? as u64;
+    let (ptr, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+    if len > MAX_OUTPUT {
+        return Err(wasmtime::Error::msg(format!(
+            "the output is {} bytes, at most {} are read",
+            len, MAX_OUTPUT
+        )));
+    }
+
+    let mut output = vec![0; len];
+    memory.read(&store, ptr, &mut output)?;
+    Ok(output)
+}
+
+/// Checks the frames a plugin returned, at most one per window and each with a size
+fn check_frames(plugin: &str, frames: Vec<Frame>, windows: usize) -> SnapResult<Vec<Frame>> {
+    if frames.len() > windows {
+        return Err(plugin_error(
+            plugin,
+            format!("returned {} frames for {} windows", frames.len(), windows),
+        ));
+    }
+
+    if let Some(index) = frames.iter().position(|frame| !frame.is_placeable()) {
+        return Err(plugin_error(
+            plugin,
+            format!("frame {} has no size or is not finite", index),
+        ));
+    }
+
+    Ok(frames)
+}
+
+/// Runs `snap_layout` of a compiled plugin. The plugin answers with one frame per window
+/// in the order of the input, they are paired with the window ids here
+fn layout_module(name: &str, module: &Module, input: &LayoutInput) -> SnapResult<Vec<WindowFrame>> {
+    let input_json =
+        serde_json::to_vec(input).map_err(|error| plugin_error(name, error.to_string()))?;
+    let output = call_layout(module, &input_json).map_err(|error| wasm_error(name, error))?;
+
+    let frames = match serde_json::from_slice(&output) {
+        Ok(LayoutOutput::Frames(frames)) => check_frames(name, frames, input.windows.len())?,
+        Ok(LayoutOutput::Error { error }) => return Err(plugin_error(name, error)),
+        Err(error) => {
+            return Err(plugin_error(
+                name,
+                format!("the output is not a list of frames: {}", error),
+            ))
+        }
+    };
+
+    Ok(input
+        .windows
+        .iter()
+        .zip(frames)
+        .map(|(window, frame)| WindowFrame {
+            window_id: window.id,
+            frame,
+        })
+        .collect())
+}
+
+/// Compiles the plugin and checks its exports and ABI version, the version is
+/// returned with the error when it's the mismatch
+fn compile(name: &str, path: &Path) -> Result<Module, (Option<u32>, SnapError)> {
+    let module =
+        Module::from_file(&ENGINE, path).map_err(|error| (None, wasm_error(name, error)))?;
+
+    check_module(name, module)
+}
+
+fn check_module(name: &str, module: Module) -> Result<Module, (Option<u32>, SnapError)> {
+    if let Some(export) = [EXPORT_MEMORY, EXPORT_ALLOC, EXPORT_LAYOUT]
+        .into_iter()
+        .find(|export| module.get_export(export).is_none())
+    {
+        return Err((
+            None,
+            plugin_error(name, format!("{} is not exported", export)),
+        ));
+    }
+
+    match abi_version(&module) {
+        Ok(ABI_VERSION) => Ok(module),
+        Ok(version) => Err((
+            Some(version),
+            plugin_error(
+                name,
+                format!(
+                    "built for ABI version {}, this Snap supports {}",
+                    version, ABI_VERSION
+                ),
+            ),
+        )),
+        Err(error) => Err((None, wasm_error(name, error))),
+    }
+}
+
+fn load(name: &str, path: &Path) -> LoadedPlugin {
+    let info = PluginInfo {
+        name: String::from(name),
+        path: path.to_path_buf(),
+        abi_version: None,
+        error: None,
+    };
+
+    match compile(name, path) {
+        Ok(module) => LoadedPlugin {
+            info: PluginInfo {
+                abi_version: Some(ABI_VERSION),
+                ..info
+            },
+            module: Some(module),
+        },
+        Err((abi_version, error)) => LoadedPlugin {
+            info: PluginInfo {
+                abi_version,
+                error: Some(error),
+                ..info
+            },
+            module: None,
+        },
+    }
+}
+
+/// Loads every `.wasm` file of the directory, named after the file.
+/// A missing directory just means no plugins
+pub fn discover(dir: &Path) -> SnapResult<Vec<PluginInfo>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            *PLUGINS.lock().unwrap() = vec![];
+            return Ok(vec![]);
+        }
+        Err(error) => {
+            return Err(SnapError::platform(format!(
+                "can't read {}: {}",
+                dir.display(),
+                error
+            )))
+        }
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == EXTENSION)
+        })
+        .collect();
+    paths.sort();
+
+    let plugins: Vec<LoadedPlugin> = paths
+        .iter()
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().to_string();
+            Some(load(&name, path))
+        })
+        .collect();
+    let infos = plugins.iter().map(|plugin| plugin.info.clone()).collect();
+
+    *PLUGINS.lock().unwrap() = plugins;
+    Ok(infos)
+}
+
+pub fn list() -> Vec<PluginInfo> {
+    PLUGINS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|plugin| plugin.info.clone())
+        .collect()
+}
+
+pub fn layout(name: &str, input: &LayoutInput) -> SnapResult<Vec<WindowFrame>> {
+    let module = {
+        let plugins = PLUGINS.lock().unwrap();
+        let plugin = plugins
+            .iter()
+            .find(|plugin| plugin.info.name == name)
+            .ok_or_else(|| {
+                SnapError::invalid_argument("plugin", format!("no plugin named {}", name))
+            })?;
+
+        match (&plugin.module, &plugin.info.error) {
+            (Some(module), _) => module.clone(),
+            (None, Some(error)) => return Err(error.clone()),
+            (None, None) => return Err(plugin_error(name, "not loaded")),
+        }
+    };
+
+    layout_module(name, &module, input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::frame::{Point, Size};
+
+    /// Where the output strings of the test plugins are placed in memory
+    const DATA_OFFSET: u64 = 1024;
+
+    fn frame(x: f64, y: f64, width: f64, height: f64) -> Frame {
+        Frame {
+            position: Point { x, y },
+            size: Size { width, height },
+        }
+    }
+
+    fn window(id: i64) -> PluginWindow {
+        PluginWindow {
+            id,
+            pid: 1,
+            name: None,
+            title: None,
+            bundle_id: None,
+            frame: frame(0.0, 0.0, 100.0, 100.0),
+        }
+    }
+
+    fn input(window_ids: &[i64]) -> LayoutInput {
+        LayoutInput {
+            abi_version: ABI_VERSION,
+            visible_frame: frame(0.0, 0.0, 1000.0, 800.0),
+            gap: 0.0,
+            windows: window_ids.iter().map(|&id| window(id)).collect(),
+        }
+    }
+
+    /// `snap_layout` returning the string `output` placed at `DATA_OFFSET`
+    fn returns(output: &str) -> String {
+        format!("i64.const {}", DATA_OFFSET << 32 | output.len() as u64)
+    }
+
+    /// Plugin with every export, `layout` is the body of `snap_layout` and `data`
+    /// the strings placed at `DATA_OFFSET`
+    fn plugin(
+        abi_version: i32,
+        layout: &str,
+        data: &str,
+    ) -> Result<Module, (Option<u32>, SnapError)> {
+        let wat = format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (data (i32.const {}) "{}")
+                (func (export "snap_abi_version") (result i32) i32.const {})
+                (func (export "snap_alloc") (param i32) (result i32) i32.const 4096)
+                (func (export "snap_layout") (param i32 i32) (result i64) {}))"#,
+            DATA_OFFSET,
+            data.replace('"', "\\\""),
+            abi_version,
+            layout
+        );
+        let module = Module::new(&ENGINE, wat).expect("the test plugin doesn't compile");
+
+        check_module("test", module)
+    }
+
+    fn layout_output(output: &str) -> SnapResult<Vec<WindowFrame>> {
+        let module = plugin(ABI_VERSION as i32, &returns(output), output).unwrap();
+        layout_module("test", &module, &input(&[1, 2]))
+    }
+
+    fn message(error: SnapError) -> String {
+        match error {
+            SnapError::Plugin { message, .. } => message,
+            error => panic!("not a plugin error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn abi_mismatch_is_reported_with_the_version() {
+        let (version, error) = plugin(2, &returns(""), "").unwrap_err();
+
+        assert_eq!(version, Some(2));
+        assert!(message(error).contains("ABI version 2"));
+    }
+
+    #[test]
+    fn missing_exports_are_refused() {
+        let module = Module::new(
+            &ENGINE,
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "snap_abi_version") (result i32) i32.const 1))"#,
+        )
+        .unwrap();
+        let (version, error) = check_module("test", module).unwrap_err();
+
+        assert_eq!(version, None);
+        assert_eq!(message(error), "snap_alloc is not exported");
+    }
+
+    #[test]
+    fn endless_loops_run_out_of_fuel() {
+        let module = plugin(1, "(loop (br 0)) unreachable", "").unwrap();
+        let error = layout_module("test", &module, &input(&[1])).unwrap_err();
+
+        assert_eq!(
+            message(error),
+            format!("stopped after {} instructions", FUEL)
+        );
+    }
+
+    #[test]
+    fn memory_cant_grow_past_the_limit() {
+        let error = r#"{"error":"out of memory"}"#;
+        let pages = (MAX_MEMORY / 65536) as i32;
+        // The module starts with one page, growing by the limit goes one page over it
+        let layout = format!(
+            "(if (i32.eq (memory.grow (i32.const {})) (i32.const -1))
+                (then ({}) return))
+             unreachable",
+            pages,
+            returns(error)
+        );
+        let module = plugin(1, &layout, error).unwrap();
+        let error = layout_module("test", &module, &input(&[1])).unwrap_err();
+
+        assert_eq!(message(error), "out of memory");
+    }
+
+    #[test]
+    fn malformed_output_is_an_error() {
+        let error = layout_output("not json").unwrap_err();
+
+        assert!(message(error).starts_with("the output is not a list of frames"));
+    }
+
+    #[test]
+    fn frames_are_paired_with_the_window_ids_in_order() {
+        let frames =
+            layout_output(r#"[{"position":{"x":0,"y":0},"size":{"width":10,"height":20}}]"#)
+                .unwrap();
+
+        assert_eq!(
+            frames,
+            vec![WindowFrame {
+                window_id: 1,
+                frame: frame(0.0, 0.0, 10.0, 20.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn more_frames_than_windows_are_refused() {
+        let frame = r#"{"position":{"x":0,"y":0},"size":{"width":10,"height":20}}"#;
+        let error = layout_output(&format!("[{},{},{}]", frame, frame, frame)).unwrap_err();
+
+        assert_eq!(message(error), "returned 3 frames for 2 windows");
+    }
+
+    #[test]
+    fn frames_without_size_are_refused() {
+        let error = layout_output(r#"[{"position":{"x":0,"y":0},"size":{"width":0,"height":20}}]"#)
+            .unwrap_err();
+
+        assert_eq!(message(error), "frame 0 has no size or is not finite");
+    }
+}
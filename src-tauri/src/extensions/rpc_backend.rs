@@ -0,0 +1,139 @@
+use accessibility_sys::AXUIElementRef;
+use core_foundation::base::{CFRelease, CFTypeRef};
+use objc2_foundation::NSProcessInfo;
+use tauri::AppHandle;
+
+use crate::{
+    commands::nsscreen::nsscreen_get_screens,
+    data::{
+        action::SnapAction,
+        alignment::Alignment,
+        error::{SnapError, SnapResult},
+        frame::Frame,
+        screen::Screen,
+    },
+};
+
+use super::{
+    accessibility_elements::{get_active_window, get_frame, get_window_from_id},
+    actions::dispatch,
+    focus_history::{focus_window, get_history},
+    placement::{place, resolve_anchor},
+    rpc_server::{Backend, WindowRef},
+    screens,
+    scripting::{ScriptHost, ScriptScreen},
+    windows::{get_windows_on_screen, WindowFilter, WindowInfo},
+};
+
+/// Serves the control socket and user scripts from the running app
+pub struct AppBackend {
+    app: AppHandle,
+}
+
+impl AppBackend {
+    pub fn new(app: AppHandle) -> Self {
+        Self { app }
+    }
+}
+
+fn resolve_window(window: Option<WindowRef>) -> SnapResult<AXUIElementRef> {
+    let WindowRef { pid, window_id } = match window {
+        Some(window) => window,
+        None => {
+            let active = get_active_window()?;
+            WindowRef {
+                pid: active.process_id,
+                window_id: active.window_id as u32,
+            }
+        }
+    };
+
+    let own_pid = unsafe { NSProcessInfo::processInfo().processIdentifier() as i64 };
+    if pid == own_pid {
+        return Err(SnapError::OwnWindowRefused);
+    }
+
+    get_window_from_id(pid, window_id)
+}
+
+impl Backend for AppBackend {
+    fn list_windows(&self, filter: &WindowFilter) -> SnapResult<Vec<WindowInfo>> {
+        filter.validate()?;
+
+        Ok(get_windows_on_screen(filter))
+    }
+
+    fn list_screens(&self) -> SnapResult<Vec<Screen>> {
+        nsscreen_get_screens()
+    }
+
+    fn get_frame(&self, window: Option<WindowRef>) -> SnapResult<Frame> {
+        let window = resolve_window(window)?;
+        let frame = get_frame(window);
+        unsafe { CFRelease(window as CFTypeRef) };
+
+        frame
+    }
+
+    fn set_frame(
+        &self,
+        window: Option<WindowRef>,
+        frame: &Frame,
+        anchor: Option<Alignment>,
+    ) -> SnapResult<Frame> {
+        let window = resolve_window(window)?;
+        let anchor = resolve_anchor(frame, anchor);
+
+        let placed = place(window, frame, anchor).map(|placement| placement.frame);
+        unsafe { CFRelease(window as CFTypeRef) };
+
+        placed
+    }
+
+    /// Mappings, modes and caffeinate live in the frontend stores
+    fn run_action(&self, action: SnapAction) -> SnapResult<()> {
+        dispatch(&self.app, action)
+    }
+}
+
+impl ScriptHost for AppBackend {
+    fn screens(&self) -> SnapResult<Vec<ScriptScreen>> {
+        Ok(screens::frames()
+            .into_iter()
+            .zip(screens::visible_frames())
+            .enumerate()
+            .map(|(index, (frame, visible_frame))| ScriptScreen {
+                index,
+                frame,
+                visible_frame,
+            })
+            .collect())
+    }
+
+    fn windows(&self) -> SnapResult<Vec<WindowInfo>> {
+        let mut windows = get_windows_on_screen(&WindowFilter::default());
+
+        let history = get_history();
+        // Stable sort, windows missing from the history keep their stacking order
+        windows.sort_by_key(|window| {
+            history
+                .iter()
+                .position(|known| known.id == window.id)
+                .unwrap_or(usize::MAX)
+        });
+
+        Ok(windows)
+    }
+
+    fn set_frame(&self, window: WindowRef, frame: &Frame) -> SnapResult<Frame> {
+        Backend::set_frame(self, Some(window), frame, None)
+    }
+
+    fn focus(&self, window: WindowRef) -> SnapResult<()> {
+        focus_window(window.pid, window.window_id)
+    }
+
+    fn run_action(&self, action: SnapAction) -> SnapResult<()> {
+        dispatch(&self.app, action)
+    }
+}
@@ -0,0 +1,594 @@
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+    sync::Arc,
+    thread,
+};
+
+use core_graphics::window::CGWindowID;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::data::{
+    action::{ArrangeStrategy, SnapAction, WindowManagerMode},
+    alignment::Alignment,
+    error::{SnapError, SnapResult},
+    frame::Frame,
+    rpc::{
+        methods, RpcError, RpcRequest, RpcResponse, INVALID_PARAMS, INVALID_REQUEST,
+        JSONRPC_VERSION, METHOD_NOT_FOUND, PARSE_ERROR, SNAP_ERROR,
+    },
+    screen::Screen,
+};
+
+use super::windows::{WindowFilter, WindowInfo};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindowRef {
+    pub pid: i64,
+    pub window_id: CGWindowID,
+}
+
+/// What the control socket can do, implemented by the app and by fakes driving the protocol
+pub trait Backend: Send + Sync {
+    fn list_windows(&self, filter: &WindowFilter) -> SnapResult<Vec<WindowInfo>>;
+    fn list_screens(&self) -> SnapResult<Vec<Screen>>;
+    /// `None` targets the focused window
+    fn get_frame(&self, window: Option<WindowRef>) -> SnapResult<Frame>;
+    /// Returns the frame the window ended up with
+    fn set_frame(
+        &self,
+        window: Option<WindowRef>,
+        frame: &Frame,
+        anchor: Option<Alignment>,
+    ) -> SnapResult<Frame>;
+    fn run_action(&self, action: SnapAction) -> SnapResult<()>;
+}
+
+#[derive(Deserialize, Default)]
+struct WindowParams {
+    pid: Option<i64>,
+    window_id: Option<CGWindowID>,
+}
+
+impl WindowParams {
+    fn window(&self) -> Result<Option<WindowRef>, RpcError> {
+        match (self.pid, self.window_id) {
+            (Some(pid), Some(window_id)) => Ok(Some(WindowRef { pid, window_id })),
+            (None, None) => Ok(None),
+            _ => Err(RpcError::new(
+                INVALID_PARAMS,
+                "pid and window_id go together",
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SetFrameParams {
+    #[serde(flatten)]
+    window: WindowParams,
+    frame: Frame,
+    #[serde(default)]
+    anchor: Option<Alignment>,
+}
+
+#[derive(Deserialize)]
+struct MappingParams {
+    frame: Frame,
+}
+
+#[derive(Deserialize, Default)]
+struct ArrangeParams {
+    #[serde(default)]
+    strategy: ArrangeStrategy,
+}
+
+#[derive(Deserialize)]
+struct ModeParams {
+    mode: WindowManagerMode,
+}
+
+#[derive(Deserialize, Default)]
+struct CaffeinateParams {
+    enabled: Option<bool>,
+    minutes: Option<u32>,
+}
+
+impl From<SnapError> for RpcError {
+    fn from(error: SnapError) -> Self {
+        RpcError {
+            code: SNAP_ERROR,
+            message: error.to_string(),
+            data: serde_json::to_value(&error).ok(),
+        }
+    }
+}
+
+/// Missing params are read as an empty object so every field falls back to its default
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = match params {
+        Value::Null => Value::Object(Default::default()),
+        params => params,
+    };
+
+    serde_json::from_value(params).map_err(|error| RpcError::new(INVALID_PARAMS, error.to_string()))
+}
+
+fn to_result<T: Serialize>(value: SnapResult<T>) -> Result<Value, RpcError> {
+    let value = value?;
+    serde_json::to_value(value)
+        .map_err(|error| RpcError::from(SnapError::platform(error.to_string())))
+}
+
+fn call(backend: &dyn Backend, method: &str, params: Value) -> Result<Value, RpcError> {
+    match method {
+        methods::LIST_WINDOWS => {
+            let filter: WindowFilter = parse(params)?;
+            to_result(backend.list_windows(&filter))
+        }
+        methods::LIST_SCREENS => to_result(backend.list_screens()),
+        methods::GET_FRAME => {
+            let params: WindowParams = parse(params)?;
+            to_result(backend.get_frame(params.window()?))
+        }
+        methods::SET_FRAME => {
+            let params: SetFrameParams = parse(params)?;
+            to_result(backend.set_frame(params.window.window()?, &params.frame, params.anchor))
+        }
+        methods::RUN_MAPPING => {
+            let params: MappingParams = parse(params)?;
+            to_result(backend.run_action(SnapAction::Mapping {
+                frame: params.frame,
+            }))
+        }
+        methods::ARRANGE => {
+            let params: ArrangeParams = parse(params)?;
+            to_result(backend.run_action(SnapAction::Arrange {
+                strategy: params.strategy,
+            }))
+        }
+        methods::SET_MODE => {
+            let params: ModeParams = parse(params)?;
+            to_result(backend.run_action(SnapAction::SetMode { mode: params.mode }))
+        }
+        methods::SET_CAFFEINATE => {
+            let params: CaffeinateParams = parse(params)?;
+            let action = match (params.enabled, params.minutes) {
+                (None, None) => SnapAction::ToggleCaffeinate,
+                (_, Some(0)) => {
+                    return Err(RpcError::new(INVALID_PARAMS, "minutes must be positive"))
+                }
+                (Some(false), Some(_)) => {
+                    return Err(RpcError::new(
+                        INVALID_PARAMS,
+                        "minutes only applies when enabling",
+                    ))
+                }
+                (enabled, minutes) => SnapAction::SetCaffeinate {
+                    enabled: enabled.unwrap_or(true),
+                    minutes,
+                },
+            };
+            to_result(backend.run_action(action))
+        }
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method {}", method),
+        )),
+    }
+}
+
+/// Answers one line of the protocol, notifications are run without an answer
+pub fn handle_line(backend: &dyn Backend, line: &str) -> Option<RpcResponse> {
+    let request: RpcRequest = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(error) => {
+            return Some(RpcResponse::failure(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, error.to_string()),
+            ))
+        }
+    };
+
+    // Not a JSON-RPC 2.0 message, so not a notification either
+    if request.jsonrpc != JSONRPC_VERSION {
+        return Some(RpcResponse::failure(
+            request.id.unwrap_or(Value::Null),
+            RpcError::new(INVALID_REQUEST, "only JSON-RPC 2.0 is supported"),
+        ));
+    }
+
+    let result = call(backend, &request.method, request.params);
+    let id = request.id?;
+
+    Some(match result {
+        Ok(result) => RpcResponse::success(id, result),
+        Err(error) => RpcResponse::failure(id, error),
+    })
+}
+
+fn handle_connection(stream: UnixStream, backend: &dyn Backend) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let Some(response) = handle_line(backend, &line) else {
+            continue;
+        };
+        serde_json::to_writer(&mut writer, &response)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Listens on the socket in the background, every connection gets its own thread
+pub fn serve(path: &Path, backend: Arc<dyn Backend>) -> io::Result<()> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another Snap instance is listening on the control socket",
+            ));
+        }
+        // Left behind by an instance that didn't exit cleanly
+        fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let backend = backend.clone();
+                    thread::spawn(move || {
+                        if let Err(error) = handle_connection(stream, backend.as_ref()) {
+                            eprintln!("control socket connection failed: {}", error);
+                        }
+                    });
+                }
+                Err(error) => eprintln!("control socket accept failed: {}", error),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::data::frame::{Point, Size};
+
+    const FOCUSED: WindowRef = WindowRef {
+        pid: 10,
+        window_id: 1,
+    };
+
+    fn frame(x: f64, y: f64, width: f64, height: f64) -> Frame {
+        Frame {
+            position: Point { x, y },
+            size: Size { width, height },
+        }
+    }
+
+    /// Windows and actions kept in memory, the focused window is `FOCUSED`
+    struct FakeBackend {
+        frames: Mutex<Vec<(WindowRef, Frame)>>,
+        actions: Mutex<Vec<SnapAction>>,
+    }
+
+    impl FakeBackend {
+        fn new() -> Self {
+            Self {
+                frames: Mutex::new(vec![
+                    (FOCUSED, frame(0.0, 0.0, 800.0, 600.0)),
+                    (
+                        WindowRef {
+                            pid: 20,
+                            window_id: 2,
+                        },
+                        frame(100.0, 100.0, 400.0, 300.0),
+                    ),
+                ]),
+                actions: Mutex::new(vec![]),
+            }
+        }
+
+        fn frame_of(&self, window: Option<WindowRef>) -> SnapResult<Frame> {
+            let window = window.unwrap_or(FOCUSED);
+            self.frames
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|(known, _)| *known == window)
+                .map(|(_, frame)| frame.clone())
+                .ok_or(SnapError::WindowNotFound {
+                    pid: window.pid,
+                    window_id: Some(window.window_id),
+                })
+        }
+    }
+
+    impl Backend for FakeBackend {
+        fn list_windows(&self, filter: &WindowFilter) -> SnapResult<Vec<WindowInfo>> {
+            let windows = self.frames.lock().unwrap().clone();
+
+            Ok(windows
+                .into_iter()
+                .filter(|(_, frame)| {
+                    filter.min_size.as_ref().is_none_or(|min_size| {
+                        frame.size.width >= min_size.width && frame.size.height >= min_size.height
+                    })
+                })
+                .map(|(window, frame)| WindowInfo {
+                    id: Some(window.window_id as i64),
+                    pid: Some(window.pid),
+                    name: None,
+                    frame: Some(frame),
+                    is_main: window == FOCUSED,
+                    title: None,
+                    layer: 0,
+                    alpha: 1.0,
+                    bundle_id: None,
+                    is_on_screen: true,
+                    role: None,
+                    subrole: None,
+                    is_minimized: false,
+                    is_fullscreen: false,
+                    screen_index: Some(0),
+                })
+                .collect())
+        }
+
+        fn list_screens(&self) -> SnapResult<Vec<Screen>> {
+            Ok(vec![Screen {
+                frame: frame(0.0, 0.0, 1440.0, 900.0),
+                visible_frame: frame(0.0, 25.0, 1440.0, 875.0),
+            }])
+        }
+
+        fn get_frame(&self, window: Option<WindowRef>) -> SnapResult<Frame> {
+            self.frame_of(window)
+        }
+
+        fn set_frame(
+            &self,
+            window: Option<WindowRef>,
+            frame: &Frame,
+            _anchor: Option<Alignment>,
+        ) -> SnapResult<Frame> {
+            self.frame_of(window)?;
+            let window = window.unwrap_or(FOCUSED);
+            for (known, known_frame) in self.frames.lock().unwrap().iter_mut() {
+                if *known == window {
+                    *known_frame = frame.clone();
+                }
+            }
+
+            Ok(frame.clone())
+        }
+
+        fn run_action(&self, action: SnapAction) -> SnapResult<()> {
+            self.actions.lock().unwrap().push(action);
+            Ok(())
+        }
+    }
+
+    fn request(backend: &FakeBackend, request: Value) -> RpcResponse {
+        handle_line(backend, &request.to_string()).expect("requests with an id are answered")
+    }
+
+    fn call_method(backend: &FakeBackend, method: &str, params: Value) -> RpcResponse {
+        request(
+            backend,
+            json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params }),
+        )
+    }
+
+    fn error_code(response: &RpcResponse) -> i64 {
+        response.error.as_ref().expect("an error").code
+    }
+
+    #[test]
+    fn answers_with_the_request_id() {
+        let backend = FakeBackend::new();
+        let response = call_method(&backend, methods::LIST_SCREENS, Value::Null);
+
+        assert_eq!(response.id, json!(7));
+        assert!(response.error.is_none());
+        assert_eq!(
+            response.result.unwrap()[0]["frame"]["size"]["width"],
+            1440.0
+        );
+    }
+
+    #[test]
+    fn lists_windows_with_the_filter() {
+        let backend = FakeBackend::new();
+        let response = call_method(
+            &backend,
+            methods::LIST_WINDOWS,
+            json!({ "min_size": { "width": 500.0, "height": 500.0 } }),
+        );
+
+        let windows = response.result.unwrap();
+        assert_eq!(windows.as_array().unwrap().len(), 1);
+        assert_eq!(windows[0]["id"], 1);
+    }
+
+    #[test]
+    fn window_defaults_to_the_focused_one() {
+        let backend = FakeBackend::new();
+
+        let focused = call_method(&backend, methods::GET_FRAME, Value::Null);
+        assert_eq!(focused.result.unwrap()["size"]["width"], 800.0);
+
+        let other = call_method(
+            &backend,
+            methods::GET_FRAME,
+            json!({ "pid": 20, "window_id": 2 }),
+        );
+        assert_eq!(other.result.unwrap()["size"]["width"], 400.0);
+    }
+
+    #[test]
+    fn set_frame_returns_the_applied_frame() {
+        let backend = FakeBackend::new();
+        let target = frame(10.0, 20.0, 300.0, 200.0);
+
+        let response = call_method(
+            &backend,
+            methods::SET_FRAME,
+            json!({ "pid": 20, "window_id": 2, "frame": target }),
+        );
+
+        assert_eq!(
+            response.result.unwrap(),
+            serde_json::to_value(&target).unwrap()
+        );
+        assert_eq!(
+            backend.get_frame(Some(WindowRef {
+                pid: 20,
+                window_id: 2
+            })),
+            Ok(target)
+        );
+    }
+
+    #[test]
+    fn actions_reach_the_backend() {
+        let backend = FakeBackend::new();
+
+        call_method(&backend, methods::ARRANGE, json!({ "strategy": "grid" }));
+        call_method(
+            &backend,
+            methods::SET_MODE,
+            json!({ "mode": "fancy_zones" }),
+        );
+        call_method(&backend, methods::SET_CAFFEINATE, Value::Null);
+        call_method(&backend, methods::SET_CAFFEINATE, json!({ "minutes": 30 }));
+
+        assert_eq!(
+            *backend.actions.lock().unwrap(),
+            vec![
+                SnapAction::Arrange {
+                    strategy: ArrangeStrategy::Grid
+                },
+                SnapAction::SetMode {
+                    mode: WindowManagerMode::FancyZones
+                },
+                SnapAction::ToggleCaffeinate,
+                SnapAction::SetCaffeinate {
+                    enabled: true,
+                    minutes: Some(30)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_json_is_a_parse_error() {
+        let backend = FakeBackend::new();
+        let response = handle_line(&backend, "{ not json").unwrap();
+
+        assert_eq!(response.id, Value::Null);
+        assert_eq!(error_code(&response), PARSE_ERROR);
+    }
+
+    #[test]
+    fn other_protocol_versions_are_invalid_requests() {
+        let backend = FakeBackend::new();
+        let response = request(
+            &backend,
+            json!({ "jsonrpc": "1.0", "id": 3, "method": methods::LIST_SCREENS }),
+        );
+
+        assert_eq!(response.id, json!(3));
+        assert_eq!(error_code(&response), INVALID_REQUEST);
+    }
+
+    #[test]
+    fn unknown_methods_are_reported() {
+        let backend = FakeBackend::new();
+        let response = call_method(&backend, "window.explode", Value::Null);
+
+        assert_eq!(error_code(&response), METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn invalid_params_are_reported() {
+        let backend = FakeBackend::new();
+
+        let cases = [
+            (methods::GET_FRAME, json!({ "pid": 20 })),
+            (methods::SET_FRAME, json!({ "pid": 20, "window_id": 2 })),
+            (methods::SET_MODE, json!({ "mode": "tiling" })),
+            (methods::SET_CAFFEINATE, json!({ "minutes": 0 })),
+            (
+                methods::SET_CAFFEINATE,
+                json!({ "enabled": false, "minutes": 5 }),
+            ),
+        ];
+        for (method, params) in cases {
+            let response = call_method(&backend, method, params.clone());
+            assert_eq!(
+                error_code(&response),
+                INVALID_PARAMS,
+                "{} {}",
+                method,
+                params
+            );
+        }
+        assert!(backend.actions.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn backend_errors_carry_the_snap_error() {
+        let backend = FakeBackend::new();
+        let response = call_method(
+            &backend,
+            methods::GET_FRAME,
+            json!({ "pid": 30, "window_id": 3 }),
+        );
+
+        let error = response.error.unwrap();
+        assert_eq!(error.code, SNAP_ERROR);
+        assert_eq!(error.data.unwrap()["kind"], "window_not_found");
+    }
+
+    #[test]
+    fn notifications_run_without_a_response() {
+        let backend = FakeBackend::new();
+        let notification = json!({ "jsonrpc": "2.0", "method": methods::ARRANGE });
+
+        assert!(handle_line(&backend, &notification.to_string()).is_none());
+        assert_eq!(
+            *backend.actions.lock().unwrap(),
+            vec![SnapAction::Arrange {
+                strategy: ArrangeStrategy::Split
+            }]
+        );
+
+        // Failed notifications aren't answered either
+        let unknown = json!({ "jsonrpc": "2.0", "method": "window.explode" });
+        assert!(handle_line(&backend, &unknown.to_string()).is_none());
+    }
+}
@@ -0,0 +1,70 @@
+use cocoa::{
+    appkit::NSScreen,
+    base::{id, nil},
+    foundation::NSArray,
+};
+
+use crate::data::frame::{Frame, Point, Size};
+
+/// Converts an AppKit rect (bottom-left origin) to CG / accessibility coordinates
+/// (top-left origin of the primary screen)
+fn to_cg(rect: cocoa::foundation::NSRect, primary_height: f64) -> Frame {
+    Frame {
+        size: Size {
+            width: rect.size.width,
+            height: rect.size.height,
+        },
+        position: Point {
+            x: rect.origin.x,
+            y: primary_height - rect.origin.y - rect.size.height,
+        },
+    }
+}
+
+fn screen_frames(visible: bool) -> Vec<Frame> {
+    unsafe {
+        let screens = NSScreen::screens(nil);
+        let count = screens.count();
+        if count == 0 {
+            return vec![];
+        }
+
+        // The first screen holds the menu bar and defines the coordinate origin
+        let primary_height = NSScreen::frame(screens.objectAtIndex(0)).size.height;
+
+        (0..count)
+            .map(|i| {
+                let screen: id = screens.objectAtIndex(i);
+                let rect = if visible {
+                    NSScreen::visibleFrame(screen)
+                } else {
+                    NSScreen::frame(screen)
+                };
+                to_cg(rect, primary_height)
+            })
+            .collect()
+    }
+}
+
+/// Frames of all screens in CG coordinates, in the order of `nsscreen_get_screens`
+pub fn frames() -> Vec<Frame> {
+    screen_frames(false)
+}
+
+/// Visible frames (without menu bar and dock) of all screens in CG coordinates
+pub fn visible_frames() -> Vec<Frame> {
+    screen_frames(true)
+}
+
+/// Index of the screen the center of `frame` is on
+pub fn containing(frame: &Frame, screens: &[Frame]) -> Option<usize> {
+    let x = frame.position.x + frame.size.width / 2.0;
+    let y = frame.position.y + frame.size.height / 2.0;
+
+    screens.iter().position(|screen| {
+        x >= screen.position.x
+            && x <= screen.position.x + screen.size.width
+            && y >= screen.position.y
+            && y <= screen.position.y + screen.size.height
+    })
+}
@@ -0,0 +1,700 @@
+use std::{
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use rhai::{
+    module_resolvers::DummyModuleResolver, serde::to_dynamic, Array, CallFnOptions, Dynamic,
+    Engine, EvalAltResult, Map, Position, Scope, AST,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::data::{
+    action::{ArrangeStrategy, SnapAction},
+    error::{SnapError, SnapResult},
+    frame::{Frame, Point, Size},
+};
+
+use super::{rpc_server::WindowRef, windows::WindowInfo};
+
+/// Operations a script may run before it is stopped, loops included
+#[cfg(not(test))]
+const MAX_OPERATIONS: u64 = 5_000_000;
+/// Wall clock limit, host calls (moving windows) count too
+#[cfg(not(test))]
+const TIME_LIMIT: Duration = Duration::from_secs(5);
+// Tests run into the limits without waiting for the real ones
+#[cfg(test)]
+const MAX_OPERATIONS: u64 = 100_000;
+#[cfg(test)]
+const TIME_LIMIT: Duration = Duration::from_millis(300);
+/// Name of the function a script defines to act as an arrange strategy
+const LAYOUT_FN: &str = "layout";
+
+/// What scripts can see and do, implemented by the app and by fakes
+pub trait ScriptHost: Send + Sync {
+    /// Screens in CG coordinates, like the window frames
+    fn screens(&self) -> SnapResult<Vec<ScriptScreen>>;
+    /// Most recently focused first
+    fn windows(&self) -> SnapResult<Vec<WindowInfo>>;
+    /// Returns the frame the window ended up with
+    fn set_frame(&self, window: WindowRef, frame: &Frame) -> SnapResult<Frame>;
+    fn focus(&self, window: WindowRef) -> SnapResult<()>;
+    fn run_action(&self, action: SnapAction) -> SnapResult<()>;
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ScriptScreen {
+    pub index: usize,
+    pub frame: Frame,
+    pub visible_frame: Frame,
+}
+
+/// Frame a layout gives to a window, in CG coordinates
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct WindowFrame {
+    pub window_id: i64,
+    pub frame: Frame,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ScriptSource {
+    pub name: String,
+    pub source: String,
+}
+
+/// The known windows with these ids in the same order, and the screen of the first one.
+/// Windows closed in the meantime are left out, layouts answer with ids for that reason
+pub fn windows_and_screen(
+    host: &dyn ScriptHost,
+    window_ids: &[i64],
+) -> SnapResult<(Vec<WindowInfo>, ScriptScreen)> {
+    let known = host.windows()?;
+    let windows: Vec<_> = window_ids
+        .iter()
+        .filter_map(|id| known.iter().find(|window| window.id == Some(*id)).cloned())
+        .collect();
+
+    let index = windows
+        .first()
+        .and_then(|window| window.screen_index)
+        .unwrap_or(0);
+    let screen = host
+        .screens()?
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| SnapError::platform("screens are unavailable"))?;
+
+    Ok((windows, screen))
+}
+
+/// Compiled scripts by name
+static SCRIPTS: Lazy<Mutex<HashMap<String, Arc<AST>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn script_error(script: &str, message: impl Into<String>, position: Position) -> SnapError {
+    SnapError::Script {
+        script: String::from(script),
+        message: message.into(),
+        line: position.line(),
+        column: position.position(),
+    }
+}
+
+/// Reports where the error happened, inside the called function rather than at the call
+fn eval_error(script: &str, error: &EvalAltResult) -> SnapError {
+    let inner = error.unwrap_inner();
+    let position = match inner.position() {
+        Position::NONE => error.position(),
+        position => position,
+    };
+
+    // The position is reported on its own, not at the end of the message
+    let message = match inner {
+        // Stopped by `on_progress`, which says why
+        EvalAltResult::ErrorTerminated(reason, _) => reason.to_string(),
+        inner => inner.to_string(),
+    };
+    let message = message
+        .strip_suffix(&format!(" ({})", inner.position()))
+        .unwrap_or(&message);
+
+    script_error(script, message, position)
+}
+
+fn to_script_error(message: impl Into<String>) -> Box<EvalAltResult> {
+    message.into().into()
+}
+
+fn number(value: &Dynamic) -> Option<f64> {
+    value
+        .as_float()
+        .ok()
+        .or_else(|| value.as_int().ok().map(|value| value as f64))
+}
+
+fn field(map: &Map, key: &str) -> Result<f64, Box<EvalAltResult>> {
+    map.get(key)
+        .and_then(number)
+        .ok_or_else(|| to_script_error(format!("{} must be a number", key)))
+}
+
+fn to_frame(value: &Dynamic) -> Result<Frame, Box<EvalAltResult>> {
+    let map = value
+        .read_lock::<Map>()
+        .ok_or_else(|| to_script_error("a frame must be a map, see frame(x, y, width, height)"))?;
+    let part = |key: &str| {
+        map.get(key)
+            .and_then(|part| part.read_lock::<Map>().map(|part| part.clone()))
+            .ok_or_else(|| to_script_error(format!("frame.{} is missing", key)))
+    };
+    let (position, size) = (part("position")?, part("size")?);
+
+    Ok(Frame {
+        size: Size {
+            width: field(&size, "width")?,
+            height: field(&size, "height")?,
+        },
+        position: Point {
+            x: field(&position, "x")?,
+            y: field(&position, "y")?,
+        },
+    })
+}
+
+fn to_window(value: &Dynamic) -> Result<WindowRef, Box<EvalAltResult>> {
+    let map = value
+        .read_lock::<Map>()
+        .ok_or_else(|| to_script_error("a window must be a map from windows()"))?;
+    let int = |key: &str| {
+        map.get(key)
+            .and_then(|value| value.as_int().ok())
+            .ok_or_else(|| to_script_error(format!("window.{} is missing", key)))
+    };
+
+    Ok(WindowRef {
+        pid: int("pid")?,
+        window_id: int("id")? as u32,
+    })
+}
+
+fn new_frame(x: f64, y: f64, width: f64, height: f64) -> Frame {
+    Frame {
+        size: Size { width, height },
+        position: Point { x, y },
+    }
+}
+
+fn from_host<T: Serialize>(value: SnapResult<T>) -> Result<Dynamic, Box<EvalAltResult>> {
+    to_dynamic(value.map_err(|error| to_script_error(error.to_string()))?)
+}
+
+fn numbers(values: &[&Dynamic]) -> Result<Vec<f64>, Box<EvalAltResult>> {
+    values
+        .iter()
+        .map(|value| number(value).ok_or_else(|| to_script_error("expected a number")))
+        .collect()
+}
+
+/// Engine with the Snap API and without access to files, modules or `eval`
+fn build_engine(name: &str, host: Option<Arc<dyn ScriptHost>>) -> Engine {
+    let mut engine = Engine::new();
+
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(1 << 16)
+        .set_max_array_size(10_000)
+        .set_max_map_size(10_000)
+        .set_max_modules(0)
+        .set_module_resolver(DummyModuleResolver::new())
+        .disable_symbol("eval");
+
+    let started = Instant::now();
+    engine.on_progress(move |_| {
+        (started.elapsed() > TIME_LIMIT).then(|| Dynamic::from("time limit exceeded"))
+    });
+
+    let print_name = String::from(name);
+    engine.on_print(move |text| eprintln!("[{}] {}", print_name, text));
+    let debug_name = String::from(name);
+    engine.on_debug(move |text, _, position| eprintln!("[{}:{}] {}", debug_name, position, text));
+
+    engine.register_fn(
+        "frame",
+        |x: Dynamic, y: Dynamic, width: Dynamic, height: Dynamic| {
+            let values = numbers(&[&x, &y, &width, &height])?;
+            to_dynamic(new_frame(values[0], values[1], values[2], values[3]))
+        },
+    );
+
+    // Frame inside the visible frame of a screen, from fractions of it
+    engine.register_fn(
+        "relative",
+        |screen: Map, x: Dynamic, y: Dynamic, width: Dynamic, height: Dynamic| {
+            let visible = to_frame(screen.get("visible_frame").unwrap_or(&Dynamic::UNIT))?;
+            let values = numbers(&[&x, &y, &width, &height])?;
+            to_dynamic(new_frame(
+                visible.position.x + values[0] * visible.size.width,
+                visible.position.y + values[1] * visible.size.height,
+                values[2] * visible.size.width,
+                values[3] * visible.size.height,
+            ))
+        },
+    );
+
+    let Some(host) = host else {
+        return engine;
+    };
+
+    let screens_host = host.clone();
+    engine.register_fn("screens", move || from_host(screens_host.screens()));
+
+    let windows_host = host.clone();
+    engine.register_fn("windows", move || from_host(windows_host.windows()));
+
+    let focused_host = host.clone();
+    engine.register_fn("focused_window", move || {
+        let windows = focused_host
+            .windows()
+            .map_err(|error| to_script_error(error.to_string()))?;
+        match windows.into_iter().next() {
+            Some(window) => to_dynamic(window),
+            None => Ok(Dynamic::UNIT),
+        }
+    });
+
+    let frame_host = host.clone();
+    engine.register_fn("set_frame", move |window: Dynamic, frame: Dynamic| {
+        from_host(frame_host.set_frame(to_window(&window)?, &to_frame(&frame)?))
+    });
+
+    let focus_host = host.clone();
+    engine.register_fn("focus", move |window: Dynamic| {
+        focus_host
+            .focus(to_window(&window)?)
+            .map_err(|error| to_script_error(error.to_string()))
+    });
+
+    let arrange_host = host.clone();
+    engine.register_fn("arrange", move || {
+        arrange_host
+            .run_action(SnapAction::Arrange {
+                strategy: ArrangeStrategy::Split,
+            })
+            .map_err(|error| to_script_error(error.to_string()))
+    });
+
+    engine.register_fn("arrange", move |strategy: &str| {
+        let strategy = match strategy {
+            "split" => ArrangeStrategy::Split,
+            "grid" => ArrangeStrategy::Grid,
+            _ => return Err(to_script_error(format!("unknown strategy {}", strategy))),
+        };
+        host.run_action(SnapAction::Arrange { strategy })
+            .map_err(|error| to_script_error(error.to_string()))
+    });
+
+    engine
+}
+
+/// Runs `run` with the engine, a panic in the engine or a host call is reported as an error
+fn guarded<T>(name: &str, run: impl FnOnce() -> SnapResult<T>) -> SnapResult<T> {
+    panic::catch_unwind(AssertUnwindSafe(run))
+        .unwrap_or_else(|_| Err(script_error(name, "the script crashed", Position::NONE)))
+}
+
+/// Checks the syntax, errors carry the line and column of the mistake
+pub fn compile(name: &str, source: &str) -> SnapResult<AST> {
+    guarded(name, || {
+        build_engine(name, None).compile(source).map_err(|error| {
+            let position = error.position();
+            script_error(name, error.err_type().to_string(), position)
+        })
+    })
+}
+
+/// Replaces the known scripts, returning the errors of those that didn't compile
+pub fn configure(sources: Vec<ScriptSource>) -> Vec<SnapError> {
+    let mut scripts = HashMap::new();
+    let mut errors = vec![];
+
+    for source in sources {
+        match compile(&source.name, &source.source) {
+            Ok(ast) => {
+                scripts.insert(source.name, Arc::new(ast));
+            }
+            Err(error) => errors.push(error),
+        }
+    }
+
+    *SCRIPTS.lock().unwrap() = scripts;
+    errors
+}
+
+fn get(name: &str) -> SnapResult<Arc<AST>> {
+    SCRIPTS
+        .lock()
+        .unwrap()
+        .get(name)
+        .cloned()
+        .ok_or_else(|| SnapError::invalid_argument("script", format!("no script named {}", name)))
+}
+
+/// Runs the body of a compiled script
+pub fn run_ast(name: &str, ast: &AST, host: Arc<dyn ScriptHost>) -> SnapResult<()> {
+    guarded(name, || {
+        build_engine(name, Some(host))
+            .run_ast(ast)
+            .map_err(|error| eval_error(name, &error))
+    })
+}
+
+/// Calls `fn layout(windows, screen)` of a compiled script, which returns one frame per
+/// window in CG coordinates like `set_frame` takes, `relative(screen, ...)` builds them.
+/// The frames are paired with the windows in order, extra windows keep their frame
+pub fn layout_ast(
+    name: &str,
+    ast: &AST,
+    host: Arc<dyn ScriptHost>,
+    windows: Vec<WindowInfo>,
+    screen: ScriptScreen,
+) -> SnapResult<Vec<WindowFrame>> {
+    if !ast
+        .iter_functions()
+        .any(|function| function.name == LAYOUT_FN)
+    {
+        return Err(script_error(
+            name,
+            format!(
+                "define fn {}(windows, screen) to arrange windows",
+                LAYOUT_FN
+            ),
+            Position::NONE,
+        ));
+    }
+
+    let window_ids: Vec<i64> = windows.iter().filter_map(|window| window.id).collect();
+
+    guarded(name, || {
+        let engine = build_engine(name, Some(host));
+        let windows = to_dynamic(windows).map_err(|error| eval_error(name, &error))?;
+        let screen = to_dynamic(screen).map_err(|error| eval_error(name, &error))?;
+
+        // Only the function, the body of the script is what its shortcut runs
+        let options = CallFnOptions::new().eval_ast(false);
+        let frames: Array = engine
+            .call_fn_with_options(
+                options,
+                &mut Scope::new(),
+                ast,
+                LAYOUT_FN,
+                (windows, screen),
+            )
+            .map_err(|error| eval_error(name, &error))?;
+
+        if frames.len() > window_ids.len() {
+            return Err(script_error(
+                name,
+                format!(
+                    "{} returned {} frames for {} windows",
+                    LAYOUT_FN,
+                    frames.len(),
+                    window_ids.len()
+                ),
+                Position::NONE,
+            ));
+        }
+
+        window_ids
+            .iter()
+            .zip(&frames)
+            .enumerate()
+            .map(|(index, (&window_id, frame))| {
+                let frame = to_frame(frame).map_err(|error| eval_error(name, &error))?;
+                if !frame.is_placeable() {
+                    return Err(script_error(
+                        name,
+                        format!("frame {} has no size or is not finite", index),
+                        Position::NONE,
+                    ));
+                }
+
+                Ok(WindowFrame { window_id, frame })
+            })
+            .collect()
+    })
+}
+
+pub fn run(name: &str, host: Arc<dyn ScriptHost>) -> SnapResult<()> {
+    let ast = get(name)?;
+    run_ast(name, &ast, host)
+}
+
+pub fn layout(
+    name: &str,
+    host: Arc<dyn ScriptHost>,
+    windows: Vec<WindowInfo>,
+    screen: ScriptScreen,
+) -> SnapResult<Vec<WindowFrame>> {
+    let ast = get(name)?;
+    layout_ast(name, &ast, host, windows, screen)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    fn frame(x: f64, y: f64, width: f64, height: f64) -> Frame {
+        Frame {
+            position: Point { x, y },
+            size: Size { width, height },
+        }
+    }
+
+    fn window(id: i64) -> WindowInfo {
+        WindowInfo {
+            id: Some(id),
+            pid: Some(id * 10),
+            name: Some(format!("App {}", id)),
+            frame: Some(frame(0.0, 25.0, 800.0, 600.0)),
+            is_main: true,
+            title: None,
+            layer: 0,
+            alpha: 1.0,
+            bundle_id: None,
+            is_on_screen: true,
+            role: Some(String::from("AXWindow")),
+            subrole: Some(String::from("AXStandardWindow")),
+            is_minimized: false,
+            is_fullscreen: false,
+            screen_index: Some(0),
+        }
+    }
+
+    fn screen() -> ScriptScreen {
+        ScriptScreen {
+            index: 0,
+            frame: frame(0.0, 0.0, 1440.0, 900.0),
+            visible_frame: frame(0.0, 25.0, 1440.0, 875.0),
+        }
+    }
+
+    /// Windows kept in memory, `focus` takes `focus_delay` like a slow app would
+    #[derive(Default)]
+    struct FakeHost {
+        windows: Vec<WindowInfo>,
+        frames: Mutex<Vec<(WindowRef, Frame)>>,
+        focus_delay: Duration,
+    }
+
+    impl ScriptHost for FakeHost {
+        fn screens(&self) -> SnapResult<Vec<ScriptScreen>> {
+            Ok(vec![screen()])
+        }
+
+        fn windows(&self) -> SnapResult<Vec<WindowInfo>> {
+            Ok(self.windows.clone())
+        }
+
+        fn set_frame(&self, window: WindowRef, frame: &Frame) -> SnapResult<Frame> {
+            self.frames.lock().unwrap().push((window, frame.clone()));
+            Ok(frame.clone())
+        }
+
+        fn focus(&self, _window: WindowRef) -> SnapResult<()> {
+            thread::sleep(self.focus_delay);
+            Ok(())
+        }
+
+        fn run_action(&self, _action: SnapAction) -> SnapResult<()> {
+            Ok(())
+        }
+    }
+
+    fn host() -> Arc<FakeHost> {
+        Arc::new(FakeHost {
+            windows: vec![window(1), window(2), window(3)],
+            ..FakeHost::default()
+        })
+    }
+
+    fn run_source(source: &str, host: Arc<FakeHost>) -> SnapResult<()> {
+        let ast = compile("test", source)?;
+        run_ast("test", &ast, host)
+    }
+
+    fn layout_source(source: &str) -> SnapResult<Vec<WindowFrame>> {
+        let ast = compile("test", source)?;
+        layout_ast(
+            "test",
+            &ast,
+            host(),
+            vec![window(1), window(2), window(3)],
+            screen(),
+        )
+    }
+
+    fn message_and_position(error: SnapError) -> (String, Option<usize>, Option<usize>) {
+        match error {
+            SnapError::Script {
+                message,
+                line,
+                column,
+                ..
+            } => (message, line, column),
+            error => panic!("expected a script error, got {:?}", error),
+        }
+    }
+
+    #[test]
+    fn compile_errors_point_at_the_mistake() {
+        let error = compile("test", "let a = 1;\nlet b = ;").unwrap_err();
+        let (_, line, column) = message_and_position(error);
+
+        assert_eq!(line, Some(2));
+        assert_eq!(column, Some(9));
+    }
+
+    #[test]
+    fn runtime_errors_point_inside_the_called_function() {
+        let source = "fn place(window) {\n    missing(window)\n}\n\nplace(focused_window());";
+        let error = run_source(source, host()).unwrap_err();
+        let (message, line, column) = message_and_position(error);
+
+        assert!(message.contains("missing"), "{}", message);
+        assert_eq!(line, Some(2));
+        assert_eq!(column, Some(5));
+    }
+
+    #[test]
+    fn endless_loops_hit_the_operation_limit() {
+        let error = run_source("let i = 0;\nloop { i += 1; }", host()).unwrap_err();
+        let (message, _, _) = message_and_position(error);
+
+        assert!(message.contains("operations"), "{}", message);
+    }
+
+    #[test]
+    fn slow_host_calls_hit_the_time_limit() {
+        let host = Arc::new(FakeHost {
+            windows: vec![window(1)],
+            focus_delay: Duration::from_millis(50),
+            ..FakeHost::default()
+        });
+
+        let started = Instant::now();
+        let error = run_source("loop { focus(focused_window()); }", host).unwrap_err();
+        let (message, _, _) = message_and_position(error);
+
+        assert!(message.contains("time limit exceeded"), "{}", message);
+        assert!(started.elapsed() < TIME_LIMIT * 4);
+    }
+
+    #[test]
+    fn set_frame_moves_the_window_it_is_given() {
+        let host = host();
+        run_source(
+            "let w = windows()[1];\nset_frame(w, relative(screens()[0], 0.5, 0, 0.5, 1));",
+            host.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            *host.frames.lock().unwrap(),
+            vec![(
+                WindowRef {
+                    pid: 20,
+                    window_id: 2
+                },
+                frame(720.0, 25.0, 720.0, 875.0)
+            )]
+        );
+    }
+
+    #[test]
+    fn set_frame_reports_bad_input_as_errors() {
+        let sources = [
+            "set_frame(42, frame(0, 0, 10, 10));",
+            "set_frame(#{ pid: 1 }, frame(0, 0, 10, 10));",
+            "set_frame(focused_window(), 42);",
+            "set_frame(focused_window(), #{ position: #{ x: 0, y: 0 } });",
+            "set_frame(focused_window(), #{ position: #{ x: \"a\", y: 0 }, size: #{ width: 1, height: 1 } });",
+            "set_frame(focused_window(), frame(0, \"top\", 10, 10));",
+        ];
+
+        for source in sources {
+            let host = host();
+            let error = run_source(source, host.clone()).unwrap_err();
+            let (message, _, _) = message_and_position(error);
+
+            assert_ne!(message, "the script crashed", "{}", source);
+            assert!(host.frames.lock().unwrap().is_empty(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn layout_pairs_frames_with_windows_in_order() {
+        let frames = layout_source(
+            "fn layout(windows, screen) {\n    [relative(screen, 0, 0, 0.5, 1), relative(screen, 0.5, 0, 0.5, 1)]\n}",
+        )
+        .unwrap();
+
+        assert_eq!(
+            frames,
+            vec![
+                WindowFrame {
+                    window_id: 1,
+                    frame: frame(0.0, 25.0, 720.0, 875.0),
+                },
+                WindowFrame {
+                    window_id: 2,
+                    frame: frame(720.0, 25.0, 720.0, 875.0),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn layout_rejects_more_frames_than_windows() {
+        let error = layout_source(
+            "fn layout(windows, screen) {\n    let frames = [];\n    for i in 0..4 { frames.push(frame(i * 10, 0, 10, 10)); }\n    frames\n}",
+        )
+        .unwrap_err();
+        let (message, _, _) = message_and_position(error);
+
+        assert_eq!(message, "layout returned 4 frames for 3 windows");
+    }
+
+    #[test]
+    fn layout_rejects_frames_without_a_size_or_not_finite() {
+        for frame in [
+            "frame(0, 0, 0, 100)",
+            "frame(0, 0, 100, -1)",
+            "frame(0.0 / 0.0, 0, 100, 100)",
+        ] {
+            let source = format!("fn layout(windows, screen) {{ [{}] }}", frame);
+            let error = layout_source(&source).unwrap_err();
+            let (message, _, _) = message_and_position(error);
+
+            assert_eq!(message, "frame 0 has no size or is not finite", "{}", frame);
+        }
+    }
+
+    #[test]
+    fn layout_needs_the_layout_function() {
+        let error = layout_source("arrange();").unwrap_err();
+        let (message, _, _) = message_and_position(error);
+
+        assert!(
+            message.contains("fn layout(windows, screen)"),
+            "{}",
+            message
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+
+use super::{
+    focus_history::get_history,
+    fuzzy::{rank, Candidate, RankedMatch},
+    windows::{get_windows_on_screen, WindowFilter, WindowInfo},
+};
+
+/// Windows matching the query, best first.
+/// `app_priorities` boosts apps by bundle id or name, `0.5` ranks them 50% higher
+pub fn find_windows(
+    query: &str,
+    app_priorities: &HashMap<String, f64>,
+) -> Vec<RankedMatch<WindowInfo>> {
+    let windows = get_windows_on_screen(&WindowFilter {
+        role: Some(String::from("AXStandardWindow")),
+        include_minimized: true,
+        ..WindowFilter::default()
+    });
+    let history = get_history();
+
+    let candidates = windows
+        .into_iter()
+        .map(|window| {
+            let app_boost = [&window.bundle_id, &window.name]
+                .into_iter()
+                .flatten()
+                .find_map(|key| app_priorities.get(key))
+                .copied()
+                .unwrap_or(0.0);
+
+            Candidate {
+                // Titles are missing without the screen recording permission
+                title: window.title.clone().unwrap_or_default(),
+                app_name: window.name.clone().unwrap_or_default(),
+                recency: history.iter().position(|known| known.id == window.id),
+                app_boost,
+                item: window,
+            }
+        })
+        .collect();
+
+    rank(query, candidates)
+}
@@ -0,0 +1,68 @@
+use serde::Serialize;
+
+use crate::data::frame::Frame;
+
+use super::{
+    app_icons::{app_icon, bundle_path},
+    focus_history::get_history,
+    windows::{get_windows_on_screen, WindowFilter},
+};
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SwitcherItem {
+    pub window_id: i64,
+    pub pid: i64,
+    pub title: Option<String>,
+    pub app_name: Option<String>,
+    pub bundle_id: Option<String>,
+    pub bundle_path: Option<String>,
+    pub screen_index: Option<usize>,
+    pub frame: Option<Frame>,
+    pub is_minimized: bool,
+    /// PNG `data:` URL of the application icon
+    pub icon: Option<String>,
+}
+
+/// Standard windows of all applications, minimized ones included,
+/// ordered by focus history and then front to back
+pub fn switchable_windows(icon_size: u32) -> Vec<SwitcherItem> {
+    let mut windows = get_windows_on_screen(&WindowFilter {
+        role: Some(String::from("AXStandardWindow")),
+        include_minimized: true,
+        ..WindowFilter::default()
+    });
+
+    let history = get_history();
+    // Stable sort, windows missing from the history keep their stacking order
+    windows.sort_by_key(|window| {
+        history
+            .iter()
+            .position(|known| known.id == window.id)
+            .unwrap_or(usize::MAX)
+    });
+
+    windows
+        .into_iter()
+        .filter_map(|window| {
+            let (Some(window_id), Some(pid)) = (window.id, window.pid) else {
+                return None;
+            };
+
+            let bundle_path = bundle_path(pid);
+            let icon = app_icon(pid, bundle_path.as_deref(), icon_size);
+
+            Some(SwitcherItem {
+                window_id,
+                pid,
+                title: window.title,
+                app_name: window.name,
+                bundle_id: window.bundle_id,
+                bundle_path,
+                screen_index: window.screen_index,
+                frame: window.frame,
+                is_minimized: window.is_minimized,
+                icon,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,251 @@
+use std::sync::Mutex;
+
+use accessibility_sys::{AXIsProcessTrusted, AXUIElementRef};
+use core_foundation::base::{CFRelease, CFTypeRef};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{alignment::Alignment, error::SnapError, frame::Frame, window_info::WindowInfo};
+
+use super::{
+    accessibility_elements::{get_frame, get_window_from_id, set_frames_animated},
+    animation::{AnimationConfig, AnimationOutcome},
+    placement::{place, resolve_anchor, FRAME_TOLERANCE},
+};
+
+/// Number of transactions that can be undone
+const HISTORY_LIMIT: usize = 20;
+
+/// A window and the frame it should be moved to
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct WindowMove {
+    pub window: WindowInfo,
+    pub frame: Frame,
+    /// Side or corner of `frame` the window is pinned to when the app refuses the size,
+    /// inferred from the screen when missing
+    #[serde(default)]
+    pub anchor: Option<Alignment>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveStatus {
+    Moved,
+    /// The app limited the frame (minimum size, screen bounds)
+    Clamped,
+    /// The window is gone
+    NotFound,
+    PermissionDenied,
+    /// The window is there but couldn't be read or moved
+    Failed(SnapError),
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct MoveResult {
+    pub window_id: u32,
+    pub pid: i64,
+    pub status: MoveStatus,
+    /// Frame read back from the window, `None` when it couldn't be moved
+    pub frame: Option<Frame>,
+}
+
+/// Frames the windows had before each transaction, the last one is undone first
+static HISTORY: Lazy<Mutex<Vec<Vec<WindowMove>>>> = Lazy::new(|| Mutex::new(vec![]));
+
+struct ResolvedMove {
+    index: usize,
+    window: AXUIElementRef,
+    from: Frame,
+    to: Frame,
+    anchor: Option<Alignment>,
+}
+
+// The element was retained by `get_window_from_id`
+impl Drop for ResolvedMove {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.window as CFTypeRef) }
+    }
+}
+
+impl ResolvedMove {
+    /// Area the window takes from others, negative when it gives space up
+    fn growth(&self) -> f64 {
+        let area = |frame: &Frame| frame.size.width * frame.size.height;
+        area(&self.to) - area(&self.from)
+    }
+}
+
+/// Windows that give up space are moved before the ones that take it,
+/// so a growing window never covers one that is about to shrink away
+fn order_moves(moves: &mut [ResolvedMove]) {
+    moves.sort_by(|a, b| a.growth().total_cmp(&b.growth()));
+}
+
+/// Animates the windows together and waits until all of them are over
+fn animate(moves: &[ResolvedMove], config: AnimationConfig) -> Vec<Option<Frame>> {
+    if moves.is_empty() {
+        return vec![];
+    }
+    let targets = moves
+        .iter()
+        .map(|resolved| (resolved.window, resolved.to.clone(), resolved.anchor))
+        .collect();
+
+    set_frames_animated(targets, config)
+        .into_iter()
+        .map(
+            |completion| match completion.map(|completion| completion.recv()) {
+                Ok(Ok(AnimationOutcome::Finished(frame))) => Some(frame),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+fn status_for_error(error: &SnapError) -> MoveStatus {
+    match error {
+        SnapError::AccessibilityNotTrusted | SnapError::OwnWindowRefused => {
+            MoveStatus::PermissionDenied
+        }
+        SnapError::WindowNotFound { .. } => MoveStatus::NotFound,
+        error => MoveStatus::Failed(error.clone()),
+    }
+}
+
+/// Moves all windows as one unit and reports what happened to each of them.
+/// `config` animates the shrinking windows first and then the growing ones, `None`
+/// applies the frames instantly.
+/// Blocks until the animations are over, so call it off the main thread.
+pub fn apply(moves: Vec<WindowMove>, config: Option<AnimationConfig>) -> Vec<MoveResult> {
+    let results = run(&moves, config);
+
+    let previous: Vec<WindowMove> = results
+        .iter()
+        .zip(moves)
+        .filter(|((result, _), _)| matches!(result.status, MoveStatus::Moved | MoveStatus::Clamped))
+        .map(|((_, from), window_move)| WindowMove {
+            window: window_move.window,
+            frame: from.clone().unwrap_or(window_move.frame),
+            anchor: window_move.anchor,
+        })
+        .collect();
+
+    if !previous.is_empty() {
+        let mut history = HISTORY.lock().unwrap();
+        history.push(previous);
+        if history.len() > HISTORY_LIMIT {
+            history.remove(0);
+        }
+    }
+
+    results.into_iter().map(|(result, _)| result).collect()
+}
+
+/// Restores the frames from before the last transaction, `None` when there is nothing to undo
+pub fn undo(config: Option<AnimationConfig>) -> Option<Vec<MoveResult>> {
+    let previous = HISTORY.lock().unwrap().pop()?;
+
+    Some(
+        run(&previous, config)
+            .into_iter()
+            .map(|(result, _)| result)
+            .collect(),
+    )
+}
+
+/// Applies the moves and returns each result with the frame the window had before
+fn run(moves: &[WindowMove], config: Option<AnimationConfig>) -> Vec<(MoveResult, Option<Frame>)> {
+    let own_pid = std::process::id() as i64;
+    let trusted = unsafe { AXIsProcessTrusted() };
+
+    let mut results: Vec<(MoveResult, Option<Frame>)> = moves
+        .iter()
+        .map(|window_move| {
+            (
+                MoveResult {
+                    window_id: window_move.window.window_id,
+                    pid: window_move.window.pid,
+                    status: MoveStatus::NotFound,
+                    frame: None,
+                },
+                None,
+            )
+        })
+        .collect();
+
+    let mut resolved = vec![];
+    for (index, window_move) in moves.iter().enumerate() {
+        if !trusted || window_move.window.pid == own_pid {
+            results[index].0.status = MoveStatus::PermissionDenied;
+            continue;
+        }
+
+        let target = &window_move.window;
+        let window = match get_window_from_id(target.pid, target.window_id) {
+            Ok(window) => window,
+            Err(error) => {
+                results[index].0.status = status_for_error(&error);
+                continue;
+            }
+        };
+        let from = match get_frame(window) {
+            Ok(from) => from,
+            Err(error) => {
+                unsafe { CFRelease(window as CFTypeRef) };
+                results[index].0.status = status_for_error(&error);
+                continue;
+            }
+        };
+
+        results[index].1 = Some(from.clone());
+        resolved.push(ResolvedMove {
+            index,
+            window,
+            from,
+            to: window_move.frame.clone(),
+            anchor: window_move.anchor,
+        });
+    }
+
+    order_moves(&mut resolved);
+
+    let outcomes: Vec<Option<Frame>> = match config {
+        // Growing windows only start once the shrinking ones have made room
+        Some(config) => {
+            let first_growing = resolved
+                .iter()
+                .position(|resolved| resolved.growth() > 0.0)
+                .unwrap_or(resolved.len());
+            let (shrinking, growing) = resolved.split_at(first_growing);
+
+            let mut outcomes = animate(shrinking, config.clone());
+            outcomes.extend(animate(growing, config));
+            outcomes
+        }
+        None => resolved
+            .iter()
+            .map(|resolved| {
+                let anchor = resolve_anchor(&resolved.to, resolved.anchor);
+                place(resolved.window, &resolved.to, anchor)
+                    .ok()
+                    .map(|placement| placement.frame)
+            })
+            .collect(),
+    };
+
+    for (resolved, outcome) in resolved.iter().zip(outcomes) {
+        // An interrupted animation still reports where the window ended up
+        let actual = get_frame(resolved.window).or_else(|error| outcome.ok_or(error));
+        let status = match &actual {
+            Ok(actual) if actual.approx_eq(&resolved.to, FRAME_TOLERANCE) => MoveStatus::Moved,
+            Ok(_) => MoveStatus::Clamped,
+            Err(error) => status_for_error(error),
+        };
+
+        let result = &mut results[resolved.index].0;
+        result.status = status;
+        result.frame = actual.ok();
+    }
+
+    results
+}
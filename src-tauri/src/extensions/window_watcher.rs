@@ -0,0 +1,618 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use objc2_app_kit::NSWorkspace;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::data::frame::Frame;
+
+use super::windows::{get_windows_on_screen, WindowFilter, WindowInfo};
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct AppInfo {
+    pub pid: i64,
+    pub name: Option<String>,
+    pub bundle_id: Option<String>,
+}
+
+/// Windows and frontmost application at one point in time
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    pub windows: Vec<WindowInfo>,
+    pub frontmost: Option<AppInfo>,
+}
+
+impl Snapshot {
+    /// Main window of the frontmost application
+    pub fn focused(&self) -> Option<&WindowInfo> {
+        let pid = self.frontmost.as_ref()?.pid;
+        self.windows
+            .iter()
+            .find(|window| window.pid == Some(pid) && window.is_main)
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WindowEvent {
+    Created {
+        window: WindowInfo,
+    },
+    Destroyed {
+        window: WindowInfo,
+    },
+    /// `from` is the frame before the first move of a debounced burst
+    Moved {
+        window: WindowInfo,
+        from: Frame,
+    },
+    Resized {
+        window: WindowInfo,
+        from: Frame,
+    },
+    Focused {
+        window: WindowInfo,
+    },
+    AppActivated {
+        app: AppInfo,
+    },
+}
+
+impl WindowEvent {
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            WindowEvent::Created { .. } => "window_created",
+            WindowEvent::Destroyed { .. } => "window_destroyed",
+            WindowEvent::Moved { .. } => "window_moved",
+            WindowEvent::Resized { .. } => "window_resized",
+            WindowEvent::Focused { .. } => "window_focused",
+            WindowEvent::AppActivated { .. } => "app_activated",
+        }
+    }
+
+    pub fn window(&self) -> Option<&WindowInfo> {
+        match self {
+            WindowEvent::Created { window }
+            | WindowEvent::Destroyed { window }
+            | WindowEvent::Moved { window, .. }
+            | WindowEvent::Resized { window, .. }
+            | WindowEvent::Focused { window } => Some(window),
+            WindowEvent::AppActivated { .. } => None,
+        }
+    }
+
+    /// Folds a newer event into this pending one, returns `false` when they are unrelated
+    fn absorb(&mut self, event: &WindowEvent) -> bool {
+        match (self, event) {
+            // A window still settling after being created is reported once, where it ended up
+            (
+                WindowEvent::Created { window },
+                WindowEvent::Moved { window: next, .. } | WindowEvent::Resized { window: next, .. },
+            )
+            | (WindowEvent::Moved { window, .. }, WindowEvent::Moved { window: next, .. })
+            | (WindowEvent::Resized { window, .. }, WindowEvent::Resized { window: next, .. })
+                if window.id == next.id =>
+            {
+                *window = next.clone();
+                true
+            }
+            (WindowEvent::Focused { window }, WindowEvent::Focused { window: next }) => {
+                *window = next.clone();
+                true
+            }
+            (WindowEvent::AppActivated { app }, WindowEvent::AppActivated { app: next }) => {
+                *app = next.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Events that turn one snapshot into the next, windows are matched by id
+pub fn diff(previous: &Snapshot, next: &Snapshot) -> Vec<WindowEvent> {
+    let known: HashMap<i64, &WindowInfo> = previous
+        .windows
+        .iter()
+        .filter_map(|window| Some((window.id?, window)))
+        .collect();
+    let remaining: HashSet<i64> = next.windows.iter().filter_map(|window| window.id).collect();
+
+    let mut events = vec![];
+
+    for window in &next.windows {
+        let Some(before) = window.id.and_then(|id| known.get(&id)) else {
+            events.push(WindowEvent::Created {
+                window: window.clone(),
+            });
+            continue;
+        };
+
+        let (Some(from), Some(to)) = (&before.frame, &window.frame) else {
+            continue;
+        };
+        if from.position != to.position {
+            events.push(WindowEvent::Moved {
+                window: window.clone(),
+                from: from.clone(),
+            });
+        }
+        if from.size != to.size {
+            events.push(WindowEvent::Resized {
+                window: window.clone(),
+                from: from.clone(),
+            });
+        }
+    }
+
+    for window in &previous.windows {
+        if window.id.is_some_and(|id| !remaining.contains(&id)) {
+            events.push(WindowEvent::Destroyed {
+                window: window.clone(),
+            });
+        }
+    }
+
+    let pid = |snapshot: &Snapshot| snapshot.frontmost.as_ref().map(|app| app.pid);
+    if pid(previous) != pid(next) {
+        if let Some(app) = &next.frontmost {
+            events.push(WindowEvent::AppActivated { app: app.clone() });
+        }
+    }
+
+    let focused_id = |snapshot: &Snapshot| snapshot.focused().and_then(|window| window.id);
+    if focused_id(previous) != focused_id(next) {
+        if let Some(window) = next.focused() {
+            events.push(WindowEvent::Focused {
+                window: window.clone(),
+            });
+        }
+    }
+
+    events
+}
+
+struct PendingEvent {
+    event: WindowEvent,
+    updated_at: Instant,
+}
+
+/// Holds events back until their window stopped changing for `delay`,
+/// so dragging a window gives one `window_moved` instead of one per sample
+pub struct Debouncer {
+    delay: Duration,
+    pending: Vec<PendingEvent>,
+}
+
+impl Debouncer {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            pending: vec![],
+        }
+    }
+
+    pub fn push(&mut self, event: WindowEvent, now: Instant) {
+        if let WindowEvent::Destroyed { window } = &event {
+            let id = window.id;
+            let was_created = self.pending.iter().any(|pending| {
+                matches!(&pending.event, WindowEvent::Created { window } if window.id == id)
+            });
+            self.pending
+                .retain(|pending| pending.event.window().map(|window| window.id) != Some(id));
+
+            // Windows that came and went within the delay (menus, sheets) are never reported
+            if was_created {
+                return;
+            }
+        }
+
+        for pending in &mut self.pending {
+            if pending.event.absorb(&event) {
+                pending.updated_at = now;
+                return;
+            }
+        }
+
+        self.pending.push(PendingEvent {
+            event,
+            updated_at: now,
+        });
+    }
+
+    /// Events that have settled, in the order they were first seen
+    pub fn flush(&mut self, now: Instant) -> Vec<WindowEvent> {
+        let (ready, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|pending| now.duration_since(pending.updated_at) >= self.delay);
+        self.pending = pending;
+
+        ready
+            .into_iter()
+            .map(|pending: PendingEvent| pending.event)
+            .collect()
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct WatcherOptions {
+    /// How often the window list is sampled
+    pub interval_ms: u64,
+    /// How long a window has to stay unchanged before its events are emitted
+    pub debounce_ms: u64,
+}
+
+impl Default for WatcherOptions {
+    fn default() -> Self {
+        Self {
+            interval_ms: 250,
+            debounce_ms: 150,
+        }
+    }
+}
+
+type Subscriber = Arc<dyn Fn(&WindowEvent) + Send + Sync>;
+
+static SUBSCRIBERS: Lazy<Mutex<HashMap<&'static str, Subscriber>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Running flag of the sampling thread, cleared to stop it
+static RUNNING: Lazy<Mutex<Option<Arc<AtomicBool>>>> = Lazy::new(|| Mutex::new(None));
+
+/// Registers a listener for window events, replacing the one with the same name
+pub fn subscribe<F>(name: &'static str, listener: F)
+where
+    F: Fn(&WindowEvent) + Send + Sync + 'static,
+{
+    SUBSCRIBERS.lock().unwrap().insert(name, Arc::new(listener));
+}
+
+/// Returns `false` when nothing was subscribed under `name`
+pub fn unsubscribe(name: &'static str) -> bool {
+    SUBSCRIBERS.lock().unwrap().remove(name).is_some()
+}
+
+pub fn has_subscribers() -> bool {
+    !SUBSCRIBERS.lock().unwrap().is_empty()
+}
+
+fn notify(events: Vec<WindowEvent>) {
+    if events.is_empty() {
+        return;
+    }
+
+    let subscribers: Vec<Subscriber> = SUBSCRIBERS.lock().unwrap().values().cloned().collect();
+    for event in &events {
+        for subscriber in &subscribers {
+            subscriber(event);
+        }
+    }
+}
+
+/// Current windows, minimized ones included so minimizing doesn't look like closing.
+/// Windows on other spaces aren't listed, switching spaces reports them as destroyed and created.
+pub fn take_snapshot() -> Snapshot {
+    let windows = get_windows_on_screen(&WindowFilter {
+        include_minimized: true,
+        ..WindowFilter::default()
+    });
+
+    let frontmost =
+        unsafe { NSWorkspace::sharedWorkspace().frontmostApplication() }.map(|app| unsafe {
+            AppInfo {
+                pid: app.processIdentifier() as i64,
+                name: app.localizedName().map(|name| name.to_string()),
+                bundle_id: app
+                    .bundleIdentifier()
+                    .map(|bundle_id| bundle_id.to_string()),
+            }
+        });
+
+    Snapshot { windows, frontmost }
+}
+
+/// Starts sampling the window list, restarting the watcher if it already runs
+pub fn start(options: WatcherOptions) {
+    let running = Arc::new(AtomicBool::new(true));
+    if let Some(previous) = RUNNING.lock().unwrap().replace(running.clone()) {
+        previous.store(false, Ordering::SeqCst);
+    }
+
+    let interval = Duration::from_millis(options.interval_ms);
+    let mut debouncer = Debouncer::new(Duration::from_millis(options.debounce_ms));
+
+    thread::spawn(move || {
+        let mut previous = take_snapshot();
+
+        while running.load(Ordering::SeqCst) {
+            thread::sleep(interval);
+
+            let snapshot = take_snapshot();
+            let now = Instant::now();
+            for event in diff(&previous, &snapshot) {
+                debouncer.push(event, now);
+            }
+            previous = snapshot;
+
+            if running.load(Ordering::SeqCst) {
+                notify(debouncer.flush(now));
+            }
+        }
+    });
+}
+
+/// Returns `false` when the watcher wasn't running
+pub fn stop() -> bool {
+    match RUNNING.lock().unwrap().take() {
+        Some(running) => {
+            running.store(false, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::frame::{Point, Size};
+
+    fn window(id: i64, pid: i64, frame: (f64, f64, f64, f64)) -> WindowInfo {
+        WindowInfo {
+            id: Some(id),
+            pid: Some(pid),
+            name: Some(format!("App {}", pid)),
+            frame: Some(Frame {
+                position: Point {
+                    x: frame.0,
+                    y: frame.1,
+                },
+                size: Size {
+                    width: frame.2,
+                    height: frame.3,
+                },
+            }),
+            is_main: false,
+            title: None,
+            layer: 0,
+            alpha: 1.0,
+            bundle_id: None,
+            is_on_screen: true,
+            role: None,
+            subrole: None,
+            is_minimized: false,
+            is_fullscreen: false,
+            screen_index: Some(0),
+        }
+    }
+
+    fn main_window(id: i64, pid: i64, frame: (f64, f64, f64, f64)) -> WindowInfo {
+        WindowInfo {
+            is_main: true,
+            ..window(id, pid, frame)
+        }
+    }
+
+    fn app(pid: i64) -> AppInfo {
+        AppInfo {
+            pid,
+            name: Some(format!("App {}", pid)),
+            bundle_id: None,
+        }
+    }
+
+    fn snapshot(windows: Vec<WindowInfo>, frontmost: Option<i64>) -> Snapshot {
+        Snapshot {
+            windows,
+            frontmost: frontmost.map(app),
+        }
+    }
+
+    fn names(events: &[WindowEvent]) -> Vec<&'static str> {
+        events.iter().map(WindowEvent::event_name).collect()
+    }
+
+    #[test]
+    fn unchanged_snapshot_has_no_events() {
+        let current = snapshot(vec![main_window(1, 10, (0.0, 0.0, 800.0, 600.0))], Some(10));
+
+        assert!(diff(&current, &current.clone()).is_empty());
+    }
+
+    #[test]
+    fn created_and_destroyed_windows() {
+        let previous = snapshot(vec![window(1, 10, (0.0, 0.0, 800.0, 600.0))], None);
+        let next = snapshot(vec![window(2, 10, (0.0, 0.0, 800.0, 600.0))], None);
+
+        let events = diff(&previous, &next);
+
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], WindowEvent::Created { window } if window.id == Some(2)));
+        assert!(matches!(&events[1], WindowEvent::Destroyed { window } if window.id == Some(1)));
+    }
+
+    #[test]
+    fn move_and_resize_report_the_previous_frame() {
+        let previous = snapshot(vec![window(1, 10, (0.0, 0.0, 800.0, 600.0))], None);
+        let next = snapshot(vec![window(1, 10, (100.0, 50.0, 640.0, 480.0))], None);
+
+        let events = diff(&previous, &next);
+
+        assert_eq!(names(&events), vec!["window_moved", "window_resized"]);
+        for event in &events {
+            match event {
+                WindowEvent::Moved { from, .. } | WindowEvent::Resized { from, .. } => {
+                    assert_eq!(from, previous.windows[0].frame.as_ref().unwrap());
+                }
+                event => panic!("unexpected {:?}", event),
+            }
+        }
+    }
+
+    #[test]
+    fn frontmost_change_activates_app_and_focuses_its_main_window() {
+        let windows = vec![
+            main_window(1, 10, (0.0, 0.0, 800.0, 600.0)),
+            main_window(2, 20, (0.0, 0.0, 800.0, 600.0)),
+        ];
+        let previous = snapshot(windows.clone(), Some(10));
+        let next = snapshot(windows, Some(20));
+
+        let events = diff(&previous, &next);
+
+        assert_eq!(names(&events), vec!["app_activated", "window_focused"]);
+        assert!(matches!(&events[0], WindowEvent::AppActivated { app } if app.pid == 20));
+        assert!(matches!(&events[1], WindowEvent::Focused { window } if window.id == Some(2)));
+    }
+
+    #[test]
+    fn windows_without_id_or_frame_are_skipped() {
+        let mut untracked = window(1, 10, (0.0, 0.0, 800.0, 600.0));
+        untracked.frame = None;
+        let previous = snapshot(vec![untracked.clone()], None);
+        let next = snapshot(vec![window(1, 10, (100.0, 0.0, 800.0, 600.0))], None);
+
+        assert!(diff(&previous, &next).is_empty());
+    }
+
+    #[test]
+    fn debouncer_waits_until_the_window_settles() {
+        let start = Instant::now();
+        let delay = Duration::from_millis(150);
+        let mut debouncer = Debouncer::new(delay);
+        let from = window(1, 10, (0.0, 0.0, 800.0, 600.0)).frame.unwrap();
+
+        for (step, x) in [10.0, 20.0, 30.0].into_iter().enumerate() {
+            debouncer.push(
+                WindowEvent::Moved {
+                    window: window(1, 10, (x, 0.0, 800.0, 600.0)),
+                    from: from.clone(),
+                },
+                start + Duration::from_millis(step as u64 * 100),
+            );
+        }
+
+        // The last sample came at 200 ms, nothing has settled yet
+        assert!(debouncer
+            .flush(start + Duration::from_millis(300))
+            .is_empty());
+
+        let events = debouncer.flush(start + Duration::from_millis(350));
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            WindowEvent::Moved {
+                window,
+                from: first,
+            } => {
+                assert_eq!(window.frame.as_ref().unwrap().position.x, 30.0);
+                assert_eq!(*first, from);
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+        assert!(debouncer.flush(start + Duration::from_secs(1)).is_empty());
+    }
+
+    #[test]
+    fn debouncer_reports_a_created_window_where_it_settled() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(Duration::from_millis(150));
+        let created = window(1, 10, (0.0, 0.0, 800.0, 600.0));
+
+        debouncer.push(WindowEvent::Created { window: created }, start);
+        debouncer.push(
+            WindowEvent::Resized {
+                window: window(1, 10, (0.0, 0.0, 1024.0, 768.0)),
+                from: Frame::default(),
+            },
+            start + Duration::from_millis(50),
+        );
+
+        let events = debouncer.flush(start + Duration::from_millis(200));
+        assert_eq!(names(&events), vec!["window_created"]);
+        assert_eq!(
+            events[0]
+                .window()
+                .unwrap()
+                .frame
+                .as_ref()
+                .unwrap()
+                .size
+                .width,
+            1024.0
+        );
+    }
+
+    #[test]
+    fn debouncer_drops_windows_that_came_and_went() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(Duration::from_millis(150));
+        let sheet = window(1, 10, (0.0, 0.0, 300.0, 200.0));
+
+        debouncer.push(
+            WindowEvent::Created {
+                window: sheet.clone(),
+            },
+            start,
+        );
+        debouncer.push(
+            WindowEvent::Destroyed { window: sheet },
+            start + Duration::from_millis(50),
+        );
+
+        assert!(debouncer.flush(start + Duration::from_secs(1)).is_empty());
+    }
+
+    #[test]
+    fn debouncer_keeps_destroying_known_windows() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(Duration::from_millis(150));
+        let known = window(1, 10, (0.0, 0.0, 800.0, 600.0));
+
+        debouncer.push(
+            WindowEvent::Moved {
+                window: known.clone(),
+                from: Frame::default(),
+            },
+            start,
+        );
+        debouncer.push(
+            WindowEvent::Destroyed { window: known },
+            start + Duration::from_millis(50),
+        );
+
+        let events = debouncer.flush(start + Duration::from_millis(200));
+        assert_eq!(names(&events), vec!["window_destroyed"]);
+    }
+
+    #[test]
+    fn debouncer_keeps_unrelated_events_in_order() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(Duration::from_millis(150));
+
+        debouncer.push(WindowEvent::AppActivated { app: app(10) }, start);
+        debouncer.push(
+            WindowEvent::Moved {
+                window: window(1, 10, (10.0, 0.0, 800.0, 600.0)),
+                from: Frame::default(),
+            },
+            start,
+        );
+        debouncer.push(
+            WindowEvent::AppActivated { app: app(20) },
+            start + Duration::from_millis(10),
+        );
+
+        let events = debouncer.flush(start + Duration::from_millis(200));
+        assert_eq!(names(&events), vec!["app_activated", "window_moved"]);
+        assert!(matches!(&events[0], WindowEvent::AppActivated { app } if app.pid == 20));
+    }
+}
@@ -1,36 +1,158 @@
-use std::ptr;
+use std::collections::HashMap;
 
-use accessibility_sys::{kAXMainAttribute, AXUIElementCopyAttributeValue};
+use accessibility_sys::{
+    kAXMainAttribute, kAXMinimizedAttribute, kAXRoleAttribute, kAXSubroleAttribute, AXUIElementRef,
+};
 use core_foundation::{
     array::{CFArrayGetCount, CFArrayGetValueAtIndex},
-    base::{CFRelease, CFTypeRef, TCFType},
-    number::{CFBooleanGetValue, CFBooleanRef},
-    string::CFString,
+    base::{CFRelease, CFTypeRef},
 };
-use core_graphics::display::{
-    kCGNullWindowID, kCGWindowListExcludeDesktopElements, CFDictionaryRef,
-    CGWindowListCopyWindowInfo,
+use core_graphics::{
+    display::{
+        kCGNullWindowID, kCGWindowListExcludeDesktopElements, kCGWindowListOptionIncludingWindow,
+        kCGWindowListOptionOnScreenOnly, CFDictionaryRef, CGWindowListCopyWindowInfo,
+        CGWindowListOption,
+    },
+    window::CGWindowID,
 };
+use objc2_app_kit::NSRunningApplication;
 use objc2_foundation::NSProcessInfo;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    data::frame::Frame,
+    data::{
+        error::{SnapError, SnapResult},
+        frame::{Frame, Point, Size},
+    },
     extensions::accessibility_elements::{get_from_dict, DictEntryValue},
 };
 
-use super::accessibility_elements::get_window_from_id;
+use super::{
+    accessibility_elements::{
+        get_app_windows, get_bool_attribute, get_string_attribute, get_window_from_id,
+    },
+    screens,
+};
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct WindowInfo {
-    id: Option<i64>,
-    pid: Option<i64>,
-    name: Option<String>,
-    frame: Option<Frame>,
-    is_main: bool,
+    pub id: Option<i64>,
+    pub pid: Option<i64>,
+    /// Name of the owning application
+    pub name: Option<String>,
+    pub frame: Option<Frame>,
+    pub is_main: bool,
+    /// Window title, only available with the screen recording permission
+    pub title: Option<String>,
+    pub layer: i64,
+    pub alpha: f64,
+    pub bundle_id: Option<String>,
+    pub is_on_screen: bool,
+    pub role: Option<String>,
+    pub subrole: Option<String>,
+    pub is_minimized: bool,
+    pub is_fullscreen: bool,
+    /// Index of the screen the window center is on, same order as `nsscreen_get_screens`
+    pub screen_index: Option<usize>,
+}
+
+/// Query for `get_windows_on_screen`, every field narrows the result
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct WindowFilter {
+    /// CG frame of a screen, keeps windows whose center is on it
+    pub screen: Option<Frame>,
+    /// Application name as shown in the menu bar
+    pub app: Option<String>,
+    /// Process of the application, only that application is asked for its windows
+    pub pid: Option<i64>,
+    pub bundle_id: Option<String>,
+    pub min_size: Option<Size>,
+    /// Matches the AX role or subrole, e.g. `AXStandardWindow`
+    pub role: Option<String>,
+    /// Also list minimized windows, they are off screen otherwise
+    pub include_minimized: bool,
+}
+
+impl WindowFilter {
+    /// Rejects a screen or minimum size no window could match
+    pub fn validate(&self) -> SnapResult<()> {
+        if self
+            .screen
+            .as_ref()
+            .is_some_and(|screen| !screen.is_placeable())
+        {
+            return Err(SnapError::invalid_argument(
+                "filter.screen",
+                "has no size or is not finite",
+            ));
+        }
+        if let Some(min_size) = &self.min_size {
+            if !(min_size.width.is_finite() && min_size.height.is_finite()) {
+                return Err(SnapError::invalid_argument(
+                    "filter.min_size",
+                    "is not finite",
+                ));
+            }
+        }
+
+        Ok(())
+    }
 }
 
-pub fn get_windows_on_screen() -> Vec<WindowInfo> {
+/// Per-listing cache, every application is asked once for its bundle id and AX windows
+#[derive(Default)]
+struct AppCache {
+    bundle_ids: HashMap<i64, Option<String>>,
+    windows: HashMap<i64, Vec<(CGWindowID, AXUIElementRef)>>,
+}
+
+// Every element was retained by `get_app_windows`
+impl Drop for AppCache {
+    fn drop(&mut self) {
+        for (_, window) in self.windows.values().flatten() {
+            unsafe { CFRelease(*window as CFTypeRef) }
+        }
+    }
+}
+
+impl AppCache {
+    fn bundle_id(&mut self, pid: i64) -> Option<String> {
+        self.bundle_ids
+            .entry(pid)
+            .or_insert_with(|| unsafe {
+                NSRunningApplication::runningApplicationWithProcessIdentifier(pid as i32)
+                    .and_then(|app| app.bundleIdentifier())
+                    .map(|bundle_id| bundle_id.to_string())
+            })
+            .clone()
+    }
+
+    fn window(&mut self, pid: i64, window_id: CGWindowID) -> Option<AXUIElementRef> {
+        self.windows
+            .entry(pid)
+            .or_insert_with(|| get_app_windows(pid).unwrap_or_default())
+            .iter()
+            .find(|(id, _)| *id == window_id)
+            .map(|(_, window)| *window)
+    }
+}
+
+fn number(dic_ref: CFDictionaryRef, key: &str) -> Option<i64> {
+    match get_from_dict(dic_ref, key) {
+        DictEntryValue::_Number(value) => Some(value),
+        _ => None,
+    }
+}
+
+fn string(dic_ref: CFDictionaryRef, key: &str) -> Option<String> {
+    match get_from_dict(dic_ref, key) {
+        DictEntryValue::_String(value) => Some(value),
+        _ => None,
+    }
+}
+
+pub fn get_windows_on_screen(filter: &WindowFilter) -> Vec<WindowInfo> {
     let window_list_info =
         unsafe { CGWindowListCopyWindowInfo(kCGWindowListExcludeDesktopElements, kCGNullWindowID) };
 
@@ -39,68 +161,110 @@ pub fn get_windows_on_screen() -> Vec<WindowInfo> {
     let process_info = NSProcessInfo::processInfo();
     let pid: i64 = unsafe { process_info.processIdentifier() as i64 };
 
+    let screens = screens::frames();
+    let mut cache = AppCache::default();
     let mut windows: Vec<WindowInfo> = vec![];
 
     for i in 0..windows_count {
-        let mut win_pos = Frame::default();
-        let mut win_title = String::from("");
-
         let dic_ref = unsafe { CFArrayGetValueAtIndex(window_list_info, i) as CFDictionaryRef };
 
         if dic_ref.is_null() {
             continue;
         }
 
-        let window_pid = get_from_dict(dic_ref, "kCGWindowOwnerPID");
-        let is_on_screen = get_from_dict(dic_ref, "kCGWindowIsOnscreen");
-        let window_level = get_from_dict(dic_ref, "kCGWindowLayer");
+        let (Some(win_pid), Some(window_id), Some(layer)) = (
+            number(dic_ref, "kCGWindowOwnerPID"),
+            number(dic_ref, "kCGWindowNumber"),
+            number(dic_ref, "kCGWindowLayer"),
+        ) else {
+            continue;
+        };
+
+        if win_pid == pid || layer != 0 || filter.pid.is_some_and(|only| only != win_pid) {
+            continue;
+        }
 
-        if let DictEntryValue::_Number(win_pid) = window_pid {
-            if win_pid == pid {
-                continue;
-            }
+        let is_on_screen = matches!(
+            get_from_dict(dic_ref, "kCGWindowIsOnscreen"),
+            DictEntryValue::_Bool(true)
+        );
+        if !is_on_screen && !filter.include_minimized {
+            continue;
+        }
 
-            if let DictEntryValue::_Number(win_level) = window_level {
-                if win_level != 0 {
-                    continue;
-                }
-            } else {
+        let frame = match get_from_dict(dic_ref, "kCGWindowBounds") {
+            DictEntryValue::_Rect(window_bounds) => window_bounds,
+            _ => Frame::default(),
+        };
+        if let Some(min_size) = &filter.min_size {
+            if frame.size.width < min_size.width || frame.size.height < min_size.height {
                 continue;
             }
+        }
 
-            if let DictEntryValue::_Bool(is_on_screen) = is_on_screen {
-                if !is_on_screen {
-                    continue;
-                }
-            } else {
+        let screen_index = screens::containing(&frame, &screens);
+        if let Some(screen) = &filter.screen {
+            if screens::containing(&frame, std::slice::from_ref(screen)).is_none() {
                 continue;
             }
+        }
 
-            if let DictEntryValue::_Rect(window_bounds) = get_from_dict(dic_ref, "kCGWindowBounds")
-            {
-                win_pos = window_bounds;
-            }
+        let app_name = string(dic_ref, "kCGWindowOwnerName").unwrap_or_default();
+        if filter.app.as_ref().is_some_and(|app| *app != app_name) {
+            continue;
+        }
 
-            if let DictEntryValue::_String(window_title) =
-                get_from_dict(dic_ref, "kCGWindowOwnerName")
-            {
-                win_title = window_title;
-            }
+        let bundle_id = cache.bundle_id(win_pid);
+        if filter.bundle_id.is_some() && filter.bundle_id != bundle_id {
+            continue;
+        }
 
-            if let DictEntryValue::_Number(window_id) = get_from_dict(dic_ref, "kCGWindowNumber") {
-                let is_main = is_main_window(win_pid, window_id as u32);
+        // Accessibility is queried last, it is a round trip to every application
+        let window = cache.window(win_pid, window_id as CGWindowID);
+        let attribute = |name: &str| window.and_then(|window| get_string_attribute(window, name));
+        let flag = |name: &str| {
+            window
+                .and_then(|window| get_bool_attribute(window, name))
+                .unwrap_or(false)
+        };
 
-                let window_info = WindowInfo {
-                    pid: Some(win_pid),
-                    id: Some(window_id),
-                    name: Some(win_title),
-                    frame: Some(win_pos),
-                    is_main,
-                };
+        let is_minimized = flag(kAXMinimizedAttribute);
+        // Windows on other spaces are off screen too, only minimized ones are asked for
+        if !is_on_screen && !is_minimized {
+            continue;
+        }
 
-                windows.push(window_info);
+        let role = attribute(kAXRoleAttribute);
+        let subrole = attribute(kAXSubroleAttribute);
+        if let Some(expected) = &filter.role {
+            if role.as_ref() != Some(expected) && subrole.as_ref() != Some(expected) {
+                continue;
             }
         }
+
+        let alpha = match get_from_dict(dic_ref, "kCGWindowAlpha") {
+            DictEntryValue::_Float(alpha) => alpha,
+            DictEntryValue::_Number(alpha) => alpha as f64,
+            _ => 1.0,
+        };
+
+        windows.push(WindowInfo {
+            pid: Some(win_pid),
+            id: Some(window_id),
+            name: Some(app_name),
+            frame: Some(frame),
+            is_main: flag(kAXMainAttribute),
+            title: string(dic_ref, "kCGWindowName"),
+            layer,
+            alpha,
+            bundle_id,
+            is_on_screen,
+            role,
+            subrole,
+            is_minimized,
+            is_fullscreen: flag("AXFullScreen"),
+            screen_index,
+        });
     }
 
     unsafe { CFRelease(window_list_info as CFTypeRef) }
@@ -108,27 +272,68 @@ pub fn get_windows_on_screen() -> Vec<WindowInfo> {
     windows
 }
 
-pub fn is_main_window(window_pid: i64, window_id: u32) -> bool {
-    let mut is_main = false;
-    let mut value: CFTypeRef = ptr::null();
-
-    let win = get_window_from_id(window_pid, window_id);
-    if let Ok(window) = win {
-        unsafe {
-            AXUIElementCopyAttributeValue(
-                window,
-                CFString::new(kAXMainAttribute).as_concrete_TypeRef(),
-                &mut value as *mut CFTypeRef,
-            )
+/// Front most window of another application whose bounds contain `point`, returns
+/// its pid, id and CG frame. Only the window list is read, no application is asked
+pub fn window_at(point: &Point) -> Option<(i64, CGWindowID, Frame)> {
+    const OPTIONS: CGWindowListOption =
+        kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements;
+    let window_list_info = unsafe { CGWindowListCopyWindowInfo(OPTIONS, kCGNullWindowID) };
+    let windows_count: isize = unsafe { CFArrayGetCount(window_list_info) };
+
+    let process_info = NSProcessInfo::processInfo();
+    let pid: i64 = unsafe { process_info.processIdentifier() as i64 };
+
+    // The list goes from the front to the back
+    let found = (0..windows_count).find_map(|i| {
+        let dic_ref = unsafe { CFArrayGetValueAtIndex(window_list_info, i) as CFDictionaryRef };
+        if dic_ref.is_null() {
+            return None;
+        }
+
+        let (Some(win_pid), Some(window_id), Some(0)) = (
+            number(dic_ref, "kCGWindowOwnerPID"),
+            number(dic_ref, "kCGWindowNumber"),
+            number(dic_ref, "kCGWindowLayer"),
+        ) else {
+            return None;
         };
-        is_main = unsafe {
-            if !value.is_null() {
-                CFBooleanGetValue(value as CFBooleanRef)
-            } else {
-                false
-            }
+        let DictEntryValue::_Rect(frame) = get_from_dict(dic_ref, "kCGWindowBounds") else {
+            return None;
         };
-    }
+
+        let contains = point.x >= frame.position.x
+            && point.x < frame.position.x + frame.size.width
+            && point.y >= frame.position.y
+            && point.y < frame.position.y + frame.size.height;
+
+        (win_pid != pid && contains).then_some((win_pid, window_id as CGWindowID, frame))
+    });
+
+    unsafe { CFRelease(window_list_info as CFTypeRef) }
+
+    found
+}
+
+pub fn is_main_window(window_pid: i64, window_id: u32) -> bool {
+    let Ok(window) = get_window_from_id(window_pid, window_id) else {
+        return false;
+    };
+    let is_main = get_bool_attribute(window, kAXMainAttribute).unwrap_or(false);
+    unsafe { CFRelease(window as CFTypeRef) };
 
     is_main
 }
+
+/// Whether the window still exists, windows on other spaces included
+pub fn window_exists(window_id: CGWindowID) -> bool {
+    let window_list_info =
+        unsafe { CGWindowListCopyWindowInfo(kCGWindowListOptionIncludingWindow, window_id) };
+    if window_list_info.is_null() {
+        return false;
+    }
+
+    let count = unsafe { CFArrayGetCount(window_list_info) };
+    unsafe { CFRelease(window_list_info as CFTypeRef) }
+
+    count > 0
+}
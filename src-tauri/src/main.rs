@@ -1,10 +1,12 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use std::{sync::Arc, thread};
+
 use patch_window::overlay::patch_overlay_window;
 use tauri::{
     tray::{MouseButton, MouseButtonState, TrayIconEvent},
-    ActivationPolicy, Manager,
+    ActivationPolicy, Emitter, Manager, RunEvent, WindowEvent,
 };
 use tauri_plugin_nspopover::{AppExt, ToPopoverOptions, WindowExt};
 
@@ -18,16 +20,44 @@ use tauri_plugin_global_shortcut;
 use crate::commands::{
     accessibility_element::{
         accessibility_element_check_permission, accessibility_element_set_frame,
-        accessibility_element_set_frame_animated, accessibility_element_set_frame_instant,
-        accessibility_element_under_cursor,
+        accessibility_element_set_frame_animated, accessibility_element_set_frame_animated_await,
+        accessibility_element_set_frame_instant, accessibility_element_under_cursor,
+    },
+    animation::{
+        animation_active_count, animation_listen_events, animation_sample_easing,
+        animation_unlisten_events,
     },
+    caffeinate::{
+        caffeinate_configure_triggers, caffeinate_start, caffeinate_status, caffeinate_stop,
+    },
+    focus::{focus_mru, focus_mru_list, focus_previous_window},
     haptic_feedback::trigger_haptic_feedback,
-    nscolor::{nscolor_get_accent, nscolor_listen_accent},
-    nsevent::{
-        nsevent_add_global_monitor_for_events, nsevent_mouse_location, nsevent_remove_monitor,
+    hooks::{hooks_configure, hooks_run},
+    modifier_drag::modifier_drag_configure,
+    notification::notification_unsubscribe,
+    nscolor::{
+        nscolor_get_accent, nscolor_get_appearance, nscolor_get_palette, nscolor_listen_accent,
+        nscolor_listen_appearance, run_hooks_on_accent_change,
+    },
+    nsevent::{nsevent_add_monitor, nsevent_mouse_location, nsevent_remove_monitor},
+    nsscreen::{
+        nsscreen_get_screens, nsscreen_listen_change, nsscreen_main, run_hooks_on_screen_change,
     },
-    nsscreen::{nsscreen_get_screens, nsscreen_listen_change, nsscreen_main},
     nswindow::{cgwindow_get_on_screen, nswindow_set_frame},
+    plugins::{plugin_layout, plugins_dir, plugins_list, plugins_reload},
+    scripting::{script_check, script_configure, script_layout, script_run},
+    snapping::snapping_detect_throw,
+    window_finder::window_finder_search,
+    window_switcher::{window_switcher_focus, window_switcher_list},
+    window_transaction::{window_transaction_apply, window_transaction_undo},
+    window_watcher::{window_watcher_start, window_watcher_stop},
+};
+use crate::{
+    data::rpc::socket_path,
+    extensions::{
+        accessibility_elements, caffeinate, deep_link, event_monitor, focus_history, notification,
+        plugins, rpc_backend::AppBackend, rpc_server,
+    },
 };
 use tauri_plugin_autostart::MacosLauncher;
 
@@ -38,12 +68,40 @@ fn main() {
             None,
         ))
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_deep_link::init())
         .setup(|app| {
             app.set_activation_policy(ActivationPolicy::Accessory);
 
             let statusbar = app.get_webview_window("statusbar").unwrap();
             let overlay = app.get_webview_window("main").unwrap();
             patch_overlay_window(overlay);
+            // The first snapshot asks every application for its windows
+            thread::spawn(focus_history::start_tracking);
+
+            let backend = Arc::new(AppBackend::new(app.handle().clone()));
+            if let Err(error) = rpc_server::serve(&socket_path(), backend) {
+                eprintln!("control socket unavailable: {}", error);
+            }
+            deep_link::listen(app.handle());
+            run_hooks_on_accent_change();
+            run_hooks_on_screen_change();
+
+            let handle = app.handle().clone();
+            caffeinate::set_listener(move |status| {
+                let _ = handle.emit("caffeinate_state_changed", status);
+            });
+
+            // Compiling plugins can take a while, they are missing from arranges until then
+            match plugins_dir(app.handle()) {
+                Ok(dir) => {
+                    thread::spawn(move || {
+                        if let Err(error) = plugins::discover(&dir) {
+                            eprintln!("plugins unavailable: {}", error);
+                        }
+                    });
+                }
+                Err(error) => eprintln!("plugins unavailable: {}", error),
+            }
             statusbar.to_popover(ToPopoverOptions {
                 is_fullsize_content: false,
             });
@@ -74,24 +132,70 @@ fn main() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_nspopover::init())
+        .on_window_event(|window, event| {
+            if let WindowEvent::Destroyed = event {
+                notification::unsubscribe_owner(window.label());
+                accessibility_elements::remove_animation_listeners(window.label());
+                event_monitor::remove_owner(window.label());
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             nsscreen_get_screens,
             nsscreen_main,
             nsevent_mouse_location,
             nswindow_set_frame,
-            nsevent_add_global_monitor_for_events,
+            nsevent_add_monitor,
             nsevent_remove_monitor,
             nscolor_get_accent,
+            nscolor_get_palette,
+            nscolor_get_appearance,
             accessibility_element_under_cursor,
             accessibility_element_set_frame,
             accessibility_element_set_frame_animated,
+            accessibility_element_set_frame_animated_await,
             accessibility_element_set_frame_instant,
             accessibility_element_check_permission,
             trigger_haptic_feedback,
             nscolor_listen_accent,
+            nscolor_listen_appearance,
             nsscreen_listen_change,
-            cgwindow_get_on_screen
+            notification_unsubscribe,
+            cgwindow_get_on_screen,
+            snapping_detect_throw,
+            animation_sample_easing,
+            animation_listen_events,
+            animation_unlisten_events,
+            animation_active_count,
+            window_transaction_apply,
+            window_transaction_undo,
+            window_watcher_start,
+            window_watcher_stop,
+            focus_previous_window,
+            focus_mru,
+            focus_mru_list,
+            window_switcher_list,
+            window_switcher_focus,
+            window_finder_search,
+            hooks_configure,
+            hooks_run,
+            modifier_drag_configure,
+            script_configure,
+            script_check,
+            script_run,
+            script_layout,
+            plugins_list,
+            plugins_reload,
+            plugin_layout,
+            caffeinate_start,
+            caffeinate_stop,
+            caffeinate_status,
+            caffeinate_configure_triggers
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_, event| {
+            if let RunEvent::Exit = event {
+                caffeinate::release_all();
+            }
+        });
 }
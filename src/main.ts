@@ -26,6 +26,22 @@ themeStore.$accentColor.subscribe((color) => {
   document.body.attributeStyleMap.set("--color-accent", color);
 });
 
+themeStore.$appearance.subscribe((appearance) => {
+  if (!appearance) {
+    return;
+  }
+  const { mode, increase_contrast, palette } = appearance;
+
+  document.body.dataset.appearance = mode;
+  document.body.classList.toggle("increase-contrast", increase_contrast);
+  Object.entries(palette).forEach(([name, color]) => {
+    document.body.attributeStyleMap.set(
+      `--system-${name.replace(/_/g, "-")}`,
+      color.css,
+    );
+  });
+});
+
 document.body.classList.add(`env__${process.env.NODE_ENV}`);
 
 if (process.env.NODE_ENV === "production") {
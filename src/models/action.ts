@@ -0,0 +1,37 @@
+import { listen } from "@tauri-apps/api/event";
+import type { Frame } from "@/models/geometry/frame";
+
+export type WindowManagerMode = "snapping" | "fancy_zones";
+
+/**
+ * `split` uses hand-picked layouts, `grid` equal rows and columns, `script`
+ * the `layout` function of a user script and `plugin` a wasm plugin
+ */
+export type ArrangeStrategy =
+  | "split"
+  | "grid"
+  | { script: string }
+  | { plugin: string };
+
+/**
+ * Actions requested from outside the webview (`snapctl`, `snap://` links),
+ * handled by the stores of the overlay window
+ */
+export type SnapAction =
+  /**
+   * `frame` is relative to the screen, from 0 to 1
+   */
+  | { action: "mapping"; frame: Frame }
+  | { action: "arrange"; strategy: ArrangeStrategy }
+  | { action: "set_mode"; mode: WindowManagerMode }
+  /**
+   * With `minutes` caffeinate turns itself off after that long
+   */
+  | { action: "set_caffeinate"; enabled: boolean; minutes?: number }
+  | { action: "toggle_caffeinate" }
+  | { action: "run_script"; name: string }
+  | { action: "save_layout"; name: string }
+  | { action: "restore_layout"; name: string };
+
+export const listenSnapActions = (callback: (action: SnapAction) => void) =>
+  listen<SnapAction>("snap_action", ({ payload }) => callback(payload));
@@ -1,3 +1,39 @@
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
+import type { Frame, Position } from "@/models/geometry/frame";
+
+export type SpringEasing = {
+  stiffness: number;
+  damping: number;
+  /**
+   * @default 1
+   */
+  mass?: number;
+};
+
+/**
+ * Named curve, `cubic-bezier(x1, y1, x2, y2)` or a damped spring.
+ * Springs ignore `duration_ms`, they run until they settle.
+ */
+export type Easing =
+  | "linear"
+  | "ease"
+  | "ease-in"
+  | "ease-out"
+  | "ease-in-out"
+  | "ease-out-cubic"
+  | "ease-in-out-quad"
+  | `cubic-bezier(${number}, ${number}, ${number}, ${number})`
+  | SpringEasing;
+
+/**
+ * What happens when a window gets a new target while it is still animating:
+ * - `replace` retargets the running animation keeping its velocity
+ * - `append` runs after the animations already scheduled for the window
+ * - `ignore_while_running` drops the new target
+ */
+export type QueuePolicy = "replace" | "append" | "ignore_while_running";
+
 export interface AnimationOptions {
   /**
    * Duration of the animation in milliseconds
@@ -10,9 +46,106 @@ export interface AnimationOptions {
    * @default 60
    */
   fps?: number;
+
+  /**
+   * Easing curve of the animation
+   * @default "ease-in-out-quad"
+   */
+  easing?: Easing;
+
+  /**
+   * @default "replace"
+   */
+  queue_policy?: QueuePolicy;
 }
 
+export type EasingPreview = {
+  duration_ms: number;
+  /**
+   * `x` is the animation progress, `y` the eased value
+   */
+  points: Position[];
+};
+
+export const EASING_PRESETS: Record<string, Easing> = {
+  linear: "linear",
+  "ease-out": "ease-out",
+  "ease-in-out": "ease-in-out-quad",
+  spring: { stiffness: 170, damping: 26 },
+};
+
+export const sampleEasing = (
+  easing: Easing,
+  samples?: number,
+  durationMs?: number,
+): Promise<EasingPreview> =>
+  invoke("animation_sample_easing", {
+    easing,
+    samples: samples ?? null,
+    durationMs: durationMs ?? null,
+  });
+
 export const DEFAULT_ANIMATION_OPTIONS: AnimationOptions = {
   duration_ms: 200,
   fps: 120,
+  easing: "ease-in-out-quad",
+};
+
+export type AnimationEventName =
+  | "animation_started"
+  | "animation_finished"
+  | "animation_cancelled";
+
+export type AnimationEvent = {
+  window_id: number;
+  /**
+   * Target for `animation_started`, the reached frame for `animation_finished`
+   * and the frame at interruption for `animation_cancelled`
+   */
+  frame: Frame;
+};
+
+export type AnimationResult = {
+  status: "finished" | "cancelled" | "ignored";
+  /**
+   * Frame read back from the window after the animation
+   */
+  frame: Frame;
+  /**
+   * The app didn't accept the requested frame (minimum size, character grid)
+   * and the window was re-aligned inside it
+   */
+  clamped: boolean;
 };
+
+const EVENT_NAMES: AnimationEventName[] = [
+  "animation_started",
+  "animation_finished",
+  "animation_cancelled",
+];
+
+/**
+ * Listens for the animation events on this webview, the returned function
+ * stops listening
+ */
+export const listenAnimationEvents = async (
+  callback: (name: AnimationEventName, event: AnimationEvent) => void,
+) => {
+  const id = await invoke<number>("animation_listen_events");
+  const webview = getCurrentWebviewWindow();
+  const listeners = await Promise.all(
+    EVENT_NAMES.map((name) =>
+      webview.listen<AnimationEvent>(name, ({ payload }) =>
+        callback(name, payload),
+      ),
+    ),
+  );
+
+  return () => {
+    listeners.forEach((unlisten) => unlisten());
+    invoke("animation_unlisten_events", { id });
+  };
+};
+
+export const getActiveAnimationCount = (): Promise<number> =>
+  invoke("animation_active_count");
@@ -0,0 +1,116 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+
+/**
+ * `display` keeps the screen on, `system` only keeps the Mac from sleeping
+ */
+export type SleepKind = "display" | "system";
+
+export type CaffeinateRequest = {
+  kind?: SleepKind;
+  /**
+   * Ends the session after this long
+   */
+  minutes?: number;
+  /**
+   * Ends the session once this process exits
+   */
+  until_pid?: number;
+};
+
+export type Weekday =
+  | "monday"
+  | "tuesday"
+  | "wednesday"
+  | "thursday"
+  | "friday"
+  | "saturday"
+  | "sunday";
+
+/**
+ * `app` is a bundle id or an application name, `start` and `end` are `HH:MM`
+ * in local time, an `end` before `start` runs past midnight
+ */
+export type CaffeinateTrigger =
+  | { trigger: "app_running"; app: string }
+  | { trigger: "app_frontmost"; app: string }
+  | { trigger: "on_ac_power" }
+  | { trigger: "schedule"; weekdays: Weekday[]; start: string; end: string };
+
+export type TriggersConfig = {
+  triggers: CaffeinateTrigger[];
+  kind: SleepKind;
+};
+
+export type CaffeinateReason =
+  | { reason: "manual" }
+  | { reason: "timer" }
+  | { reason: "until_process"; pid: number }
+  | { reason: "app_running"; app: string }
+  | { reason: "app_frontmost"; app: string }
+  | { reason: "on_ac_power" }
+  | { reason: "schedule" };
+
+export type CaffeinateStatus = {
+  active: boolean;
+  kind: SleepKind | null;
+  reason: CaffeinateReason | null;
+  /**
+   * Unix time in milliseconds
+   */
+  ends_at_ms: number | null;
+  /**
+   * Seconds left when the status was taken
+   */
+  remaining_seconds: number | null;
+  until_pid: number | null;
+};
+
+/**
+ * Replaces the running session, if any
+ */
+export const startCaffeinate = (
+  request: CaffeinateRequest = {},
+): Promise<CaffeinateStatus> => invoke("caffeinate_start", { request });
+
+export const stopCaffeinate = (): Promise<CaffeinateStatus> =>
+  invoke("caffeinate_stop");
+
+export const getCaffeinateStatus = (): Promise<CaffeinateStatus> =>
+  invoke("caffeinate_status");
+
+/**
+ * Triggers switch caffeinate on while they match, an empty list turns them off
+ */
+export const configureCaffeinateTriggers = (
+  config: TriggersConfig,
+): Promise<void> => invoke("caffeinate_configure_triggers", { config });
+
+export const describeCaffeinateReason = (reason: CaffeinateReason) => {
+  switch (reason.reason) {
+    case "manual":
+      return "Turned on by hand";
+    case "timer":
+      return "Timer";
+    case "until_process":
+      return `Until process ${reason.pid} exits`;
+    case "app_running":
+      return `${reason.app} is running`;
+    case "app_frontmost":
+      return `${reason.app} is in front`;
+    case "on_ac_power":
+      return "On power adapter";
+    case "schedule":
+      return "Scheduled";
+  }
+};
+
+/**
+ * Fires on every change, including sessions ending on their own
+ */
+export const listenCaffeinateState = (
+  callback: (status: CaffeinateStatus) => void,
+) =>
+  listen<CaffeinateStatus>("caffeinate_state_changed", ({ payload }) =>
+    callback(payload),
+  );
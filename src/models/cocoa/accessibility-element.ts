@@ -1,6 +1,10 @@
 import { invoke } from "@tauri-apps/api/core";
 import { Frame } from "@/models/geometry/frame";
-import { type AnimationOptions } from "@/models/animation";
+import type { Alignment } from "@/models/geometry/alignment";
+import {
+  type AnimationOptions,
+  type AnimationResult,
+} from "@/models/animation";
 import * as settingsStore from "@/stores/settings";
 
 export class AccessibilityElement {
@@ -28,22 +32,31 @@ export class AccessibilityElement {
     return accessibilityElement;
   }
 
+  /**
+   * Snaps the window to `frame`, runs the `window_snapped` hooks once the
+   * window got there
+   */
   async setFrame(frame: Frame): Promise<void> {
     const animationOptions = settingsStore.getAnimationOptions();
 
     if (animationOptions) {
-      await this.setFrameAnimated(frame, animationOptions);
+      await this.setFrameAnimated(frame, animationOptions, undefined, true);
     } else {
-      await this.setFrameInstant(frame);
+      await this.setFrameInstant(frame, undefined, true);
     }
   }
 
   /**
-   * Set window frame with animation
+   * Set window frame with animation.
+   * `anchor` pins the window to a side or corner of `frame` when the app
+   * refuses the size, by default it follows where the frame is on the screen.
+   * `snapped` runs the `window_snapped` hooks once the animation finished
    */
   async setFrameAnimated(
     frame: Frame,
     animationOptions?: AnimationOptions,
+    anchor?: Alignment,
+    snapped = false,
   ): Promise<void> {
     await invoke("accessibility_element_set_frame_animated", {
       windowInfo: {
@@ -52,19 +65,61 @@ export class AccessibilityElement {
         frame: frame,
       },
       animationOptions: animationOptions || null,
+      anchor: anchor ?? null,
+      snapped,
     });
   }
 
   /**
-   * Set window frame instantly without animation
+   * Set window frame with animation, resolves once the animation is over
+   * with the frame the window actually ended up with
    */
-  async setFrameInstant(frame: Frame): Promise<void> {
-    await invoke("accessibility_element_set_frame_instant", {
-      windowInfo: {
-        window_id: this.windowId,
-        pid: this.pid,
-        frame: frame,
+  async setFrameAnimatedAwait(
+    frame: Frame,
+    animationOptions?: AnimationOptions,
+    anchor?: Alignment,
+  ): Promise<AnimationResult> {
+    const result: AnimationResult = await invoke(
+      "accessibility_element_set_frame_animated_await",
+      {
+        windowInfo: {
+          window_id: this.windowId,
+          pid: this.pid,
+          frame: frame,
+        },
+        animationOptions: animationOptions || null,
+        anchor: anchor ?? null,
       },
-    });
+    );
+
+    return {
+      ...result,
+      frame: new Frame(result.frame.size, result.frame.position),
+    };
+  }
+
+  /**
+   * Set window frame instantly without animation,
+   * resolves with the frame the window actually ended up with
+   */
+  async setFrameInstant(
+    frame: Frame,
+    anchor?: Alignment,
+    snapped = false,
+  ): Promise<Frame> {
+    const result: Frame = await invoke(
+      "accessibility_element_set_frame_instant",
+      {
+        windowInfo: {
+          window_id: this.windowId,
+          pid: this.pid,
+          frame: frame,
+        },
+        anchor: anchor ?? null,
+        snapped,
+      },
+    );
+
+    return new Frame(result.size, result.position);
   }
 }
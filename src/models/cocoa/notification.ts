@@ -0,0 +1,30 @@
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
+
+/**
+ * Stops a subscription made by one of the `*_listen_*` commands. Subscriptions
+ * of a webview also end when it closes
+ */
+export const unsubscribeNotification = (id: number): Promise<boolean> =>
+  invoke("notification_unsubscribe", { id });
+
+/**
+ * Subscribes with `command` and listens for `event` on this webview only,
+ * the returned function undoes both
+ */
+export const subscribeNotification = async <T>(
+  command: string,
+  event: string,
+  callback: (payload: T) => void,
+) => {
+  const id = await invoke<number>(command);
+  const unlisten = await getCurrentWebviewWindow().listen<T>(
+    event,
+    ({ payload }) => callback(payload),
+  );
+
+  return () => {
+    unlisten();
+    unsubscribeNotification(id);
+  };
+};
@@ -1,24 +1,77 @@
 import { invoke } from "@tauri-apps/api/core";
-import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import { type UnlistenFn } from "@tauri-apps/api/event";
+import { subscribeNotification } from "./notification";
+
+/**
+ * sRGB with whole channels, `hsl` is in degrees and percent
+ */
+export type Color = {
+  red: number;
+  green: number;
+  blue: number;
+  alpha: number;
+  hex: string;
+  hsl: { hue: number; saturation: number; lightness: number };
+  css: string;
+};
+
+/**
+ * Semantic system colors, resolved for the current appearance
+ */
+export type Palette = {
+  accent: Color;
+  highlight: Color;
+  label: Color;
+  separator: Color;
+  window_background: Color;
+  selected_content: Color;
+};
+
+export type Appearance = {
+  mode: "light" | "dark";
+  increase_contrast: boolean;
+  palette: Palette;
+};
 
 export class NSColor {
-  color!: string;
+  static getAccentColor(): Promise<Color> {
+    return invoke("nscolor_get_accent");
+  }
 
-  static async getAccentColor(): Promise<NSColor> {
-    const payload = await invoke("nscolor_get_accent");
+  static getPalette(): Promise<Palette> {
+    return invoke("nscolor_get_palette");
+  }
 
-    return { color: String(payload) };
+  static getAppearance(): Promise<Appearance> {
+    return invoke("nscolor_get_appearance");
   }
 
   private static _listener: UnlistenFn | null = null;
-  static async listenChange(callback: (color: NSColor) => void) {
+  static async listenChange(callback: (color: Color) => void) {
     if (this._listener) {
       this._listener();
     }
 
-    invoke("nscolor_listen_accent");
-    this._listener = await listen("nscolor_change", ({ payload }) => {
-      callback({ color: String(payload) });
-    });
+    this._listener = await subscribeNotification(
+      "nscolor_listen_accent",
+      "nscolor_change",
+      callback,
+    );
+  }
+
+  private static _appearanceListener: UnlistenFn | null = null;
+  /**
+   * Dark mode, increase contrast, the accent and the highlight color
+   */
+  static async listenAppearance(callback: (appearance: Appearance) => void) {
+    if (this._appearanceListener) {
+      this._appearanceListener();
+    }
+
+    this._appearanceListener = await subscribeNotification(
+      "nscolor_listen_appearance",
+      "appearance_change",
+      callback,
+    );
   }
 }
@@ -1,148 +1,174 @@
 import type { Position } from "@/models/geometry/frame";
 import { invoke } from "@tauri-apps/api/core";
-import { listen } from "@tauri-apps/api/event";
+import { type UnlistenFn } from "@tauri-apps/api/event";
+import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
 
-export enum NSEventType {
-  leftMouseDownMask = "NSEventType.leftMouseDownMask",
-  leftMouseUpMask = "NSEventType.leftMouseUpMask",
-  rightMouseDownMask = "NSEventType.rightMouseDownMask",
-  rightMouseUpMask = "NSEventType.rightMouseUpMask",
-  mouseMovedMask = "NSEventType.mouseMovedMask",
-  leftMouseDraggedMask = "NSEventType.leftMouseDraggedMask",
-  rightMouseDraggedMask = "NSEventType.rightMouseDraggedMask",
-  mouseEnteredMask = "NSEventType.mouseEnteredMask",
-  mouseExitedMask = "NSEventType.mouseExitedMask",
-  keyDownMask = "NSEventType.keyDownMask",
-  keyUpMask = "NSEventType.keyUpMask",
-  flagsChangedMask = "NSEventType.flagsChangedMask",
-  appKitDefinedMask = "NSEventType.appKitDefinedMask",
-  systemDefinedMask = "NSEventType.systemDefinedMask",
-  applicationDefinedMask = "NSEventType.applicationDefinedMask",
-  periodicMask = "NSEventType.periodicMask",
-  cursorUpdateMask = "NSEventType.cursorUpdateMask",
-  scrollWheelMask = "NSEventType.scrollWheelMask",
-  tabletPointMask = "NSEventType.tabletPointMask",
-  tabletProximityMask = "NSEventType.tabletProximityMask",
-  otherMouseDownMask = "NSEventType.otherMouseDownMask",
-  otherMouseUpMask = "NSEventType.otherMouseUpMask",
-  otherMouseDraggedMask = "NSEventType.otherMouseDraggedMask",
-  eventMaskGesture = "NSEventType.eventMaskGesture",
-  eventMaskSwipe = "NSEventType.eventMaskSwipe",
-  eventMaskRotate = "NSEventType.eventMaskRotate",
-  eventMaskBeginGesture = "NSEventType.eventMaskBeginGesture",
-  eventMaskEndGesture = "NSEventType.eventMaskEndGesture",
-  eventMaskPressure = "NSEventType.eventMaskPressure",
-  anyEventMask = "NSEventType.anyEventMask",
-}
+/**
+ * Event types a monitor can subscribe to
+ */
+export type NSEventKind =
+  | "left_mouse_down"
+  | "left_mouse_up"
+  | "left_mouse_dragged"
+  | "right_mouse_down"
+  | "right_mouse_up"
+  | "right_mouse_dragged"
+  | "other_mouse_down"
+  | "other_mouse_up"
+  | "other_mouse_dragged"
+  | "mouse_moved"
+  | "scroll_wheel"
+  | "key_down"
+  | "key_up"
+  | "flags_changed";
+
+export type MonitorOptions = {
+  /**
+   * At most one move or drag per interval, clicks, keys and scrolls always go
+   * out
+   */
+  throttleMs?: number;
+  /**
+   * Send the latest held back move or drag once the interval is over instead
+   * of dropping it, on by default
+   */
+  coalesce?: boolean;
+};
+
+export type Modifiers = {
+  shift: boolean;
+  control: boolean;
+  option: boolean;
+  command: boolean;
+  caps_lock: boolean;
+  function: boolean;
+};
+
+/**
+ * Where a trackpad or Magic Mouse gesture is, plain wheels are always `none`
+ */
+export type ScrollPhase =
+  | "none"
+  | "may_begin"
+  | "began"
+  | "stationary"
+  | "changed"
+  | "ended"
+  | "cancelled";
+
+export type ScrollDelta = {
+  delta_x: number;
+  delta_y: number;
+  /**
+   * Points from a trackpad, lines from a plain wheel otherwise
+   */
+  precise: boolean;
+  phase: ScrollPhase;
+  momentum_phase: ScrollPhase;
+};
 
-const _maskToValue = (mask: NSEventType): number =>
-  ({
-    [NSEventType.leftMouseDownMask]: 1 << 1,
-    [NSEventType.leftMouseUpMask]: 1 << 2,
-    [NSEventType.rightMouseDownMask]: 1 << 3,
-    [NSEventType.rightMouseUpMask]: 1 << 4,
-    [NSEventType.mouseMovedMask]: 1 << 5,
-    [NSEventType.leftMouseDraggedMask]: 1 << 6,
-    [NSEventType.rightMouseDraggedMask]: 1 << 7,
-    [NSEventType.mouseEnteredMask]: 1 << 8,
-    [NSEventType.mouseExitedMask]: 1 << 9,
-    [NSEventType.keyDownMask]: 1 << 10,
-    [NSEventType.keyUpMask]: 1 << 11,
-    [NSEventType.flagsChangedMask]: 1 << 12,
-    [NSEventType.appKitDefinedMask]: 1 << 13,
-    [NSEventType.systemDefinedMask]: 1 << 14,
-    [NSEventType.applicationDefinedMask]: 1 << 15,
-    [NSEventType.periodicMask]: 1 << 16,
-    [NSEventType.cursorUpdateMask]: 1 << 17,
-    [NSEventType.scrollWheelMask]: 1 << 22,
-    [NSEventType.tabletPointMask]: 1 << 23,
-    [NSEventType.tabletProximityMask]: 1 << 24,
-    [NSEventType.otherMouseDownMask]: 1 << 25,
-    [NSEventType.otherMouseUpMask]: 1 << 26,
-    [NSEventType.otherMouseDraggedMask]: 1 << 27,
-    [NSEventType.eventMaskGesture]: 1 << 29,
-    [NSEventType.eventMaskSwipe]: 1 << 31,
-    [NSEventType.eventMaskRotate]: 1 << 18,
-    [NSEventType.eventMaskBeginGesture]: 1 << 19,
-    [NSEventType.eventMaskEndGesture]: 1 << 20,
-    [NSEventType.eventMaskPressure]: 1 << 34,
-    [NSEventType.anyEventMask]: 0xffffffffffffffff,
-  })[mask];
+export type KeyInfo = {
+  key_code: number;
+  characters: string | null;
+  characters_ignoring_modifiers: string | null;
+  is_repeat: boolean;
+};
 
-const _valueToMask = (value: number): NSEventType =>
-  ({
-    1: NSEventType.leftMouseDownMask,
-    2: NSEventType.leftMouseUpMask,
-    3: NSEventType.rightMouseDownMask,
-    4: NSEventType.rightMouseUpMask,
-    5: NSEventType.mouseMovedMask,
-    6: NSEventType.leftMouseDraggedMask,
-    7: NSEventType.rightMouseDraggedMask,
-    8: NSEventType.mouseEnteredMask,
-    9: NSEventType.mouseExitedMask,
-    10: NSEventType.keyDownMask,
-    11: NSEventType.keyUpMask,
-    12: NSEventType.flagsChangedMask,
-    13: NSEventType.appKitDefinedMask,
-    14: NSEventType.systemDefinedMask,
-    15: NSEventType.applicationDefinedMask,
-    16: NSEventType.periodicMask,
-    17: NSEventType.cursorUpdateMask,
-    22: NSEventType.scrollWheelMask,
-    23: NSEventType.tabletPointMask,
-    24: NSEventType.tabletProximityMask,
-    25: NSEventType.otherMouseDownMask,
-    26: NSEventType.otherMouseUpMask,
-    27: NSEventType.otherMouseDraggedMask,
-    29: NSEventType.eventMaskGesture,
-    31: NSEventType.eventMaskSwipe,
-    18: NSEventType.eventMaskRotate,
-    19: NSEventType.eventMaskBeginGesture,
-    20: NSEventType.eventMaskEndGesture,
-    34: NSEventType.eventMaskPressure,
-    0xffffffffffffffff: NSEventType.anyEventMask,
-  })[value]!;
+type MonitorPayload = {
+  monitor_id: string;
+  point: Position;
+  event_type: NSEventKind;
+  modifiers: Modifiers;
+  button: number | null;
+  click_count: number | null;
+  scroll: ScrollDelta | null;
+  key: KeyInfo | null;
+  timestamp: number;
+};
+
+const CONTINUOUS: NSEventKind[] = [
+  "mouse_moved",
+  "left_mouse_dragged",
+  "right_mouse_dragged",
+  "other_mouse_dragged",
+];
 
 export class NSEvent {
   position!: Position;
-  type!: NSEventType;
+  type!: NSEventKind;
+  modifiers!: Modifiers;
+  /**
+   * 0 is the left button, 1 the right one, set for clicks and drags
+   */
+  button!: number | null;
+  /**
+   * Set for mouse down and up events
+   */
+  clickCount!: number | null;
+  scroll!: ScrollDelta | null;
+  key!: KeyInfo | null;
+  /**
+   * Seconds since the system started
+   */
+  timestamp!: number;
+
+  private static _unlisteners = new Map<string, UnlistenFn>();
 
+  /**
+   * Events reach this webview only, the monitor is removed when it closes
+   */
   static async addGlobalMonitor(
-    masks: NSEventType[],
+    events: NSEventKind[],
     handler: (event: NSEvent) => void,
+    { throttleMs, coalesce = true }: MonitorOptions = {},
   ): Promise<string> {
-    let mask = 0;
     let monitorId: string;
-    for (const m of masks) {
-      mask = mask | _maskToValue(m)!;
-    }
     let prevEvent: NSEvent | null = null;
-    listen("nsevent_on_event", ({ payload }) => {
-      if ((payload as any).monitor_id !== monitorId) {
-        return;
-      }
-      let event: NSEvent = {
-        type: _valueToMask((payload as any).event_type),
-        position: (payload as any).point,
-      };
-      const isSameEvent =
-        event.type === prevEvent?.type &&
-        event.position.x === prevEvent?.position.x &&
-        event.position.y === prevEvent?.position.y;
+    const unlisten = await getCurrentWebviewWindow().listen<MonitorPayload>(
+      "nsevent_on_event",
+      ({ payload }) => {
+        if (payload.monitor_id !== monitorId) {
+          return;
+        }
+        const event: NSEvent = {
+          type: payload.event_type,
+          position: payload.point,
+          modifiers: payload.modifiers,
+          button: payload.button,
+          clickCount: payload.click_count,
+          scroll: payload.scroll,
+          key: payload.key,
+          timestamp: payload.timestamp,
+        };
+        // Moves to the same point carry nothing new
+        const isSameEvent =
+          CONTINUOUS.includes(event.type) &&
+          event.type === prevEvent?.type &&
+          event.position.x === prevEvent?.position.x &&
+          event.position.y === prevEvent?.position.y;
 
-      if (!isSameEvent) {
-        handler(event);
-      }
-      prevEvent = event;
-    });
-    monitorId = await invoke("nsevent_add_global_monitor_for_events", {
-      mask: mask,
-    });
+        if (!isSameEvent) {
+          handler(event);
+        }
+        prevEvent = event;
+      },
+    );
+    try {
+      monitorId = await invoke("nsevent_add_monitor", {
+        options: { events, throttle_ms: throttleMs ?? null, coalesce },
+      });
+    } catch (error) {
+      unlisten();
+      throw error;
+    }
+    this._unlisteners.set(monitorId, unlisten);
 
     return String(monitorId);
   }
 
   static async removeMonitor(id: string) {
+    this._unlisteners.get(id)?.();
+    this._unlisteners.delete(id);
     await invoke("nsevent_remove_monitor", { id });
   }
 }
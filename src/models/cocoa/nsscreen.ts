@@ -1,6 +1,7 @@
 import { Frame, Position } from "@/models/geometry/frame";
 import { invoke } from "@tauri-apps/api/core";
-import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import { type UnlistenFn } from "@tauri-apps/api/event";
+import { subscribeNotification } from "./notification";
 
 export class NSScreen {
   constructor(
@@ -21,10 +22,11 @@ export class NSScreen {
       this._listener();
     }
 
-    invoke("nsscreen_listen_change");
-    this._listener = await listen("nsscreen_change", () => {
-      callback();
-    });
+    this._listener = await subscribeNotification(
+      "nsscreen_listen_change",
+      "nsscreen_change",
+      () => callback(),
+    );
   }
 
   static async screens(): Promise<Array<NSScreen>> {
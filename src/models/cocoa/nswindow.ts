@@ -1,4 +1,4 @@
-import type { Frame } from "@/models/geometry/frame";
+import type { Frame, Size } from "@/models/geometry/frame";
 import { invoke } from "@tauri-apps/api/core";
 
 export class NSWindow {
@@ -11,14 +11,36 @@ export class NSWindow {
   }
 }
 
+export type WindowFilter = {
+  /** CG frame of a screen, keeps windows whose center is on it */
+  screen?: Frame;
+  app?: string;
+  pid?: number;
+  bundle_id?: string;
+  min_size?: Size;
+  /** AX role or subrole, e.g. `AXStandardWindow` */
+  role?: string;
+  include_minimized?: boolean;
+};
+
 export class CGWindow {
   frame!: Frame;
   id!: number;
   name!: string;
   pid!: number;
   is_main!: boolean;
+  title!: string | null;
+  layer!: number;
+  alpha!: number;
+  bundle_id!: string | null;
+  is_on_screen!: boolean;
+  role!: string | null;
+  subrole!: string | null;
+  is_minimized!: boolean;
+  is_fullscreen!: boolean;
+  screen_index!: number | null;
 
-  static async getAllWindows(): Promise<CGWindow[]> {
-    return invoke("cgwindow_get_on_screen");
+  static async getAllWindows(filter?: WindowFilter): Promise<CGWindow[]> {
+    return invoke("cgwindow_get_on_screen", { filter });
   }
 }
@@ -0,0 +1,51 @@
+/**
+ * Error returned by the Rust commands, invoke promises reject with it
+ */
+export type SnapError =
+  | { kind: "accessibility_not_trusted" }
+  | { kind: "window_not_found"; pid: number; window_id: number | null }
+  | { kind: "ax_error"; code: number; operation: string }
+  | { kind: "invalid_argument"; argument: string; reason: string }
+  | { kind: "own_window_refused" }
+  | { kind: "platform"; message: string }
+  /**
+   * `line` and `column` start at 1
+   */
+  | {
+      kind: "script";
+      script: string;
+      message: string;
+      line: number | null;
+      column: number | null;
+    }
+  | { kind: "plugin"; plugin: string; message: string };
+
+export const isSnapError = (error: unknown): error is SnapError =>
+  typeof error === "object" &&
+  error !== null &&
+  typeof (error as SnapError).kind === "string";
+
+export const describeSnapError = (error: SnapError): string => {
+  switch (error.kind) {
+    case "accessibility_not_trusted":
+      return "Accessibility access is not granted";
+    case "window_not_found":
+      return error.window_id === null
+        ? `Process ${error.pid} has no matching window`
+        : `Window ${error.window_id} of process ${error.pid} not found`;
+    case "ax_error":
+      return `${error.operation} failed with AXError ${error.code}`;
+    case "invalid_argument":
+      return `Invalid ${error.argument}: ${error.reason}`;
+    case "own_window_refused":
+      return "Refusing to move a window of Snap itself";
+    case "platform":
+      return error.message;
+    case "script":
+      return error.line === null
+        ? `${error.script}: ${error.message}`
+        : `${error.script} line ${error.line}:${error.column ?? 1}: ${error.message}`;
+    case "plugin":
+      return `Plugin ${error.plugin}: ${error.message}`;
+  }
+};
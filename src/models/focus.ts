@@ -0,0 +1,19 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { CGWindow } from "@/models/cocoa/nswindow";
+
+/**
+ * Focuses the window that had focus before the current one
+ */
+export const focusPreviousWindow = (): Promise<CGWindow> =>
+  invoke("focus_previous_window");
+
+/**
+ * Focuses the `n`-th most recently used window, `0` being the focused one
+ */
+export const focusMru = (n: number): Promise<CGWindow> =>
+  invoke("focus_mru", { n });
+
+/**
+ * Windows ordered from the most recently focused one
+ */
+export const getMruList = (): Promise<CGWindow[]> => invoke("focus_mru_list");
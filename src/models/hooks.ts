@@ -0,0 +1,65 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { Frame } from "@/models/geometry/frame";
+import type { ArrangeStrategy, WindowManagerMode } from "@/models/action";
+
+/**
+ * Passed to hooked commands as JSON on stdin, every field is also set as a
+ * `SNAP_*` environment variable (`SNAP_EVENT`, `SNAP_FRAME_POSITION_X`, ...)
+ */
+export type HookEvent =
+  /**
+   * `zone` is the frame relative to the visible frame of `screen`, from 0 to 1
+   */
+  | {
+      event: "window_snapped";
+      pid: number;
+      window_id: number;
+      frame: Frame;
+      screen: number | null;
+      zone: Frame | null;
+    }
+  | { event: "arrange_finished"; strategy: ArrangeStrategy; windows: number }
+  | {
+      event: "screens_changed";
+      screens: { frame: Frame; visible_frame: Frame }[];
+    }
+  | { event: "mode_changed"; mode: WindowManagerMode }
+  | { event: "caffeinate_changed"; enabled: boolean }
+  | { event: "accent_color_changed"; color: string };
+
+export type HookEventName = HookEvent["event"];
+
+export const HOOK_EVENTS: HookEventName[] = [
+  "window_snapped",
+  "arrange_finished",
+  "screens_changed",
+  "mode_changed",
+  "caffeinate_changed",
+  "accent_color_changed",
+];
+
+export type HooksConfig = {
+  /**
+   * Commands by event, each run with `/bin/sh -c`
+   */
+  hooks: Partial<Record<HookEventName, string[]>>;
+  /**
+   * Hooks still running after this are killed
+   * @default 10000
+   */
+  timeout_ms?: number;
+  /**
+   * Hooks over this wait for a running one to finish
+   * @default 4
+   */
+  max_concurrent?: number;
+};
+
+export const configureHooks = (config: HooksConfig): Promise<void> =>
+  invoke("hooks_configure", { config });
+
+/**
+ * Runs the hooks of an event that happened in the frontend
+ */
+export const runHooks = (event: HookEvent): Promise<void> =>
+  invoke("hooks_run", { event });
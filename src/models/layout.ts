@@ -0,0 +1,82 @@
+import type { CGWindow } from "@/models/cocoa/nswindow";
+import type { Frame } from "@/models/geometry/frame";
+import type { WindowMove } from "@/models/window-transaction";
+
+/**
+ * A window of a saved layout, `frame` is in CG coordinates
+ */
+export type SavedWindow = {
+  window_id: number;
+  bundle_id: string | null;
+  app: string;
+  title: string | null;
+  frame: Frame;
+};
+
+/**
+ * Window frames saved by `snap://layout/save?name=...` and moved back by
+ * `snap://layout/restore?name=...`
+ */
+export type SavedLayout = {
+  name: string;
+  windows: SavedWindow[];
+};
+
+export const createLayout = (
+  name: string,
+  windows: CGWindow[],
+): SavedLayout => ({
+  name,
+  windows: windows.map((window) => ({
+    window_id: window.id,
+    bundle_id: window.bundle_id,
+    app: window.name,
+    title: window.title,
+    frame: window.frame,
+  })),
+});
+
+const isSameApp = (saved: SavedWindow, window: CGWindow) =>
+  saved.bundle_id
+    ? window.bundle_id === saved.bundle_id
+    : window.name === saved.app;
+
+/**
+ * Pairs the saved windows with open ones. A window that is still open keeps
+ * its place, windows reopened since then are matched by app and title, then
+ * by app alone. Every open window is moved at most once
+ */
+export const layoutMoves = (
+  layout: SavedLayout,
+  windows: CGWindow[],
+): WindowMove[] => {
+  const available = [...windows];
+  const take = (predicate: (window: CGWindow) => boolean) => {
+    const index = available.findIndex(predicate);
+    return index === -1 ? undefined : available.splice(index, 1)[0];
+  };
+
+  // Ids go first so a window matched by app doesn't take one still open
+  const sameWindows = layout.windows.map((saved) =>
+    take((window) => window.id === saved.window_id),
+  );
+
+  return layout.windows.flatMap((saved, i) => {
+    const window =
+      sameWindows[i] ??
+      take(
+        (window) => isSameApp(saved, window) && window.title === saved.title,
+      ) ??
+      take((window) => isSameApp(saved, window));
+    if (!window) {
+      return [];
+    }
+
+    return [
+      {
+        window: { window_id: window.id, pid: window.pid, frame: window.frame },
+        frame: saved.frame,
+      },
+    ];
+  });
+};
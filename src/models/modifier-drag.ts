@@ -0,0 +1,96 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { Modifiers } from "@/models/cocoa/nsevent";
+import type { Alignment } from "@/models/geometry/alignment";
+import type { Frame, Position } from "@/models/geometry/frame";
+
+/**
+ * Keys held to grab a window, caps lock and fn are ignored
+ */
+export type DragModifiers = Pick<
+  Modifiers,
+  "shift" | "control" | "option" | "command"
+>;
+
+export type ModifierDragConfig = {
+  enabled: boolean;
+  modifiers: DragModifiers;
+  /**
+   * Right drags resize from the corner nearest to the cursor
+   */
+  resize: boolean;
+};
+
+export const DEFAULT_MODIFIER_DRAG: ModifierDragConfig = {
+  enabled: false,
+  modifiers: { shift: false, control: true, option: false, command: true },
+  resize: true,
+};
+
+/**
+ * When resizing, `corner` follows the cursor and the opposite one stays
+ */
+export type DragMode = { type: "move" } | { type: "resize"; corner: Alignment };
+
+export type ModifierDragEvent = {
+  pid: number;
+  window_id: number;
+  mode: DragMode;
+  /**
+   * Frame the window was given, in CG coordinates
+   */
+  frame: Frame;
+  point: Position;
+};
+
+export type ModifierDragHandlers = {
+  started?: (event: ModifierDragEvent) => void;
+  moved?: (event: ModifierDragEvent) => void;
+  ended?: (event: ModifierDragEvent) => void;
+};
+
+export const configureModifierDrag = (
+  config: ModifierDragConfig,
+): Promise<void> => invoke("modifier_drag_configure", { config });
+
+/**
+ * Whether `modifiers` are the keys that grab a window
+ */
+export const isModifierDrag = (
+  config: ModifierDragConfig,
+  modifiers: Modifiers,
+) =>
+  config.enabled &&
+  (["shift", "control", "option", "command"] as const).every(
+    (key) => config.modifiers[key] === modifiers[key],
+  );
+
+export const describeModifiers = (modifiers: DragModifiers) =>
+  [
+    modifiers.control && "⌃",
+    modifiers.option && "⌥",
+    modifiers.shift && "⇧",
+    modifiers.command && "⌘",
+  ]
+    .filter(Boolean)
+    .join("");
+
+export const listenModifierDrag = async ({
+  started,
+  moved,
+  ended,
+}: ModifierDragHandlers): Promise<UnlistenFn> => {
+  const unlisteners = await Promise.all([
+    listen<ModifierDragEvent>("modifier_drag_started", ({ payload }) =>
+      started?.(payload),
+    ),
+    listen<ModifierDragEvent>("modifier_drag_moved", ({ payload }) =>
+      moved?.(payload),
+    ),
+    listen<ModifierDragEvent>("modifier_drag_ended", ({ payload }) =>
+      ended?.(payload),
+    ),
+  ]);
+
+  return () => unlisteners.forEach((unlisten) => unlisten());
+};
@@ -0,0 +1,40 @@
+import { invoke } from "@tauri-apps/api/core";
+import { Frame } from "@/models/geometry/frame";
+import type { SnapError } from "@/models/error";
+import type { WindowFrame } from "@/models/script";
+
+/**
+ * A `.wasm` file of the plugins directory, `error` tells why it can't be used
+ */
+export type PluginInfo = {
+  name: string;
+  path: string;
+  abi_version: number | null;
+  error: SnapError | null;
+};
+
+export const listPlugins = (): Promise<PluginInfo[]> => invoke("plugins_list");
+
+export const reloadPlugins = (): Promise<PluginInfo[]> =>
+  invoke("plugins_reload");
+
+/**
+ * Frames in screen coordinates by window id, windows that are closed or left
+ * out by the plugin are missing
+ */
+export const pluginLayout = async (
+  name: string,
+  windowIds: number[],
+  gap: number,
+): Promise<WindowFrame[]> => {
+  const frames: WindowFrame[] = await invoke("plugin_layout", {
+    name,
+    windowIds,
+    gap,
+  });
+
+  return frames.map(({ window_id, frame }) => ({
+    window_id,
+    frame: new Frame(frame.size, frame.position),
+  }));
+};
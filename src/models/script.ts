@@ -0,0 +1,57 @@
+import { invoke } from "@tauri-apps/api/core";
+import { Frame } from "@/models/geometry/frame";
+import type { SnapError } from "@/models/error";
+
+/**
+ * A Rhai script, run by its shortcut or `snap://script?name=...`, or used as
+ * an arrange strategy when it defines `fn layout(windows, screen)`
+ */
+export type Script = {
+  name: string;
+  source: string;
+  shortcut: string[];
+};
+
+/**
+ * Replaces the scripts known to Snap, resolves with the errors of the ones
+ * that don't compile
+ */
+export const configureScripts = (scripts: Script[]): Promise<SnapError[]> =>
+  invoke("script_configure", {
+    scripts: scripts.map(({ name, source }) => ({ name, source })),
+  });
+
+/**
+ * Compiles a script without keeping it, rejects with its syntax error
+ */
+export const checkScript = (name: string, source: string): Promise<void> =>
+  invoke("script_check", { name, source });
+
+export const runScript = (name: string): Promise<void> =>
+  invoke("script_run", { name });
+
+/**
+ * Frame a layout gives to a window
+ */
+export type WindowFrame = {
+  window_id: number;
+  frame: Frame;
+};
+
+/**
+ * Frames in CG coordinates by window id, closed windows are left out
+ */
+export const scriptLayout = async (
+  name: string,
+  windowIds: number[],
+): Promise<WindowFrame[]> => {
+  const frames: WindowFrame[] = await invoke("script_layout", {
+    name,
+    windowIds,
+  });
+
+  return frames.map(({ window_id, frame }) => ({
+    window_id,
+    frame: new Frame(frame.size, frame.position),
+  }));
+};
@@ -1,3 +1,4 @@
+import { invoke } from "@tauri-apps/api/core";
 import type { NSScreen } from "../cocoa/nsscreen";
 import { Alignment } from "../geometry/alignment";
 import { Frame, type Position } from "../geometry/frame";
@@ -45,3 +46,62 @@ export class ActiveSnapSide {
     );
   }
 }
+
+/**
+ * Thresholds for turning a drag release into a throw, unset ones keep the
+ * defaults
+ */
+export type ThrowOptions = {
+  /**
+   * Minimum release speed in points per second
+   * @default 1500
+   */
+  min_speed?: number;
+  /**
+   * Only samples this close to the release are used for the estimate
+   * @default 80
+   */
+  sample_window_ms?: number;
+  /**
+   * How far ahead the release position is projected along the velocity
+   * @default 250
+   */
+  projection_ms?: number;
+  /**
+   * Distance from a screen edge the projected point has to reach
+   * @default 100
+   */
+  edge_reach?: number;
+  /**
+   * Minimal share of the speed on the secondary axis for a corner throw
+   * @default 0.38
+   */
+  corner_ratio?: number;
+};
+
+export type Throw = {
+  alignment: Alignment;
+  velocity: Position;
+};
+
+/**
+ * Checks whether the last drag ended with a flick toward an edge or corner.
+ * `screen` is the CG frame of the screen the drag ended on.
+ */
+export const detectThrow = async (
+  screen: Frame,
+  throwOptions?: ThrowOptions,
+): Promise<Throw | null> => {
+  const result: any = await invoke("snapping_detect_throw", {
+    screen,
+    throwOptions: throwOptions ?? null,
+  });
+  if (!result) {
+    return null;
+  }
+
+  return {
+    alignment: new Alignment(result.alignment.x, result.alignment.y),
+    velocity: result.velocity,
+  };
+};
@@ -0,0 +1,39 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { CGWindow } from "@/models/cocoa/nswindow";
+
+/**
+ * Highlighted part of a text, usable with `String.prototype.slice`
+ */
+export type MatchRange = {
+  start: number;
+  end: number;
+};
+
+export type FoundWindow = {
+  item: CGWindow;
+  score: number;
+  title_ranges: MatchRange[];
+  app_name_ranges: MatchRange[];
+};
+
+export type FindWindowsOptions = {
+  limit?: number;
+  /**
+   * Boost per bundle id or app name, `0.5` ranks the app 50% higher
+   */
+  appPriorities?: Record<string, number>;
+};
+
+/**
+ * Windows ranked by fuzzy match over their title and app name.
+ * An empty query lists the most recently used windows first
+ */
+export const findWindows = (
+  query: string,
+  options: FindWindowsOptions = {},
+): Promise<FoundWindow[]> =>
+  invoke("window_finder_search", {
+    query,
+    limit: options.limit ?? null,
+    appPriorities: options.appPriorities ?? null,
+  });
@@ -0,0 +1,36 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { Frame } from "@/models/geometry/frame";
+
+export type SwitcherItem = {
+  window_id: number;
+  pid: number;
+  title: string | null;
+  app_name: string | null;
+  bundle_id: string | null;
+  bundle_path: string | null;
+  screen_index: number | null;
+  frame: Frame | null;
+  is_minimized: boolean;
+  /**
+   * PNG `data:` URL of the application icon, usable as an `img` source
+   */
+  icon: string | null;
+};
+
+/**
+ * Windows to show in the switcher, most recently used first
+ * @param iconSize icon width and height in pixels, 64 by default
+ */
+export const getSwitcherWindows = (
+  iconSize?: number,
+): Promise<SwitcherItem[]> =>
+  invoke("window_switcher_list", { iconSize: iconSize ?? null });
+
+/**
+ * Focuses the chosen window, restoring it if it is minimized
+ */
+export const focusSwitcherWindow = (item: SwitcherItem): Promise<void> =>
+  invoke("window_switcher_focus", {
+    pid: item.pid,
+    windowId: item.window_id,
+  });
@@ -0,0 +1,81 @@
+import { invoke } from "@tauri-apps/api/core";
+import { Frame } from "@/models/geometry/frame";
+import type { Alignment } from "@/models/geometry/alignment";
+import type { AnimationOptions } from "@/models/animation";
+import { describeSnapError, type SnapError } from "@/models/error";
+
+export type WindowMove = {
+  window: {
+    window_id: number;
+    pid: number;
+    frame: Frame;
+  };
+  frame: Frame;
+  /**
+   * Side or corner of `frame` the window stays pinned to when the app refuses
+   * the size, inferred from the screen by default
+   */
+  anchor?: Alignment;
+};
+
+/**
+ * - `moved` the window got the requested frame
+ * - `clamped` the app limited the frame (minimum size, screen bounds)
+ * - `not_found` the window is gone
+ * - `permission_denied` accessibility access is missing or the window belongs to Snap
+ * - `failed` the window is there but couldn't be read or moved
+ */
+export type MoveStatus =
+  | "moved"
+  | "clamped"
+  | "not_found"
+  | "permission_denied"
+  | { failed: SnapError };
+
+export const describeMoveStatus = (status: MoveStatus): string =>
+  typeof status === "string" ? status : describeSnapError(status.failed);
+
+export type MoveResult = {
+  window_id: number;
+  pid: number;
+  status: MoveStatus;
+  frame: Frame | null;
+};
+
+const parseResults = (results: MoveResult[]): MoveResult[] =>
+  results.map((result) => ({
+    ...result,
+    frame: result.frame
+      ? new Frame(result.frame.size, result.frame.position)
+      : null,
+  }));
+
+/**
+ * Moves all windows as one undoable unit, animations start together.
+ * Resolves once every window settled, results keep the order of `moves`.
+ */
+export const applyWindowTransaction = async (
+  moves: WindowMove[],
+  animationOptions?: AnimationOptions | null,
+): Promise<MoveResult[]> => {
+  const results: MoveResult[] = await invoke("window_transaction_apply", {
+    moves,
+    animationOptions: animationOptions || null,
+  });
+
+  return parseResults(results);
+};
+
+/**
+ * Restores the frames from before the last transaction, `null` when there is nothing to undo
+ */
+export const undoWindowTransaction = async (
+  animationOptions?: AnimationOptions | null,
+): Promise<MoveResult[] | null> => {
+  const results: MoveResult[] | null = await invoke(
+    "window_transaction_undo",
+    { animationOptions: animationOptions || null },
+  );
+
+  return results ? parseResults(results) : null;
+};
@@ -0,0 +1,70 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { Frame } from "@/models/geometry/frame";
+import type { CGWindow } from "@/models/cocoa/nswindow";
+
+export type WatcherOptions = {
+  /**
+   * How often the window list is sampled
+   * @default 250
+   */
+  interval_ms?: number;
+  /**
+   * How long a window has to stay unchanged before its events are emitted
+   * @default 150
+   */
+  debounce_ms?: number;
+};
+
+export type AppInfo = {
+  pid: number;
+  name: string | null;
+  bundle_id: string | null;
+};
+
+export type WindowEvent =
+  | { type: "created"; window: CGWindow }
+  | { type: "destroyed"; window: CGWindow }
+  /**
+   * `from` is the frame before the first move of a debounced burst
+   */
+  | { type: "moved"; window: CGWindow; from: Frame }
+  | { type: "resized"; window: CGWindow; from: Frame }
+  | { type: "focused"; window: CGWindow }
+  | { type: "app_activated"; app: AppInfo };
+
+export type WindowEventName =
+  | "window_created"
+  | "window_destroyed"
+  | "window_moved"
+  | "window_resized"
+  | "window_focused"
+  | "app_activated";
+
+const EVENT_NAMES: WindowEventName[] = [
+  "window_created",
+  "window_destroyed",
+  "window_moved",
+  "window_resized",
+  "window_focused",
+  "app_activated",
+];
+
+export const startWindowWatcher = (options?: WatcherOptions): Promise<void> =>
+  invoke("window_watcher_start", { options: options ?? null });
+
+export const stopWindowWatcher = (): Promise<boolean> =>
+  invoke("window_watcher_stop");
+
+let listeners: UnlistenFn[] = [];
+export const listenWindowEvents = async (
+  callback: (event: WindowEvent) => void,
+) => {
+  listeners.forEach((unlisten) => unlisten());
+
+  listeners = await Promise.all(
+    EVENT_NAMES.map((name) =>
+      listen<WindowEvent>(name, ({ payload }) => callback(payload)),
+    ),
+  );
+};
@@ -0,0 +1,51 @@
+import { getCurrentWindow } from "@tauri-apps/api/window";
+import { listenSnapActions, type SnapAction } from "@/models/action";
+import { Frame } from "@/models/geometry/frame";
+import { MappingAction } from "@/models/mapping";
+import * as settingsStore from "../settings";
+import * as caffeinateStore from "../statusbar/caffeinate";
+import { arrangeWindowsOnCurrentScreen } from "../spaces";
+import { restoreLayoutFx, saveLayoutFx } from "../layouts";
+
+const runAction = (action: SnapAction) => {
+  switch (action.action) {
+    case "mapping":
+      settingsStore.mappingActivated(
+        new MappingAction(Frame.fromMap(action.frame), []),
+      );
+      break;
+    case "arrange":
+      arrangeWindowsOnCurrentScreen(action.strategy);
+      break;
+    case "set_mode":
+      settingsStore.setWindowManagerMode(action.mode);
+      break;
+    case "set_caffeinate":
+      if (action.enabled && action.minutes) {
+        caffeinateStore.caffeinateForMinutes(action.minutes);
+      } else {
+        caffeinateStore.onChangeCaffeinateModeClicked(action.enabled);
+      }
+      break;
+    case "toggle_caffeinate":
+      caffeinateStore.onChangeCaffeinateModeClicked(
+        !caffeinateStore.$enabled.getState(),
+      );
+      break;
+    case "run_script":
+      settingsStore.scriptActivated(action.name);
+      break;
+    case "save_layout":
+      saveLayoutFx(action.name);
+      break;
+    case "restore_layout":
+      restoreLayoutFx(action.name);
+      break;
+  }
+};
+
+if (getCurrentWindow().label === "main") {
+  listenSnapActions(runAction);
+}
+
+export { runAction };
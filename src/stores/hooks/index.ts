@@ -0,0 +1,64 @@
+import { getCurrentWindow } from "@tauri-apps/api/window";
+import { createEffect, createEvent, sample } from "effector";
+import { createSharedStore } from "@/helpers";
+import {
+  configureHooks,
+  runHooks,
+  type HookEventName,
+  type HooksConfig,
+} from "@/models/hooks";
+import * as settingsStore from "../settings";
+import * as caffeinateStore from "../statusbar/caffeinate";
+
+const $hooks = createSharedStore<HooksConfig["hooks"]>("hooks", {});
+const $hooksTimeoutMs = createSharedStore("hooks_timeout_ms", 10_000);
+
+const setHookCommands = createEvent<{
+  event: HookEventName;
+  commands: string[];
+}>();
+const setHooksTimeoutMs = createEvent<number>();
+
+const configureHooksFx = createEffect(
+  ({ hooks, timeoutMs }: { hooks: HooksConfig["hooks"]; timeoutMs: number }) =>
+    configureHooks({ hooks, timeout_ms: timeoutMs }),
+);
+
+sample({
+  clock: setHookCommands,
+  source: $hooks,
+  fn: (hooks, { event, commands }) => ({ ...hooks, [event]: commands }),
+  target: $hooks,
+});
+
+sample({
+  clock: setHooksTimeoutMs,
+  filter: (timeoutMs) => timeoutMs > 0,
+  target: $hooksTimeoutMs,
+});
+
+configureHooksFx.failData.watch((error) => console.warn(error));
+
+// Hooks run from the overlay only, every window shares the same settings
+if (getCurrentWindow().label === "main") {
+  sample({
+    clock: [$hooks, $hooksTimeoutMs],
+    source: { hooks: $hooks, timeoutMs: $hooksTimeoutMs },
+    target: configureHooksFx,
+  });
+
+  configureHooksFx({
+    hooks: $hooks.getState(),
+    timeoutMs: $hooksTimeoutMs.getState(),
+  });
+
+  settingsStore.$windowManagerMode.updates.watch((mode) => {
+    runHooks({ event: "mode_changed", mode });
+  });
+
+  caffeinateStore.$enabled.updates.watch((enabled) => {
+    runHooks({ event: "caffeinate_changed", enabled });
+  });
+}
+
+export { $hooks, $hooksTimeoutMs, setHookCommands, setHooksTimeoutMs };
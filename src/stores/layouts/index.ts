@@ -0,0 +1,53 @@
+import { createEffect } from "effector";
+import { CGWindow } from "@/models/cocoa/nswindow";
+import { describeSnapError, isSnapError } from "@/models/error";
+import { createLayout, layoutMoves } from "@/models/layout";
+import {
+  applyWindowTransaction,
+  describeMoveStatus,
+} from "@/models/window-transaction";
+import * as settingsStore from "../settings";
+import { IGNORED_WINDOWS } from "../spaces/ignored-apps";
+
+const warn = (error: unknown) =>
+  console.warn(isSnapError(error) ? describeSnapError(error) : error);
+
+const listWindows = async () => {
+  const windows = await CGWindow.getAllWindows();
+
+  return windows.filter(
+    (window) => !IGNORED_WINDOWS.some((ignored) => ignored.test(window.name)),
+  );
+};
+
+// Every screen is saved and restored, unlike arranging
+const saveLayoutFx = createEffect(async (name: string) => {
+  settingsStore.setLayout(createLayout(name, await listWindows()));
+});
+
+const restoreLayoutFx = createEffect(async (name: string) => {
+  const layout = settingsStore.$layouts
+    .getState()
+    .find((layout) => layout.name === name);
+  if (!layout) {
+    throw new Error(`there is no layout named ${name}`);
+  }
+
+  const moves = layoutMoves(layout, await listWindows());
+  const results = await applyWindowTransaction(
+    moves,
+    settingsStore.getAnimationOptions(),
+  );
+  results
+    .filter((result) => result.status !== "moved")
+    .forEach((result) => {
+      console.warn(
+        `window ${result.window_id}: ${describeMoveStatus(result.status)}`,
+      );
+    });
+});
+
+saveLayoutFx.failData.watch(warn);
+restoreLayoutFx.failData.watch(warn);
+
+export { saveLayoutFx, restoreLayoutFx };
@@ -0,0 +1,16 @@
+import { createEffect, createStore, sample } from "effector";
+import { listPlugins, reloadPlugins, type PluginInfo } from "@/models/plugin";
+
+const $plugins = createStore<PluginInfo[]>([]);
+
+const loadPluginsFx = createEffect(() => listPlugins());
+const reloadPluginsFx = createEffect(() => reloadPlugins());
+
+sample({
+  clock: [loadPluginsFx.doneData, reloadPluginsFx.doneData],
+  target: $plugins,
+});
+
+reloadPluginsFx.failData.watch((error) => console.warn(error));
+
+export { $plugins, loadPluginsFx, reloadPluginsFx };
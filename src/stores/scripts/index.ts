@@ -0,0 +1,35 @@
+import { getCurrentWindow } from "@tauri-apps/api/window";
+import { createEffect, sample } from "effector";
+import { describeSnapError, isSnapError } from "@/models/error";
+import { configureScripts, runScript, type Script } from "@/models/script";
+import * as settingsStore from "../settings";
+
+const warn = (error: unknown) =>
+  console.warn(isSnapError(error) ? describeSnapError(error) : error);
+
+const configureScriptsFx = createEffect(async (scripts: Script[]) => {
+  const errors = await configureScripts(scripts);
+  errors.forEach(warn);
+});
+
+const runScriptFx = createEffect((name: string) => runScript(name));
+
+configureScriptsFx.failData.watch(warn);
+runScriptFx.failData.watch(warn);
+
+// Scripts run from the overlay only, every window shares the same settings
+if (getCurrentWindow().label === "main") {
+  sample({
+    clock: settingsStore.$scripts,
+    target: configureScriptsFx,
+  });
+
+  sample({
+    clock: settingsStore.scriptActivated,
+    target: runScriptFx,
+  });
+
+  configureScriptsFx(settingsStore.$scripts.getState());
+}
+
+export { configureScriptsFx, runScriptFx };
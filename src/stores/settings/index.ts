@@ -3,11 +3,27 @@ import * as globalShortcut from "@tauri-apps/plugin-global-shortcut";
 import { createSharedStore } from "@/helpers";
 import { Frame } from "@/models/geometry/frame";
 import { MappingAction, keysToShortcut } from "@/models/mapping";
-import { type AnimationOptions } from "@/models/animation";
+import { EASING_PRESETS, type AnimationOptions } from "@/models/animation";
 import { getCurrentWindow } from "@tauri-apps/api/window";
-import { createEffect, createEvent, createStore, sample } from "effector";
+import {
+  combine,
+  createEffect,
+  createEvent,
+  createStore,
+  sample,
+} from "effector";
 import { MAPPING_ACTIONS } from "./mapping-actions";
 import * as autostartPlugin from "@tauri-apps/plugin-autostart";
+import type { Script } from "@/models/script";
+import type { SavedLayout } from "@/models/layout";
+import type { ArrangeStrategy } from "@/models/action";
+import { describeSnapError, isSnapError } from "@/models/error";
+import type { ThrowOptions } from "@/models/snapping";
+import {
+  DEFAULT_MODIFIER_DRAG,
+  configureModifierDrag,
+  type ModifierDragConfig,
+} from "@/models/modifier-drag";
 
 const $windowGap = createSharedStore<number>("window_gap", 10);
 const $snowfallEnabled = createSharedStore<boolean>("snowfall_enabled", false);
@@ -15,6 +31,10 @@ const $animationsEnabled = createSharedStore<boolean>(
   "animations_enabled",
   true,
 );
+const $animationEasing = createSharedStore<string>(
+  "animation_easing",
+  "ease-in-out",
+);
 const $mappings = createSharedStore("mappings", MAPPING_ACTIONS, {
   restoreMap: (data) =>
     (data as Array<object>).map(
@@ -30,11 +50,23 @@ const $windowManagerMode = createSharedStore<"snapping" | "fancy_zones">(
   "window_manager_mode",
   "snapping",
 );
+const $scripts = createSharedStore<Script[]>("scripts", []);
+const $layouts = createSharedStore<SavedLayout[]>("layouts", []);
+const $arrangeStrategy = createSharedStore<ArrangeStrategy>(
+  "arrange_strategy",
+  "split",
+);
 const $showFancyZonesPlaceholder = createSharedStore(
   "show_fancy_zones_placeholder",
   true,
 );
 
+const $throwOptions = createSharedStore<ThrowOptions>("throw_options", {});
+const $modifierDrag = createSharedStore<ModifierDragConfig>(
+  "modifier_drag",
+  DEFAULT_MODIFIER_DRAG,
+);
+
 const $autostartEnabled = createStore(false);
 
 const mappingActivated = createEvent<MappingAction>();
@@ -46,6 +78,25 @@ const setShowFancyZonesPlaceholder = createEvent<boolean>();
 const setAutostartEnabled = createEvent<boolean>();
 const setSnowfallEnabled = createEvent<boolean>();
 const setAnimationsEnabled = createEvent<boolean>();
+const setAnimationEasing = createEvent<string>();
+const scriptActivated = createEvent<string>();
+/**
+ * Adds the script, or replaces the one with the same name
+ */
+const setScript = createEvent<Script>();
+const removeScript = createEvent<string>();
+/**
+ * Adds the layout, or replaces the one with the same name
+ */
+const setLayout = createEvent<SavedLayout>();
+const setArrangeStrategy = createEvent<ArrangeStrategy>();
+const setModifierDrag = createEvent<ModifierDragConfig>();
+const setThrowOptions = createEvent<ThrowOptions>();
+
+const configureModifierDragFx = createEffect(configureModifierDrag);
+configureModifierDragFx.failData.watch((error) => {
+  console.warn(isSnapError(error) ? describeSnapError(error) : error);
+});
 
 if (getCurrentWindow().label === "main") {
   let prevShortcut: string[] | null = null;
@@ -64,7 +115,9 @@ if (getCurrentWindow().label === "main") {
     prevShortcut = shortcut;
   });
 
-  $mappings.subscribe(async (mappings) => {
+  $modifierDrag.subscribe(configureModifierDragFx);
+
+  combine($mappings, $scripts).subscribe(async ([mappings, scripts]) => {
     globalShortcut.unregisterAll();
 
     mappings
@@ -77,6 +130,17 @@ if (getCurrentWindow().label === "main") {
           mappingActivated(mapping);
         });
       });
+
+    scripts
+      .filter((script) => script.shortcut.length)
+      .forEach((script) => {
+        globalShortcut.register(keysToShortcut(script.shortcut), (event) => {
+          if (event.state !== "Pressed") {
+            return;
+          }
+          scriptActivated(script.name);
+        });
+      });
   });
 }
 
@@ -90,6 +154,11 @@ sample({
   target: $animationsEnabled,
 });
 
+sample({
+  clock: setAnimationEasing,
+  target: $animationEasing,
+});
+
 sample({
   clock: setShowFancyZonesPlaceholder,
   target: $showFancyZonesPlaceholder,
@@ -108,6 +177,49 @@ sample({
   target: $mappings,
 });
 
+sample({
+  clock: setScript,
+  source: $scripts,
+  fn: (scripts, script) =>
+    scripts.some((item) => item.name === script.name)
+      ? scripts.map((item) => (item.name === script.name ? script : item))
+      : [...scripts, script],
+  target: $scripts,
+});
+
+sample({
+  clock: removeScript,
+  source: $scripts,
+  fn: (scripts, name) => scripts.filter((script) => script.name !== name),
+  target: $scripts,
+});
+
+sample({
+  clock: setLayout,
+  source: $layouts,
+  fn: (layouts, layout) => [
+    ...layouts.filter((item) => item.name !== layout.name),
+    layout,
+  ],
+  target: $layouts,
+});
+
+sample({
+  clock: setArrangeStrategy,
+  target: $arrangeStrategy,
+});
+
+sample({
+  clock: removeScript,
+  source: $arrangeStrategy,
+  filter: (strategy, name) =>
+    typeof strategy === "object" &&
+    "script" in strategy &&
+    strategy.script === name,
+  fn: (): ArrangeStrategy => "split",
+  target: $arrangeStrategy,
+});
+
 sample({
   clock: setArrangeWindowShortcut,
   target: $arrangeWindowsShortcut,
@@ -118,6 +230,18 @@ sample({
   target: $windowManagerMode,
 });
 
+sample({
+  clock: setThrowOptions,
+  source: $throwOptions,
+  fn: (options, changes) => ({ ...options, ...changes }),
+  target: $throwOptions,
+});
+
+sample({
+  clock: setModifierDrag,
+  target: $modifierDrag,
+});
+
 sample({
   clock: setAutostartEnabled,
   target: $autostartEnabled,
@@ -146,14 +270,13 @@ autostartPlugin.isEnabled().then((isEnabled) => {
 });
 
 const getAnimationOptions = (): AnimationOptions | null => {
-  return null; // Fix the animation and return in the next versions
-
   const enabled = $animationsEnabled.getState();
   if (!enabled) return null;
 
   return {
     duration_ms: 200,
     fps: 120,
+    easing: EASING_PRESETS[$animationEasing.getState()],
   };
 };
 
@@ -166,6 +289,12 @@ export {
   $autostartEnabled,
   $snowfallEnabled,
   $animationsEnabled,
+  $animationEasing,
+  $scripts,
+  $layouts,
+  $arrangeStrategy,
+  $modifierDrag,
+  $throwOptions,
   setSnowfallEnabled,
   setWindowManagerMode,
   mappingActivated,
@@ -175,5 +304,13 @@ export {
   setArrangeWindowShortcut,
   setAutostartEnabled,
   setAnimationsEnabled,
+  setAnimationEasing,
+  scriptActivated,
+  setScript,
+  removeScript,
+  setLayout,
+  setArrangeStrategy,
+  setModifierDrag,
+  setThrowOptions,
   getAnimationOptions,
 };
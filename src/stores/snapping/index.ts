@@ -1,8 +1,13 @@
 import type { NSScreen } from "@/models/cocoa/nsscreen";
+import { describeSnapError, isSnapError } from "@/models/error";
 import { Alignment } from "@/models/geometry/alignment";
 import { Frame, Position, Size } from "@/models/geometry/frame";
-import { ActiveSnapSide } from "@/models/snapping";
-import { combine, createStore, sample } from "effector";
+import {
+  ActiveSnapSide,
+  detectThrow,
+  type ThrowOptions,
+} from "@/models/snapping";
+import { combine, createEffect, createStore, sample } from "effector";
 import * as settingsStore from "../settings";
 import * as statusbarStore from "../statusbar";
 import * as windowManagerStore from "../window-manager";
@@ -81,6 +86,31 @@ const computeAreaFromScreen = ({
   );
 };
 
+const detectThrowFx = createEffect(
+  async ({
+    screen,
+    gap,
+    throwOptions,
+  }: {
+    screen: NSScreen;
+    gap: number;
+    throwOptions: ThrowOptions;
+  }) => {
+    const result = await detectThrow(screen.frame, throwOptions);
+    const side = _snapSides.find(
+      (side) =>
+        side.alignment.x === result?.alignment.x &&
+        side.alignment.y === result?.alignment.y,
+    );
+
+    return computeAreaFromScreen({ frame: side?.frame, screen, gap });
+  },
+);
+// Invalid throw settings only skip the throw, the drag itself still ends
+detectThrowFx.failData.watch((error) => {
+  console.warn(isSnapError(error) ? describeSnapError(error) : error);
+});
+
 const $gap = settingsStore.$windowGap;
 const $enabled = combine(
   [statusbarStore.$windowManagerEnabled, settingsStore.$windowManagerMode],
@@ -161,6 +191,33 @@ sample({
   target: windowManagerStore.setWindowFrameFx,
 });
 
+sample({
+  clock: windowManagerStore.onDragEnded,
+  filter: combine($enabled, $placeholder).map(
+    ([enabled, placeholder]) => enabled && !placeholder,
+  ),
+  source: {
+    screen: windowManagerStore.$currentCGScreen,
+    gap: $gap,
+    throwOptions: settingsStore.$throwOptions,
+  },
+  fn: ({ screen, gap, throwOptions }) => ({
+    screen: screen!,
+    gap,
+    throwOptions,
+  }),
+  target: detectThrowFx,
+});
+
+sample({
+  clock: detectThrowFx.doneData,
+  filter: Boolean,
+  source: windowManagerStore.$currentCGScreen,
+  fn: (screen, placeholder) =>
+    windowManagerStore.placeholderToScreen(screen!)(placeholder!),
+  target: windowManagerStore.setWindowFrameFx,
+});
+
 sample({
   clock: $placeholder,
   filter: Boolean,
@@ -1,48 +1,61 @@
 import { createEffect, createEvent, sample } from "effector";
 import { CGWindow } from "@/models/cocoa/nswindow";
-import { invoke } from "@tauri-apps/api/core";
 import { Frame, Position, Size } from "@/models/geometry/frame";
 import type { NSScreen } from "@/models/cocoa/nsscreen";
+import type { ArrangeStrategy } from "@/models/action";
+import { scriptLayout, type WindowFrame } from "@/models/script";
+import { pluginLayout } from "@/models/plugin";
 import * as windowManagerStore from "../window-manager";
 import * as settingsStore from "../settings";
 import { listen } from "@tauri-apps/api/event";
 import { IGNORED_WINDOWS } from "./ignored-apps";
-import { type AnimationOptions } from "@/models/animation";
+import {
+  applyWindowTransaction,
+  describeMoveStatus,
+  undoWindowTransaction,
+} from "@/models/window-transaction";
+import { runHooks } from "@/models/hooks";
+
+const loadAllData = createEvent<ArrangeStrategy>();
+const arrangeWindowsOnCurrentScreen = createEvent<ArrangeStrategy | void>();
+const undoArrangeWindows = createEvent();
+
+// Only windows whose center is on the screen are arranged
+const loadAllDataFx = createEffect(
+  async ({
+    strategy,
+    screen,
+  }: {
+    strategy: ArrangeStrategy;
+    screen: NSScreen;
+  }) => {
+    const windows = await CGWindow.getAllWindows({ screen: screen.frame });
 
-const loadAllData = createEvent();
-const arrangeWindowsOnCurrentScreen = createEvent();
+    return {
+      windows,
+      strategy,
+      screen,
+    };
+  },
+);
 
-const loadAllDataFx = createEffect(async () => {
-  const windows = await CGWindow.getAllWindows();
+const generateFramesByGrid = (windowCount: number): Frame[] => {
+  if (windowCount <= 0) return [];
 
-  return {
-    windows,
-  };
-});
+  const columns = Math.ceil(Math.sqrt(windowCount));
+  const rows = Math.ceil(windowCount / columns);
 
-const setFrame = async (window: CGWindow, frame: Frame) => {
-  const animationSettings = settingsStore.getAnimationOptions();
-  const finalAnimationOptions = false;
-
-  if (finalAnimationOptions) {
-    await invoke("accessibility_element_set_frame_animated", {
-      windowInfo: {
-        window_id: window.id,
-        pid: window.pid,
-        frame: frame,
-      },
-      animationOptions: finalAnimationOptions,
-    });
-  } else {
-    // Use instant positioning when animations are disabled
-    await invoke("accessibility_element_set_frame_instant", {
-      windowInfo: {
-        window_id: window.id,
-        pid: window.pid,
-        frame: frame,
-      },
-    });
-  }
+  return Array.from({ length: windowCount }, (_, i) => {
+    const row = Math.floor(i / columns);
+    // The last row may be shorter, its cells grow to fill the width
+    const columnsInRow = Math.min(columns, windowCount - row * columns);
+    const column = i % columns;
+
+    return new Frame(
+      new Size(1 / columnsInRow, 1 / rows),
+      new Position(column / columnsInRow, row / rows),
+    );
+  });
 };
 
 const generateFramesByRecursiveSplit = (windowCount: number): Frame[] => {
@@ -121,10 +134,12 @@ const generateFramesByRecursiveSplit = (windowCount: number): Frame[] => {
 const arrangeWindowsOnCurrentScreenFx = createEffect(
   async ({
     windows: allWindows,
+    strategy,
     screen,
     gap,
   }: {
     windows: CGWindow[];
+    strategy: ArrangeStrategy;
     screen: NSScreen;
     gap: number;
   }) => {
@@ -168,29 +183,78 @@ const arrangeWindowsOnCurrentScreenFx = createEffect(
         return !has;
       });
 
-      // Use predefined frames for 1-5 windows, generate recursive split for 6+ windows
-      let rawFrames: Frame[];
-      if (windows.length <= 5) {
-        rawFrames = predefinedFrames[windows.length - 1];
-      } else {
-        rawFrames = generateFramesByRecursiveSplit(windows.length);
-      }
-
-      if (!rawFrames?.length) {
-        return;
-      }
-
-      const framesForWindows = rawFrames.map((frame) => {
+      const windowIds = windows.map((window) => window.id);
+      const toScreen = (frame: Frame) => {
         const placeholder = windowManagerStore.frameToPlaceholder({
           gap,
           screen: screen!,
         })(frame);
 
         return windowManagerStore.placeholderToScreen(screen!)(placeholder!);
+      };
+
+      const byPosition = (frames: Frame[]): WindowFrame[] =>
+        frames.map((frame, i) => ({ window_id: windows[i].id, frame }));
+
+      // Use predefined frames for 1-5 windows, generate recursive split for 6+ windows.
+      // Plugins and scripts answer by window id, in screen coordinates
+      let framesForWindows: WindowFrame[];
+      if (typeof strategy === "object" && "plugin" in strategy) {
+        framesForWindows = await pluginLayout(strategy.plugin, windowIds, gap);
+      } else if (typeof strategy === "object") {
+        framesForWindows = await scriptLayout(strategy.script, windowIds);
+      } else if (strategy === "grid") {
+        framesForWindows = byPosition(
+          generateFramesByGrid(windows.length).map(toScreen),
+        );
+      } else if (windows.length <= 5) {
+        framesForWindows = byPosition(
+          (predefinedFrames[windows.length - 1] ?? []).map(toScreen),
+        );
+      } else {
+        framesForWindows = byPosition(
+          generateFramesByRecursiveSplit(windows.length).map(toScreen),
+        );
+      }
+
+      const moves = framesForWindows.flatMap(({ window_id, frame }) => {
+        const window = windows.find((window) => window.id === window_id);
+
+        return window
+          ? [
+              {
+                window: {
+                  window_id: window.id,
+                  pid: window.pid,
+                  frame: window.frame,
+                },
+                frame,
+              },
+            ]
+          : [];
       });
 
-      windows.forEach((window, i) => {
-        setFrame(window, framesForWindows[i]);
+      if (!moves.length) {
+        return;
+      }
+
+      const results = await applyWindowTransaction(
+        moves,
+        settingsStore.getAnimationOptions(),
+      );
+
+      results
+        .filter((result) => result.status !== "moved")
+        .forEach((result) => {
+          console.warn(
+            `window ${result.window_id}: ${describeMoveStatus(result.status)}`,
+          );
+        });
+
+      runHooks({
+        event: "arrange_finished",
+        strategy,
+        windows: results.filter((result) => result.status === "moved").length,
       });
     } catch (err) {
       console.error(err);
@@ -200,54 +264,38 @@ const arrangeWindowsOnCurrentScreenFx = createEffect(
 
 sample({
   clock: arrangeWindowsOnCurrentScreen,
+  source: settingsStore.$arrangeStrategy,
+  fn: (defaultStrategy, strategy): ArrangeStrategy =>
+    strategy || defaultStrategy,
   target: loadAllData,
 });
 
 sample({
   clock: loadAllData,
+  source: windowManagerStore.$currentCGScreen,
+  filter: Boolean,
+  fn: (screen, strategy) => ({ strategy, screen: screen! }),
   target: loadAllDataFx,
 });
 
 sample({
   clock: loadAllDataFx.doneData,
-  source: {
-    screen: windowManagerStore.$currentCGScreen,
-    gap: settingsStore.$windowGap,
-  },
-  fn: ({ screen, gap }, data) => ({
-    ...data,
-    windows: data.windows.filter((window) => {
-      return (
-        (window.is_main &&
-          screen?.frame.includesPoint(window.frame.position)) ||
-        screen?.frame.includesPoint(
-          new Position(
-            window.frame.position.x + window.frame.size.width,
-            window.frame.position.y + window.frame.size.height,
-          ),
-        ) ||
-        screen?.frame.includesPoint(
-          new Position(
-            window.frame.position.x + window.frame.size.width,
-            window.frame.position.y,
-          ),
-        ) ||
-        screen?.frame.includesPoint(
-          new Position(
-            window.frame.position.x,
-            window.frame.position.y + window.frame.size.height,
-          ),
-        )
-      );
-    }),
-    screen: screen!,
-    gap,
-  }),
+  source: settingsStore.$windowGap,
+  fn: (gap, data) => ({ ...data, gap }),
   target: arrangeWindowsOnCurrentScreenFx,
 });
 
+const undoArrangeWindowsFx = createEffect(() =>
+  undoWindowTransaction(settingsStore.getAnimationOptions()),
+);
+
+sample({
+  clock: undoArrangeWindows,
+  target: undoArrangeWindowsFx,
+});
+
 listen("spaces_on_click_arrange", () => {
   arrangeWindowsOnCurrentScreen();
 });
 
-export { arrangeWindowsOnCurrentScreen };
+export { arrangeWindowsOnCurrentScreen, undoArrangeWindows };
@@ -1,47 +1,107 @@
-import { createSharedStore, wait } from "@/helpers";
+import { createSharedStore } from "@/helpers";
 import { getCurrentWindow } from "@tauri-apps/api/window";
-import { Command } from "@tauri-apps/plugin-shell";
-import { createEffect, createEvent, sample } from "effector";
+import { createEffect, createEvent, createStore, sample } from "effector";
+import {
+  configureCaffeinateTriggers,
+  getCaffeinateStatus,
+  listenCaffeinateState,
+  startCaffeinate,
+  stopCaffeinate,
+  type CaffeinateRequest,
+  type CaffeinateStatus,
+  type TriggersConfig,
+} from "@/models/caffeinate";
 
 const appWindow = getCurrentWindow();
 
-const changeCaffeinateFx = createEffect(async (state: boolean) => {
-  if (appWindow.label !== "main") {
-    return;
-  }
-
-  const start = async () => {
-    await stop();
-    await Command.create("caffeinate", ["-di"]).spawn();
-  };
-
-  const stop = async () => {
-    const command = Command.create("killall", ["caffeinate"]);
-    command.execute();
-
-    await wait(400);
-  };
+const startCaffeinateFx = createEffect((request: CaffeinateRequest) =>
+  startCaffeinate(request),
+);
+const stopCaffeinateFx = createEffect(() => stopCaffeinate());
+const getCaffeinateStatusFx = createEffect(() => getCaffeinateStatus());
+const configureTriggersFx = createEffect((config: TriggersConfig) =>
+  configureCaffeinateTriggers(config),
+);
 
+const changeCaffeinateFx = createEffect(async (state: boolean) => {
   if (state) {
-    await start().catch(console.error);
+    await startCaffeinateFx({});
   } else {
-    await stop().catch(console.error);
+    await stopCaffeinateFx();
   }
 });
 
-const $enabled = createSharedStore("caffeinate_enabled", false);
+const statusChanged = createEvent<CaffeinateStatus>();
+
+// The session lives in Rust, the stores only mirror it
+const $status = createStore<CaffeinateStatus>({
+  active: false,
+  kind: null,
+  reason: null,
+  ends_at_ms: null,
+  remaining_seconds: null,
+  until_pid: null,
+});
+const $enabled = $status.map((status) => status.active);
+// Restored on the next launch, timed sessions are not
+const $restoreOnLaunch = createSharedStore("caffeinate_enabled", false);
+const $triggers = createSharedStore<TriggersConfig>("caffeinate_triggers", {
+  triggers: [],
+  kind: "display",
+});
+
 const onChangeCaffeinateModeClicked = createEvent<boolean>();
+const caffeinateForMinutes = createEvent<number>();
+const setTriggers = createEvent<TriggersConfig>();
+
+sample({
+  clock: [statusChanged, getCaffeinateStatusFx.doneData],
+  target: $status,
+});
 
 sample({
   clock: onChangeCaffeinateModeClicked,
-  target: $enabled,
+  target: [changeCaffeinateFx, $restoreOnLaunch],
+});
+
+sample({
+  clock: caffeinateForMinutes,
+  fn: (minutes): CaffeinateRequest => ({ minutes }),
+  target: startCaffeinateFx,
 });
 
 sample({
-  clock: $enabled,
-  target: changeCaffeinateFx,
+  clock: setTriggers,
+  target: $triggers,
 });
 
-changeCaffeinateFx($enabled.getState());
+[startCaffeinateFx, stopCaffeinateFx, configureTriggersFx].forEach((fx) =>
+  fx.failData.watch((error) => console.error(error)),
+);
+
+listenCaffeinateState(statusChanged);
+getCaffeinateStatusFx();
+
+// Triggers are checked in Rust, the overlay keeps them in sync
+if (appWindow.label === "main") {
+  sample({
+    clock: $triggers,
+    target: configureTriggersFx,
+  });
+
+  configureTriggersFx($triggers.getState());
+
+  if ($restoreOnLaunch.getState()) {
+    startCaffeinateFx({});
+  }
+}
 
-export { $enabled, changeCaffeinateFx, onChangeCaffeinateModeClicked };
+export {
+  $enabled,
+  $status,
+  $triggers,
+  caffeinateForMinutes,
+  changeCaffeinateFx,
+  onChangeCaffeinateModeClicked,
+  setTriggers,
+};
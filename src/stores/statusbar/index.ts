@@ -4,7 +4,7 @@ import {
   WebviewWindow,
   getCurrentWebviewWindow,
 } from "@tauri-apps/api/webviewWindow";
-import * as caffeinateStore from "./caffeinate";
+import "./caffeinate";
 import { createSharedStore, wait } from "@/helpers";
 import {
   startWindowManagerListenFx,
@@ -49,7 +49,6 @@ const openSettingsWindowFx = createEffect(async () => {
 });
 
 const exitAppFx = createEffect(async () => {
-  await caffeinateStore.changeCaffeinateFx(false);
   // PerformanceMeasure
   exit(0);
 });
@@ -1,17 +1,40 @@
 import { createSharedStore } from "@/helpers";
-import { NSColor } from "@/models/cocoa/nscolor";
-import { createEvent, sample } from "effector";
+import { NSColor, type Appearance } from "@/models/cocoa/nscolor";
+import { describeSnapError, isSnapError } from "@/models/error";
+import { createEvent, createStore, sample } from "effector";
 
 const $accentColor = createSharedStore("accent_color", "rgb(21, 193, 216)");
+// `null` until read, the CSS media queries cover that moment
+const $appearance = createStore<Appearance | null>(null);
 
 const setAccentColor = createEvent<string>();
+const setAppearance = createEvent<Appearance>();
 
 sample({
   clock: setAccentColor,
   target: $accentColor,
 });
 
-NSColor.getAccentColor().then(({ color }) => setAccentColor(color));
-NSColor.listenChange(({ color }) => setAccentColor(color));
+sample({
+  clock: setAppearance,
+  target: $appearance,
+});
+
+sample({
+  clock: setAppearance,
+  fn: (appearance) => appearance.palette.accent.css,
+  target: $accentColor,
+});
+
+const warn = (error: unknown) =>
+  console.warn(isSnapError(error) ? describeSnapError(error) : error);
+
+// Failures keep the defaults, the CSS media queries still apply
+NSColor.getAccentColor()
+  .then((color) => setAccentColor(color.css))
+  .catch(warn);
+NSColor.listenChange((color) => setAccentColor(color.css));
+NSColor.getAppearance().then(setAppearance).catch(warn);
+NSColor.listenAppearance(setAppearance);
 
-export { $accentColor };
+export { $accentColor, $appearance };
@@ -11,10 +11,17 @@ import {
   createStore,
   sample,
 } from "effector";
-import { NSEvent, NSEventType } from "@/models/cocoa/nsevent";
+import { NSEvent } from "@/models/cocoa/nsevent";
 import { AccessibilityElement } from "@/models/cocoa/accessibility-element";
-import { throttle } from "@/helpers";
+import { describeSnapError, isSnapError } from "@/models/error";
+import {
+  isModifierDrag,
+  listenModifierDrag,
+  type ModifierDragEvent,
+} from "@/models/modifier-drag";
+import * as settingsStore from "@/stores/settings";
 import { getCurrentWebview } from "@tauri-apps/api/webview";
+import type { UnlistenFn } from "@tauri-apps/api/event";
 
 const isOverlayWindow = getCurrentWebview().label === "main";
 
@@ -24,43 +31,71 @@ type MouseEvent<T extends "down" | "up" | "dragged"> = {
 };
 
 let MONITOR_ID: string | null = null;
+let UNLISTEN_MODIFIER_DRAG: UnlistenFn | null = null;
 
 const onMouseDown = createEvent<MouseEvent<"down">>();
 const onMouseDragged = createEvent<MouseEvent<"dragged">>();
 const onMouseUp = createEvent<MouseEvent<"up">>();
 const onDragStarted = createEvent("onDragStarted");
 const onDragEnded = createEvent("onDragEnded");
+/**
+ * A window grabbed with the modifier drag, it is moved by Rust
+ */
+const onModifierDragStarted = createEvent<ModifierDragEvent>();
 
 const startWindowManagerListenFx = createEffect(async () => {
-  const onGlobalEvent = throttle(100)((event: NSEvent) => {
+  const onGlobalEvent = (event: NSEvent) => {
+    // Those drags come back as modifier drag events
+    const modifierDrag = settingsStore.$modifierDrag.getState();
+    if (isModifierDrag(modifierDrag, event.modifiers)) {
+      return;
+    }
     switch (event.type) {
-      case NSEventType.leftMouseDownMask:
+      case "left_mouse_down":
         onMouseDown({ type: "down", position: event.position });
         break;
-      case NSEventType.leftMouseUpMask:
+      case "left_mouse_up":
         onMouseUp({ type: "up", position: event.position });
         break;
-      case NSEventType.leftMouseDraggedMask:
+      case "left_mouse_dragged":
         onMouseDragged({ type: "dragged", position: event.position });
         break;
     }
-  });
+  };
 
   await AccessibilityElement.checkPermission();
   MONITOR_ID = await NSEvent.addGlobalMonitor(
-    [
-      NSEventType.leftMouseDownMask,
-      NSEventType.leftMouseDraggedMask,
-      NSEventType.leftMouseUpMask,
-    ],
+    ["left_mouse_down", "left_mouse_dragged", "left_mouse_up"],
     onGlobalEvent,
+    // Drags are only followed by the placeholder, clicks are never held back
+    { throttleMs: 100 },
   );
+  // Resizes don't snap, only moves are followed
+  UNLISTEN_MODIFIER_DRAG = await listenModifierDrag({
+    started: (event) => {
+      if (event.mode.type === "move") {
+        onModifierDragStarted(event);
+      }
+    },
+    moved: (event) => {
+      if (event.mode.type === "move") {
+        onMouseDragged({ type: "dragged", position: event.point });
+      }
+    },
+    ended: (event) => {
+      if (event.mode.type === "move") {
+        onMouseUp({ type: "up", position: event.point });
+      }
+    },
+  });
 });
 
 const stopWindowManagerListenFx = createEffect(async () => {
   if (MONITOR_ID) {
     await NSEvent.removeMonitor(MONITOR_ID);
   }
+  UNLISTEN_MODIFIER_DRAG?.();
+  UNLISTEN_MODIFIER_DRAG = null;
 });
 
 const getCurrentAccessibilityElementFx = createEffect(() =>
@@ -75,10 +110,19 @@ const setAccessibilityElementFrameFx = createEffect(
     element: AccessibilityElement;
     frame: Frame;
   }) => {
-    element.setFrame(frame);
+    await element.setFrame(frame);
   },
 );
 
+sample({
+  clock: [
+    getCurrentAccessibilityElementFx.failData,
+    setAccessibilityElementFrameFx.failData,
+  ],
+}).watch((error) => {
+  console.warn(isSnapError(error) ? describeSnapError(error) : error);
+});
+
 const $mousePosition = createStore<Position | null>(null)
   .on(onMouseDown, (_state, event) => event.position)
   .on(onMouseDragged, (_state, event) => event.position)
@@ -195,6 +239,29 @@ sample({
   target: $draggingPosition,
 });
 
+sample({
+  clock: onModifierDragStarted,
+  fn: (event) => {
+    const element = new AccessibilityElement();
+    element.frame = new Frame(event.frame.size, event.frame.position);
+    element.windowId = event.window_id;
+    element.pid = event.pid;
+    return element;
+  },
+  target: $currentWindow,
+});
+
+sample({
+  clock: onModifierDragStarted,
+  fn: (event) => event.point,
+  target: $mousePosition,
+});
+
+sample({
+  clock: onModifierDragStarted,
+  target: [onDragStarted, screensStore.getScreensFx],
+});
+
 if (isOverlayWindow) {
   $currentScreen.subscribe((screen) => {
     if (screen) {
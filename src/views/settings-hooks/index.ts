@@ -0,0 +1,3 @@
+import SettingsHooks from "./SettingsHooks.svelte";
+
+export { SettingsHooks };
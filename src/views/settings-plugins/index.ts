@@ -0,0 +1,3 @@
+import SettingsPlugins from "./SettingsPlugins.svelte";
+
+export { SettingsPlugins };
@@ -0,0 +1,3 @@
+import SettingsScripts from "./SettingsScripts.svelte";
+
+export { SettingsScripts };